@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use bevy::math::DVec3;
+use the_sapphire_star::galaxy::{Galaxy, GalaxyStar};
+use the_sapphire_star::length::Length;
+use the_sapphire_star::spatial::{Frustum, Octree};
+use the_sapphire_star::star::SpectralType;
+
+const STARS: usize = 1_000_000;
+const QUERIES: u32 = 1_000;
+
+/// Keeps the optimiser from dropping a result unused; `std::hint::black_box` needs a newer compiler.
+#[inline(never)]
+fn black_box<T>(value: T) -> T {
+    unsafe {
+        let kept = std::ptr::read_volatile(&value);
+        std::mem::forget(value);
+        kept
+    }
+}
+
+fn report(name: &str, elapsed: Duration, iterations: u32) {
+    println!("{:<32} {:>12.3?} total {:>12.3?} each", name, elapsed, elapsed / iterations);
+}
+
+fn main() {
+    let galaxy = Galaxy::new(1);
+    let sun = galaxy.solar_position();
+    // About a million stars live within 150 pc of the Sun.
+    let stars: Vec<GalaxyStar> = galaxy.stars_within(sun, Length::pc(150.0)).take(STARS).collect();
+    println!("{} stars", stars.len());
+
+    let start = Instant::now();
+    let mut tree = Octree::new(sun, Length::pc(160.0));
+    for star in stars.iter() {
+        tree.insert(star.position, *star);
+    }
+    report("insert", start.elapsed(), 1);
+
+    let probes: Vec<_> = stars.iter().step_by(stars.len() / QUERIES as usize).take(QUERIES as usize).collect();
+
+    let start = Instant::now();
+    for probe in probes.iter() {
+        black_box(tree.within(probe.position, Length::ly(10.0)));
+    }
+    report("within 10 ly", start.elapsed(), QUERIES);
+
+    let start = Instant::now();
+    for probe in probes.iter() {
+        black_box(tree.nearest(probe.position, 10));
+    }
+    report("nearest 10", start.elapsed(), QUERIES);
+
+    let start = Instant::now();
+    for probe in probes.iter() {
+        black_box(tree.nearest_of_types(probe.position, 1, &[SpectralType::K]));
+    }
+    report("nearest K-type", start.elapsed(), QUERIES);
+
+    let start = Instant::now();
+    for probe in probes.iter() {
+        black_box(tree.within_of_types(probe.position, Length::pc(20.0), &[SpectralType::O, SpectralType::B]));
+    }
+    report("O/B types within 20 pc", start.elapsed(), QUERIES);
+
+    let frustum = Frustum::perspective(sun, DVec3::X, DVec3::Y, 60f64.to_radians(), 16.0 / 9.0,
+                                       Length::pc(0.1), Length::pc(50.0));
+    let start = Instant::now();
+    let visible = black_box(tree.in_frustum(&frustum)).len();
+    report("frustum", start.elapsed(), 1);
+    println!("{} stars in the frustum", visible);
+
+    let start = Instant::now();
+    for probe in probes.iter() {
+        tree.remove(probe.position, probe);
+    }
+    report("remove", start.elapsed(), QUERIES);
+}
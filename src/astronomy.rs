@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::clock::GameClock;
+use super::floating_origin::WorldPosition;
+use super::length;
+use super::length::Length;
+use super::mass;
+use super::orbit::Orbit;
+use super::power;
+use super::temperature;
+
+/// The body an entity with an `Orbit` goes around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Orbiting(pub Entity);
+
+/// The scales the player prefers quantities to be shown in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitPreferences {
+    /// `None` picks a natural scale for each length.
+    pub length: Option<length::Scale>,
+    pub mass: mass::Scale,
+    pub power: power::Scale,
+    pub temperature: temperature::Scale
+}
+
+impl Default for UnitPreferences {
+    fn default() -> UnitPreferences {
+        UnitPreferences {
+            length: None,
+            mass: mass::Scale::SolarMass,
+            power: power::Scale::SolarLuminosity,
+            temperature: temperature::Scale::Kelvin
+        }
+    }
+}
+
+impl UnitPreferences {
+    pub fn length(&self, length: Length) -> Length {
+        match self.length {
+            Some(scale) => length.to_scale(scale),
+            None        => length.auto_scale()
+        }
+    }
+}
+
+/// Places every orbiting body relative to its parent, parents first, so moons follow their planets.
+pub fn propagate_orbits(
+    clock: Res<GameClock>,
+    roots: Query<&WorldPosition, Without<Orbit>>,
+    mut orbiting: Query<(Entity, &Orbit, &Orbiting, &mut WorldPosition)>
+) {
+    let offsets: HashMap<Entity, (Entity, bevy::math::DVec3)> = orbiting.iter_mut()
+        .map(|(entity, orbit, parent, _)| (entity, (parent.0, orbit.position_at(clock.seconds))))
+        .collect();
+    fn absolute(entity: Entity,
+                offsets: &HashMap<Entity, (Entity, bevy::math::DVec3)>,
+                roots: &Query<&WorldPosition, Without<Orbit>>) -> Option<WorldPosition> {
+        let (parent, offset) = offsets.get(&entity)?;
+        let mut position = match roots.get(*parent) {
+            Ok(root) => *root,
+            Err(_)   => absolute(*parent, offsets, roots)?
+        };
+        position.translate(*offset);
+        Some(position)
+    }
+    for (entity, _, _, mut position) in orbiting.iter_mut() {
+        if let Some(absolute) = absolute(entity, &offsets, &roots) {
+            *position = absolute;
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum AstronomySystem {
+    PropagateOrbits
+}
+
+/// The units and astronomy core: display preferences and orbital motion. Needs the `ClockPlugin`.
+pub struct AstronomyPlugin;
+
+impl Plugin for AstronomyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<UnitPreferences>()
+            .add_system(propagate_orbits.system().label(AstronomySystem::PropagateOrbits));
+    }
+}
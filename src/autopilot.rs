@@ -0,0 +1,634 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use std::f64::consts::{PI, TAU};
+use std::fmt;
+
+use super::astronomy::Orbiting;
+use super::clock::{ClockSystem, GameClock, SimulationStage};
+use super::flight::{body_velocity, Attitude, FlightSystem, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::gravity::{patched_conic, sphere_of_influence, Attractor, Body};
+use super::length::Length;
+use super::mass;
+use super::mass::Mass;
+use super::orbit::Orbit;
+use super::planet::Planet;
+use super::ship;
+use super::ship::Ship;
+use super::star::Star;
+
+/// Radians off the burn direction within which the autopilot fires the main engines.
+pub const ALIGNMENT_TOLERANCE: f64 = 0.01;
+/// A burn is finished once less than this much of it is left, in m s⁻¹.
+pub const DELTA_V_TOLERANCE: f64 = 0.001;
+/// Orbits rounder than this count as circular, so transfers from them leave at once rather than waiting for an apsis.
+pub const CIRCULAR_ECCENTRICITY: f64 = 0.001;
+/// Steps per synodic period in the search for a rendezvous transfer window.
+pub const WINDOW_SEARCH_STEPS: usize = 720;
+
+/// A change of velocity at a moment, and what it takes the ship to make it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Burn {
+    /// Seconds since the epoch of the middle of the burn.
+    pub at: f64,
+    /// In m s⁻¹, in the galaxy's frame.
+    pub delta_v: DVec3,
+    /// Seconds at full thrust.
+    pub duration: f64,
+    pub propellant: Mass
+}
+
+impl Burn {
+    /// Burns are centred on their moment, so the engines start half their duration early.
+    pub fn start(&self) -> f64 {
+        self.at - self.duration / 2.0
+    }
+}
+
+/// The standard manoeuvres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Manoeuvre {
+    Circularise,
+    Hohmann,
+    BiElliptic,
+    Rendezvous,
+    MatchVelocity,
+    BurnAtNode
+}
+
+impl fmt::Display for Manoeuvre {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Manoeuvre::Circularise   => write!(f, "circularise"),
+            Manoeuvre::Hohmann       => write!(f, "Hohmann transfer"),
+            Manoeuvre::BiElliptic    => write!(f, "bi-elliptic transfer"),
+            Manoeuvre::Rendezvous    => write!(f, "rendezvous"),
+            Manoeuvre::MatchVelocity => write!(f, "match velocity"),
+            Manoeuvre::BurnAtNode    => write!(f, "burn at node")
+        }
+    }
+}
+
+/// A manoeuvre as burns sized for a particular ship.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManoeuvrePlan {
+    pub manoeuvre: Manoeuvre,
+    pub burns: Vec<Burn>
+}
+
+impl ManoeuvrePlan {
+    /// Sizes impulses, each a moment and a change of velocity, for a ship's mass and engines.
+    /// Each burn starts with the ship lighter by the propellant of those before it.
+    pub fn for_ship(manoeuvre: Manoeuvre, impulses: &[(f64, DVec3)], ship: &Ship) -> ManoeuvrePlan {
+        let exhaust_velocity = ship.exhaust_velocity();
+        let mass_flow = ship.mass_flow();
+        let mut mass = ship.total_mass();
+        let burns = impulses.iter()
+            .map(|(at, delta_v)| {
+                let (propellant, duration) = if mass_flow > 0.0 {
+                    let propellant = ship::propellant_for(delta_v.length(), exhaust_velocity, mass);
+                    (propellant, propellant.in_kilograms() / mass_flow)
+                } else {
+                    (mass::ZERO, f64::INFINITY)
+                };
+                mass = mass - propellant;
+                Burn { at: *at, delta_v: *delta_v, duration, propellant: propellant.to_scale(mass::Scale::Tonne) }
+            })
+            .collect();
+        ManoeuvrePlan { manoeuvre, burns }
+    }
+
+    /// The total cost, in m s⁻¹.
+    pub fn delta_v(&self) -> f64 {
+        self.burns.iter().map(|burn| burn.delta_v.length()).sum()
+    }
+
+    pub fn propellant(&self) -> Mass {
+        self.burns.iter().map(|burn| burn.propellant).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    /// Seconds of burning.
+    pub fn burn_time(&self) -> f64 {
+        self.burns.iter().map(|burn| burn.duration).sum()
+    }
+
+    /// Seconds since the epoch when the last burn ends.
+    pub fn end(&self) -> Option<f64> {
+        self.burns.last().map(|burn| burn.at + burn.duration / 2.0)
+    }
+
+    /// Whether the ship has the fuel for it.
+    pub fn is_feasible(&self, ship: &Ship) -> bool {
+        self.propellant() <= ship.fuel()
+    }
+}
+
+impl fmt::Display for ManoeuvrePlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} burns, Δv {:.1} m/s, {:.0} s burning, {:.2}",
+               self.manoeuvre, self.burns.len(), self.delta_v(), self.burn_time(), self.propellant())
+    }
+}
+
+/// The mean anomaly at a true anomaly.
+pub fn mean_anomaly_at(true_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    let eccentric = 2.0 * ((1.0 - e).sqrt() * (true_anomaly / 2.0).sin()).atan2((1.0 + e).sqrt() * (true_anomaly / 2.0).cos());
+    eccentric - e * eccentric.sin()
+}
+
+/// Seconds since the epoch of the first time at or after `now` that the orbit reaches a true anomaly.
+pub fn time_of_true_anomaly(orbit: &Orbit, true_anomaly: f64, now: f64) -> f64 {
+    let target = mean_anomaly_at(true_anomaly, orbit.eccentricity);
+    now + (target - orbit.mean_anomaly(now)).rem_euclid(TAU) / orbit.mean_motion()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Apsis {
+    Periapsis,
+    Apoapsis
+}
+
+impl Apsis {
+    pub fn true_anomaly(self) -> f64 {
+        match self {
+            Apsis::Periapsis => 0.0,
+            Apsis::Apoapsis  => PI
+        }
+    }
+}
+
+/// Which apsis the orbit reaches next after `now`.
+pub fn next_apsis(orbit: &Orbit, now: f64) -> Apsis {
+    if time_of_true_anomaly(orbit, 0.0, now) <= time_of_true_anomaly(orbit, PI, now) { Apsis::Periapsis } else { Apsis::Apoapsis }
+}
+
+/// The velocity of a circular orbit through a position, in the plane of the current one.
+fn circular_velocity(mu: f64, position: DVec3, velocity: DVec3) -> DVec3 {
+    let radial = position.normalize();
+    let tangent = (velocity - radial * velocity.dot(radial)).normalize();
+    tangent * (mu / position.length()).sqrt()
+}
+
+/// Vis-viva: the speed at a radius on an orbit of a semi-major axis, both in meters.
+fn speed(mu: f64, radius: f64, semi_major_axis: f64) -> f64 {
+    (mu * (2.0 / radius - 1.0 / semi_major_axis)).sqrt()
+}
+
+/// Makes the orbit circular at an apsis; from a nearly circular orbit, now.
+pub fn circularise(ship: &Ship, orbit: &Orbit, apsis: Apsis, now: f64) -> ManoeuvrePlan {
+    let at = if orbit.eccentricity < CIRCULAR_ECCENTRICITY { now } else { time_of_true_anomaly(orbit, apsis.true_anomaly(), now) };
+    let (position, velocity) = orbit.state_at(at);
+    let delta_v = circular_velocity(orbit.gravitational_parameter(), position, velocity) - velocity;
+    ManoeuvrePlan::for_ship(Manoeuvre::Circularise, &[(at, delta_v)], ship)
+}
+
+/// Where a transfer outwards leaves from periapsis, and one inwards from apoapsis; from a circular orbit, now.
+fn departure(orbit: &Orbit, target_radius: Length, now: f64) -> (f64, DVec3, DVec3) {
+    let apsis = if target_radius > orbit.semi_major_axis { Apsis::Periapsis } else { Apsis::Apoapsis };
+    let at = if orbit.eccentricity < CIRCULAR_ECCENTRICITY { now } else { time_of_true_anomaly(orbit, apsis.true_anomaly(), now) };
+    let (position, velocity) = orbit.state_at(at);
+    (at, position, velocity)
+}
+
+/// Moves to a circular orbit of another radius in the same plane, by two burns half an ellipse apart.
+pub fn hohmann(ship: &Ship, orbit: &Orbit, target_radius: Length, now: f64) -> ManoeuvrePlan {
+    let mu = orbit.gravitational_parameter();
+    let (at, position, velocity) = departure(orbit, target_radius, now);
+    let (r1, r2) = (position.length(), target_radius.in_meters());
+    let transfer = (r1 + r2) / 2.0;
+    let prograde = velocity.normalize();
+    let arrival = at + PI * (transfer.powi(3) / mu).sqrt();
+    let impulses = [
+        (at, prograde * (speed(mu, r1, transfer) - velocity.length())),
+        // Half an ellipse on, the ship is moving the opposite way.
+        (arrival, -prograde * ((mu / r2).sqrt() - speed(mu, r2, transfer)))
+    ];
+    ManoeuvrePlan::for_ship(Manoeuvre::Hohmann, &impulses, ship)
+}
+
+/// Moves to a circular orbit of another radius by way of an intermediate radius beyond both,
+/// cheaper than a Hohmann transfer when the radii differ by more than about twelve times.
+pub fn bi_elliptic(ship: &Ship, orbit: &Orbit, target_radius: Length, intermediate_radius: Length, now: f64) -> ManoeuvrePlan {
+    let mu = orbit.gravitational_parameter();
+    let (at, position, velocity) = departure(orbit, intermediate_radius, now);
+    let (r1, r2, rb) = (position.length(), target_radius.in_meters(), intermediate_radius.in_meters());
+    let (first, second) = ((r1 + rb) / 2.0, (rb + r2) / 2.0);
+    let prograde = velocity.normalize();
+    let turn = at + PI * (first.powi(3) / mu).sqrt();
+    let arrival = turn + PI * (second.powi(3) / mu).sqrt();
+    let impulses = [
+        (at, prograde * (speed(mu, r1, first) - velocity.length())),
+        (turn, -prograde * (speed(mu, rb, second) - speed(mu, rb, first))),
+        (arrival, prograde * ((mu / r2).sqrt() - speed(mu, r2, second)))
+    ];
+    ManoeuvrePlan::for_ship(Manoeuvre::BiElliptic, &impulses, ship)
+}
+
+/// Matches another body's velocity with a single burn now.
+pub fn match_velocity(ship: &Ship, velocity: DVec3, target_velocity: DVec3, now: f64) -> ManoeuvrePlan {
+    ManoeuvrePlan::for_ship(Manoeuvre::MatchVelocity, &[(now, target_velocity - velocity)], ship)
+}
+
+/// Meets a body on another orbit about the same central mass: waits for the transfer window,
+/// then a Hohmann-like transfer whose last burn matches the target's velocity.
+/// `None` if the orbits go round at the same rate, so no window ever comes.
+pub fn rendezvous(ship: &Ship, orbit: &Orbit, target: &Orbit, now: f64) -> Option<ManoeuvrePlan> {
+    let mu = orbit.gravitational_parameter();
+    let transfer = (orbit.semi_major_axis.in_meters() + target.semi_major_axis.in_meters()) / 2.0;
+    let flight = PI * (transfer.powi(3) / mu).sqrt();
+    let synodic = TAU / (orbit.mean_motion() - target.mean_motion()).abs();
+    if !synodic.is_finite() {
+        return None;
+    }
+    // The target must arrive opposite where the ship leaves; the angle it falls short by, signed about the ship's orbit normal.
+    let shortfall = |at: f64| {
+        let (position, velocity) = orbit.state_at(at);
+        let normal = position.cross(velocity).normalize();
+        let opposite = -position;
+        let arriving = target.position_at(at + flight);
+        opposite.cross(arriving).dot(normal).atan2(opposite.dot(arriving))
+    };
+    let step = synodic / WINDOW_SEARCH_STEPS as f64;
+    let (mut from, mut to) = (0..WINDOW_SEARCH_STEPS)
+        .map(|i| (now + step * i as f64, now + step * (i + 1) as f64))
+        .find(|(from, to)| {
+            let (a, b) = (shortfall(*from), shortfall(*to));
+            a.signum() != b.signum() && (a - b).abs() < PI
+        })?;
+    for _ in 0..60 {
+        let middle = (from + to) / 2.0;
+        if shortfall(from).signum() == shortfall(middle).signum() { from = middle; } else { to = middle; }
+    }
+    let at = (from + to) / 2.0;
+    let (position, velocity) = orbit.state_at(at);
+    let (arrival_position, arrival_velocity) = target.state_at(at + flight);
+    let (r1, r2) = (position.length(), arrival_position.length());
+    let prograde = velocity.normalize();
+    let impulses = [
+        (at, prograde * speed(mu, r1, transfer) - velocity),
+        (at + flight, arrival_velocity + prograde * speed(mu, r2, transfer))
+    ];
+    Some(ManoeuvrePlan::for_ship(Manoeuvre::Rendezvous, &impulses, ship))
+}
+
+/// A change of velocity at a moment on an orbit, in the orbit's own directions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ManoeuvreNode {
+    /// Seconds since the epoch.
+    pub at: f64,
+    /// Along the velocity, in m s⁻¹.
+    pub prograde: f64,
+    /// Along the orbit normal, in m s⁻¹.
+    pub normal: f64,
+    /// Away from the central mass, square to the other two, in m s⁻¹.
+    pub radial: f64
+}
+
+impl ManoeuvreNode {
+    /// The change of velocity in the galaxy's frame.
+    pub fn delta_v(&self, orbit: &Orbit) -> DVec3 {
+        let (position, velocity) = orbit.state_at(self.at);
+        let prograde = velocity.normalize();
+        let normal = position.cross(velocity).normalize();
+        let radial = prograde.cross(normal);
+        prograde * self.prograde + normal * self.normal + radial * self.radial
+    }
+}
+
+/// Burns as a manoeuvre node says.
+pub fn burn_at_node(ship: &Ship, orbit: &Orbit, node: &ManoeuvreNode) -> ManoeuvrePlan {
+    ManoeuvrePlan::for_ship(Manoeuvre::BurnAtNode, &[(node.at, node.delta_v(orbit))], ship)
+}
+
+/// Flies a ship through a plan, turning with its thrusters and burning with its main engines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Autopilot {
+    pub plan: ManoeuvrePlan,
+    /// The burn under way or next.
+    pub burn: usize,
+    /// What is left of that burn, in m s⁻¹ in the galaxy's frame.
+    pub remaining: DVec3
+}
+
+impl Autopilot {
+    pub fn new(plan: ManoeuvrePlan) -> Autopilot {
+        let remaining = plan.burns.first().map(|burn| burn.delta_v).unwrap_or(DVec3::ZERO);
+        Autopilot { plan, burn: 0, remaining }
+    }
+
+    pub fn current(&self) -> Option<&Burn> {
+        self.plan.burns.get(self.burn)
+    }
+
+    fn next_burn(&mut self) {
+        self.burn += 1;
+        self.remaining = self.current().map(|burn| burn.delta_v).unwrap_or(DVec3::ZERO);
+    }
+}
+
+/// The angle between two vectors, accurate near zero where the arccosine of their dot product is not.
+pub fn angle_between(a: DVec3, b: DVec3) -> f64 {
+    a.cross(b).length().atan2(a.dot(b))
+}
+
+/// The rotation controls that turn a ship's engines towards a direction as fast as its thrusters allow
+/// without overshooting: the turn rate is what it could still brake from in the angle left.
+pub fn steer(attitude: &Attitude, direction: DVec3, angular_acceleration: f64, seconds: f64) -> DVec3 {
+    if angular_acceleration <= 0.0 || seconds <= 0.0 {
+        return DVec3::ZERO;
+    }
+    let forward = attitude.forward();
+    let cross = forward.cross(direction);
+    let angle = angle_between(forward, direction);
+    let axis = if cross.length() > 1e-12 {
+        cross.normalize()
+    } else if angle > 0.0 {
+        // Pointing exactly away, so any axis square to forward will do.
+        forward.any_orthonormal_vector()
+    } else {
+        DVec3::ZERO
+    };
+    let rate = (2.0 * angular_acceleration * angle).sqrt().min(angle / seconds);
+    let wanted = attitude.rotation.inverse() * axis * rate;
+    ((wanted - attitude.angular_velocity) / (angular_acceleration * seconds)).clamp(DVec3::splat(-1.0), DVec3::splat(1.0))
+}
+
+pub fn fly_autopilot(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut ships: Query<(Entity, &Ship, &Attitude, &mut ShipControls, &mut Autopilot)>
+) {
+    let dt = clock.tick_seconds();
+    for (entity, ship, attitude, mut controls, mut autopilot) in ships.iter_mut() {
+        let burn = match autopilot.current() {
+            Some(burn) => *burn,
+            None       => {
+                *controls = ShipControls::default();
+                commands.entity(entity).remove::<Autopilot>();
+                continue;
+            }
+        };
+        let direction = if autopilot.remaining.length() > 0.0 { autopilot.remaining.normalize() } else { burn.delta_v.normalize_or_zero() };
+        *controls = ShipControls { rotation: steer(attitude, direction, ship.angular_acceleration(), dt), ..ShipControls::default() };
+        let aligned = angle_between(attitude.forward(), direction) < ALIGNMENT_TOLERANCE;
+        let acceleration = ship.acceleration();
+        if clock.seconds >= burn.start() && aligned && acceleration > 0.0 && dt > 0.0 {
+            let throttle = (autopilot.remaining.length() / (acceleration * dt)).min(1.0);
+            controls.throttle = throttle;
+            autopilot.remaining -= direction * (acceleration * throttle * dt);
+        }
+        if autopilot.remaining.length() < DELTA_V_TOLERANCE || autopilot.remaining.dot(burn.delta_v) <= 0.0 {
+            autopilot.next_burn();
+        }
+    }
+}
+
+/// What the autopilot can be asked to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutopilotOrder {
+    /// At whichever apsis comes next.
+    Circularise,
+    Hohmann { radius: Length },
+    BiElliptic { radius: Length, intermediate_radius: Length },
+    /// With another ship or a body.
+    MatchVelocity { target: Entity },
+    /// With a body orbiting whatever the ship orbits.
+    Rendezvous { target: Entity },
+    BurnAtNode(ManoeuvreNode),
+    Cancel
+}
+
+/// Asks a ship's autopilot to plan and fly a manoeuvre.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutopilotCommand {
+    pub ship: Entity,
+    pub order: AutopilotOrder
+}
+
+/// The ship's orbit about whichever dominates where it is of the nearest star and that star's planets,
+/// and that body.
+fn current_orbit(
+    position: WorldPosition,
+    velocity: DVec3,
+    seconds: f64,
+    stars: &Query<(Entity, &Star, &WorldPosition)>,
+    planets: &Query<(Entity, &Planet, &Orbit, &Orbiting, &WorldPosition)>,
+    orbits: &Query<(&Orbit, &Orbiting)>
+) -> Option<(Entity, Orbit)> {
+    let (star, star_mass, star_position) = stars.iter()
+        .map(|(entity, star, star_position)| (entity, star.mass, *star_position))
+        .min_by(|(_, _, a), (_, _, b)| a.distance(position).partial_cmp(&b.distance(position)).unwrap_or(std::cmp::Ordering::Equal))?;
+    let mut bodies = vec![(star, Attractor {
+        body: Body::new(star_mass, star_position.relative_to(position), DVec3::ZERO),
+        sphere_of_influence: None
+    })];
+    bodies.extend(planets.iter()
+                  .filter(|(_, _, _, parent, _)| parent.0 == star)
+                  .map(|(entity, planet, orbit, _, planet_position)| (entity, Attractor {
+                      body: Body::new(planet.mass, planet_position.relative_to(position), body_velocity(entity, seconds, orbits)),
+                      sphere_of_influence: Some(sphere_of_influence(orbit, planet.mass))
+                  })));
+    let attractors: Vec<Attractor> = bodies.iter().map(|(_, attractor)| *attractor).collect();
+    let (index, orbit) = patched_conic(&attractors, DVec3::ZERO, velocity, seconds)?;
+    Some((bodies[index].0, orbit))
+}
+
+/// Seconds a ship takes to turn about, from rest to rest.
+pub fn turn_time(ship: &Ship) -> f64 {
+    let angular_acceleration = ship.angular_acceleration();
+    if angular_acceleration > 0.0 { 2.0 * (PI / angular_acceleration).sqrt() } else { f64::INFINITY }
+}
+
+/// Turns autopilot orders into plans and sets the autopilot flying them.
+/// Plans start late enough for the ship to turn to its first burn and start it on time.
+pub fn plan_manoeuvres(
+    mut commands: Commands,
+    mut orders: EventReader<AutopilotCommand>,
+    clock: Res<GameClock>,
+    ships: Query<(&Ship, &WorldPosition, &Velocity)>,
+    stars: Query<(Entity, &Star, &WorldPosition)>,
+    planets: Query<(Entity, &Planet, &Orbit, &Orbiting, &WorldPosition)>,
+    orbits: Query<(&Orbit, &Orbiting)>
+) {
+    for AutopilotCommand { ship: entity, order } in orders.iter() {
+        let (ship, position, velocity) = match ships.get(*entity) {
+            Ok(ship) => ship,
+            Err(_)   => continue
+        };
+        let orbit = || current_orbit(*position, velocity.0, clock.seconds, &stars, &planets, &orbits);
+        let plan_from = |now: f64| match *order {
+            AutopilotOrder::Cancel                  => None,
+            AutopilotOrder::Circularise             => orbit().map(|(_, orbit)| circularise(ship, &orbit, next_apsis(&orbit, now), now)),
+            AutopilotOrder::Hohmann { radius }      => orbit().map(|(_, orbit)| hohmann(ship, &orbit, radius, now)),
+            AutopilotOrder::BiElliptic { radius, intermediate_radius } =>
+                orbit().map(|(_, orbit)| bi_elliptic(ship, &orbit, radius, intermediate_radius, now)),
+            AutopilotOrder::MatchVelocity { target } => {
+                let target_velocity = match ships.get(target) {
+                    Ok((_, _, target_velocity)) => target_velocity.0,
+                    Err(_)                      => body_velocity(target, now, &orbits)
+                };
+                Some(match_velocity(ship, velocity.0, target_velocity, now))
+            }
+            AutopilotOrder::Rendezvous { target }   => orbit().and_then(|(central, orbit)| match orbits.get(target) {
+                Ok((target_orbit, parent)) if parent.0 == central => rendezvous(ship, &orbit, target_orbit, now),
+                _                                                 => None
+            }),
+            AutopilotOrder::BurnAtNode(node)        => orbit().map(|(_, orbit)| burn_at_node(ship, &orbit, &node))
+        };
+        if *order == AutopilotOrder::Cancel {
+            commands.entity(*entity).remove::<Autopilot>().insert(ShipControls::default());
+            continue;
+        }
+        let earliest = clock.seconds + turn_time(ship);
+        let plan = plan_from(earliest).and_then(|plan| match plan.burns.first() {
+            Some(first) if first.start() < earliest => plan_from(earliest + first.duration / 2.0),
+            _                                       => Some(plan)
+        });
+        match plan {
+            Some(plan) => {
+                if !plan.is_feasible(ship) {
+                    warn!("not enough fuel for the {}: it needs {:.2} and the ship has {:.2}", plan.manoeuvre, plan.propellant(), ship.fuel());
+                }
+                info!("autopilot: {}", plan);
+                commands.entity(*entity).insert(Autopilot::new(plan));
+            }
+            None => warn!("the autopilot could not plan that manoeuvre from the ship's orbit")
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum AutopilotSystem {
+    Plan,
+    Fly
+}
+
+/// Manoeuvre planning and an autopilot to fly the plans. Needs the `FlightPlugin`.
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<AutopilotCommand>()
+            .add_system(plan_manoeuvres.system().label(AutopilotSystem::Plan))
+            .add_system_to_stage(SimulationStage, fly_autopilot.system()
+                                 .label(AutopilotSystem::Fly)
+                                 .after(ClockSystem::Advance)
+                                 .before(FlightSystem::Fly));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ship::courier;
+
+    fn earth_orbit(km: f64) -> Orbit {
+        Orbit::circular(Length::km(km), Mass::earth_masses(1.0))
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * a.abs().max(b.abs())
+    }
+
+    /// The orbit a plan's burn leaves the ship on.
+    fn after(orbit: &Orbit, burn: &Burn) -> Orbit {
+        let (position, velocity) = orbit.state_at(burn.at);
+        Orbit::from_state(position, velocity + burn.delta_v, orbit.central_mass, burn.at).unwrap()
+    }
+
+    #[test]
+    fn hohmann_transfers_cost_what_the_textbooks_say() {
+        let ship = courier();
+        for (r1, r2) in [(7000.0, 42164.0), (42164.0, 7000.0), (6700.0, 384400.0)].iter() {
+            let orbit = earth_orbit(*r1);
+            let mu = orbit.gravitational_parameter();
+            let (r1, r2) = (r1 * 1000.0, r2 * 1000.0);
+            let departure = (mu / r1).sqrt() * ((2.0 * r2 / (r1 + r2)).sqrt() - 1.0);
+            let arrival = (mu / r2).sqrt() * (1.0 - (2.0 * r1 / (r1 + r2)).sqrt());
+            let plan = hohmann(&ship, &orbit, Length::m(r2), 100.0);
+            assert_eq!(plan.burns.len(), 2);
+            assert!(close(plan.burns[0].delta_v.length(), departure.abs(), 1.0e-9), "leaving {} m", r1);
+            assert!(close(plan.burns[1].delta_v.length(), arrival.abs(), 1.0e-9), "arriving at {} m", r2);
+            assert_eq!(plan.burns[0].at, 100.0);
+            assert!(close(plan.burns[1].at - 100.0, PI * ((r1 + r2).powi(3) / (8.0 * mu)).sqrt(), 1.0e-9));
+
+            let transfer = after(&orbit, &plan.burns[0]);
+            assert!(close(transfer.semi_major_axis.in_meters(), (r1 + r2) / 2.0, 1.0e-9));
+            let arrived = after(&transfer, &plan.burns[1]);
+            assert!(arrived.eccentricity < 1.0e-6 && close(arrived.semi_major_axis.in_meters(), r2, 1.0e-6));
+        }
+    }
+
+    #[test]
+    fn bi_elliptic_transfers_win_only_between_very_different_orbits() {
+        let ship = courier();
+        let orbit = earth_orbit(7000.0);
+        // Beyond a ratio of 15.58 any intermediate radius past the target beats a Hohmann transfer;
+        // below 11.94 none does.
+        for (ratio, intermediate, cheaper) in [(20.0, 1.5, true), (20.0, 10.0, true), (16.0, 1.1, true), (10.0, 4.0, false), (10.0, 100.0, false)].iter() {
+            let target = Length::km(7000.0 * ratio);
+            let through = Length::km(7000.0 * ratio * intermediate);
+            let direct = hohmann(&ship, &orbit, target, 0.0).delta_v();
+            let plan = bi_elliptic(&ship, &orbit, target, through, 0.0);
+            assert_eq!(plan.delta_v() < direct, *cheaper, "{} times out by way of {} times that", ratio, intermediate);
+
+            let mut ellipse = orbit;
+            for burn in plan.burns.iter() {
+                ellipse = after(&ellipse, burn);
+            }
+            assert!(ellipse.eccentricity < 1.0e-6 && close(ellipse.semi_major_axis.in_meters(), target.in_meters(), 1.0e-6));
+        }
+    }
+
+    #[test]
+    fn circularising_at_an_apsis_leaves_a_circular_orbit() {
+        let ship = courier();
+        let orbit = Orbit { eccentricity: 0.3, argument_of_periapsis: 1.0, mean_anomaly_at_epoch: 2.0, ..earth_orbit(10000.0) };
+        for apsis in [Apsis::Periapsis, Apsis::Apoapsis].iter() {
+            let plan = circularise(&ship, &orbit, *apsis, 500.0);
+            let burn = plan.burns[0];
+            assert!(burn.at >= 500.0 && burn.at < 500.0 + orbit.period());
+            let radius = match apsis {
+                Apsis::Periapsis => orbit.periapsis(),
+                Apsis::Apoapsis  => orbit.apoapsis()
+            };
+            assert!(close(orbit.position_at(burn.at).length(), radius.in_meters(), 1.0e-9));
+            let circular = after(&orbit, &burn);
+            assert!(circular.eccentricity < 1.0e-6, "{:?}: e = {}", apsis, circular.eccentricity);
+            assert!(close(circular.semi_major_axis.in_meters(), radius.in_meters(), 1.0e-6));
+        }
+        assert_eq!(next_apsis(&orbit, circularise(&ship, &orbit, Apsis::Periapsis, 500.0).burns[0].at + 1.0), Apsis::Apoapsis);
+    }
+
+    #[test]
+    fn rendezvous_meets_the_target_at_its_velocity() {
+        let ship = courier();
+        let orbit = earth_orbit(7000.0);
+        let target = Orbit { mean_anomaly_at_epoch: 1.0, ..earth_orbit(20000.0) };
+        let plan = rendezvous(&ship, &orbit, &target, 0.0).unwrap();
+        let (leave, arrive) = (plan.burns[0], plan.burns[1]);
+        let transfer = after(&orbit, &leave);
+        let (position, velocity) = transfer.state_at(arrive.at);
+        let (target_position, target_velocity) = target.state_at(arrive.at);
+        assert!(position.distance(target_position) < 1.0e-6 * target_position.length(), "missed by {} m", position.distance(target_position));
+        assert!((velocity + arrive.delta_v).distance(target_velocity) < 1.0e-6 * target_velocity.length());
+        assert!(rendezvous(&ship, &orbit, &earth_orbit(7000.0), 0.0).is_none());
+    }
+
+    #[test]
+    fn burns_take_the_ship_as_long_as_its_engines_need() {
+        let ship = courier();
+        let plan = hohmann(&ship, &earth_orbit(7000.0), Length::km(42164.0), 0.0);
+        let (first, second) = (plan.burns[0], plan.burns[1]);
+        assert!(close(first.duration, ship.burn_time(first.delta_v.length()), 1.0e-9));
+        // The second burn starts lighter by the propellant of the first.
+        let mut lighter = ship.clone();
+        lighter.consume_fuel(first.propellant);
+        assert!(close(second.duration, lighter.burn_time(second.delta_v.length()), 1.0e-9));
+        assert!(second.duration < ship.burn_time(second.delta_v.length()));
+        assert!(close(plan.burn_time(), first.duration + second.duration, 1.0e-12));
+        assert_eq!(first.start(), -first.duration / 2.0);
+        assert!(plan.is_feasible(&ship));
+    }
+}
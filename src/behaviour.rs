@@ -0,0 +1,976 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use super::astronomy::Orbiting;
+use super::autopilot::{angle_between, steer, AutopilotSystem};
+use super::clock::{ClockSystem, GameClock, SimulationStage};
+use super::combat::Target;
+use super::flight::{body_velocity, Attitude, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::industry::{Commodity, Deposits, Industry, Stockpile, HYDROGEN};
+use super::mass;
+use super::mass::Mass;
+use super::market::{Credits, Manifest, Market, MarketSystem, Side, TradeOrder, DOCKING_RANGE_METERS};
+use super::orbit::Orbit;
+use super::planet::{Planet, PlanetKind};
+use super::scene;
+use super::sensors::Contacts;
+use super::ship::{Ship, Subsystem};
+
+/// The trees every agent can be given, by name, each in `behaviours/<name>.ron` under the assets directory.
+pub const BEHAVIOURS: [&str; 4] = ["trader", "miner", "pirate", "patrol"];
+/// Ships plan to brake at this fraction of their full acceleration, to leave some in hand.
+pub const APPROACH_MARGIN: f64 = 0.5;
+/// Ships cruise at no more than this fraction of the Δv they have left, so a trip costs at most twice it.
+pub const CRUISE_FRACTION: f64 = 0.25;
+/// Ships run from threats at no more than this fraction of their Δv, keeping enough to get home.
+pub const FLEE_FRACTION: f64 = 0.1;
+/// Radians off the wanted direction within which an agent fires its engines; rougher than the autopilot.
+pub const STEERING_TOLERANCE: f64 = 0.1;
+/// Close enough to a point to count as there, in m s⁻¹ of relative speed.
+pub const ARRIVAL_SPEED: f64 = 100.0;
+/// How far from a body ships hold station, well inside docking range.
+pub const PARKING_METERS: f64 = DOCKING_RANGE_METERS * 0.5;
+/// How far from home patrols fly their beat.
+pub const PATROL_RADIUS_METERS: f64 = 1.0e10;
+/// Ore a mining ship takes aboard a second at a deposit of richness one, in kg s⁻¹.
+pub const MINING_RATE: f64 = 10.0;
+/// The least fuel worth docking for, in kg; an order bringing less aboard counts as unfilled.
+pub const MINIMUM_REFUEL_KG: f64 = 1000.0;
+
+/// What a node did this tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    Running
+}
+
+/// Tests of an agent's state and surroundings, which succeed or fail at once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    /// The blackboard has an entry under the key.
+    Has(String),
+    /// Fuel is below a fraction of the tanks' capacity.
+    FuelBelow(f64),
+    /// Cargo is above a fraction of the holds' capacity.
+    CargoAbove(f64),
+    /// Less than a fraction of the hull is left.
+    HullBelow(f64),
+    /// The ship or body under the key is within this many meters.
+    Closer(String, f64)
+}
+
+/// What agents know how to do. Keys name entries on the blackboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Task {
+    /// Puts the nearest market under the key, preferring those that take all the cargo aboard.
+    FindMarket(String),
+    /// Puts the nearest market with hydrogen in stock, that the agent can pay for some of, under the key.
+    FindFuel(String),
+    /// Puts the nearest solid body with deposits left under the key.
+    FindDeposit(String),
+    /// Puts the nearest ship in sight that the agent's role is hostile to under the key.
+    FindHostile(String),
+    /// Flies to hold station by the ship or body under the key; runs until there.
+    Navigate(String),
+    /// Succeeds while within docking range of the body under the key, holding station by it.
+    Dock(String),
+    /// Sells everything aboard at the market under the key.
+    Sell(String),
+    /// Buys for the most profitable route from the market `at`, putting where to sell under `destination`.
+    Buy { at: String, destination: String },
+    /// Fills the tanks with hydrogen from the market under the key; runs until they are full or it has none left.
+    /// Fails, forgetting the market, once an order is left unfilled, as when the ship can pay for no more.
+    Refuel(String),
+    /// Mines the body under the key until the holds are full.
+    Mine(String),
+    /// Fights the ship under the key, keeping in range of it; runs until it is destroyed, and fails if it is lost sight of.
+    Attack(String),
+    /// Runs from the ship under the key until it is out of sight.
+    Flee(String),
+    /// Flies a beat about the ship or body under the key, forever.
+    Patrol(String),
+    /// Forgets what is under the key.
+    Clear(String),
+    /// Lets the ship drift.
+    Idle
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Task::FindMarket(key)           => write!(f, "find a market for {}", key),
+            Task::FindFuel(key)             => write!(f, "find fuel for {}", key),
+            Task::FindDeposit(key)          => write!(f, "find a deposit for {}", key),
+            Task::FindHostile(key)          => write!(f, "look for {}", key),
+            Task::Navigate(key)             => write!(f, "navigate to {}", key),
+            Task::Dock(key)                 => write!(f, "dock at {}", key),
+            Task::Sell(key)                 => write!(f, "sell at {}", key),
+            Task::Buy { at, destination }   => write!(f, "buy at {} for {}", at, destination),
+            Task::Refuel(key)               => write!(f, "refuel at {}", key),
+            Task::Mine(key)                 => write!(f, "mine {}", key),
+            Task::Attack(key)               => write!(f, "attack {}", key),
+            Task::Flee(key)                 => write!(f, "flee {}", key),
+            Task::Patrol(key)               => write!(f, "patrol about {}", key),
+            Task::Clear(key)                => write!(f, "forget {}", key),
+            Task::Idle                      => write!(f, "idle")
+        }
+    }
+}
+
+/// A behaviour tree, evaluated from its root every tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Node {
+    /// Runs its children in order until one does not succeed, and does what that one did.
+    Sequence(Vec<Node>),
+    /// Runs its children in order until one does not fail, and does what that one did.
+    Selector(Vec<Node>),
+    /// Swaps success and failure.
+    Invert(Box<Node>),
+    /// Succeeds whatever its child does, unless it is still running.
+    Succeed(Box<Node>),
+    Check(Condition),
+    Task(Task)
+}
+
+impl Node {
+    pub fn from_ron(text: &str) -> Result<Node, ron::Error> {
+        ron::de::from_str(text)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Node, ron::Error> {
+        Node::from_ron(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Every tree agents can run, by name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BehaviourTrees(pub BTreeMap<String, Node>);
+
+impl BehaviourTrees {
+    /// The trees of `BEHAVIOURS`, leaving out and logging any that will not load.
+    pub fn load() -> BehaviourTrees {
+        let mut trees = BehaviourTrees::default();
+        for name in BEHAVIOURS.iter() {
+            let path = format!("behaviours/{}.ron", name);
+            match Node::load(scene::asset_path(&path)) {
+                Ok(tree)   => { trees.0.insert(name.to_string(), tree); }
+                Err(error) => error!("could not load the behaviour {}: {}", path, error)
+            }
+        }
+        trees
+    }
+}
+
+/// Who a ship flies for, which decides who it fights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Role {
+    Trader,
+    Miner,
+    Pirate,
+    Patrol
+}
+
+impl Role {
+    /// Pirates prey on every other ship, the player's among them; everyone else is hostile only to pirates.
+    pub fn hostile_to(self, other: Option<Role>) -> bool {
+        match (self, other) {
+            (Role::Pirate, Some(Role::Pirate)) => false,
+            (Role::Pirate, _)                  => true,
+            (_, Some(Role::Pirate))            => true,
+            _                                  => false
+        }
+    }
+}
+
+/// A ship flown by a behaviour tree.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Agent {
+    pub behaviour: String
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Entity(Entity),
+    Number(f64)
+}
+
+/// An agent's memory, which its tasks read and write by key.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Blackboard {
+    pub values: BTreeMap<String, Value>,
+    /// What it was last busy with.
+    pub task: Option<String>
+}
+
+impl Blackboard {
+    pub fn entity(&self, key: &str) -> Option<Entity> {
+        match self.values.get(key) {
+            Some(Value::Entity(entity)) => Some(*entity),
+            _                           => None
+        }
+    }
+
+    pub fn number(&self, key: &str) -> Option<f64> {
+        match self.values.get(key) {
+            Some(Value::Number(number)) => Some(*number),
+            _                           => None
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.values.insert(key.to_string(), value);
+    }
+}
+
+/// Sent when an agent turns to a new task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BehaviourEvent {
+    pub agent: Entity,
+    pub task: String
+}
+
+impl fmt::Display for BehaviourEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: {}", self.agent, self.task)
+    }
+}
+
+/// Asks to take ore aboard a ship from a body's deposits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extract {
+    pub ship: Entity,
+    pub body: Entity,
+    pub amount: Mass
+}
+
+/// Asks to fill a ship's tanks at a market.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Refuel {
+    pub ship: Entity,
+    pub market: Entity
+}
+
+/// The controls that bring a ship to rest `standoff` meters from a point moving at `target_velocity`,
+/// cruising no faster than a fraction of its Δv and braking in time, and whether it is there to within `tolerance` meters.
+pub fn approach(
+    ship: &Ship,
+    attitude: &Attitude,
+    (position, velocity): (WorldPosition, DVec3),
+    (target, target_velocity): (WorldPosition, DVec3),
+    standoff: f64,
+    tolerance: f64,
+    seconds: f64
+) -> (ShipControls, bool) {
+    let offset = target.relative_to(position);
+    let distance = offset.length();
+    let relative = velocity - target_velocity;
+    let gap = distance - standoff;
+    if gap.abs() < tolerance {
+        // Near enough; only the drift is left to stop.
+        let arrived = relative.length() < ARRIVAL_SPEED;
+        let controls = if arrived { ShipControls::default() } else { burn(ship, attitude, -relative, seconds) };
+        return (controls, arrived);
+    }
+    let toward = if distance > 0.0 { offset / distance } else { DVec3::ZERO };
+    let speed = (2.0 * ship.acceleration() * APPROACH_MARGIN * gap.abs()).sqrt().min(ship.delta_v() * CRUISE_FRACTION);
+    (burn(ship, attitude, toward * (speed * gap.signum()) - relative, seconds), false)
+}
+
+/// The controls that turn a ship toward a change of velocity, firing its engines for as much of it as a tick gives
+/// once it points near enough that way.
+pub fn burn(ship: &Ship, attitude: &Attitude, change: DVec3, seconds: f64) -> ShipControls {
+    let acceleration = ship.acceleration();
+    let direction = change.normalize_or_zero();
+    let mut controls = ShipControls { rotation: steer(attitude, direction, ship.angular_acceleration(), seconds), ..ShipControls::default() };
+    if acceleration > 0.0 && seconds > 0.0 && angle_between(attitude.forward(), direction) < STEERING_TOLERANCE {
+        controls.throttle = (change.length() / (acceleration * seconds)).min(1.0);
+    }
+    controls
+}
+
+/// Where something is and how it moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fix {
+    position: WorldPosition,
+    velocity: DVec3,
+    role: Option<Role>
+}
+
+/// What every agent can see of the world this tick.
+struct View<'a> {
+    now: f64,
+    seconds: f64,
+    ships: BTreeMap<Entity, Fix>,
+    bodies: BTreeMap<Entity, Fix>,
+    markets: Vec<(Entity, &'a Market, &'a Stockpile, WorldPosition)>,
+    deposits: Vec<(Entity, &'a Deposits, WorldPosition)>
+}
+
+/// An agent as its tree runs, with what it decides.
+struct Pilot<'a> {
+    entity: Entity,
+    role: Option<Role>,
+    ship: &'a Ship,
+    attitude: &'a Attitude,
+    position: WorldPosition,
+    velocity: DVec3,
+    contacts: Option<&'a Contacts>,
+    account: Option<(&'a Manifest, f64)>,
+    blackboard: &'a mut Blackboard,
+    controls: ShipControls,
+    target: Option<Entity>,
+    task: Option<String>,
+    running: bool,
+    trades: Vec<TradeOrder>,
+    extracts: Vec<Extract>,
+    refuels: Vec<Refuel>
+}
+
+impl<'a> Pilot<'a> {
+    /// Where the ship or body under a key is: a ship as the agent's sensors last showed it, if it has any.
+    fn locate(&self, view: &View, key: &str) -> Option<(Entity, Fix)> {
+        let entity = self.blackboard.entity(key)?;
+        if let Some(body) = view.bodies.get(&entity) {
+            return Some((entity, *body));
+        }
+        let ship = view.ships.get(&entity)?;
+        match self.contacts {
+            Some(contacts) => contacts.0.get(&entity).map(|contact| (entity, Fix {
+                position: contact.projected_position(view.now),
+                velocity: contact.velocity,
+                role: ship.role
+            })),
+            None           => Some((entity, *ship))
+        }
+    }
+
+    fn fly_to(&mut self, view: &View, fix: Fix, standoff: f64, tolerance: f64) -> bool {
+        let (controls, arrived) = approach(self.ship, self.attitude, (self.position, self.velocity),
+                                           (fix.position, fix.velocity), standoff, tolerance, view.seconds);
+        self.controls = controls;
+        arrived
+    }
+
+    fn docked_at(&self, fix: &Fix) -> bool {
+        fix.position.distance(self.position).in_meters() <= DOCKING_RANGE_METERS
+    }
+
+    fn check(&self, condition: &Condition, view: &View) -> bool {
+        match condition {
+            Condition::Has(key)            => self.blackboard.values.contains_key(key),
+            Condition::FuelBelow(limit)    => {
+                let capacity = self.ship.fuel_capacity();
+                capacity > mass::ZERO && self.ship.fuel() / capacity < *limit
+            }
+            Condition::CargoAbove(limit)   => {
+                let capacity = self.ship.cargo() + self.ship.cargo_space();
+                capacity > mass::ZERO && self.ship.cargo() / capacity > *limit
+            }
+            Condition::HullBelow(limit)    => self.ship.working(Subsystem::Hull) < *limit,
+            Condition::Closer(key, meters) => {
+                matches!(self.locate(view, key), Some((_, fix)) if fix.position.distance(self.position).in_meters() < *meters)
+            }
+        }
+    }
+
+    fn run(&mut self, node: &Node, view: &View) -> Status {
+        match node {
+            Node::Sequence(children) => {
+                for child in children {
+                    match self.run(child, view) {
+                        Status::Success => continue,
+                        status          => return status
+                    }
+                }
+                Status::Success
+            }
+            Node::Selector(children) => {
+                for child in children {
+                    match self.run(child, view) {
+                        Status::Failure => continue,
+                        status          => return status
+                    }
+                }
+                Status::Failure
+            }
+            Node::Invert(child)      => match self.run(child, view) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running
+            },
+            Node::Succeed(child)     => match self.run(child, view) {
+                Status::Running => Status::Running,
+                _               => Status::Success
+            },
+            Node::Check(condition)   => if self.check(condition, view) { Status::Success } else { Status::Failure },
+            Node::Task(task)         => {
+                // The task still running is what the agent is busy with; failing that, the last it did.
+                let status = self.perform(task, view);
+                if !self.running {
+                    self.task = Some(task.to_string());
+                    self.running = status == Status::Running;
+                }
+                status
+            }
+        }
+    }
+
+    fn perform(&mut self, task: &Task, view: &View) -> Status {
+        let succeed = |done: bool| if done { Status::Success } else { Status::Failure };
+        match task {
+            Task::FindMarket(key) => {
+                let cargo: Vec<&Commodity> = self.account.iter()
+                    .flat_map(|(manifest, _)| manifest.0.0.iter())
+                    .filter(|(_, amount)| **amount > mass::ZERO)
+                    .map(|(commodity, _)| commodity)
+                    .collect();
+                let takes_all = |market: &Market| cargo.iter().all(|commodity| market.listings.contains_key(*commodity));
+                let distance = |position: &WorldPosition| position.distance(self.position).in_meters();
+                let nearest = view.markets.iter()
+                    .map(|(entity, market, _, position)| (!takes_all(market), distance(position), *entity))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                if let Some((_, _, market)) = nearest {
+                    self.blackboard.set(key, Value::Entity(market));
+                }
+                succeed(nearest.is_some())
+            }
+            Task::FindFuel(key) => {
+                let hydrogen = Commodity::new(HYDROGEN);
+                let minimum = Mass::kg(MINIMUM_REFUEL_KG);
+                let affordable = |market: &Market, stock: &Stockpile| match self.account {
+                    Some((_, credits)) => matches!(market.quote(stock, &hydrogen, minimum, Side::Buy), Ok(cost) if cost <= credits),
+                    None               => true
+                };
+                let nearest = view.markets.iter()
+                    .filter(|(_, market, stock, _)| stock.amount(&hydrogen) >= minimum && affordable(market, stock))
+                    .map(|(entity, _, _, position)| (position.distance(self.position).in_meters(), *entity))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                if let Some((_, market)) = nearest {
+                    self.blackboard.set(key, Value::Entity(market));
+                }
+                succeed(nearest.is_some())
+            }
+            Task::FindDeposit(key) => {
+                let nearest = view.deposits.iter()
+                    .filter(|(_, deposits, _)| deposits.0.iter().any(|deposit| deposit.reserves > mass::ZERO))
+                    .map(|(entity, _, position)| (position.distance(self.position).in_meters(), *entity))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                if let Some((_, body)) = nearest {
+                    self.blackboard.set(key, Value::Entity(body));
+                }
+                succeed(nearest.is_some())
+            }
+            Task::FindHostile(key) => {
+                let role = match self.role {
+                    Some(role) => role,
+                    None       => return Status::Failure
+                };
+                let nearest = self.contacts.iter()
+                    .flat_map(|contacts| contacts.detected())
+                    .filter(|(entity, _)| matches!(view.ships.get(*entity), Some(ship) if role.hostile_to(ship.role)))
+                    .map(|(entity, contact)| (contact.position.distance(self.position).in_meters(), *entity))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                match nearest {
+                    Some((_, hostile)) => self.blackboard.set(key, Value::Entity(hostile)),
+                    None               => { self.blackboard.values.remove(key); }
+                }
+                succeed(nearest.is_some())
+            }
+            Task::Navigate(key) => match self.locate(view, key) {
+                Some((_, fix)) => if self.fly_to(view, fix, PARKING_METERS, PARKING_METERS * 0.5) { Status::Success } else { Status::Running },
+                None           => Status::Failure
+            },
+            Task::Dock(key) => match self.locate(view, key) {
+                Some((_, fix)) if self.docked_at(&fix) => {
+                    self.fly_to(view, fix, PARKING_METERS, PARKING_METERS * 0.5);
+                    Status::Success
+                }
+                _ => Status::Failure
+            },
+            Task::Sell(key) => match (self.locate(view, key), self.account) {
+                (Some((market, fix)), Some((manifest, _))) if self.docked_at(&fix) => {
+                    for (commodity, amount) in manifest.0.0.iter().filter(|(_, amount)| **amount > mass::ZERO) {
+                        self.trades.push(TradeOrder { ship: self.entity, market, commodity: commodity.clone(), amount: *amount, side: Side::Sell });
+                    }
+                    Status::Success
+                }
+                _ => Status::Failure
+            },
+            Task::Buy { at, destination } => {
+                let (market, fix) = match self.locate(view, at) {
+                    Some(found) if self.docked_at(&found.1) => found,
+                    _                                      => return Status::Failure
+                };
+                let (here, credits) = match (view.markets.iter().find(|(entity, _, _, _)| *entity == market), self.account) {
+                    (Some(here), Some((_, credits))) => (*here, credits),
+                    _                                => return Status::Failure
+                };
+                match super::market::best_route(here, &view.markets, self.ship.cargo_space(), credits) {
+                    Some(route) if route.profit > 0.0 => {
+                        self.trades.push(TradeOrder { ship: self.entity, market, commodity: route.commodity, amount: route.amount, side: Side::Buy });
+                        self.blackboard.set(destination, Value::Entity(route.to));
+                        self.fly_to(view, fix, PARKING_METERS, PARKING_METERS * 0.5);
+                        Status::Success
+                    }
+                    _ => Status::Failure
+                }
+            }
+            Task::Refuel(key) => match self.locate(view, key) {
+                Some((market, fix)) if self.docked_at(&fix) => {
+                    self.fly_to(view, fix, PARKING_METERS, PARKING_METERS * 0.5);
+                    let hydrogen = Commodity::new(HYDROGEN);
+                    let stock = view.markets.iter()
+                        .find(|(entity, _, _, _)| *entity == market)
+                        .map_or(mass::ZERO, |(_, _, stock, _)| stock.amount(&hydrogen));
+                    // What was aboard when the last order went in, to tell whether it was filled.
+                    let ordered_key = format!("{} ordered", key);
+                    let ordered = self.blackboard.number(&ordered_key);
+                    let fuel = self.ship.fuel().in_kilograms();
+                    if self.ship.fuel() >= self.ship.fuel_capacity() || stock <= mass::ZERO {
+                        self.blackboard.values.remove(&ordered_key);
+                        return Status::Success;
+                    }
+                    if matches!(ordered, Some(before) if fuel < before + MINIMUM_REFUEL_KG) {
+                        self.blackboard.values.remove(&ordered_key);
+                        self.blackboard.values.remove(key);
+                        return Status::Failure;
+                    }
+                    self.blackboard.set(&ordered_key, Value::Number(fuel));
+                    self.refuels.push(Refuel { ship: self.entity, market });
+                    Status::Running
+                }
+                _ => Status::Failure
+            },
+            Task::Mine(key) => match self.locate(view, key) {
+                Some((body, fix)) if self.docked_at(&fix) => {
+                    self.fly_to(view, fix, PARKING_METERS, PARKING_METERS * 0.5);
+                    let space = self.ship.cargo_space();
+                    if space <= Mass::kg(1.0) {
+                        return Status::Success;
+                    }
+                    let amount = Mass::kg(MINING_RATE * view.seconds);
+                    self.extracts.push(Extract { ship: self.entity, body, amount: if amount < space { amount } else { space } });
+                    Status::Running
+                }
+                _ => Status::Failure
+            },
+            Task::Attack(key) => {
+                let target = match self.blackboard.entity(key) {
+                    Some(target) if view.ships.contains_key(&target) => target,
+                    _                                                => {
+                        self.blackboard.values.remove(key);
+                        return Status::Success;
+                    }
+                };
+                let fix = match self.locate(view, key) {
+                    Some((_, fix)) if !matches!(self.contacts, Some(contacts) if !contacts.is_detected(target)) => fix,
+                    _                                                                                  => return Status::Failure
+                };
+                let reach = self.ship.weapons.iter().map(|weapon| weapon.range.in_meters()).fold(0.0, f64::max);
+                self.fly_to(view, fix, reach * 0.5, reach * 0.25);
+                self.target = Some(target);
+                Status::Running
+            }
+            Task::Flee(key) => {
+                let (threat, fix) = match self.locate(view, key) {
+                    Some(found) => found,
+                    None        => return Status::Success
+                };
+                if !matches!(self.contacts, Some(contacts) if contacts.is_detected(threat)) {
+                    return Status::Success;
+                }
+                let away = self.position.relative_to(fix.position).normalize_or_zero();
+                let change = away * (self.ship.delta_v() * FLEE_FRACTION) - (self.velocity - fix.velocity);
+                self.controls = burn(self.ship, self.attitude, change, view.seconds);
+                Status::Running
+            }
+            Task::Patrol(key) => {
+                let home = match self.locate(view, key) {
+                    Some((_, home)) => home,
+                    None            => return Status::Failure
+                };
+                let leg_key = format!("{} leg", key);
+                let leg = self.blackboard.number(&leg_key).unwrap_or(0.0);
+                let angle = leg * std::f64::consts::FRAC_PI_2;
+                let mut waypoint = home.position;
+                waypoint.translate(DVec3::new(angle.cos(), 0.0, angle.sin()) * PATROL_RADIUS_METERS);
+                if self.fly_to(view, Fix { position: waypoint, ..home }, 0.0, PARKING_METERS) {
+                    self.blackboard.set(&leg_key, Value::Number((leg + 1.0) % 4.0));
+                }
+                Status::Running
+            }
+            Task::Clear(key) => {
+                self.blackboard.values.remove(key);
+                Status::Success
+            }
+            Task::Idle => {
+                self.controls = ShipControls::default();
+                Status::Success
+            }
+        }
+    }
+}
+
+type Surroundings<'w, 'q> = (
+    Query<'w, (Entity, &'q WorldPosition, Option<&'q Velocity>, Option<&'q Role>), With<Ship>>,
+    Query<'w, (Entity, &'q Market, &'q Industry, &'q WorldPosition)>,
+    Query<'w, (Entity, &'q Planet, &'q Deposits, &'q WorldPosition)>,
+    Query<'w, (&'q Orbit, &'q Orbiting)>
+);
+
+type Orders<'a> = (EventWriter<'a, TradeOrder>, EventWriter<'a, Extract>, EventWriter<'a, Refuel>, EventWriter<'a, BehaviourEvent>);
+
+type Piloted<'a> = (Entity, &'a Agent, &'a mut Blackboard, &'a mut ShipControls, &'a Ship, &'a Attitude, &'a WorldPosition, &'a Velocity,
+                    Option<&'a Role>, Option<&'a Contacts>, Option<(&'a Manifest, &'a Credits)>);
+
+/// Runs every agent's tree, one agent after another in the order of their entities, so the same world always
+/// gives the same decisions.
+pub fn think(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    trees: Res<BehaviourTrees>,
+    (ships, markets, deposits, orbits): Surroundings,
+    mut agents: Query<Piloted>,
+    (mut trades, mut extracts, mut refuels, mut events): Orders
+) {
+    let now = clock.seconds;
+    let fix = |entity: Entity, position: &WorldPosition| (entity, Fix { position: *position, velocity: body_velocity(entity, now, &orbits), role: None });
+    let view = View {
+        now,
+        seconds: clock.tick_seconds(),
+        ships: ships.iter()
+            .map(|(entity, position, velocity, role)| (entity, Fix { position: *position, velocity: velocity.map_or(DVec3::ZERO, |velocity| velocity.0), role: role.copied() }))
+            .collect(),
+        bodies: markets.iter().map(|(entity, _, _, position)| fix(entity, position))
+            .chain(deposits.iter().map(|(entity, _, _, position)| fix(entity, position)))
+            .collect(),
+        markets: markets.iter().map(|(entity, market, industry, position)| (entity, market, &industry.stockpile, *position)).collect(),
+        deposits: deposits.iter()
+            .filter(|(_, planet, _, _)| planet.kind != PlanetKind::GasGiant)
+            .map(|(entity, _, deposits, position)| (entity, deposits, *position))
+            .collect()
+    };
+    let mut agents: Vec<_> = agents.iter_mut().collect();
+    agents.sort_by_key(|(entity, ..)| *entity);
+    for (entity, agent, blackboard, controls, ship, attitude, position, velocity, role, contacts, account) in agents.iter_mut() {
+        let tree = match trees.0.get(&agent.behaviour) {
+            Some(tree) => tree,
+            None       => continue
+        };
+        let mut pilot = Pilot {
+            entity: *entity,
+            role: role.copied(),
+            ship,
+            attitude,
+            position: **position,
+            velocity: velocity.0,
+            contacts: *contacts,
+            account: account.map(|(manifest, credits)| (manifest, credits.0)),
+            blackboard,
+            controls: ShipControls::default(),
+            target: None,
+            task: None,
+            running: false,
+            trades: Vec::new(),
+            extracts: Vec::new(),
+            refuels: Vec::new()
+        };
+        pilot.run(tree, &view);
+        match pilot.target {
+            Some(target) => { commands.entity(*entity).insert(Target(target)); }
+            None         => { commands.entity(*entity).remove::<Target>(); }
+        }
+        for order in pilot.trades.drain(..) {
+            trades.send(order);
+        }
+        for order in pilot.extracts.drain(..) {
+            extracts.send(order);
+        }
+        for order in pilot.refuels.drain(..) {
+            refuels.send(order);
+        }
+        if pilot.task != pilot.blackboard.task {
+            pilot.blackboard.task = pilot.task.clone();
+            events.send(BehaviourEvent { agent: *entity, task: pilot.task.clone().unwrap_or_default() });
+        }
+        **controls = pilot.controls;
+    }
+}
+
+/// Takes ore aboard mining ships, from the richest deposits of the bodies they are at.
+pub fn extract_resources(
+    mut orders: EventReader<Extract>,
+    mut ships: Query<(&mut Ship, &mut Manifest)>,
+    mut bodies: Query<&mut Deposits>
+) {
+    for Extract { ship, body, amount } in orders.iter() {
+        let (mut ship, mut manifest) = match ships.get_mut(*ship) {
+            Ok(ship) => ship,
+            Err(_)   => continue
+        };
+        let mut deposits = match bodies.get_mut(*body) {
+            Ok(deposits) => deposits,
+            Err(_)       => continue
+        };
+        let richest = deposits.0.iter_mut()
+            .filter(|deposit| deposit.reserves > mass::ZERO)
+            .max_by(|a, b| a.richness.partial_cmp(&b.richness).unwrap_or(Ordering::Equal));
+        if let Some(deposit) = richest {
+            let wanted = *amount * deposit.richness;
+            let mined = manifest.stow(&mut ship, &deposit.commodity, if wanted < deposit.reserves { wanted } else { deposit.reserves });
+            deposit.reserves = deposit.reserves - mined;
+        }
+    }
+}
+
+/// Fills ships' tanks with a market's hydrogen, at its price to those with an account, as far as they can pay for it.
+pub fn refuel_ships(
+    mut orders: EventReader<Refuel>,
+    mut ships: Query<(&mut Ship, Option<&mut Credits>, &WorldPosition)>,
+    mut markets: Query<(&Market, &mut Industry, &WorldPosition)>
+) {
+    let hydrogen = Commodity::new(HYDROGEN);
+    for Refuel { ship, market } in orders.iter() {
+        let (mut ship, credits, position) = match ships.get_mut(*ship) {
+            Ok(ship) => ship,
+            Err(_)   => continue
+        };
+        let (market, mut industry, market_position) = match markets.get_mut(*market) {
+            Ok(market) => market,
+            Err(_)     => continue
+        };
+        if market_position.distance(*position).in_meters() > DOCKING_RANGE_METERS {
+            continue;
+        }
+        let stock = industry.stockpile.amount(&hydrogen);
+        let wanted = ship.fuel_capacity() - ship.fuel();
+        let mut amount = if wanted < stock { wanted } else { stock };
+        if let (Some(credits), Ok(cost)) = (&credits, market.quote(&industry.stockpile, &hydrogen, amount, Side::Buy)) {
+            // Less costs less a tonne, so this much is affordable; a little is left over for rounding.
+            if cost > credits.0 {
+                amount = amount * (credits.0.max(0.0) / cost * 0.999);
+            }
+        }
+        // Ships without an account, patrols and pirates, take their fuel as they find it.
+        let mut unlimited = Credits(f64::INFINITY);
+        let paid = match credits {
+            Some(mut credits) => market.buy(&mut industry.stockpile, &hydrogen, amount, &mut credits),
+            None              => market.buy(&mut industry.stockpile, &hydrogen, amount, &mut unlimited)
+        };
+        if paid.is_ok() {
+            let mut remaining = amount;
+            for tank in ship.fuel_tanks.iter_mut() {
+                let space = tank.capacity - tank.fuel;
+                let filled = if space < remaining { space } else { remaining };
+                tank.fuel = tank.fuel + filled;
+                remaining = remaining - filled;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum BehaviourSystem {
+    Think,
+    Extract,
+    Refuel
+}
+
+/// NPC ships flown by data-defined behaviour trees. Agents think in a fixed order in the simulation's ticks,
+/// so a run replays exactly. Needs the `ClockPlugin`, `FlightPlugin` and `MarketPlugin`, and the `SensorPlugin`
+/// and `CombatPlugin` for agents to see and fight.
+pub struct BehaviourPlugin;
+
+impl Plugin for BehaviourPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(BehaviourTrees::load())
+            .add_event::<Extract>()
+            .add_event::<Refuel>()
+            .add_event::<BehaviourEvent>()
+            .add_system_to_stage(SimulationStage, think.system()
+                                 .label(BehaviourSystem::Think)
+                                 .after(ClockSystem::Advance)
+                                 .after(MarketSystem::Traders)
+                                 .before(MarketSystem::Trade)
+                                 .before(AutopilotSystem::Fly))
+            .add_system_to_stage(SimulationStage, extract_resources.system()
+                                 .label(BehaviourSystem::Extract)
+                                 .after(MarketSystem::Trade))
+            .add_system_to_stage(SimulationStage, refuel_ships.system()
+                                 .label(BehaviourSystem::Refuel)
+                                 .after(BehaviourSystem::Extract));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+    use super::super::market::Catalogue;
+    use super::super::ship::courier;
+
+    const CATALOGUE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/industry/commodities.ron");
+
+    fn has(key: &str) -> Node {
+        Node::Check(Condition::Has(key.to_string()))
+    }
+
+    fn task(task: Task) -> Node {
+        Node::Task(task)
+    }
+
+    /// Runs a tree once for a courier at rest at the origin, with the blackboard given and the bodies and markets about it.
+    fn run(node: &Node, blackboard: &mut Blackboard, bodies: &[(Entity, f64)], markets: &[(Entity, &Market, &Stockpile, f64)], credits: f64) -> Status {
+        let (ship, attitude, manifest) = (courier(), Attitude::default(), Manifest::default());
+        let at = |x: f64| WorldPosition::from_meters(DVec3::new(x, 0.0, 0.0));
+        let view = View {
+            now: 0.0,
+            seconds: 1.0,
+            ships: BTreeMap::new(),
+            bodies: bodies.iter().map(|(entity, x)| (*entity, Fix { position: at(*x), velocity: DVec3::ZERO, role: None })).collect(),
+            markets: markets.iter().map(|(entity, market, stock, x)| (*entity, *market, *stock, at(*x))).collect(),
+            deposits: Vec::new()
+        };
+        let mut pilot = Pilot {
+            entity: Entity::new(0),
+            role: Some(Role::Trader),
+            ship: &ship,
+            attitude: &attitude,
+            position: WorldPosition::default(),
+            velocity: DVec3::ZERO,
+            contacts: None,
+            account: Some((&manifest, credits)),
+            blackboard,
+            controls: ShipControls::default(),
+            target: None,
+            task: None,
+            running: false,
+            trades: Vec::new(),
+            extracts: Vec::new(),
+            refuels: Vec::new()
+        };
+        pilot.run(node, &view)
+    }
+
+    fn hydrogen_market(tonnes: f64) -> (Market, Stockpile) {
+        let mut stock = Stockpile::default();
+        stock.add(&Commodity::new(HYDROGEN), Mass::t(tonnes));
+        (Market::new(&Catalogue::load(CATALOGUE_PATH).unwrap()), stock)
+    }
+
+    #[test]
+    fn composites_combine_what_their_children_do() {
+        let mut blackboard = Blackboard::default();
+        blackboard.set("yes", Value::Number(1.0));
+        let far = Entity::new(1);
+        blackboard.set("far", Value::Entity(far));
+        let bodies = [(far, 1.0e12)];
+        let mut status = |node: Node| run(&node, &mut blackboard, &bodies, &[], 0.0);
+        let running = || task(Task::Navigate("far".to_string()));
+
+        assert_eq!(status(Node::Sequence(vec![has("yes"), has("yes")])), Status::Success);
+        assert_eq!(status(Node::Sequence(vec![has("yes"), has("no"), running()])), Status::Failure);
+        assert_eq!(status(Node::Sequence(vec![running(), has("no")])), Status::Running);
+        assert_eq!(status(Node::Sequence(Vec::new())), Status::Success);
+
+        assert_eq!(status(Node::Selector(vec![has("no"), has("yes")])), Status::Success);
+        assert_eq!(status(Node::Selector(vec![has("no"), has("no")])), Status::Failure);
+        assert_eq!(status(Node::Selector(vec![running(), has("yes")])), Status::Running);
+        assert_eq!(status(Node::Selector(Vec::new())), Status::Failure);
+
+        assert_eq!(status(Node::Invert(Box::new(has("yes")))), Status::Failure);
+        assert_eq!(status(Node::Invert(Box::new(has("no")))), Status::Success);
+        assert_eq!(status(Node::Invert(Box::new(running()))), Status::Running);
+
+        assert_eq!(status(Node::Succeed(Box::new(has("no")))), Status::Success);
+        assert_eq!(status(Node::Succeed(Box::new(running()))), Status::Running);
+    }
+
+    #[test]
+    fn sequences_stop_at_the_first_child_not_to_succeed() {
+        let mut blackboard = Blackboard::default();
+        blackboard.set("a", Value::Number(1.0));
+        let tree = Node::Sequence(vec![has("missing"), task(Task::Clear("a".to_string()))]);
+        assert_eq!(run(&tree, &mut blackboard, &[], &[], 0.0), Status::Failure);
+        assert!(blackboard.values.contains_key("a"));
+
+        let tree = Node::Sequence(vec![has("a"), task(Task::Clear("a".to_string()))]);
+        assert_eq!(run(&tree, &mut blackboard, &[], &[], 0.0), Status::Success);
+        assert!(!blackboard.values.contains_key("a"));
+    }
+
+    #[test]
+    fn agents_look_for_fuel_they_can_pay_for() {
+        let (near, far) = (Entity::new(1), Entity::new(2));
+        let (market, stock) = hydrogen_market(100.0);
+        let (_, empty) = hydrogen_market(0.0);
+        let cost = market.quote(&stock, &Commodity::new(HYDROGEN), Mass::kg(MINIMUM_REFUEL_KG), Side::Buy).unwrap();
+        let find = task(Task::FindFuel("fuel".to_string()));
+
+        let mut blackboard = Blackboard::default();
+        let markets = [(near, &market, &empty, 1.0e6), (far, &market, &stock, 1.0e9)];
+        assert_eq!(run(&find, &mut blackboard, &[], &markets, cost * 2.0), Status::Success);
+        assert_eq!(blackboard.entity("fuel"), Some(far));
+
+        let mut blackboard = Blackboard::default();
+        assert_eq!(run(&find, &mut blackboard, &[], &markets, cost * 0.5), Status::Failure);
+        assert_eq!(blackboard.entity("fuel"), None);
+    }
+
+    #[test]
+    fn poor_traders_buy_what_fuel_they_can_and_move_on() {
+        let hydrogen = Commodity::new(HYDROGEN);
+        let (market, stock) = hydrogen_market(1000.0);
+        let mut ship = courier();
+        for tank in ship.fuel_tanks.iter_mut() {
+            tank.fuel = mass::ZERO;
+        }
+        let full = market.quote(&stock, &hydrogen, ship.fuel_capacity(), Side::Buy).unwrap();
+        let tree = Node::Selector(vec![
+            Node::Sequence(vec![task(Task::Dock("fuel".to_string())), task(Task::Refuel("fuel".to_string()))]),
+            task(Task::Idle)
+        ]);
+
+        let mut world = World::default();
+        let industry = Industry { stockpile: stock, ..Industry::default() };
+        let market = world.spawn().insert_bundle((market, industry, WorldPosition::default())).id();
+        let mut blackboard = Blackboard::default();
+        blackboard.set("fuel", Value::Entity(market));
+        let trader = world.spawn()
+            .insert_bundle((Agent { behaviour: "trader".to_string() }, blackboard, ShipControls::default(), ship, Attitude::default()))
+            .insert_bundle((WorldPosition::default(), Velocity(DVec3::ZERO), Role::Trader, Manifest::default(), Credits(full / 4.0)))
+            .id();
+        let mut trees = BehaviourTrees::default();
+        trees.0.insert("trader".to_string(), tree);
+        world.insert_resource(trees);
+        world.insert_resource(GameClock::default());
+        world.insert_resource(Events::<TradeOrder>::default());
+        world.insert_resource(Events::<Extract>::default());
+        world.insert_resource(Events::<Refuel>::default());
+        world.insert_resource(Events::<BehaviourEvent>::default());
+        let mut stage = SystemStage::single_threaded()
+            .with_system(think.system().label(BehaviourSystem::Think))
+            .with_system(refuel_ships.system().after(BehaviourSystem::Think));
+
+        stage.run(&mut world);
+        let ship = world.get::<Ship>(trader).unwrap();
+        assert!(ship.fuel() > mass::ZERO && ship.fuel() < ship.fuel_capacity());
+        let credits = world.get::<Credits>(trader).unwrap().0;
+        assert!(credits >= 0.0 && credits < full / 400.0);
+
+        for _ in 0..3 {
+            stage.run(&mut world);
+        }
+        let blackboard = world.get::<Blackboard>(trader).unwrap();
+        assert_eq!(blackboard.task.as_deref(), Some("idle"));
+        assert_eq!(blackboard.entity("fuel"), None);
+        assert!(world.get::<Ship>(trader).unwrap().fuel() < world.get::<Ship>(trader).unwrap().fuel_capacity());
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::process;
+
+use the_sapphire_star::length;
+use the_sapphire_star::length::Length;
+use the_sapphire_star::mass;
+use the_sapphire_star::mass::Mass;
+use the_sapphire_star::power;
+use the_sapphire_star::power::Power;
+use the_sapphire_star::star::{LuminosityClass, SpectralType};
+use the_sapphire_star::star_catalogue::{SortKey, StarCatalogue, StarQuery};
+use the_sapphire_star::temperature::Temperature;
+use the_sapphire_star::universe::{UniverseSeed, UniverseSettings};
+
+const USAGE: &str = "usage: catalogue [--seed SEED] [--radius LIGHT_YEARS] [--type G,K] [--class V] [--distance LY..LY] \
+                     [--temperature K..K] [--mass SOLAR..SOLAR] [--luminosity SOLAR..SOLAR] \
+                     [--sort id|distance|temperature|mass|luminosity] [--order ascending|descending] [--page N] [--per-page N]";
+
+struct Options {
+    seed: Option<u64>,
+    radius: f64,
+    spectral_types: Vec<SpectralType>,
+    luminosity_classes: Vec<LuminosityClass>,
+    /// In light years from where the universe starts.
+    distance: Option<Range<f64>>,
+    /// In kelvin.
+    temperature: Option<Range<f64>>,
+    /// In solar masses.
+    mass: Option<Range<f64>>,
+    /// In solar luminosities.
+    luminosity: Option<Range<f64>>,
+    sort: SortKey,
+    descending: bool,
+    /// Counting from one.
+    page: usize,
+    per_page: usize
+}
+
+fn parse_range(value: &str) -> Option<Range<f64>> {
+    let mut parts = value.splitn(2, "..");
+    let (start, end) = (parts.next()?, parts.next()?);
+    let bound = |part: &str, unbounded: f64| if part.trim().is_empty() { Some(unbounded) } else { part.trim().parse().ok() };
+    Some(bound(start, f64::NEG_INFINITY)?..bound(end, f64::INFINITY)?)
+}
+
+/// A comma separated list of variants, written as they are in RON.
+fn parse_list<T: serde::de::DeserializeOwned>(value: &str) -> Option<Vec<T>> {
+    value.split(',').map(|part| ron::de::from_str(part.trim()).ok()).collect()
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        seed: None, radius: 20.0, spectral_types: Vec::new(), luminosity_classes: Vec::new(), distance: None, temperature: None,
+        mass: None, luminosity: None, sort: SortKey::Distance, descending: false, page: 1, per_page: 50
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = || format!("invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--seed"        => options.seed = Some(value.parse().map_err(|_| invalid())?),
+            "--radius"      => options.radius = value.parse().map_err(|_| invalid())?,
+            "--type"        => options.spectral_types = parse_list(&value).ok_or_else(invalid)?,
+            "--class"       => options.luminosity_classes = parse_list(&value).ok_or_else(invalid)?,
+            "--distance"    => options.distance = Some(parse_range(&value).ok_or_else(invalid)?),
+            "--temperature" => options.temperature = Some(parse_range(&value).ok_or_else(invalid)?),
+            "--mass"        => options.mass = Some(parse_range(&value).ok_or_else(invalid)?),
+            "--luminosity"  => options.luminosity = Some(parse_range(&value).ok_or_else(invalid)?),
+            "--sort"        => options.sort = match value.as_str() {
+                "id"          => SortKey::Id,
+                "distance"    => SortKey::Distance,
+                "temperature" => SortKey::Temperature,
+                "mass"        => SortKey::Mass,
+                "luminosity"  => SortKey::Luminosity,
+                _             => return Err(invalid())
+            },
+            "--order"       => options.descending = match value.as_str() {
+                "ascending"  => false,
+                "descending" => true,
+                _            => return Err(invalid())
+            },
+            "--page"        => options.page = value.parse::<usize>().map_err(|_| invalid())?.max(1),
+            "--per-page"    => options.per_page = value.parse().map_err(|_| invalid())?,
+            _               => return Err(format!("unknown option {}", arg))
+        }
+    }
+    Ok(options)
+}
+
+/// Lists the stars around the start matching the options, a page at a time, with how many of each type matched.
+fn main() {
+    let options = parse_options().unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(2);
+    });
+    let seed = options.seed.map_or_else(UniverseSeed::default, UniverseSeed);
+    let settings = UniverseSettings { radius: Length::ly(options.radius), ..UniverseSettings::from_seed(seed) };
+    let catalogue = StarCatalogue::from_galaxy(&settings.galaxy, settings.start, settings.radius);
+
+    let mut query = StarQuery::new(settings.start)
+        .with_spectral_types(&options.spectral_types)
+        .with_luminosity_classes(&options.luminosity_classes)
+        .sorted_by(options.sort, options.descending)
+        .paged(options.page - 1, options.per_page);
+    if let Some(range) = &options.distance {
+        query = query.within(Length::range(range.clone(), length::Scale::LightYear));
+    }
+    if let Some(range) = &options.temperature {
+        query = query.with_temperature(Temperature::K_range(range.start as f32..range.end as f32));
+    }
+    if let Some(range) = &options.mass {
+        query = query.with_mass(Mass::Msol(range.start)..Mass::Msol(range.end));
+    }
+    if let Some(range) = &options.luminosity {
+        query = query.with_luminosity(Power::Lsol(range.start)..Power::Lsol(range.end));
+    }
+
+    let matching = catalogue.matching(&query);
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (entry, _) in matching.iter() {
+        *counts.entry(format!("{:?}{:?}", entry.star.spectral_type, entry.star.luminosity_class)).or_default() += 1;
+    }
+    let page = catalogue.query(&query);
+    for (entry, distance) in page.entries.iter() {
+        println!("{:<16} {:<6} {:>8.3}  {:>9.0}  {:.3}  {:.4}",
+                 entry.id.to_string(), format!("{:?}{:?}", entry.star.spectral_type, entry.star.luminosity_class), distance,
+                 entry.star.temperature, entry.star.mass.to_scale(mass::Scale::SolarMass),
+                 entry.star.luminosity.to_scale(power::Scale::SolarLuminosity));
+    }
+    let counts: Vec<String> = counts.iter().map(|(kind, count)| format!("{} {}", kind, count)).collect();
+    println!("{} of {} stars match, page {} of {}: {}", page.total, catalogue.0.len(), page.page + 1, page.pages, counts.join(", "));
+}
@@ -1,10 +1,10 @@
 use bevy::prelude::*;
-use the_sapphire_star::startup;
+use the_sapphire_star::plugins::SapphireStarPlugins;
 
 fn main() {
     App::build()
         .insert_resource(Msaa { samples: 4 })
         .add_plugins(DefaultPlugins)
-        .add_startup_system(startup::startup.system())
+        .add_plugins(SapphireStarPlugins)
         .run();
 }
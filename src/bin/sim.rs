@@ -0,0 +1,382 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::process;
+
+use the_sapphire_star::astronomy::Orbiting;
+use bevy::app::{Events, ManualEventReader};
+use bevy::math::DVec3;
+use the_sapphire_star::behaviour::{Agent, BehaviourEvent, Blackboard, Role, Value, PARKING_METERS};
+use the_sapphire_star::clock::GameClock;
+use the_sapphire_star::colony::Colony;
+use the_sapphire_star::faction::{Borders, FactionEvent, FactionSettings, Factions};
+use the_sapphire_star::flight::{Attitude, ShipControls, Velocity};
+use the_sapphire_star::floating_origin::WorldPosition;
+use the_sapphire_star::galaxy::{Galaxy, Sector, StarId};
+use the_sapphire_star::industry::{Deposits, Extractor, Industry, Status};
+use the_sapphire_star::length::Length;
+use the_sapphire_star::market::{Catalogue, Credits, Manifest, Market, Trader, STARTING_CREDITS};
+use the_sapphire_star::mass;
+use the_sapphire_star::mass::Mass;
+use the_sapphire_star::mission::{MissionLog, MissionStatus};
+use the_sapphire_star::orbit::Orbit;
+use the_sapphire_star::planet;
+use the_sapphire_star::planet::Planet;
+use the_sapphire_star::plugins;
+use the_sapphire_star::power::Power;
+use the_sapphire_star::scene;
+use the_sapphire_star::ship::{Ship, Subsystem, STARTER_SHIP};
+use the_sapphire_star::star::Star;
+use the_sapphire_star::universe::{UniverseSeed, UniverseSettings};
+
+const USAGE: &str = "usage: sim [--ticks N] [--step SECONDS] [--seed SEED] [--radius LIGHT_YEARS] [--sector X,Y,Z] [--markets N] [--traders N] [--npcs N] [--factions N] [--report TICKS]";
+
+/// What each extractor of a simulated market gets out of a deposit of richness one, in tonnes a day.
+const EXTRACTION_TONNES_PER_DAY: f64 = 100.0;
+/// What each simulated market uses of what its body does not yield, in tonnes a day.
+const DEMAND_TONNES_PER_DAY: f64 = 20.0;
+const TRADER_CAPACITY_TONNES: f64 = 500.0;
+/// What pirates and patrols fly; traders and miners fly the starter ship.
+const WARSHIP: &str = "ships/frigate.ron";
+
+struct Options {
+    ticks: u64,
+    step: f64,
+    seed: Option<u64>,
+    radius: f64,
+    /// Print exactly what one sector generates, instead of running the simulation.
+    sector: Option<Sector>,
+    /// Open markets on this many of the starting system's planets, and print their prices at the end.
+    markets: usize,
+    /// NPC traders working those markets.
+    traders: usize,
+    /// Ships flown by behaviour trees, this many of each role, starting at those markets.
+    npcs: usize,
+    /// How many factions to found, instead of the default.
+    factions: Option<usize>,
+    /// Print what the factions do, and every this many ticks how they stand.
+    report: u64
+}
+
+fn parse_sector(value: &str) -> Option<Sector> {
+    let coordinates: Vec<i64> = value.split(',').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+    match coordinates.as_slice() {
+        [x, y, z] => Some(Sector::new(*x, *y, *z)),
+        _         => None
+    }
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options { ticks: 100, step: 3600.0, seed: None, radius: 20.0, sector: None, markets: 0, traders: 0, npcs: 0, factions: None, report: 0 };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = || format!("invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--ticks"    => options.ticks = value.parse().map_err(|_| invalid())?,
+            "--step"     => options.step = value.parse().map_err(|_| invalid())?,
+            "--seed"     => options.seed = Some(value.parse().map_err(|_| invalid())?),
+            "--radius"   => options.radius = value.parse().map_err(|_| invalid())?,
+            "--sector"   => options.sector = Some(parse_sector(&value).ok_or_else(invalid)?),
+            "--markets"  => options.markets = value.parse().map_err(|_| invalid())?,
+            "--traders"  => options.traders = value.parse().map_err(|_| invalid())?,
+            "--npcs"     => options.npcs = value.parse().map_err(|_| invalid())?,
+            "--factions" => options.factions = Some(value.parse().map_err(|_| invalid())?),
+            "--report"   => options.report = value.parse().map_err(|_| invalid())?,
+            _            => return Err(format!("unknown option {}", arg))
+        }
+    }
+    Ok(options)
+}
+
+/// Every generated value of a sector, at full precision, for comparing generation across builds and platforms.
+fn dump_sector(galaxy: &Galaxy, sector: Sector) {
+    println!("galaxy {:#018x} sector {},{},{} seed {:#018x}",
+             galaxy.seed, sector.x, sector.y, sector.z, galaxy.sector_seed(sector));
+    for galaxy_star in galaxy.sector_stars(sector) {
+        println!("{:?}", galaxy_star);
+        println!("  {:?}", galaxy_star.star());
+        for (planet, orbit) in planet::generate_planets(&galaxy_star.star(), galaxy_star.system_seed()) {
+            println!("    {:?}", planet);
+            println!("    {:?}", orbit);
+        }
+    }
+}
+
+fn dump(world: &mut World, origin: WorldPosition) {
+    if let Some(clock) = world.get_resource::<GameClock>() {
+        println!("time {} s, {}", clock.seconds, clock.date());
+    }
+
+    let mut planets: BTreeMap<Entity, Vec<(Planet, WorldPosition)>> = BTreeMap::new();
+    for (planet, parent, position) in world.query::<(&Planet, &Orbiting, &WorldPosition)>().iter(world) {
+        planets.entry(parent.0).or_default().push((*planet, *position));
+    }
+    let mut stars: Vec<(StarId, Entity, Star, WorldPosition)> = world.query::<(Entity, &StarId, &Star, &WorldPosition)>()
+        .iter(world)
+        .map(|(entity, id, star, position)| (*id, entity, *star, *position))
+        .collect();
+    stars.sort_by_key(|(id, _, _, _)| *id);
+
+    for (id, entity, star, position) in stars {
+        let at = position.relative_to(origin);
+        println!("star {},{},{}#{} {:?}{:?} {:.3} at ({:.0}, {:.0}, {:.0}) m",
+                 id.sector.x, id.sector.y, id.sector.z, id.index,
+                 star.spectral_type, star.luminosity_class,
+                 star.mass.to_scale(mass::Scale::SolarMass),
+                 at.x, at.y, at.z);
+        for (planet, planet_position) in planets.get(&entity).into_iter().flatten() {
+            let at = planet_position.relative_to(position);
+            println!("  planet {:?} {:.3} at ({:.0}, {:.0}, {:.0}) m",
+                     planet.kind, planet.mass.to_scale(mass::Scale::EarthMass), at.x, at.y, at.z);
+        }
+    }
+}
+
+/// Opens markets on the planets of the star nearest `origin`, each extracting everything its body yields
+/// and wanting what it does not, and starts traders between them. Returns the markets, innermost first.
+fn open_markets(world: &mut World, origin: WorldPosition, markets: usize, traders: usize) -> Vec<Entity> {
+    let star = world.query::<(Entity, &Star, &WorldPosition)>()
+        .iter(world)
+        .min_by(|(_, _, a), (_, _, b)| a.distance(origin).partial_cmp(&b.distance(origin)).unwrap_or(Ordering::Equal))
+        .map(|(entity, _, _)| entity);
+    let mut planets: Vec<(Entity, f64, Deposits)> = world.query::<(Entity, &Orbiting, &WorldPosition, &Deposits)>()
+        .iter(world)
+        .filter(|(_, parent, _, _)| Some(parent.0) == star)
+        .map(|(entity, _, position, deposits)| (entity, position.distance(origin).in_meters(), deposits.clone()))
+        .collect();
+    planets.sort_by(|(_, a, _), (_, b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    planets.truncate(markets);
+
+    let catalogue = world.get_resource::<Catalogue>().cloned().unwrap_or_default();
+    for (planet, _, deposits) in planets.iter() {
+        let mut industry = Industry::new(Power::MW(1000.0));
+        industry.extractors = (0..deposits.0.len())
+            .map(|deposit| Extractor {
+                deposit,
+                per_day: Mass::t(EXTRACTION_TONNES_PER_DAY),
+                power: Power::MW(10.0),
+                status: Status::Idle
+            })
+            .collect();
+        let market = catalogue.0.iter()
+            .filter(|info| deposits.0.iter().all(|deposit| deposit.commodity != info.commodity))
+            .fold(Market::new(&catalogue), |market, info| market.with_demand(&info.commodity, Mass::t(DEMAND_TONNES_PER_DAY)));
+        world.entity_mut(*planet).insert_bundle((industry, market));
+    }
+    if !planets.is_empty() {
+        for trader in 0..traders {
+            let at = planets[trader % planets.len()].0;
+            world.spawn().insert_bundle((
+                Trader { capacity: Mass::t(TRADER_CAPACITY_TONNES), at, voyage: None },
+                Credits(STARTING_CREDITS),
+                Manifest::default()
+            ));
+        }
+    }
+    planets.into_iter().map(|(planet, _, _)| planet).collect()
+}
+
+/// Spawns `count` ships of each role, flown by the behaviour of the same name, parked by the markets in turn,
+/// which are their homes.
+fn spawn_npcs(world: &mut World, markets: &[Entity], count: usize) {
+    if markets.is_empty() {
+        return;
+    }
+    let designs = (Ship::load(scene::asset_path(STARTER_SHIP)), Ship::load(scene::asset_path(WARSHIP)));
+    let (merchant, warship) = match designs {
+        (Ok(merchant), Ok(warship)) => (merchant, warship),
+        (Err(error), _) | (_, Err(error)) => {
+            eprintln!("could not load the NPCs' ships: {}", error);
+            return;
+        }
+    };
+    let now = world.get_resource::<GameClock>().map_or(0.0, |clock| clock.seconds);
+    let roles = [(Role::Trader, "trader"), (Role::Miner, "miner"), (Role::Pirate, "pirate"), (Role::Patrol, "patrol")];
+    let mut number = 0;
+    for (role, behaviour) in roles.iter() {
+        for _ in 0..count {
+            let home = markets[number % markets.len()];
+            number += 1;
+            let (mut position, velocity) = match (world.get::<WorldPosition>(home), world.get::<Orbit>(home)) {
+                (Some(position), Some(orbit)) => (*position, orbit.velocity_at(now)),
+                _                             => continue
+            };
+            // Spread about the market so no two start in the same place.
+            let angle = number as f64;
+            position.translate(DVec3::new(angle.cos(), 0.0, angle.sin()) * PARKING_METERS);
+            let mut blackboard = Blackboard::default();
+            blackboard.set("home", Value::Entity(home));
+            let ship = if matches!(role, Role::Pirate | Role::Patrol) { warship.clone() } else { merchant.clone() };
+            let mut npc = world.spawn();
+            npc.insert_bundle((ship, ShipControls::default(), Attitude::facing(velocity), Velocity(velocity), position))
+                .insert_bundle((Agent { behaviour: behaviour.to_string() }, *role, blackboard));
+            if matches!(role, Role::Trader | Role::Miner) {
+                npc.insert_bundle((Credits(STARTING_CREDITS), Manifest::default()));
+            }
+        }
+    }
+}
+
+/// What the NPCs have turned to since the last call.
+fn log_behaviour_events(world: &World, reader: &mut ManualEventReader<BehaviourEvent>) {
+    let (events, clock) = match (world.get_resource::<Events<BehaviourEvent>>(), world.get_resource::<GameClock>()) {
+        (Some(events), Some(clock)) => (events, clock),
+        _                           => return
+    };
+    for event in reader.iter(events) {
+        println!("{}  {}", clock.date(), event);
+    }
+}
+
+/// Every NPC still flying, with what it is doing and how it has fared.
+fn dump_npcs(world: &mut World) {
+    let mut npcs: Vec<(Entity, String)> = world.query::<(Entity, &Role, &Blackboard, &Ship, Option<&Credits>)>()
+        .iter(world)
+        .map(|(entity, role, blackboard, ship, credits)| (entity, format!("{:?} hull {:.0}% {}{}", role, ship.working(Subsystem::Hull) * 100.0,
+                                                                           blackboard.task.clone().unwrap_or_default(),
+                                                                           credits.map_or_else(String::new, |credits| format!(", {:.0} credits", credits.0)))))
+        .collect();
+    npcs.sort_by_key(|(entity, _)| *entity);
+    for (entity, line) in npcs {
+        println!("npc {:?} {}", entity, line);
+    }
+}
+
+/// Each market's stock and price of everything it lists, with the range its price has had.
+fn dump_markets(world: &mut World, markets: &[Entity]) {
+    for (number, entity) in markets.iter().enumerate() {
+        let mut query = world.query::<(&Planet, &Market, &Industry)>();
+        let (planet, market, industry) = match query.get(world, *entity) {
+            Ok(found) => found,
+            Err(_)    => continue
+        };
+        println!("market {} on {:?}", number, planet.kind);
+        for (commodity, listing) in market.listings.iter() {
+            let low = listing.history.iter().cloned().fold(f64::INFINITY, f64::min);
+            let high = listing.history.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            println!("  {:<12} {:>14.1} {:>10.2} ({:.2} to {:.2})",
+                     commodity.0, industry.stockpile.amount(commodity).to_scale(mass::Scale::Tonne), listing.price, low, high);
+        }
+    }
+    let mut credits: Vec<f64> = world.query::<(&Trader, &Credits)>().iter(world).map(|(_, credits)| credits.0).collect();
+    if !credits.is_empty() {
+        credits.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        println!("traders' credits {:.0} to {:.0}", credits[0], credits[credits.len() - 1]);
+    }
+}
+
+/// What the factions have done since the last call, as it happened.
+fn log_faction_events(world: &World, reader: &mut ManualEventReader<FactionEvent>) {
+    let (events, clock) = match (world.get_resource::<Events<FactionEvent>>(), world.get_resource::<GameClock>()) {
+        (Some(events), Some(clock)) => (events, clock),
+        _                           => return
+    };
+    for event in reader.iter(events) {
+        if !matches!(event, FactionEvent::OpinionChanged { .. }) {
+            println!("{}  {}", clock.date(), event);
+        }
+    }
+}
+
+/// Each faction's holdings and borders, and how it stands with the others.
+fn report_factions(world: &World) {
+    let (factions, borders) = match (world.get_resource::<Factions>(), world.get_resource::<Borders>()) {
+        (Some(factions), Some(borders)) => (factions, borders),
+        _                               => return
+    };
+    if let Some(clock) = world.get_resource::<GameClock>() {
+        println!("factions at {}", clock.date());
+    }
+    for faction in factions.factions.values() {
+        let relations: Vec<String> = factions.factions.keys()
+            .filter(|other| **other != faction.id)
+            .map(|other| {
+                let relation = factions.relation(faction.id, *other);
+                let treaties: Vec<String> = relation.treaties.iter().map(|treaty| format!("{:?}", treaty)).collect();
+                format!("{} {:+.0}{}{}", other.0, relation.opinion,
+                        if relation.war.is_some() { " war" } else { "" },
+                        if treaties.is_empty() { String::new() } else { format!(" {}", treaties.join("+")) })
+            })
+            .collect();
+        println!("  {} {:<28} {:>3} held {:>3} within borders  {}", faction.id.0, faction.name,
+                 factions.systems(faction.id).count(), borders.extent(faction.id), relations.join(", "));
+    }
+}
+
+/// Prints the settled worlds, most populous first.
+fn report_colonies(world: &mut World) {
+    let mut colonies: Vec<(Planet, Entity, Colony)> = world.query::<(&Planet, &Orbiting, &Colony)>()
+        .iter(world)
+        .map(|(planet, parent, colony)| (*planet, parent.0, colony.clone()))
+        .collect();
+    colonies.sort_by(|a, b| b.2.population.partial_cmp(&a.2.population).unwrap_or(Ordering::Equal));
+    let population: f64 = colonies.iter().map(|(_, _, colony)| colony.population).sum();
+    println!("colonies: {} worlds, {:.0} people", colonies.len(), population);
+    for (planet, parent, colony) in colonies.iter().take(5) {
+        let star = world.get::<StarId>(*parent).map_or_else(String::new, |id| id.to_string());
+        println!("  {:<10} {:<14} {}", format!("{:?}", planet.kind), star, colony);
+    }
+}
+
+/// The missions the markets have on offer, by market.
+fn report_missions(world: &World, markets: &[Entity]) {
+    let log = match world.get_resource::<MissionLog>() {
+        Some(log) => log,
+        None      => return
+    };
+    println!("missions: {} offered", log.with_status(MissionStatus::Offered).count());
+    for (number, market) in markets.iter().enumerate() {
+        for mission in log.offers(*market) {
+            println!("  market {} {}", number, mission);
+        }
+    }
+}
+
+/// Runs the universe headless for a number of fixed ticks, then prints every star and planet.
+fn main() {
+    let options = parse_options().unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(2);
+    });
+    let seed = options.seed.map_or_else(UniverseSeed::default, UniverseSeed);
+    let settings = UniverseSettings { radius: Length::ly(options.radius), ..UniverseSettings::from_seed(seed) };
+    if let Some(sector) = options.sector {
+        dump_sector(&settings.galaxy, sector);
+        return;
+    }
+    let origin = WorldPosition::from_position(settings.start);
+
+    let mut builder = plugins::headless_app(settings);
+    // Runs in lockstep, so the tick is simulated seconds per update rather than real time.
+    let mut clock = GameClock::default();
+    clock.tick = options.step;
+    // The update that generates the universe is also the first tick, so with none to run the clock starts paused.
+    clock.paused = options.ticks == 0;
+    builder.insert_resource(clock);
+    if let Some(count) = options.factions {
+        builder.insert_resource(FactionSettings { count });
+    }
+    let mut app = builder.app;
+    let mut faction_events = ManualEventReader::<FactionEvent>::default();
+    let mut behaviour_events = ManualEventReader::<BehaviourEvent>::default();
+    // The first update generates the universe, which markets need to open on.
+    app.update();
+    let markets = open_markets(&mut app.world, origin, options.markets, options.traders);
+    spawn_npcs(&mut app.world, &markets, options.npcs);
+    for tick in 1..options.ticks {
+        app.update();
+        if options.report > 0 {
+            log_faction_events(&app.world, &mut faction_events);
+            log_behaviour_events(&app.world, &mut behaviour_events);
+            if tick % options.report == 0 {
+                report_factions(&app.world);
+                report_colonies(&mut app.world);
+                report_missions(&app.world, &markets);
+            }
+        }
+    }
+    dump(&mut app.world, origin);
+    dump_markets(&mut app.world, &markets);
+    dump_npcs(&mut app.world);
+}
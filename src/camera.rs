@@ -1,8 +1,495 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::math::DVec3;
 use bevy::prelude::*;
+use bevy::render::camera::{Camera, CameraProjection, OrthographicProjection, PerspectiveProjection, ScalingMode};
 
-pub fn camera() -> bevy::prelude::OrthographicCameraBundle {
-    let mut camera = OrthographicCameraBundle::new_3d();
-    camera.orthographic_projection.scale = 3.0;
-    camera.transform = Transform::from_xyz(5.0, 5.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y);
-    camera
+use super::floating_origin::{FloatingOrigin, RenderScale, WorldPosition};
+use super::length;
+
+/// The closest the camera will zoom in.
+pub const MIN_DISTANCE_METERS: f64 = 1.0;
+/// The furthest the camera will zoom out, enough to frame the whole galaxy.
+pub const MAX_DISTANCE_METERS: f64 = 100.0 * length::KILOPARSECS_TO_METERS;
+/// Beyond this the camera switches to an orthographic map view.
+pub const ORTHOGRAPHIC_DISTANCE_METERS: f64 = length::KILOPARSECS_TO_METERS;
+/// Render space is rescaled so that the camera's focus is always this many units away.
+/// Together with the floating origin, this keeps f32 depth and lighting well behaved at every zoom level.
+pub const FOCUS_DISTANCE_UNITS: f64 = 10.0;
+pub const NEAR_PLANE_UNITS: f32 = 0.01;
+pub const FAR_PLANE_UNITS: f32 = 100_000.0;
+pub const FIELD_OF_VIEW: f32 = std::f32::consts::PI / 4.0;
+/// Keeps the camera off the poles, where looking at the focus is ill defined.
+pub const MAX_PITCH: f64 = 89.0 * std::f64::consts::PI / 180.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Circles a fixed point.
+    Orbit,
+    /// Flies freely, at a speed set by the zoom level.
+    FreeFly,
+    /// Circles an entity, moving with it.
+    Follow(Entity)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectionKind {
+    Perspective,
+    Orthographic
+}
+
+/// An in progress move of the camera's focus, eased over its duration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FocusTransition {
+    pub from: WorldPosition,
+    pub elapsed: f64,
+    pub duration: f64
+}
+
+pub struct CameraController {
+    pub mode: CameraMode,
+    /// What the camera looks at; for free flight, a point `distance` ahead.
+    pub focus: WorldPosition,
+    /// Radians around the vertical axis, and above the horizontal.
+    pub yaw: f64,
+    pub pitch: f64,
+    /// Meters from the focus. For free flight this sets the speed instead.
+    pub distance: f64,
+    pub target_distance: f64,
+    /// How quickly `distance` closes on `target_distance`, per second.
+    pub zoom_sharpness: f64,
+    pub transition: Option<FocusTransition>
+}
+
+impl CameraController {
+    pub fn orbiting(focus: WorldPosition, yaw: f64, pitch: f64, distance: f64) -> CameraController {
+        let distance = clamp_distance(distance);
+        CameraController {
+            mode: CameraMode::Orbit,
+            focus,
+            yaw,
+            pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH),
+            distance,
+            target_distance: distance,
+            zoom_sharpness: 8.0,
+            transition: None
+        }
+    }
+
+    pub fn eye(&self) -> WorldPosition {
+        let mut eye = self.focus;
+        eye.translate(orbit_offset(self.yaw, self.pitch, self.distance));
+        eye
+    }
+
+    pub fn projection(&self) -> ProjectionKind {
+        projection_for_distance(self.distance)
+    }
+
+    /// Starts an eased move of the focus; the new focus is applied by `update_camera`.
+    pub fn start_transition(&mut self, duration: f64) {
+        self.transition = Some(FocusTransition { from: self.focus, elapsed: 0.0, duration });
+    }
+}
+
+/// Keys and buttons that drive the camera.
+pub struct CameraBindings {
+    pub rotate: MouseButton,
+    pub forward: KeyCode,
+    pub back: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub zoom_in: KeyCode,
+    pub zoom_out: KeyCode,
+    pub boost: KeyCode,
+    pub orbit_mode: KeyCode,
+    pub free_fly_mode: KeyCode,
+    /// Radians per pixel of mouse movement.
+    pub rotate_sensitivity: f64,
+    /// The fraction the distance changes per scroll line.
+    pub zoom_sensitivity: f64
+}
+
+impl Default for CameraBindings {
+    fn default() -> CameraBindings {
+        CameraBindings {
+            rotate: MouseButton::Right,
+            forward: KeyCode::W,
+            back: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+            up: KeyCode::E,
+            down: KeyCode::Q,
+            zoom_in: KeyCode::Equals,
+            zoom_out: KeyCode::Minus,
+            boost: KeyCode::LShift,
+            orbit_mode: KeyCode::F1,
+            free_fly_mode: KeyCode::F2,
+            rotate_sensitivity: 0.005,
+            zoom_sensitivity: 0.15
+        }
+    }
+}
+
+/// Asks the camera to move its focus to an entity, following it afterwards if `follow` is set.
+pub struct FocusCamera {
+    pub target: Entity,
+    pub follow: bool
+}
+
+pub fn clamp_distance(distance: f64) -> f64 {
+    distance.clamp(MIN_DISTANCE_METERS, MAX_DISTANCE_METERS)
+}
+
+/// The vector from the focus to the eye.
+pub fn orbit_offset(yaw: f64, pitch: f64, distance: f64) -> DVec3 {
+    DVec3::new(distance * pitch.cos() * yaw.sin(),
+               distance * pitch.sin(),
+               distance * pitch.cos() * yaw.cos())
+}
+
+/// The direction the camera looks in.
+pub fn view_direction(yaw: f64, pitch: f64) -> DVec3 {
+    -orbit_offset(yaw, pitch, 1.0)
+}
+
+/// The camera rotation that looks along `view_direction`.
+pub fn view_rotation(yaw: f64, pitch: f64) -> Quat {
+    let direction = view_direction(yaw, pitch);
+    Transform::identity()
+        .looking_at(Vec3::new(direction.x as f32, direction.y as f32, direction.z as f32), Vec3::Y)
+        .rotation
+}
+
+/// Zooms by a number of scroll steps, positive zooming in.
+/// Zoom is multiplicative so that one step feels the same from metres to kiloparsecs.
+pub fn zoom(distance: f64, steps: f64, sensitivity: f64) -> f64 {
+    clamp_distance(distance * (1.0 + sensitivity).powf(-steps))
+}
+
+/// Moves `current` towards `target` exponentially in log space, which reads as smooth at every scale.
+pub fn smooth_distance(current: f64, target: f64, sharpness: f64, delta_seconds: f64) -> f64 {
+    let blend = 1.0 - (-sharpness * delta_seconds).exp();
+    (current.ln() + (target.ln() - current.ln()) * blend).exp()
+}
+
+/// Smoothstep easing over [0, 1].
+pub fn ease_in_out(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub fn interpolate(from: WorldPosition, to: WorldPosition, t: f64) -> WorldPosition {
+    let mut position = from;
+    position.translate(to.relative_to(from) * t);
+    position
+}
+
+pub fn projection_for_distance(distance: f64) -> ProjectionKind {
+    if distance >= ORTHOGRAPHIC_DISTANCE_METERS {
+        ProjectionKind::Orthographic
+    } else {
+        ProjectionKind::Perspective
+    }
+}
+
+pub fn render_scale_for_distance(distance: f64) -> RenderScale {
+    RenderScale { meters_per_unit: distance / FOCUS_DISTANCE_UNITS }
+}
+
+/// Free flight speed in meters per second, crossing the current zoom distance each second.
+pub fn free_fly_speed(distance: f64, boost: bool) -> f64 {
+    if boost { distance * 10.0 } else { distance }
+}
+
+/// Moves the camera in its own frame: x right, y up and z forward.
+pub fn free_fly_step(yaw: f64, pitch: f64, input: DVec3, speed: f64, delta_seconds: f64) -> DVec3 {
+    let forward = view_direction(yaw, pitch);
+    let right = forward.cross(DVec3::Y).normalize();
+    let up = right.cross(forward);
+    (right * input.x + up * input.y + forward * input.z) * speed * delta_seconds
+}
+
+pub fn perspective_projection() -> PerspectiveProjection {
+    PerspectiveProjection {
+        fov: FIELD_OF_VIEW,
+        near: NEAR_PLANE_UNITS,
+        far: FAR_PLANE_UNITS,
+        ..Default::default()
+    }
+}
+
+/// An orthographic projection framing the same height at the focus as `perspective_projection`.
+pub fn orthographic_projection() -> OrthographicProjection {
+    OrthographicProjection {
+        near: -FAR_PLANE_UNITS,
+        far: FAR_PLANE_UNITS,
+        scaling_mode: ScalingMode::FixedVertical,
+        scale: (FOCUS_DISTANCE_UNITS as f32) * (FIELD_OF_VIEW / 2.0).tan(),
+        ..Default::default()
+    }
+}
+
+/// Spawns the camera, which is also the floating origin.
+pub fn spawn_camera(commands: &mut Commands, controller: CameraController) -> Entity {
+    let mut camera = PerspectiveCameraBundle::new_3d();
+    camera.perspective_projection = perspective_projection();
+    camera.transform.rotation = view_rotation(controller.yaw, controller.pitch);
+    let eye = controller.eye();
+    commands.spawn_bundle(camera)
+        .insert(eye)
+        .insert(FloatingOrigin)
+        .insert(controller)
+        .id()
+}
+
+pub fn camera_input(
+    time: Res<Time>,
+    bindings: Res<CameraBindings>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut CameraController, &mut WorldPosition)>
+) {
+    let delta_seconds = time.delta_seconds_f64();
+    let mut rotation = Vec2::ZERO;
+    for event in motion.iter() {
+        rotation += event.delta;
+    }
+    let mut scroll = 0.0;
+    for event in wheel.iter() {
+        scroll += match event.unit {
+            MouseScrollUnit::Line => event.y as f64,
+            MouseScrollUnit::Pixel => event.y as f64 / 40.0
+        };
+    }
+    if keys.pressed(bindings.zoom_in) {
+        scroll += 10.0 * delta_seconds;
+    }
+    if keys.pressed(bindings.zoom_out) {
+        scroll -= 10.0 * delta_seconds;
+    }
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        (if keys.pressed(positive) { 1.0 } else { 0.0 }) - (if keys.pressed(negative) { 1.0 } else { 0.0 })
+    };
+    let movement = DVec3::new(axis(bindings.right, bindings.left),
+                              axis(bindings.up, bindings.down),
+                              axis(bindings.forward, bindings.back));
+
+    for (mut controller, mut eye) in cameras.iter_mut() {
+        if keys.just_pressed(bindings.orbit_mode) {
+            controller.mode = CameraMode::Orbit;
+        }
+        if keys.just_pressed(bindings.free_fly_mode) {
+            controller.mode = CameraMode::FreeFly;
+        }
+        if buttons.pressed(bindings.rotate) {
+            controller.yaw -= rotation.x as f64 * bindings.rotate_sensitivity;
+            controller.pitch = (controller.pitch + rotation.y as f64 * bindings.rotate_sensitivity)
+                .clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        controller.target_distance = zoom(controller.target_distance, scroll, bindings.zoom_sensitivity);
+        if controller.mode == CameraMode::FreeFly && movement != DVec3::ZERO {
+            let speed = free_fly_speed(controller.distance, keys.pressed(bindings.boost));
+            eye.translate(free_fly_step(controller.yaw, controller.pitch, movement, speed, delta_seconds));
+        }
+    }
+}
+
+pub fn focus_camera(
+    mut requests: EventReader<FocusCamera>,
+    targets: Query<&WorldPosition, Without<CameraController>>,
+    mut cameras: Query<&mut CameraController>
+) {
+    for request in requests.iter() {
+        if let Ok(target) = targets.get(request.target) {
+            for mut controller in cameras.iter_mut() {
+                controller.start_transition(1.5);
+                controller.focus = *target;
+                controller.mode = if request.follow { CameraMode::Follow(request.target) } else { CameraMode::Orbit };
+            }
+        }
+    }
+}
+
+/// Moves each camera according to its mode, and rescales render space to its zoom level.
+pub fn update_camera(
+    time: Res<Time>,
+    mut render_scale: ResMut<RenderScale>,
+    targets: Query<&WorldPosition, Without<CameraController>>,
+    mut cameras: Query<(&mut CameraController, &mut WorldPosition, &mut Transform)>
+) {
+    let delta_seconds = time.delta_seconds_f64();
+    for (mut controller, mut eye, mut transform) in cameras.iter_mut() {
+        controller.distance = smooth_distance(controller.distance, controller.target_distance,
+                                              controller.zoom_sharpness, delta_seconds);
+        if let CameraMode::Follow(target) = controller.mode {
+            match targets.get(target) {
+                Ok(position) => controller.focus = *position,
+                Err(_) => controller.mode = CameraMode::Orbit
+            }
+        }
+        let focus = match controller.transition {
+            Some(mut transition) => {
+                transition.elapsed += delta_seconds;
+                let t = ease_in_out(transition.elapsed / transition.duration);
+                controller.transition = if t < 1.0 { Some(transition) } else { None };
+                interpolate(transition.from, controller.focus, t)
+            }
+            None => controller.focus
+        };
+        match controller.mode {
+            CameraMode::FreeFly => {
+                // Keep the focus ahead of us, so returning to orbit circles what we were looking at.
+                let ahead = view_direction(controller.yaw, controller.pitch) * controller.distance;
+                controller.focus = *eye;
+                controller.focus.translate(ahead);
+            }
+            CameraMode::Orbit | CameraMode::Follow(_) => {
+                *eye = focus;
+                eye.translate(orbit_offset(controller.yaw, controller.pitch, controller.distance));
+            }
+        }
+        transform.rotation = view_rotation(controller.yaw, controller.pitch);
+        *render_scale = render_scale_for_distance(controller.distance);
+    }
+}
+
+type ProjectedCamera<'a> = (Entity, &'a CameraController, &'a mut Camera,
+                            Option<&'a PerspectiveProjection>, Option<&'a OrthographicProjection>);
+
+/// Swaps cameras between perspective and orthographic projections as they zoom across `ORTHOGRAPHIC_DISTANCE_METERS`.
+pub fn switch_projection(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    mut cameras: Query<ProjectedCamera>
+) {
+    for (entity, controller, mut camera, perspective, orthographic) in cameras.iter_mut() {
+        let (width, height) = windows.get(camera.window).map_or((1.0, 1.0), |window| (window.width(), window.height()));
+        match (controller.projection(), perspective, orthographic) {
+            (ProjectionKind::Orthographic, Some(_), _) => {
+                let mut projection = orthographic_projection();
+                projection.update(width, height);
+                camera.projection_matrix = projection.get_projection_matrix();
+                camera.depth_calculation = projection.depth_calculation();
+                commands.entity(entity).remove::<PerspectiveProjection>().insert(projection);
+            }
+            (ProjectionKind::Perspective, _, Some(_)) => {
+                let mut projection = perspective_projection();
+                projection.update(width, height);
+                camera.projection_matrix = projection.get_projection_matrix();
+                camera.depth_calculation = projection.depth_calculation();
+                commands.entity(entity).remove::<OrthographicProjection>().insert(projection);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum CameraSystem {
+    Input,
+    Focus,
+    Update
+}
+
+/// Orbit, free flight and follow cameras spanning metres to kiloparsecs.
+/// Needs the `FloatingOriginPlugin`, as the camera is the floating origin.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CameraBindings>()
+            .add_event::<FocusCamera>()
+            .add_system(camera_input.system().label(CameraSystem::Input))
+            .add_system(focus_camera.system().label(CameraSystem::Focus).after(CameraSystem::Input))
+            .add_system(update_camera.system().label(CameraSystem::Update).after(CameraSystem::Focus))
+            .add_system(switch_projection.system().after(CameraSystem::Update));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn orbit_offset_is_distance_away_against_the_view() {
+        let (yaw, pitch) = (0.7, -0.3);
+        let offset = orbit_offset(yaw, pitch, 250.0);
+        assert!(close(offset.length(), 250.0));
+        assert!(close(view_direction(yaw, pitch).dot(offset), -250.0));
+        assert!(close(orbit_offset(0.0, MAX_PITCH, 1.0).y, MAX_PITCH.sin()));
+    }
+
+    #[test]
+    fn eye_is_distance_from_focus() {
+        let controller = CameraController::orbiting(WorldPosition::from_meters(DVec3::new(3.0e12, 0.0, -1.0e12)), 1.0, 2.0, 1.0e9);
+        assert_eq!(controller.pitch, MAX_PITCH);
+        assert!(close(controller.eye().relative_to(controller.focus).length(), 1.0e9));
+    }
+
+    #[test]
+    fn zoom_is_multiplicative_and_clamped() {
+        let distance = 1.0e6;
+        assert!(close(zoom(zoom(distance, 3.0, 0.1), -3.0, 0.1), distance));
+        assert!(close(zoom(distance, 1.0, 0.1) / distance, zoom(1.0e15, 1.0, 0.1) / 1.0e15));
+        assert_eq!(zoom(MIN_DISTANCE_METERS, 5.0, 0.1), MIN_DISTANCE_METERS);
+        assert_eq!(zoom(MAX_DISTANCE_METERS, -5.0, 0.1), MAX_DISTANCE_METERS);
+    }
+
+    #[test]
+    fn smooth_distance_closes_without_overshooting() {
+        let mut distance = 1.0;
+        for _ in 0..120 {
+            let next = smooth_distance(distance, 1.0e18, 8.0, 1.0 / 60.0);
+            assert!(next > distance && next < 1.0e18);
+            distance = next;
+        }
+        assert!((distance.ln() - 1.0e18f64.ln()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn easing_and_interpolation_reach_their_ends() {
+        assert_eq!(ease_in_out(-1.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(2.0), 1.0);
+        let from = WorldPosition::from_meters(DVec3::new(-5.0e12, 0.0, 0.0));
+        let to = WorldPosition::from_meters(DVec3::new(5.0e12, 2.0, 0.0));
+        assert!(interpolate(from, to, 1.0).relative_to(to).length() < 1e-3);
+        assert!(interpolate(from, to, 0.5).relative_to(WorldPosition::from_meters(DVec3::new(0.0, 1.0, 0.0))).length() < 1e-3);
+    }
+
+    #[test]
+    fn projection_switches_at_the_map_distance() {
+        assert_eq!(projection_for_distance(ORTHOGRAPHIC_DISTANCE_METERS * 0.99), ProjectionKind::Perspective);
+        assert_eq!(projection_for_distance(ORTHOGRAPHIC_DISTANCE_METERS), ProjectionKind::Orthographic);
+    }
+
+    #[test]
+    fn focus_stays_inside_the_depth_range_at_every_zoom() {
+        for distance in [MIN_DISTANCE_METERS, length::AU_TO_METERS, MAX_DISTANCE_METERS].iter() {
+            let focus = distance / render_scale_for_distance(*distance).meters_per_unit;
+            assert!(close(focus, FOCUS_DISTANCE_UNITS));
+            assert!(focus > NEAR_PLANE_UNITS as f64 && focus < FAR_PLANE_UNITS as f64);
+        }
+        let half_height = FOCUS_DISTANCE_UNITS as f32 * (FIELD_OF_VIEW / 2.0).tan();
+        assert!((orthographic_projection().scale - half_height).abs() < 1e-6);
+    }
+
+    #[test]
+    fn free_flight_moves_in_the_camera_frame() {
+        let (yaw, pitch) = (0.4, 0.2);
+        let forward = free_fly_step(yaw, pitch, DVec3::new(0.0, 0.0, 1.0), 10.0, 0.5);
+        assert!(close(forward.length(), 5.0));
+        assert!(close(forward.normalize().dot(view_direction(yaw, pitch)), 1.0));
+        let right = free_fly_step(yaw, pitch, DVec3::new(1.0, 0.0, 0.0), 10.0, 0.5);
+        assert!(right.dot(forward).abs() < 1e-9 && right.y.abs() < 1e-9);
+        assert_eq!(free_fly_speed(100.0, true), 1_000.0);
+    }
 }
@@ -0,0 +1,412 @@
+use bevy::ecs::component::Component;
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use super::star::Star;
+
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+/// A Julian year, the unit of stellar ages.
+pub const SECONDS_PER_YEAR: f64 = 365.25 * SECONDS_PER_DAY;
+/// The Julian date of J2000.0, 2000 January 1 at 12:00 TT, the default epoch.
+pub const J2000: f64 = 2_451_545.0;
+/// Real seconds per simulation tick.
+pub const DEFAULT_TICK: f64 = 1.0 / 60.0;
+/// At most this many ticks run per frame; a slower machine falls behind real time rather than spiralling.
+pub const MAX_TICKS_PER_FRAME: u32 = 8;
+/// The warp multipliers the player steps through, up to those for interstellar cruises.
+pub const WARP_LEVELS: [f64; 8] = [1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0, 10_000_000.0];
+
+/// The simulation's clock. Simulated time runs in fixed ticks, decoupled from the frame rate,
+/// each advancing it by `tick` real seconds times the `warp`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameClock {
+    /// Simulated seconds since the epoch.
+    pub seconds: f64,
+    /// The Julian date at which `seconds` is zero.
+    pub epoch: f64,
+    pub paused: bool,
+    pub warp: f64,
+    pub tick: f64,
+    /// Ticks run since the clock started, paused or not.
+    pub ticks: u64,
+    #[serde(skip)]
+    accumulator: f64,
+    #[serde(skip)]
+    looping: bool
+}
+
+impl Default for GameClock {
+    fn default() -> GameClock {
+        GameClock {
+            seconds: 0.0,
+            epoch: J2000,
+            paused: false,
+            warp: 1.0,
+            tick: DEFAULT_TICK,
+            ticks: 0,
+            accumulator: 0.0,
+            looping: false
+        }
+    }
+}
+
+impl GameClock {
+    /// Simulated seconds each tick advances the clock by.
+    pub fn tick_seconds(&self) -> f64 {
+        if self.paused { 0.0 } else { self.tick * self.warp }
+    }
+
+    pub fn julian_date(&self) -> f64 {
+        self.epoch + self.seconds / SECONDS_PER_DAY
+    }
+
+    pub fn date(&self) -> CalendarDate {
+        CalendarDate::from_julian_date(self.julian_date())
+    }
+
+    /// Steps to the next warp level above the current warp, if there is one.
+    pub fn warp_up(&mut self) {
+        if let Some(warp) = WARP_LEVELS.iter().find(|warp| **warp > self.warp) {
+            self.warp = *warp;
+        }
+    }
+
+    pub fn warp_down(&mut self) {
+        if let Some(warp) = WARP_LEVELS.iter().rev().find(|warp| **warp < self.warp) {
+            self.warp = *warp;
+        }
+    }
+}
+
+/// A Gregorian calendar date and time of day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalendarDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64
+}
+
+impl CalendarDate {
+    /// Meeus, Astronomical Algorithms, chapter 7; Gregorian throughout, proleptically before 1582.
+    pub fn from_julian_date(julian_date: f64) -> CalendarDate {
+        let shifted = julian_date + 0.5;
+        let z = shifted.floor();
+        let fraction = shifted - z;
+        let alpha = ((z - 1_867_216.25) / 36_524.25).floor();
+        let a = z + 1.0 + alpha - (alpha / 4.0).floor();
+        let b = a + 1524.0;
+        let c = ((b - 122.1) / 365.25).floor();
+        let d = (365.25 * c).floor();
+        let e = ((b - d) / 30.6001).floor();
+        let day = b - d - (30.6001 * e).floor();
+        let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+        let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+        let seconds = fraction * SECONDS_PER_DAY;
+        CalendarDate {
+            year: year as i64,
+            month: month as u32,
+            day: day as u32,
+            hour: (seconds / 3600.0) as u32,
+            minute: (seconds % 3600.0 / 60.0) as u32,
+            second: seconds % 60.0
+        }
+    }
+
+    pub fn julian_date(&self) -> f64 {
+        let (year, month) = if self.month > 2 { (self.year as f64, self.month as f64) } else { (self.year as f64 - 1.0, self.month as f64 + 12.0) };
+        let a = (year / 100.0).floor();
+        let b = 2.0 - a + (a / 4.0).floor();
+        let day = self.day as f64 + (self.hour as f64 * 3600.0 + self.minute as f64 * 60.0 + self.second) / SECONDS_PER_DAY;
+        (365.25 * (year + 4716.0)).floor() + (30.6001 * (month + 1.0)).floor() + day + b - 1524.5
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second.floor())
+    }
+}
+
+/// Makes each app update run exactly one tick, whatever the real time elapsed.
+/// Headless runs use this so that a number of updates always simulates the same span.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lockstep;
+
+/// Runs the simulation stage once per tick due.
+pub fn tick_due(time: Res<Time>, lockstep: Option<Res<Lockstep>>, mut clock: ResMut<GameClock>) -> ShouldRun {
+    if lockstep.is_some() {
+        return ShouldRun::Yes;
+    }
+    if !clock.looping {
+        let limit = clock.tick * MAX_TICKS_PER_FRAME as f64;
+        clock.accumulator = (clock.accumulator + time.delta_seconds_f64()).min(limit);
+    }
+    if clock.accumulator >= clock.tick {
+        clock.accumulator -= clock.tick;
+        clock.looping = true;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        clock.looping = false;
+        ShouldRun::No
+    }
+}
+
+pub fn advance_clock(mut clock: ResMut<GameClock>) {
+    clock.seconds += clock.tick_seconds();
+    clock.ticks += 1;
+}
+
+pub fn age_stars(clock: Res<GameClock>, mut stars: Query<&mut Star>) {
+    let years = clock.tick_seconds() / SECONDS_PER_YEAR;
+    if years > 0.0 {
+        for mut star in stars.iter_mut() {
+            star.age += years;
+        }
+    }
+}
+
+struct Pending<T> {
+    at: f64,
+    sequence: u64,
+    event: T
+}
+
+impl<T> PartialEq for Pending<T> {
+    fn eq(&self, other: &Pending<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Pending<T> {}
+
+impl<T> PartialOrd for Pending<T> {
+    fn partial_cmp(&self, other: &Pending<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Pending<T> {
+    /// Reversed, so the heap pops the earliest event first; ties go in the order they were scheduled.
+    fn cmp(&self, other: &Pending<T>) -> Ordering {
+        other.at.partial_cmp(&self.at).unwrap_or(Ordering::Equal)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// Events of type `T` waiting for the clock to reach their time.
+pub struct Scheduled<T> {
+    pending: BinaryHeap<Pending<T>>,
+    sequence: u64
+}
+
+impl<T> Default for Scheduled<T> {
+    fn default() -> Scheduled<T> {
+        Scheduled { pending: BinaryHeap::new(), sequence: 0 }
+    }
+}
+
+impl<T> Scheduled<T> {
+    /// Sends `event` on the first tick at or after `at`, in simulated seconds since the epoch.
+    pub fn at(&mut self, at: f64, event: T) {
+        self.sequence += 1;
+        self.pending.push(Pending { at, sequence: self.sequence, event });
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// When the next event is due.
+    pub fn next_at(&self) -> Option<f64> {
+        self.pending.peek().map(|pending| pending.at)
+    }
+
+    fn pop_due(&mut self, now: f64) -> Option<T> {
+        if self.next_at()? <= now {
+            self.pending.pop().map(|pending| pending.event)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn dispatch_scheduled<T: Component>(clock: Res<GameClock>, mut scheduled: ResMut<Scheduled<T>>, mut events: EventWriter<T>) {
+    while let Some(event) = scheduled.pop_due(clock.seconds) {
+        events.send(event);
+    }
+}
+
+/// The stage simulation ticks run in, zero or more times a frame, before `CoreStage::Update`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub struct SimulationStage;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum ClockSystem {
+    Advance,
+    Dispatch
+}
+
+pub trait ScheduleEvents {
+    /// Adds the event `T`, which can also be scheduled for a time through the `Scheduled<T>` resource.
+    fn add_scheduled_event<T: Component>(&mut self) -> &mut Self;
+}
+
+impl ScheduleEvents for AppBuilder {
+    fn add_scheduled_event<T: Component>(&mut self) -> &mut Self {
+        self.add_event::<T>()
+            .init_resource::<Scheduled<T>>()
+            .add_system_to_stage(SimulationStage, dispatch_scheduled::<T>.system()
+                                 .label(ClockSystem::Dispatch)
+                                 .after(ClockSystem::Advance))
+    }
+}
+
+/// The game clock, its fixed tick simulation stage, and stellar aging.
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameClock>()
+            .add_stage_before(CoreStage::Update, SimulationStage,
+                              SystemStage::parallel().with_run_criteria(tick_due.system()))
+            .add_system_to_stage(SimulationStage, advance_clock.system().label(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, age_stars.system().after(ClockSystem::Advance));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::{Events, ManualEventReader};
+    use std::time::Duration;
+
+    fn date(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: f64) -> CalendarDate {
+        CalendarDate { year, month, day, hour, minute, second }
+    }
+
+    #[test]
+    fn julian_dates_convert_to_the_calendar_and_back() {
+        let known = [
+            (J2000, date(2000, 1, 1, 12, 0, 0.0)),
+            (2_451_179.5, date(1999, 1, 1, 0, 0, 0.0)),
+            (2_436_116.31, date(1957, 10, 4, 19, 26, 24.0)),
+            (2_299_160.5, date(1582, 10, 15, 0, 0, 0.0)),
+            (2_451_603.5, date(2000, 2, 29, 0, 0, 0.0)),
+            (2_488_128.5, date(2100, 3, 1, 0, 0, 0.0))
+        ];
+        for (julian_date, expected) in known.iter() {
+            let converted = CalendarDate::from_julian_date(*julian_date);
+            assert_eq!((converted.year, converted.month, converted.day, converted.hour, converted.minute),
+                       (expected.year, expected.month, expected.day, expected.hour, expected.minute), "JD {}", julian_date);
+            assert!((converted.second - expected.second).abs() < 1.0e-3, "JD {}", julian_date);
+            assert!((expected.julian_date() - julian_date).abs() < 1.0e-8, "{}", expected);
+        }
+
+        // 2000 and 1600 have a leap day, 1900 and 2100 none.
+        for (year, leap) in [(1600, true), (1900, false), (2000, true), (2023, false), (2024, true), (2100, false)].iter() {
+            let next = CalendarDate::from_julian_date(date(*year, 2, 28, 0, 0, 0.0).julian_date() + 1.0);
+            assert_eq!((next.month, next.day), if *leap { (2, 29) } else { (3, 1) }, "{}", year);
+        }
+
+        // Every day across four centuries, at a few times of day.
+        let start = date(1800, 1, 1, 0, 0, 0.0).julian_date();
+        for day in (0..146_097).step_by(7) {
+            for hours in [0.0, 6.5, 23.75].iter() {
+                let julian_date = start + day as f64 + hours / 24.0;
+                let back = CalendarDate::from_julian_date(julian_date).julian_date();
+                assert!((back - julian_date).abs() < 1.0e-8, "JD {} came back as {}", julian_date, back);
+            }
+        }
+        assert_eq!(GameClock::default().date().to_string(), "2000-01-01 12:00:00");
+    }
+
+    fn ticking(tick: f64) -> (World, SystemStage) {
+        let mut world = World::default();
+        world.insert_resource(GameClock { tick, ..GameClock::default() });
+        world.insert_resource(Time::default());
+        let stage = SystemStage::single_threaded()
+            .with_run_criteria(tick_due.system())
+            .with_system(advance_clock.system());
+        (world, stage)
+    }
+
+    fn ticks(world: &World) -> u64 {
+        world.get_resource::<GameClock>().unwrap().ticks
+    }
+
+    #[test]
+    fn ticks_run_as_real_time_passes_but_not_without_bound() {
+        // A tick of a 64th of a second, so eight of them add up exactly.
+        let (mut world, mut stage) = ticking(1.0 / 64.0);
+        world.get_resource_mut::<Time>().unwrap().update();
+        stage.run(&mut world);
+        assert_eq!(ticks(&world), 0);
+
+        // A frame long enough for more than eight ticks runs only eight.
+        std::thread::sleep(Duration::from_millis(200));
+        world.get_resource_mut::<Time>().unwrap().update();
+        stage.run(&mut world);
+        assert_eq!(ticks(&world), MAX_TICKS_PER_FRAME as u64);
+        assert_eq!(world.get_resource::<GameClock>().unwrap().seconds, MAX_TICKS_PER_FRAME as f64 / 64.0);
+
+        // A frame shorter than a tick runs none, and is carried over.
+        let (mut world, mut stage) = ticking(3600.0);
+        world.get_resource_mut::<Time>().unwrap().update();
+        std::thread::sleep(Duration::from_millis(10));
+        world.get_resource_mut::<Time>().unwrap().update();
+        stage.run(&mut world);
+        assert_eq!(ticks(&world), 0);
+        assert!(world.get_resource::<GameClock>().unwrap().accumulator >= 0.01);
+    }
+
+    #[test]
+    fn lockstep_runs_one_tick_an_update() {
+        let (mut world, mut stage) = ticking(3600.0);
+        world.insert_resource(Lockstep);
+        for update in 1..=5 {
+            stage.run(&mut world);
+            assert_eq!(ticks(&world), update);
+        }
+        assert_eq!(world.get_resource::<GameClock>().unwrap().seconds, 5.0 * 3600.0);
+    }
+
+    #[test]
+    fn scheduled_events_are_sent_in_order_when_due() {
+        let (mut world, _) = ticking(10.0);
+        world.insert_resource(Lockstep);
+        world.insert_resource(Events::<u32>::default());
+        let mut scheduled = Scheduled::<u32>::default();
+        for (at, event) in [(30.0, 4), (10.0, 1), (25.0, 3), (10.0, 2), (45.0, 5)].iter() {
+            scheduled.at(*at, *event);
+        }
+        assert_eq!((scheduled.len(), scheduled.next_at()), (5, Some(10.0)));
+        world.insert_resource(scheduled);
+        let mut stage = SystemStage::single_threaded()
+            .with_run_criteria(tick_due.system())
+            .with_system(advance_clock.system().label(ClockSystem::Advance))
+            .with_system(dispatch_scheduled::<u32>.system().after(ClockSystem::Advance));
+
+        let mut reader = ManualEventReader::<u32>::default();
+        let mut sent = Vec::new();
+        for _ in 0..4 {
+            stage.run(&mut world);
+            sent.push(reader.iter(world.get_resource::<Events<u32>>().unwrap()).copied().collect::<Vec<_>>());
+        }
+        // Ties go in the order they were scheduled, and late events on the first tick after their time.
+        assert_eq!(sent, vec![vec![1, 2], vec![], vec![3, 4], vec![]]);
+        let scheduled = world.get_resource::<Scheduled<u32>>().unwrap();
+        assert_eq!((scheduled.len(), scheduled.next_at()), (1, Some(45.0)));
+    }
+}
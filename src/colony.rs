@@ -0,0 +1,460 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::astronomy::Orbiting;
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY, SECONDS_PER_YEAR};
+use super::floating_origin::WorldPosition;
+use super::force::STANDARD_GRAVITY;
+use super::length;
+use super::length::Length;
+use super::orbit::{Orbit, GRAVITATIONAL_CONSTANT};
+use super::planet::{Composition, Planet, PlanetKind};
+use super::power;
+use super::radiation::{mean_flux_distance, Irradiation, STEFAN_BOLTZMANN_CONSTANT};
+use super::star::Star;
+use super::temperature::Temperature;
+
+/// The fraction of starlight a planet reflects, Earth's.
+pub const PLANET_ALBEDO: f64 = 0.3;
+/// Earth's escape velocity and equilibrium temperature, which hold on to about a bar of air.
+pub const EARTH_ESCAPE_VELOCITY: f64 = 11_186.0;
+pub const EARTH_EQUILIBRIUM_KELVIN: f64 = 255.0;
+/// The surface pressure, in bar, a body of one Earth mass keeps per unit fraction of volatiles it is made of.
+pub const VOLATILE_PRESSURE: f64 = 50.0;
+/// The greenhouse warming of a bar of Earth-like air, in kelvin.
+pub const GREENHOUSE_KELVIN_PER_BAR: f64 = 33.0;
+/// The people an Earth-sized surface that is perfectly habitable supports, and feeds, without help.
+pub const NATURAL_CAPACITY: f64 = 1.0e10;
+/// Planets at least this habitable are settled when the universe is generated, with this fraction of what they support.
+pub const SETTLED_HABITABILITY: f64 = 0.8;
+pub const SETTLED_FRACTION: f64 = 1.0e-3;
+/// The growth rate of a contented, healthy colony with room to grow, per year.
+pub const BASE_GROWTH: f64 = 0.02;
+/// Happiness relaxes towards what a colony's conditions warrant over about this long.
+pub const HAPPINESS_SECONDS: f64 = 30.0 * SECONDS_PER_DAY;
+/// How content people are living in sealed habitats, whatever is outside.
+pub const HABITAT_COMFORT: f64 = 0.6;
+/// The fraction of a colony that moves each year to one that is wholly more attractive, within about `MIGRATION_RANGE_LY`.
+pub const MIGRATION_RATE: f64 = 0.05;
+pub const MIGRATION_RANGE_LY: f64 = 10.0;
+
+/// What a world's surface is like to live on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// At the surface, in m s⁻².
+    pub gravity: f64,
+    /// The mean at the surface, with the greenhouse warming.
+    pub temperature: Temperature,
+    /// At the surface, in bar; infinite for giants, which have no surface.
+    pub pressure: f64
+}
+
+impl Environment {
+    /// A planet's surface, lit by `irradiation` in W m⁻², with an atmosphere of what volatiles its mass can hold at that warmth.
+    pub fn of(planet: &Planet, composition: &Composition, irradiation: f64) -> Environment {
+        let mass = planet.mass.in_kilograms();
+        let radius = planet.radius.in_meters();
+        let gravity = GRAVITATIONAL_CONSTANT * mass / (radius * radius);
+        let equilibrium = (irradiation * (1.0 - PLANET_ALBEDO) / (4.0 * STEFAN_BOLTZMANN_CONSTANT)).powf(0.25);
+        let pressure = match planet.kind {
+            PlanetKind::Rocky | PlanetKind::SuperEarth => {
+                let escape_velocity = (2.0 * GRAVITATIONAL_CONSTANT * mass / radius).sqrt();
+                let retention = ((escape_velocity / EARTH_ESCAPE_VELOCITY).powi(2)
+                                 * EARTH_EQUILIBRIUM_KELVIN / equilibrium.max(1.0)).min(1.0);
+                (composition.ices + composition.gases) * VOLATILE_PRESSURE * planet.mass.in_earth_masses() * retention.powi(4)
+            }
+            PlanetKind::IceGiant | PlanetKind::GasGiant => f64::INFINITY
+        };
+        let greenhouse = GREENHOUSE_KELVIN_PER_BAR * pressure.min(1000.0).sqrt();
+        Environment { gravity, temperature: Temperature::K((equilibrium + greenhouse) as f32), pressure }
+    }
+
+    pub fn has_surface(&self) -> bool {
+        self.pressure.is_finite()
+    }
+
+    pub fn habitability(&self) -> Habitability {
+        Habitability::of(self)
+    }
+}
+
+fn falloff(value: f64, comfortable: std::ops::RangeInclusive<f64>, bearable: std::ops::RangeInclusive<f64>) -> f64 {
+    if comfortable.contains(&value) {
+        1.0
+    } else if value < *comfortable.start() {
+        ((value - bearable.start()) / (comfortable.start() - bearable.start())).clamp(0.0, 1.0)
+    } else {
+        ((bearable.end() - value) / (bearable.end() - comfortable.end())).clamp(0.0, 1.0)
+    }
+}
+
+/// How well people can live in the open on a world, as factors from nothing to one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Habitability {
+    pub gravity: f64,
+    pub temperature: f64,
+    pub atmosphere: f64
+}
+
+impl Habitability {
+    /// Comfortable from 0.7 to 1.3 g, and unbearable below 0.1 g, where bones waste, and above 3 g.
+    /// Comfortable from freezing to 40 °C, and unbearable at -70 °C or boiling.
+    /// Comfortable from half a bar to three, and unbearable in vacuum or a crushing 50.
+    pub fn of(environment: &Environment) -> Habitability {
+        let kelvin = environment.temperature.in_kelvin() as f64;
+        Habitability {
+            gravity: falloff(environment.gravity / STANDARD_GRAVITY, 0.7..=1.3, 0.1..=3.0),
+            temperature: falloff(kelvin, 273.0..=313.0, 203.0..=373.0),
+            atmosphere: if environment.has_surface() { falloff(environment.pressure, 0.5..=3.0, 0.0..=50.0) } else { 0.0 }
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.gravity * self.temperature * self.atmosphere
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Building {
+    /// Sealed housing, for worlds too harsh to live on in the open.
+    Habitat,
+    /// Hydroponics, feeding people the land cannot.
+    Farm,
+    Hospital,
+    /// Parks, arenas and theatres.
+    Leisure,
+    /// Lets people come and go, to and from other colonies.
+    Spaceport
+}
+
+impl Building {
+    /// People housed, fed, cared for, entertained, or moved each year, per building.
+    pub fn serves(self) -> f64 {
+        match self {
+            Building::Habitat   => 1.0e5,
+            Building::Farm      => 1.0e5,
+            Building::Hospital  => 1.0e6,
+            Building::Leisure   => 1.0e6,
+            Building::Spaceport => 1.0e5
+        }
+    }
+
+    /// Seconds to build.
+    pub fn build_time(self) -> f64 {
+        match self {
+            Building::Habitat   => 180.0 * SECONDS_PER_DAY,
+            Building::Farm      => 90.0 * SECONDS_PER_DAY,
+            Building::Hospital  => 365.0 * SECONDS_PER_DAY,
+            Building::Leisure   => 120.0 * SECONDS_PER_DAY,
+            Building::Spaceport => 730.0 * SECONDS_PER_DAY
+        }
+    }
+}
+
+/// People living on a world, and what they have built there.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Colony {
+    pub population: f64,
+    /// From nothing to one.
+    pub happiness: f64,
+    pub buildings: BTreeMap<Building, u32>,
+    /// Buildings going up, with when they are finished, in seconds since the epoch.
+    pub construction: Vec<(Building, f64)>,
+    /// The people the world and the colony's buildings can house and feed, as of the last tick.
+    pub capacity: f64,
+    /// People arriving, less those leaving, per year, as of the last tick.
+    pub migration: f64
+}
+
+impl Colony {
+    pub fn new(population: f64) -> Colony {
+        Colony {
+            population,
+            happiness: 0.5,
+            buildings: BTreeMap::new(),
+            construction: Vec::new(),
+            capacity: 0.0,
+            migration: 0.0
+        }
+    }
+
+    pub fn count(&self, building: Building) -> u32 {
+        self.buildings.get(&building).copied().unwrap_or(0)
+    }
+
+    fn served(&self, building: Building) -> f64 {
+        self.count(building) as f64 * building.serves()
+    }
+
+    /// The fraction of the colony a kind of building serves.
+    pub fn coverage(&self, building: Building) -> f64 {
+        if self.population > 0.0 { (self.served(building) / self.population).min(1.0) } else { 1.0 }
+    }
+
+    /// How many the world can take, housed and fed, given how much of its surface is habitable.
+    pub fn supportable(&self, natural: f64) -> f64 {
+        (natural + self.served(Building::Habitat)).min(natural + self.served(Building::Farm))
+    }
+
+    /// What the colony's happiness tends to: comfort, leisure, health and room to live, most to least.
+    pub fn contentment(&self, habitability: f64, natural: f64) -> f64 {
+        let in_open = if self.population > 0.0 { (natural / self.population).min(1.0) } else { 1.0 };
+        let comfort = in_open * habitability + (1.0 - in_open) * HABITAT_COMFORT;
+        let crowding = if self.capacity > 0.0 { ((self.population / self.capacity - 0.8) / 0.2).clamp(0.0, 1.0) } else { 1.0 };
+        0.35 * comfort + 0.25 * self.coverage(Building::Leisure) + 0.2 * self.coverage(Building::Hospital) + 0.2 * (1.0 - crowding)
+    }
+
+    /// How much people elsewhere would like to move here.
+    pub fn attraction(&self) -> f64 {
+        if self.capacity > 0.0 { self.happiness * (1.0 - self.population / self.capacity).max(0.0) } else { 0.0 }
+    }
+
+    /// Finishes what is due, then grows, or shrinks, and settles the colony's mood for `seconds`.
+    /// Growth is logistic towards the capacity, integrated exactly so any tick is stable.
+    pub fn step(&mut self, environment: &Environment, natural: f64, seconds: f64, now: f64) {
+        let (done, building): (Vec<_>, Vec<_>) = self.construction.iter().partition(|(_, finished)| *finished <= now);
+        self.construction = building;
+        for (building, _) in done {
+            *self.buildings.entry(building).or_default() += 1;
+        }
+        self.capacity = self.supportable(natural);
+        if seconds <= 0.0 {
+            return;
+        }
+        let target = self.contentment(environment.habitability().total(), natural);
+        self.happiness = target + (self.happiness - target) * (-seconds / HAPPINESS_SECONDS).exp();
+        let rate = BASE_GROWTH * (0.5 + self.happiness) * (1.0 + self.coverage(Building::Hospital)) * seconds / SECONDS_PER_YEAR;
+        self.population = if self.capacity <= 0.0 {
+            self.population * (-rate).exp()
+        } else if self.population > 0.0 {
+            self.capacity / (1.0 + (self.capacity / self.population - 1.0) * (-rate).exp())
+        } else {
+            0.0
+        };
+    }
+}
+
+impl fmt::Display for Colony {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0} people of {:.0}, {:.0}% happy, {:+.0} a year migrating",
+               self.population, self.capacity, self.happiness * 100.0, self.migration)?;
+        for (building, count) in self.buildings.iter() {
+            write!(f, ", {} {:?}", count, building)?;
+        }
+        Ok(())
+    }
+}
+
+/// The people a planet supports in the open, scaled from Earth by its surface area and habitability.
+pub fn natural_capacity(planet: &Planet, environment: &Environment) -> f64 {
+    let earth_radii = planet.radius / Length::scaled(1.0, length::Scale::EarthRadius);
+    NATURAL_CAPACITY * earth_radii * earth_radii * environment.habitability().total()
+}
+
+type UnsurveyedPlanet<'a> = (Entity, &'a Planet, &'a Composition, &'a Orbit, &'a Orbiting, Option<&'a Colony>);
+
+/// Surveys the surfaces of planets as they appear, and settles those fit to live on.
+pub fn survey_planets(
+    mut commands: Commands,
+    planets: Query<UnsurveyedPlanet, Without<Environment>>,
+    stars: Query<&Star>
+) {
+    for (entity, planet, composition, orbit, parent, colony) in planets.iter() {
+        let star = match stars.get(parent.0) {
+            Ok(star) => star,
+            Err(_)   => continue
+        };
+        let environment = Environment::of(planet, composition, Irradiation::from_star(star, power::ZERO, mean_flux_distance(orbit)).total);
+        commands.entity(entity).insert(environment);
+        if colony.is_none() && environment.habitability().total() >= SETTLED_HABITABILITY {
+            commands.entity(entity).insert(Colony::new(natural_capacity(planet, &environment) * SETTLED_FRACTION));
+        }
+    }
+}
+
+/// Founds a colony on a world with a surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FoundColony {
+    pub body: Entity,
+    pub population: f64
+}
+
+/// Starts building on a colony.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Construct {
+    pub body: Entity,
+    pub building: Building
+}
+
+pub fn found_colonies(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut foundings: EventReader<FoundColony>,
+    mut constructions: EventReader<Construct>,
+    mut colonies: Query<(&Environment, Option<&mut Colony>)>
+) {
+    for founding in foundings.iter() {
+        match colonies.get_mut(founding.body) {
+            Ok((environment, None)) if environment.has_surface() => {
+                commands.entity(founding.body).insert(Colony::new(founding.population));
+            }
+            _ => warn!("cannot found a colony on {:?}", founding.body)
+        }
+    }
+    for construct in constructions.iter() {
+        match colonies.get_mut(construct.body) {
+            Ok((_, Some(mut colony))) => colony.construction.push((construct.building, clock.seconds + construct.building.build_time())),
+            _                         => warn!("there is no colony on {:?} to build on", construct.body)
+        }
+    }
+}
+
+pub fn grow_colonies(clock: Res<GameClock>, mut colonies: Query<(&mut Colony, &Planet, &Environment)>) {
+    for (mut colony, planet, environment) in colonies.iter_mut() {
+        colony.step(environment, natural_capacity(planet, environment), clock.tick_seconds(), clock.seconds);
+    }
+}
+
+struct Port {
+    colony: Entity,
+    population: f64,
+    attraction: f64,
+    /// People a year its spaceports carry.
+    throughput: f64,
+    position: WorldPosition
+}
+
+/// Moves people between colonies with spaceports, from the less attractive to the more, fewer the further apart
+/// they are. Each colony's flows are scaled down together to what its spaceports can carry, arrivals and departures
+/// alike, and to no more leaving than it has.
+pub fn migrate(clock: Res<GameClock>, mut colonies: Query<(Entity, &mut Colony, &WorldPosition)>) {
+    let years = clock.tick_seconds() / SECONDS_PER_YEAR;
+    let mut ports: Vec<Port> = colonies.iter_mut()
+        .filter(|(_, colony, _)| colony.count(Building::Spaceport) > 0)
+        .map(|(entity, colony, position)| Port {
+            colony: entity,
+            population: colony.population,
+            attraction: colony.attraction(),
+            throughput: colony.served(Building::Spaceport),
+            position: *position
+        })
+        .collect();
+    ports.sort_by_key(|port| port.colony);
+    let mut flows: BTreeMap<Entity, f64> = BTreeMap::new();
+    if years > 0.0 {
+        let range = length::LIGHT_YEARS_TO_METERS * MIGRATION_RANGE_LY;
+        // People a year wanting to move between each pair, by the ports they leave and arrive at.
+        let mut routes: Vec<(usize, usize, f64)> = Vec::new();
+        let mut traffic = vec![0.0; ports.len()];
+        let mut leaving = vec![0.0; ports.len()];
+        for (i, a) in ports.iter().enumerate() {
+            for (j, b) in ports.iter().enumerate().skip(i + 1) {
+                let (from, to) = if a.attraction < b.attraction { (i, j) } else { (j, i) };
+                let nearness = (-a.position.distance(b.position).in_meters() / range).exp();
+                let per_year = MIGRATION_RATE * (ports[to].attraction - ports[from].attraction) * nearness * ports[from].population;
+                routes.push((from, to, per_year));
+                traffic[from] += per_year;
+                traffic[to] += per_year;
+                leaving[from] += per_year;
+            }
+        }
+        let scale: Vec<f64> = ports.iter().enumerate()
+            .map(|(i, port)| {
+                let carried = if traffic[i] > port.throughput { port.throughput / traffic[i] } else { 1.0 };
+                let left = if leaving[i] * years > port.population { port.population / (leaving[i] * years) } else { 1.0 };
+                carried.min(left)
+            })
+            .collect();
+        for (from, to, per_year) in routes {
+            let moved = per_year * scale[from].min(scale[to]) * years;
+            *flows.entry(ports[from].colony).or_default() -= moved;
+            *flows.entry(ports[to].colony).or_default() += moved;
+        }
+    }
+    for (entity, mut colony, _) in colonies.iter_mut() {
+        let flow = flows.get(&entity).copied().unwrap_or(0.0);
+        // Departures never exceed the population, so this only catches rounding.
+        colony.population = (colony.population + flow).max(0.0);
+        colony.migration = if years > 0.0 { flow / years } else { 0.0 };
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum ColonySystem {
+    Grow,
+    Migrate
+}
+
+/// Colonies on the habitable worlds of the universe, growing and moving between each other over game time.
+/// Needs the `ClockPlugin` and `UniversePlugin`.
+pub struct ColonyPlugin;
+
+impl Plugin for ColonyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<FoundColony>()
+            .add_event::<Construct>()
+            .add_system(survey_planets.system())
+            .add_system(found_colonies.system())
+            .add_system_to_stage(SimulationStage, grow_colonies.system()
+                                 .label(ColonySystem::Grow)
+                                 .after(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, migrate.system()
+                                 .label(ColonySystem::Migrate)
+                                 .after(ColonySystem::Grow));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::DVec3;
+
+    /// A colony `happiness` happy, at `fill` of its capacity, with some spaceports.
+    fn colony(population: f64, fill: f64, happiness: f64, spaceports: u32) -> Colony {
+        let mut colony = Colony::new(population);
+        colony.capacity = population / fill;
+        colony.happiness = happiness;
+        colony.buildings.insert(Building::Spaceport, spaceports);
+        colony
+    }
+
+    /// Runs `migrate` over one tick of `years` on colonies a light year apart, and returns their populations.
+    fn migrate_for(years: f64, colonies: Vec<Colony>) -> Vec<f64> {
+        let mut world = World::default();
+        let mut clock = GameClock::default();
+        clock.tick = years * SECONDS_PER_YEAR;
+        world.insert_resource(clock);
+        let entities: Vec<Entity> = colonies.into_iter().enumerate()
+            .map(|(i, colony)| {
+                let position = WorldPosition::from_meters(DVec3::new(i as f64 * length::LIGHT_YEARS_TO_METERS, 0.0, 0.0));
+                world.spawn().insert_bundle((colony, position)).id()
+            })
+            .collect();
+        SystemStage::single(migrate.system()).run(&mut world);
+        entities.iter().map(|entity| world.get::<Colony>(*entity).unwrap().population).collect()
+    }
+
+    #[test]
+    fn a_spaceport_carries_no_more_than_its_throughput_in_all() {
+        let hub = colony(1.0e6, 0.1, 1.0, 1);
+        let sources = (0..3).map(|_| colony(1.0e9, 0.99, 0.1, 100_000));
+        let before = 1.0e6 + 3.0e9;
+        let after = migrate_for(1.0, std::iter::once(hub).chain(sources).collect());
+        let arrived = after[0] - 1.0e6;
+        assert!(arrived > 0.0);
+        assert!(arrived <= Building::Spaceport.serves() * (1.0 + 1.0e-9), "{} arrived", arrived);
+        assert!((after.iter().sum::<f64>() - before).abs() < before * 1.0e-12);
+    }
+
+    #[test]
+    fn no_more_leave_than_live_there() {
+        let crowded = colony(10.0, 1.0, 0.0, 10);
+        let roomy = colony(10.0, 0.01, 1.0, 10);
+        let after = migrate_for(10_000.0, vec![crowded, roomy]);
+        assert!(after[0] >= 0.0);
+        assert!(after[0] < 1.0e-9);
+        assert!((after[1] - 20.0).abs() < 1.0e-9);
+    }
+}
@@ -0,0 +1,597 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::clock::{ClockSystem, GameClock, SimulationStage};
+use super::flight::{FlightSystem, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::length::Length;
+use super::random;
+use super::random::Rng;
+use super::sensors::{Contacts, SensorSystem};
+use super::ship::{Ship, Subsystem, Weapon, WeaponKind, SUBSYSTEMS};
+use super::universe::UniverseSettings;
+
+/// The energy that wrecks a kilogram of a ship's parts, in J kg⁻¹.
+pub const TOUGHNESS: f64 = 1.0e5;
+/// The chance a guided missile that can match its target's manoeuvres goes on to hit it.
+pub const MISSILE_RELIABILITY: f64 = 0.9;
+/// The fraction of its Δv a missile spends closing, keeping the rest to steer onto its target.
+pub const MISSILE_CLOSING_FRACTION: f64 = 0.5;
+/// The radius of a diffraction-limited spot is this many wavelengths per aperture of range.
+pub const AIRY_FACTOR: f64 = 1.22;
+
+const COMBAT_KEY: u64 = 0x636f_6d62_6174;
+
+/// Seconds from a weapon firing to its shot reaching a target at `range`, closing at `closing_speed` m s⁻¹;
+/// `None` if the shot never gets there.
+pub fn time_of_flight(kind: &WeaponKind, range: Length, closing_speed: f64) -> Option<f64> {
+    let distance = range.in_meters();
+    match kind {
+        WeaponKind::Kinetic { muzzle_velocity, .. } => {
+            let speed = muzzle_velocity + closing_speed;
+            if speed > 0.0 { Some(distance / speed) } else { None }
+        }
+        WeaponKind::Laser { .. } => Some(range.light_time()),
+        WeaponKind::Missile { delta_v, acceleration, .. } => {
+            if *acceleration <= 0.0 || *delta_v <= 0.0 {
+                return None;
+            }
+            // A burn to its cruising speed, then a coast; the closing speed it starts with is left out.
+            let cruise = delta_v * MISSILE_CLOSING_FRACTION;
+            let burn = cruise / acceleration;
+            let burn_distance = 0.5 * acceleration * burn * burn;
+            if distance <= burn_distance {
+                Some((2.0 * distance / acceleration).sqrt())
+            } else {
+                Some(burn + (distance - burn_distance) / cruise)
+            }
+        }
+    }
+}
+
+/// The chance a shot hits a target of `target_radius` at `range`, when the target may accelerate at up to
+/// `target_acceleration` m s⁻² between being seen and the shot arriving.
+/// Unguided shots are aimed at where the target was seen, a light delay ago, so its acceleration spreads them
+/// like the weapon's own inaccuracy does; guided missiles steer out what their reserve of Δv allows.
+pub fn hit_probability(weapon: &Weapon, range: Length, closing_speed: f64, target_acceleration: f64, target_radius: Length) -> f64 {
+    let flight = match time_of_flight(&weapon.kind, range, closing_speed) {
+        Some(flight) => flight,
+        None         => return 0.0
+    };
+    let radius = target_radius.in_meters();
+    match weapon.kind {
+        WeaponKind::Missile { delta_v, acceleration, .. } => {
+            let reserve = delta_v * (1.0 - MISSILE_CLOSING_FRACTION);
+            let needed = target_acceleration * flight;
+            let steering = if needed > reserve { reserve / needed } else { 1.0 };
+            let agility = if target_acceleration > acceleration { acceleration / target_acceleration } else { 1.0 };
+            MISSILE_RELIABILITY * steering * agility
+        }
+        _ => {
+            let lag = range.light_time() + flight;
+            let aim = weapon.accuracy * range.in_meters();
+            let drift = 0.5 * target_acceleration * lag * lag;
+            let spread = aim * aim + drift * drift;
+            if spread > 0.0 { 1.0 - (-radius * radius / (2.0 * spread)).exp() } else { 1.0 }
+        }
+    }
+}
+
+/// The energy a hit delivers to a target of `target_radius` at `range`, in joules.
+pub fn hit_energy(kind: &WeaponKind, range: Length, closing_speed: f64, target_radius: Length) -> f64 {
+    match kind {
+        WeaponKind::Kinetic { muzzle_velocity, projectile } => {
+            let speed = muzzle_velocity + closing_speed;
+            0.5 * projectile.in_kilograms() * speed * speed
+        }
+        WeaponKind::Laser { beam, pulse, aperture, wavelength } => {
+            let spot = AIRY_FACTOR * wavelength.in_meters() * range.in_meters() / aperture.in_meters();
+            let focused = if spot > 0.0 { (target_radius.in_meters() / spot).powi(2).min(1.0) } else { 1.0 };
+            beam.in_watts() * pulse * focused
+        }
+        WeaponKind::Missile { warhead, .. } => *warhead
+    }
+}
+
+/// Where a hit's energy went.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HitReport {
+    pub energy: f64,
+    pub shields: f64,
+    pub armour: f64,
+    /// What the rest of it struck, and the fraction of that subsystem it destroyed.
+    pub damage: Option<(Subsystem, f64)>,
+    pub destroyed: bool
+}
+
+/// Lands a hit of `energy` joules on a ship: its shields take what they can hold, then its armour stops what it can,
+/// and what gets through wrecks a subsystem chosen by how much of the ship it makes up.
+pub fn apply_hit(ship: &mut Ship, energy: f64, rng: &mut Rng) -> HitReport {
+    let mut report = HitReport { energy, ..HitReport::default() };
+    let mut remaining = energy;
+    for shield in ship.shields.iter_mut() {
+        let absorbed = remaining.min(shield.charge);
+        shield.charge -= absorbed;
+        report.shields += absorbed;
+        remaining -= absorbed;
+    }
+    for armour in ship.armour.iter_mut() {
+        let condition = if armour.capacity > 0.0 { (armour.integrity / armour.capacity).clamp(0.0, 1.0) } else { 0.0 };
+        let absorbed = remaining.min(armour.threshold * condition).min(armour.integrity);
+        armour.integrity -= absorbed;
+        report.armour += absorbed;
+        remaining -= absorbed;
+    }
+    if remaining > 0.0 {
+        let masses: Vec<(Subsystem, f64)> = SUBSYSTEMS.iter()
+            .map(|subsystem| (*subsystem, ship.subsystem_mass(*subsystem).in_kilograms()))
+            .filter(|(_, kilograms)| *kilograms > 0.0)
+            .collect();
+        let total: f64 = masses.iter().map(|(_, kilograms)| kilograms).sum();
+        let mut pick = rng.next_f64() * total;
+        if let Some((struck, _)) = masses.iter().find(|(_, kilograms)| { pick -= kilograms; pick < 0.0 }).or_else(|| masses.last()) {
+            // Hits on what is already wrecked go on into the structure.
+            let subsystem = if ship.working(*struck) > 0.0 { *struck } else { Subsystem::Hull };
+            let kilograms = ship.subsystem_mass(subsystem).in_kilograms().max(1.0);
+            let lost = (remaining / (kilograms * TOUGHNESS)).min(ship.working(subsystem));
+            *ship.damage.entry(subsystem).or_insert(0.0) += lost;
+            report.damage = Some((subsystem, lost));
+        }
+    }
+    report.destroyed = ship.working(Subsystem::Hull) <= 0.0;
+    report
+}
+
+/// The ship a ship is firing on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Target(pub Entity);
+
+/// Shots from one weapon on their way to a target, all arriving together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Projectile {
+    pub shooter: Entity,
+    pub target: Entity,
+    pub weapon: String,
+    pub rounds: u32,
+    /// Of each round that hits, in joules.
+    pub energy: f64,
+    pub probability: f64,
+    pub origin: WorldPosition,
+    pub launched: f64,
+    /// The game time the shots get there, in seconds.
+    pub arrives: f64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombatEvent {
+    Fired { shooter: Entity, target: Entity, weapon: String, rounds: u32, probability: f64 },
+    Hit { shooter: Entity, target: Entity, weapon: String, report: HitReport },
+    Missed { shooter: Entity, target: Entity, weapon: String, rounds: u32 },
+    Destroyed { ship: Entity, by: Entity }
+}
+
+impl fmt::Display for CombatEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CombatEvent::Fired { shooter, target, weapon, rounds, probability } =>
+                write!(f, "{:?} fired {} rounds of its {} at {:?}, each with a {:.0}% chance", shooter, rounds, weapon, target, probability * 100.0),
+            CombatEvent::Hit { shooter, target, weapon, report } => {
+                write!(f, "{:?} hit {:?} with its {} for {:.3e} J, {:.3e} J on shields and {:.3e} J on armour",
+                       shooter, target, weapon, report.energy, report.shields, report.armour)?;
+                match report.damage {
+                    Some((subsystem, lost)) => write!(f, ", wrecking {:.1}% of its {:?}", lost * 100.0, subsystem),
+                    None                    => Ok(())
+                }
+            }
+            CombatEvent::Missed { shooter, target, weapon, rounds } =>
+                write!(f, "{} rounds of {:?}'s {} missed {:?}", rounds, shooter, weapon, target),
+            CombatEvent::Destroyed { ship, by } => write!(f, "{:?} was destroyed by {:?}", ship, by)
+        }
+    }
+}
+
+/// Recharges ships' shields from their reactors.
+pub fn recharge_shields(clock: Res<GameClock>, mut ships: Query<&mut Ship>) {
+    let dt = clock.tick_seconds();
+    for mut ship in ships.iter_mut() {
+        if ship.shields.iter().all(|shield| shield.charge >= shield.capacity) {
+            continue;
+        }
+        let working = ship.working(Subsystem::Shields) * ship.working(Subsystem::Reactors);
+        for shield in ship.shields.iter_mut() {
+            let capacity = shield.capacity * working;
+            if shield.charge < capacity {
+                shield.charge = (shield.charge + shield.recharge.in_watts() * working * dt).min(capacity);
+            }
+        }
+    }
+}
+
+/// What a shooter needs to know of its target.
+#[derive(Clone, Copy, Debug)]
+struct Sighting {
+    position: WorldPosition,
+    velocity: Velocity,
+    acceleration: f64,
+    radius: Length
+}
+
+type ArmedShip<'a> = (Entity, &'a mut Ship, &'a WorldPosition, Option<&'a Velocity>, Option<&'a ShipControls>, Option<&'a Target>,
+                      Option<&'a Contacts>);
+
+/// Fires every ship's weapons at its target as they come ready, launching the shots due over the tick as one salvo.
+/// Ships with sensors hold their fire while they cannot see their target.
+pub fn fire_weapons(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut ships: Query<ArmedShip>,
+    mut events: EventWriter<CombatEvent>
+) {
+    let now = clock.seconds;
+    let dt = clock.tick_seconds();
+    let mut ships: Vec<_> = ships.iter_mut().collect();
+    let sightings: BTreeMap<Entity, Sighting> = ships.iter()
+        .map(|(entity, ship, position, velocity, controls, _, _)| {
+            // Targets dodge with whatever their pilots are asking of the engines and thrusters.
+            let acceleration = controls.map_or(0.0, |controls| {
+                let thrust = ship.thrust().in_newtons() * controls.throttle.clamp(0.0, 1.0)
+                    + ship.thruster_thrust().in_newtons() * controls.translation.abs().max_element().min(1.0);
+                thrust / ship.total_mass().in_kilograms()
+            });
+            (*entity, Sighting { position: **position, velocity: velocity.copied().unwrap_or_default(), acceleration, radius: ship.hull.radius })
+        })
+        .collect();
+    for (entity, ship, position, velocity, _, target, contacts) in ships.iter_mut() {
+        let target = match target {
+            Some(target) => target.0,
+            None         => continue
+        };
+        let sighting = match sightings.get(&target) {
+            Some(sighting) if target != *entity => *sighting,
+            _                                   => {
+                commands.entity(*entity).remove::<Target>();
+                continue;
+            }
+        };
+        if matches!(contacts, Some(contacts) if !contacts.is_detected(target)) {
+            continue;
+        }
+        let offset = sighting.position.relative_to(**position);
+        let range = Length::m(offset.length());
+        let closing_speed = if offset.length() > 0.0 {
+            (velocity.copied().unwrap_or_default().0 - sighting.velocity.0).dot(offset.normalize())
+        } else {
+            0.0
+        };
+        let working = ship.working(Subsystem::Weapons);
+        if working <= 0.0 || ship.working(Subsystem::Hull) <= 0.0 || ship.weapons.iter().all(|weapon| range > weapon.range || weapon.ready_at > now) {
+            continue;
+        }
+        for weapon in ship.weapons.iter_mut().filter(|weapon| range <= weapon.range) {
+            // Shots the weapon could have fired before this tick are not saved up.
+            weapon.ready_at = weapon.ready_at.max(now - dt);
+            if weapon.ready_at > now {
+                continue;
+            }
+            let reload = weapon.reload / working;
+            let mut rounds = if reload > 0.0 { ((now - weapon.ready_at) / reload).floor() as u32 + 1 } else { 1 };
+            if let WeaponKind::Missile { magazine, .. } = &mut weapon.kind {
+                rounds = rounds.min(*magazine);
+                *magazine -= rounds;
+            }
+            weapon.ready_at += reload * rounds as f64;
+            let flight = match time_of_flight(&weapon.kind, range, closing_speed) {
+                Some(flight) if rounds > 0 => flight,
+                _                          => continue
+            };
+            let probability = hit_probability(weapon, range, closing_speed, sighting.acceleration, sighting.radius);
+            commands.spawn()
+                .insert(Projectile {
+                    shooter: *entity,
+                    target,
+                    weapon: weapon.name.clone(),
+                    rounds,
+                    energy: hit_energy(&weapon.kind, range, closing_speed, sighting.radius),
+                    probability,
+                    origin: **position,
+                    launched: now,
+                    arrives: now + flight
+                })
+                .insert(**position);
+            events.send(CombatEvent::Fired { shooter: *entity, target, weapon: weapon.name.clone(), rounds, probability });
+        }
+    }
+}
+
+/// Moves shots towards their targets, and lands those that have arrived, destroying the ships they wreck.
+pub fn resolve_projectiles(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    universe: Res<UniverseSettings>,
+    mut projectiles: Query<(Entity, &Projectile, &mut WorldPosition), Without<Ship>>,
+    mut ships: Query<(&mut Ship, &WorldPosition)>,
+    mut events: EventWriter<CombatEvent>
+) {
+    let now = clock.seconds;
+    let mut rng = Rng::new(random::derive(random::derive(universe.galaxy.seed, COMBAT_KEY), now.to_bits()));
+    let mut projectiles: Vec<_> = projectiles.iter_mut().collect();
+    projectiles.sort_by_key(|(entity, _, _)| *entity);
+    for (entity, projectile, position) in projectiles.iter_mut() {
+        let (mut ship, target_position) = match ships.get_mut(projectile.target) {
+            Ok(target) => target,
+            Err(_)     => {
+                commands.entity(*entity).despawn();
+                continue;
+            }
+        };
+        if now < projectile.arrives {
+            let fraction = (now - projectile.launched) / (projectile.arrives - projectile.launched);
+            let mut moved = projectile.origin;
+            moved.translate(target_position.relative_to(projectile.origin) * fraction);
+            **position = moved;
+            continue;
+        }
+        commands.entity(*entity).despawn();
+        let mut missed = 0;
+        for _ in 0..projectile.rounds {
+            if ship.working(Subsystem::Hull) <= 0.0 {
+                break;
+            }
+            if !rng.chance(projectile.probability) {
+                missed += 1;
+                continue;
+            }
+            let report = apply_hit(&mut ship, projectile.energy, &mut rng);
+            events.send(CombatEvent::Hit { shooter: projectile.shooter, target: projectile.target, weapon: projectile.weapon.clone(), report });
+            if report.destroyed {
+                commands.entity(projectile.target).despawn();
+                events.send(CombatEvent::Destroyed { ship: projectile.target, by: projectile.shooter });
+            }
+        }
+        if missed > 0 {
+            events.send(CombatEvent::Missed { shooter: projectile.shooter, target: projectile.target, weapon: projectile.weapon.clone(), rounds: missed });
+        }
+    }
+}
+
+pub fn log_combat_events(mut events: EventReader<CombatEvent>) {
+    for event in events.iter() {
+        match event {
+            CombatEvent::Fired { .. } => {}
+            _                         => info!("{}", event)
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum CombatSystem {
+    Fire,
+    Resolve
+}
+
+/// Ships firing on their targets, with shots that take time to arrive and damage that wears ships down.
+/// Needs the `ClockPlugin`, `UniversePlugin` and `FlightPlugin`, and the `SensorPlugin` for ships to see what they fire on.
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<CombatEvent>()
+            .add_system_to_stage(SimulationStage, recharge_shields.system()
+                                 .after(ClockSystem::Advance)
+                                 .before(CombatSystem::Resolve))
+            .add_system_to_stage(SimulationStage, resolve_projectiles.system()
+                                 .label(CombatSystem::Resolve)
+                                 .after(FlightSystem::Fly)
+                                 .after(SensorSystem::Detect))
+            .add_system_to_stage(SimulationStage, fire_weapons.system()
+                                 .label(CombatSystem::Fire)
+                                 .after(CombatSystem::Resolve)
+                                 .after(SensorSystem::Detect))
+            .add_system(log_combat_events.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::{Events, ManualEventReader};
+    use bevy::math::DVec3;
+    use super::super::ship::courier;
+
+    const COILGUN: usize = 0;
+    const LASER: usize = 1;
+    const MISSILES: usize = 2;
+
+    fn frigate() -> Ship {
+        Ship::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ships/frigate.ron")).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1.0e-9 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn shots_take_the_time_their_weapons_need() {
+        let weapons = frigate().weapons;
+        let coilgun = &weapons[COILGUN].kind;
+        assert_eq!(time_of_flight(coilgun, Length::km(20000.0), 0.0), Some(1000.0));
+        assert_eq!(time_of_flight(coilgun, Length::km(20000.0), 5000.0), Some(800.0));
+        assert_eq!(time_of_flight(coilgun, Length::km(20000.0), -20000.0), None);
+
+        let range = Length::km(300000.0);
+        assert_eq!(time_of_flight(&weapons[LASER].kind, range, -1.0e6), Some(range.light_time()));
+
+        // The launcher's missiles burn to 20 km s⁻¹ in 66⅔ s, over 666⅔ km, and coast from there.
+        let missiles = &weapons[MISSILES].kind;
+        let burn = 20000.0 / 300.0;
+        let inside = time_of_flight(missiles, Length::km(100.0), 0.0).unwrap();
+        assert!(close(inside, (2.0 * 1.0e5 / 300.0_f64).sqrt()) && inside < burn);
+        let beyond = time_of_flight(missiles, Length::km(10000.0), 0.0).unwrap();
+        assert!(close(beyond, burn + (1.0e7 - 0.5 * 300.0 * burn * burn) / 20000.0));
+        let spent = WeaponKind::Missile { delta_v: 0.0, acceleration: 300.0, warhead: 1.0, magazine: 1 };
+        assert_eq!(time_of_flight(&spent, Length::km(100.0), 0.0), None);
+    }
+
+    #[test]
+    fn shots_miss_more_at_range_and_against_agile_targets() {
+        let weapons = frigate().weapons;
+        let radius = Length::m(50.0);
+        // Missiles hold their chance until their target can outrun their reserve of Δv, and unguided shots against
+        // a target at rest miss only by their own spread, so the chances need only never rise.
+        let falling = |chances: &[f64]| chances.windows(2).all(|pair| pair[1] <= pair[0]) && chances[chances.len() - 1] < chances[0];
+        for weapon in weapons.iter() {
+            let by_range: Vec<f64> = [1.0e3, 1.0e4, 1.0e5, 1.0e6].iter()
+                .map(|km| hit_probability(weapon, Length::km(*km), 0.0, 1.0, radius))
+                .collect();
+            assert!(falling(&by_range) && by_range.iter().all(|chance| (0.0..=1.0).contains(chance)), "{} {:?}", weapon.name, by_range);
+            let by_acceleration: Vec<f64> = [0.0, 0.1, 1.0, 10.0, 1000.0].iter()
+                .map(|acceleration| hit_probability(weapon, Length::km(1.0e6), 0.0, *acceleration, radius))
+                .collect();
+            assert!(falling(&by_acceleration), "{} {:?}", weapon.name, by_acceleration);
+        }
+        let coilgun = &weapons[COILGUN];
+        assert!(hit_probability(coilgun, Length::km(1000.0), 0.0, 0.0, radius) > 0.99);
+        assert_eq!(hit_probability(coilgun, Length::km(1000.0), -20000.0, 0.0, radius), 0.0);
+        let missiles = &weapons[MISSILES];
+        assert!(close(hit_probability(missiles, Length::km(1.0e6), 0.0, 0.0, radius), MISSILE_RELIABILITY));
+    }
+
+    #[test]
+    fn lasers_spread_beyond_their_focus() {
+        let weapons = frigate().weapons;
+        let laser = &weapons[LASER].kind;
+        let radius = Length::m(10.0);
+        // Aperture 2 m at 500 nm: the spot grows to the target's size at 10 m / (1.22 × 2.5e-7), some 32800 km.
+        let focus = 10.0 / (AIRY_FACTOR * 2.5e-7);
+        let whole = 50.0e6 * 2.0;
+        assert!(close(hit_energy(laser, Length::m(focus * 0.5), 0.0, radius), whole));
+        assert!(close(hit_energy(laser, Length::m(focus), 0.0, radius), whole));
+        assert!(close(hit_energy(laser, Length::m(focus * 2.0), 0.0, radius), whole / 4.0));
+        assert!(close(hit_energy(laser, Length::m(focus * 10.0), 0.0, radius), whole / 100.0));
+
+        let coilgun = &weapons[COILGUN].kind;
+        assert!(close(hit_energy(coilgun, Length::km(1.0e6), 0.0, radius), 0.5 * 5.0 * 20000.0 * 20000.0));
+        assert!(close(hit_energy(coilgun, Length::km(10.0), 10000.0, radius), 0.5 * 5.0 * 30000.0 * 30000.0));
+        assert_eq!(hit_energy(&weapons[MISSILES].kind, Length::km(1.0e6), 0.0, radius), 4.0e9);
+    }
+
+    #[test]
+    fn shields_take_hits_before_armour() {
+        let mut rng = Rng::new(1);
+        let mut ship = frigate();
+        let report = apply_hit(&mut ship, 1.5e9, &mut rng);
+        assert_eq!((report.shields, report.armour, report.damage), (1.5e9, 0.0, None));
+        assert_eq!(ship.shields[0].charge, 0.5e9);
+        assert_eq!(ship.armour[0].integrity, 2.0e10);
+
+        let report = apply_hit(&mut ship, 1.0e9, &mut rng);
+        assert_eq!((report.shields, report.armour, report.damage), (0.5e9, 0.5e9, None));
+        assert_eq!(ship.armour[0].integrity, 2.0e10 - 0.5e9);
+
+        let mut ship = frigate();
+        let report = apply_hit(&mut ship, 4.0e9, &mut rng);
+        assert_eq!((report.shields, report.armour), (2.0e9, 1.0e9));
+        let (struck, lost) = report.damage.unwrap();
+        assert!(close(lost, 1.0e9 / (ship.subsystem_mass(struck).in_kilograms() * TOUGHNESS)));
+        assert!(close(ship.working(struck), 1.0 - lost));
+        assert!(!report.destroyed);
+    }
+
+    #[test]
+    fn worn_armour_stops_less() {
+        let mut rng = Rng::new(2);
+        let mut ship = frigate();
+        ship.shields[0].charge = 0.0;
+        ship.armour[0].integrity = 0.25 * ship.armour[0].capacity;
+        let report = apply_hit(&mut ship, 4.0e9, &mut rng);
+        assert_eq!((report.shields, report.armour), (0.0, 0.25e9));
+        assert!(report.damage.is_some());
+
+        ship.armour[0].capacity = 0.0;
+        let report = apply_hit(&mut ship, 4.0e9, &mut rng);
+        assert_eq!(report.armour, 0.0);
+    }
+
+    #[test]
+    fn hits_on_wrecked_parts_go_into_the_hull() {
+        let mut rng = Rng::new(3);
+        let mut ship = courier();
+        ship.shields.clear();
+        ship.armour.clear();
+        for subsystem in SUBSYSTEMS.iter().filter(|subsystem| **subsystem != Subsystem::Hull) {
+            ship.damage.insert(*subsystem, 1.0);
+        }
+        let hull = ship.subsystem_mass(Subsystem::Hull).in_kilograms();
+        for _ in 0..20 {
+            let report = apply_hit(&mut ship, 0.01 * hull * TOUGHNESS, &mut rng);
+            assert_eq!(report.damage.map(|(struck, _)| struck), Some(Subsystem::Hull));
+        }
+        assert!(close(ship.working(Subsystem::Hull), 0.8));
+        assert!(!apply_hit(&mut ship, 0.5 * hull * TOUGHNESS, &mut rng).destroyed);
+
+        let report = apply_hit(&mut ship, hull * TOUGHNESS, &mut rng);
+        let (struck, lost) = report.damage.unwrap();
+        assert!(struck == Subsystem::Hull && close(lost, 0.3));
+        assert!(report.destroyed);
+        assert_eq!(ship.working(Subsystem::Hull), 0.0);
+    }
+
+    fn fire(world: &mut World, seconds: f64, tick_seconds: f64) {
+        let mut clock = world.get_resource_mut::<GameClock>().unwrap();
+        clock.seconds = seconds;
+        clock.tick = tick_seconds;
+        SystemStage::single(fire_weapons.system()).run(world);
+    }
+
+    fn rounds_fired(world: &World, reader: &mut ManualEventReader<CombatEvent>) -> Vec<u32> {
+        let mut rounds = vec![0; 3];
+        for event in reader.iter(world.get_resource::<Events<CombatEvent>>().unwrap()) {
+            if let CombatEvent::Fired { weapon, rounds: fired, .. } = event {
+                let index = ["Coilgun", "Pulse laser", "Missile launcher"].iter().position(|name| name == weapon).unwrap();
+                rounds[index] += fired;
+            }
+        }
+        rounds
+    }
+
+    fn magazine(world: &World, shooter: Entity) -> u32 {
+        match world.get::<Ship>(shooter).unwrap().weapons[MISSILES].kind {
+            WeaponKind::Missile { magazine, .. } => magazine,
+            _                                    => unreachable!()
+        }
+    }
+
+    #[test]
+    fn weapons_fire_as_they_reload_until_their_magazines_run_dry() {
+        let mut world = World::default();
+        let target = world.spawn().insert_bundle((courier(), WorldPosition::from_meters(DVec3::new(1.0e6, 0.0, 0.0)))).id();
+        let shooter = world.spawn().insert_bundle((frigate(), WorldPosition::default(), Target(target))).id();
+        world.insert_resource(GameClock::default());
+        world.insert_resource(Events::<CombatEvent>::default());
+        let mut reader = ManualEventReader::<CombatEvent>::default();
+
+        fire(&mut world, 0.0, 1.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![1, 1, 1]);
+        assert_eq!(magazine(&world, shooter), 11);
+
+        // Over ten minutes the coilgun reloads every 5 s, the laser every 10 s and the launcher every minute.
+        fire(&mut world, 600.0, 600.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![120, 60, 10]);
+        assert_eq!(magazine(&world, shooter), 1);
+        assert_eq!(world.query::<&Projectile>().iter(&world).count(), 6);
+
+        fire(&mut world, 1200.0, 600.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![120, 60, 1]);
+        assert_eq!(magazine(&world, shooter), 0);
+        fire(&mut world, 1800.0, 600.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![120, 60, 0]);
+
+        // Shots not fired while the ship was idle are not saved up, and wrecked weapons reload slower.
+        fire(&mut world, 10000.0, 1.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![1, 1, 0]);
+        world.get_mut::<Ship>(shooter).unwrap().damage.insert(Subsystem::Weapons, 0.5);
+        fire(&mut world, 10600.0, 600.0);
+        assert_eq!(rounds_fired(&world, &mut reader), vec![60, 30, 0]);
+    }
+}
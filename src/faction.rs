@@ -0,0 +1,683 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY};
+use super::galaxy::{Galaxy, StarId};
+use super::length::Length;
+use super::position::Position;
+use super::random;
+use super::random::Rng;
+use super::spatial::Octree;
+use super::universe::UniverseSettings;
+
+/// How far a system's influence reaches, falling smoothly to nothing.
+pub const INFLUENCE_RANGE_LY: f64 = 8.0;
+/// A capital projects this many times the influence of any other system.
+pub const CAPITAL_INFLUENCE: f64 = 2.0;
+/// The influence a faction needs to claim a system inside its borders.
+pub const BORDER_INFLUENCE: f64 = 0.25;
+/// Capitals are founded at least this far apart.
+pub const MIN_CAPITAL_SEPARATION_LY: f64 = 6.0;
+/// How often each faction may settle a system, or take one from an enemy.
+pub const EXPANSION_SECONDS: f64 = 180.0 * SECONDS_PER_DAY;
+/// How often factions reconsider their relations.
+pub const DIPLOMACY_SECONDS: f64 = 30.0 * SECONDS_PER_DAY;
+/// Opinions run from hostile at -100 to friendly at 100.
+pub const MAX_OPINION: f64 = 100.0;
+/// Opinion lost each diplomacy turn for each system two factions both claim.
+pub const BORDER_FRICTION: f64 = 2.0;
+/// Opinion drifts back towards neutral by this fraction each diplomacy turn.
+pub const OPINION_DECAY: f64 = 0.05;
+/// Opinion gained each diplomacy turn from trading.
+pub const TRADE_GOODWILL: f64 = 1.0;
+/// Opinion regained each diplomacy turn of war, as both sides tire of it.
+pub const WAR_WEARINESS: f64 = 3.0;
+/// Opinion lost for a captured system, or a broken treaty.
+pub const GRIEVANCE: f64 = 15.0;
+/// Below this, factions consider war; above the others, treaties.
+pub const WAR_OPINION: f64 = -50.0;
+/// Below this, a faction breaks its treaties to go to war.
+pub const BETRAYAL_OPINION: f64 = -80.0;
+pub const PEACE_OPINION: f64 = -10.0;
+pub const TRADE_OPINION: f64 = 20.0;
+pub const ALLIANCE_OPINION: f64 = 60.0;
+/// Wars last at least this long.
+pub const MIN_WAR_SECONDS: f64 = 365.0 * SECONDS_PER_DAY;
+/// An attacker needs this many times the support a system has from the defender's others to take it.
+/// Capitals fall last.
+pub const CAPTURE_ADVANTAGE: f64 = 1.5;
+/// How far opinions wander each diplomacy turn, for reasons beyond borders and treaties.
+pub const OPINION_NOISE: f64 = 3.0;
+
+const FACTION_KEY: u64 = 0x6661_6374_696f_6e73;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FactionId(pub u32);
+
+impl fmt::Display for FactionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "faction {}", self.0)
+    }
+}
+
+/// A power among the stars, and its temperament.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Faction {
+    pub id: FactionId,
+    pub name: String,
+    pub capital: StarId,
+    /// The chance, from nothing to one, it settles a system when it can.
+    pub expansionism: f64,
+    /// The chance it declares war on a faction it hates, or presses one it is fighting.
+    pub aggression: f64
+}
+
+const SYLLABLES: [&str; 24] = [
+    "ar", "bel", "cor", "dra", "el", "fen", "gal", "hes", "ix", "jor", "kal", "lun",
+    "mor", "nev", "or", "pra", "qua", "rho", "sel", "tor", "ul", "vex", "wyn", "zan"
+];
+const FORMS: [&str; 6] = ["Republic", "Hegemony", "Concord", "Dominion", "League", "Compact"];
+
+/// A faction's name, generated from its seed.
+pub fn faction_name(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let syllables = 2 + rng.below(2);
+    let mut name: String = (0..syllables).map(|_| SYLLABLES[rng.below(SYLLABLES.len() as u64) as usize]).collect();
+    if let Some(first) = name.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    format!("{} {}", name, FORMS[rng.below(FORMS.len() as u64) as usize])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Treaty {
+    NonAggression,
+    Trade,
+    Alliance
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct War {
+    pub aggressor: FactionId,
+    /// In seconds since the epoch.
+    pub since: f64
+}
+
+/// How two factions stand with each other.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    pub opinion: f64,
+    pub treaties: BTreeSet<Treaty>,
+    pub war: Option<War>
+}
+
+/// Everything that changes factions. Their state only ever changes by applying these, in order,
+/// so a log of them replays a history exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FactionEvent {
+    Founded(Faction),
+    Settled { faction: FactionId, star: StarId },
+    Captured { faction: FactionId, from: FactionId, star: StarId },
+    OpinionChanged { a: FactionId, b: FactionId, by: f64 },
+    TreatySigned { a: FactionId, b: FactionId, treaty: Treaty },
+    TreatyBroken { by: FactionId, with: FactionId, treaty: Treaty },
+    WarDeclared { aggressor: FactionId, defender: FactionId },
+    PeaceMade { a: FactionId, b: FactionId }
+}
+
+impl fmt::Display for FactionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FactionEvent::Founded(faction)                    => write!(f, "{} founded the {}", faction.id, faction.name),
+            FactionEvent::Settled { faction, star }           => write!(f, "{} settled {}", faction, star),
+            FactionEvent::Captured { faction, from, star }    => write!(f, "{} took {} from {}", faction, star, from),
+            FactionEvent::OpinionChanged { a, b, by }         => write!(f, "{} and {} opinion {:+.1}", a, b, by),
+            FactionEvent::TreatySigned { a, b, treaty }       => write!(f, "{} and {} signed a {:?} treaty", a, b, treaty),
+            FactionEvent::TreatyBroken { by, with, treaty }   => write!(f, "{} broke its {:?} treaty with {}", by, treaty, with),
+            FactionEvent::WarDeclared { aggressor, defender } => write!(f, "{} declared war on {}", aggressor, defender),
+            FactionEvent::PeaceMade { a, b }                  => write!(f, "{} and {} made peace", a, b)
+        }
+    }
+}
+
+/// The star systems factions can hold: those of the generated universe, indexed by position.
+pub struct Territory {
+    pub systems: Vec<(StarId, Position)>,
+    index: Octree<usize>,
+    ids: HashMap<StarId, usize>
+}
+
+impl Territory {
+    pub fn new(systems: Vec<(StarId, Position)>, center: Position, half_size: Length) -> Territory {
+        let mut index = Octree::new(center, half_size);
+        for (i, (_, position)) in systems.iter().enumerate() {
+            index.insert(*position, i);
+        }
+        let ids = systems.iter().enumerate().map(|(i, (id, _))| (*id, i)).collect();
+        Territory { systems, index, ids }
+    }
+
+    /// Every star of the galaxy within a sphere.
+    pub fn around(galaxy: &Galaxy, center: Position, radius: Length) -> Territory {
+        Territory::new(galaxy.stars_within(center, radius).map(|star| (star.id, star.position)).collect(), center, radius)
+    }
+
+    pub fn position(&self, star: StarId) -> Option<Position> {
+        self.ids.get(&star).map(|i| self.systems[*i].1)
+    }
+
+    /// The systems within `radius` of `position`, with their distances.
+    pub fn within(&self, position: Position, radius: Length) -> Vec<(StarId, Length)> {
+        self.index.within(position, radius).into_iter()
+            .map(|neighbour| (self.systems[*neighbour.item].0, neighbour.distance))
+            .collect()
+    }
+}
+
+/// Each faction's influence over a system.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Influence(pub BTreeMap<FactionId, f64>);
+
+impl Influence {
+    pub fn of(&self, faction: FactionId) -> f64 {
+        self.0.get(&faction).copied().unwrap_or(0.0)
+    }
+
+    /// The strongest faction here, if it has enough influence to claim the system. Ties go to the lower id.
+    pub fn claimant(&self) -> Option<FactionId> {
+        self.0.iter()
+            .filter(|(_, influence)| **influence >= BORDER_INFLUENCE)
+            .fold(None, |best: Option<(FactionId, f64)>, (faction, influence)| match best {
+                Some((_, strongest)) if strongest >= *influence => best,
+                _                                               => Some((*faction, *influence))
+            })
+            .map(|(faction, _)| faction)
+    }
+
+    /// Whether both factions have enough influence here to claim it.
+    pub fn contested_by(&self, a: FactionId, b: FactionId) -> bool {
+        self.of(a) >= BORDER_INFLUENCE && self.of(b) >= BORDER_INFLUENCE
+    }
+}
+
+/// Every system any faction has influence over, recomputed whenever a system changes hands.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Borders(pub BTreeMap<StarId, Influence>);
+
+impl Borders {
+    pub fn claimant(&self, star: StarId) -> Option<FactionId> {
+        self.0.get(&star).and_then(Influence::claimant)
+    }
+
+    /// The systems both factions claim.
+    pub fn contested(&self, a: FactionId, b: FactionId) -> usize {
+        self.0.values().filter(|influence| influence.contested_by(a, b)).count()
+    }
+
+    /// How many systems inside a faction's borders.
+    pub fn extent(&self, faction: FactionId) -> usize {
+        self.0.values().filter(|influence| influence.claimant() == Some(faction)).count()
+    }
+}
+
+/// The factions, who holds which system, and how they all stand with each other.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Factions {
+    pub factions: BTreeMap<FactionId, Faction>,
+    pub owners: BTreeMap<StarId, FactionId>,
+    /// By the pair of factions, lower id first.
+    pub relations: BTreeMap<(FactionId, FactionId), Relation>,
+    /// When the next turns are due, in seconds since the epoch.
+    pub next_expansion: f64,
+    pub next_diplomacy: f64
+}
+
+fn pair(a: FactionId, b: FactionId) -> (FactionId, FactionId) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+impl Factions {
+    pub fn relation(&self, a: FactionId, b: FactionId) -> Relation {
+        self.relations.get(&pair(a, b)).cloned().unwrap_or_default()
+    }
+
+    fn relation_mut(&mut self, a: FactionId, b: FactionId) -> &mut Relation {
+        self.relations.entry(pair(a, b)).or_default()
+    }
+
+    pub fn at_war(&self, a: FactionId, b: FactionId) -> bool {
+        matches!(self.relations.get(&pair(a, b)), Some(Relation { war: Some(_), .. }))
+    }
+
+    pub fn systems(&self, faction: FactionId) -> impl Iterator<Item = StarId> + '_ {
+        self.owners.iter().filter(move |(_, owner)| **owner == faction).map(|(star, _)| *star)
+    }
+
+    /// Whether a faction holds `star` as its capital. A capital only falls with the last of its faction's systems,
+    /// which ends the faction, so no faction ever holds another's.
+    pub fn is_capital(&self, star: StarId) -> bool {
+        self.factions.values().any(|faction| faction.capital == star && self.owners.get(&star) == Some(&faction.id))
+    }
+
+    /// Each faction's influence over every system within reach of one it holds.
+    pub fn borders(&self, territory: &Territory) -> Borders {
+        let range = Length::ly(INFLUENCE_RANGE_LY);
+        let mut borders = Borders::default();
+        for (star, owner) in self.owners.iter() {
+            let position = match territory.position(*star) {
+                Some(position) => position,
+                None           => continue
+            };
+            let weight = if self.is_capital(*star) { CAPITAL_INFLUENCE } else { 1.0 };
+            for (other, distance) in territory.within(position, range) {
+                let x = distance / range;
+                *borders.0.entry(other).or_default().0.entry(*owner).or_default() += weight * (1.0 - x * x);
+            }
+        }
+        borders
+    }
+
+    /// Changes the factions by `event`, at time `now`. Events that no longer make sense, like settling a system
+    /// someone else has just settled, change nothing and return false. A faction losing its last system is gone.
+    pub fn apply(&mut self, event: &FactionEvent, now: f64) -> bool {
+        match event {
+            FactionEvent::Founded(faction) => {
+                if self.factions.contains_key(&faction.id) || self.owners.contains_key(&faction.capital) {
+                    return false;
+                }
+                self.owners.insert(faction.capital, faction.id);
+                self.factions.insert(faction.id, faction.clone());
+            }
+            FactionEvent::Settled { faction, star } => {
+                if !self.factions.contains_key(faction) || self.owners.contains_key(star) {
+                    return false;
+                }
+                self.owners.insert(*star, *faction);
+            }
+            FactionEvent::Captured { faction, from, star } => {
+                if self.owners.get(star) != Some(from) || !self.at_war(*faction, *from) {
+                    return false;
+                }
+                if self.is_capital(*star) && self.systems(*from).nth(1).is_some() {
+                    return false;
+                }
+                self.owners.insert(*star, *faction);
+                if self.systems(*from).next().is_none() {
+                    self.factions.remove(from);
+                    self.relations.retain(|(a, b), _| a != from && b != from);
+                } else {
+                    let relation = self.relation_mut(*faction, *from);
+                    relation.opinion = (relation.opinion - GRIEVANCE).max(-MAX_OPINION);
+                }
+            }
+            FactionEvent::OpinionChanged { a, b, by } => {
+                let relation = self.relation_mut(*a, *b);
+                relation.opinion = (relation.opinion + by).clamp(-MAX_OPINION, MAX_OPINION);
+            }
+            FactionEvent::TreatySigned { a, b, treaty } => {
+                let relation = self.relation_mut(*a, *b);
+                if relation.war.is_some() {
+                    return false;
+                }
+                return relation.treaties.insert(*treaty);
+            }
+            FactionEvent::TreatyBroken { by, with, treaty } => {
+                let relation = self.relation_mut(*by, *with);
+                if !relation.treaties.remove(treaty) {
+                    return false;
+                }
+                relation.opinion = (relation.opinion - GRIEVANCE).max(-MAX_OPINION);
+            }
+            FactionEvent::WarDeclared { aggressor, defender } => {
+                let relation = self.relation_mut(*aggressor, *defender);
+                if relation.war.is_some() || !relation.treaties.is_empty() {
+                    return false;
+                }
+                relation.war = Some(War { aggressor: *aggressor, since: now });
+            }
+            FactionEvent::PeaceMade { a, b } => {
+                return self.relation_mut(*a, *b).war.take().is_some();
+            }
+        }
+        true
+    }
+
+    /// Founds `count` factions with capitals spread across the territory, each on a system at least
+    /// `MIN_CAPITAL_SEPARATION_LY` from the others, for as many as there is room for.
+    pub fn found(territory: &Territory, seed: u64, count: usize) -> Vec<FactionEvent> {
+        let mut rng = Rng::new(random::derive(seed, FACTION_KEY));
+        let mut candidates: Vec<(u64, StarId, Position)> = territory.systems.iter()
+            .map(|(star, position)| (rng.next_u64(), *star, *position))
+            .collect();
+        candidates.sort_by_key(|(order, star, _)| (*order, *star));
+        let separation = Length::ly(MIN_CAPITAL_SEPARATION_LY);
+        let mut capitals: Vec<Position> = Vec::new();
+        let mut events = Vec::new();
+        for (_, star, position) in candidates {
+            if events.len() == count {
+                break;
+            }
+            if capitals.iter().any(|capital| capital.distance(position) < separation) {
+                continue;
+            }
+            capitals.push(position);
+            let id = FactionId(events.len() as u32);
+            let faction_seed = random::derive(random::derive(seed, FACTION_KEY), id.0 as u64 + 1);
+            let mut temperament = Rng::new(faction_seed);
+            events.push(FactionEvent::Founded(Faction {
+                id,
+                name: faction_name(faction_seed),
+                capital: star,
+                expansionism: temperament.range_f64(0.3..1.0),
+                aggression: temperament.range_f64(0.1..0.9)
+            }));
+        }
+        events
+    }
+
+    /// What each faction does on an expansion turn: settle the unclaimed system it most dominates,
+    /// or take the enemy system it most outweighs.
+    pub fn expand(&self, territory: &Territory, borders: &Borders, rng: &mut Rng) -> Vec<FactionEvent> {
+        let mut taken: BTreeSet<StarId> = BTreeSet::new();
+        let mut events = Vec::new();
+        for faction in self.factions.values() {
+            let mut best: Option<(f64, FactionEvent)> = None;
+            for (star, influence) in borders.0.iter() {
+                let own = influence.of(faction.id);
+                if own <= 0.0 || taken.contains(star) || territory.position(*star).is_none() {
+                    continue;
+                }
+                let candidate = match self.owners.get(star) {
+                    None => {
+                        let others = influence.0.iter()
+                            .filter(|(other, _)| **other != faction.id)
+                            .fold(0.0, |strongest: f64, (_, influence)| strongest.max(*influence));
+                        if others > own || !rng.chance(faction.expansionism) {
+                            continue;
+                        }
+                        ((own - others) * rng.range_f64(0.5..1.5), FactionEvent::Settled { faction: faction.id, star: *star })
+                    }
+                    Some(owner) if self.at_war(faction.id, *owner) => {
+                        let capital = self.is_capital(*star);
+                        if capital && self.systems(*owner).nth(1).is_some() {
+                            continue;
+                        }
+                        let support = influence.of(*owner) - if capital { CAPITAL_INFLUENCE } else { 1.0 };
+                        if own < support * CAPTURE_ADVANTAGE || !rng.chance(faction.aggression) {
+                            continue;
+                        }
+                        (own / support.max(f64::EPSILON), FactionEvent::Captured { faction: faction.id, from: *owner, star: *star })
+                    }
+                    Some(_) => continue
+                };
+                let better = match &best {
+                    Some((score, _)) => candidate.0 > *score,
+                    None             => true
+                };
+                if better {
+                    best = Some(candidate);
+                }
+            }
+            if let Some((_, event)) = best {
+                if let FactionEvent::Settled { star, .. } | FactionEvent::Captured { star, .. } = &event {
+                    taken.insert(*star);
+                }
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// What the factions do on a diplomacy turn, pair by pair: opinions drift with friction along
+    /// shared borders and goodwill from trade, then wars start and end and treaties are made and broken.
+    pub fn negotiate(&self, borders: &Borders, now: f64, rng: &mut Rng) -> Vec<FactionEvent> {
+        let mut events = Vec::new();
+        let ids: Vec<FactionId> = self.factions.keys().copied().collect();
+        for (i, a) in ids.iter().enumerate() {
+            for b in ids[i + 1..].iter() {
+                let (a, b) = (*a, *b);
+                let relation = self.relation(a, b);
+                let contested = borders.contested(a, b);
+                let mut by = -relation.opinion * OPINION_DECAY + rng.gaussian() * OPINION_NOISE;
+                // At war, the fighting is grievance enough, and the borders are what it is about.
+                by += if relation.war.is_some() { WAR_WEARINESS } else { -(contested as f64) * BORDER_FRICTION };
+                if relation.treaties.contains(&Treaty::Trade) {
+                    by += TRADE_GOODWILL;
+                }
+                if by.abs() > 1.0e-6 {
+                    events.push(FactionEvent::OpinionChanged { a, b, by });
+                }
+                let opinion = (relation.opinion + by).clamp(-MAX_OPINION, MAX_OPINION);
+                let (hawk, dove) = if self.factions[&a].aggression >= self.factions[&b].aggression { (a, b) } else { (b, a) };
+                match relation.war {
+                    Some(war) => {
+                        if now - war.since >= MIN_WAR_SECONDS && opinion > PEACE_OPINION {
+                            events.push(FactionEvent::PeaceMade { a, b });
+                            events.push(FactionEvent::TreatySigned { a, b, treaty: Treaty::NonAggression });
+                        }
+                    }
+                    None if contested > 0 && opinion < if relation.treaties.is_empty() { WAR_OPINION } else { BETRAYAL_OPINION } => {
+                        if rng.chance(self.factions[&hawk].aggression) {
+                            for treaty in relation.treaties.iter() {
+                                events.push(FactionEvent::TreatyBroken { by: hawk, with: dove, treaty: *treaty });
+                            }
+                            events.push(FactionEvent::WarDeclared { aggressor: hawk, defender: dove });
+                        }
+                    }
+                    None => {
+                        for (treaty, threshold) in [(Treaty::Trade, TRADE_OPINION), (Treaty::Alliance, ALLIANCE_OPINION)].iter() {
+                            let holds = relation.treaties.contains(treaty);
+                            if !holds && opinion >= *threshold {
+                                events.push(FactionEvent::TreatySigned { a, b, treaty: *treaty });
+                            } else if holds && opinion < *threshold - TRADE_OPINION {
+                                events.push(FactionEvent::TreatyBroken { by: hawk, with: dove, treaty: *treaty });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+/// How many factions to found in a new universe. Insert before the first update to change it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FactionSettings {
+    pub count: usize
+}
+
+impl Default for FactionSettings {
+    fn default() -> FactionSettings {
+        FactionSettings { count: 5 }
+    }
+}
+
+/// Maps the territory of the generated universe, and founds the factions if none have been loaded.
+pub fn found_factions(
+    mut commands: Commands,
+    universe: Res<UniverseSettings>,
+    settings: Option<Res<FactionSettings>>,
+    factions: Res<Factions>,
+    mut events: EventWriter<FactionEvent>
+) {
+    let territory = Territory::around(&universe.galaxy, universe.start, universe.radius);
+    if factions.factions.is_empty() {
+        let count = settings.map_or_else(|| FactionSettings::default().count, |settings| settings.count);
+        for event in Factions::found(&territory, universe.galaxy.seed, count) {
+            events.send(event);
+        }
+    }
+    commands.insert_resource(factions.borders(&territory));
+    commands.insert_resource(territory);
+}
+
+/// Takes the factions' turns as they come due, sending what they decide as `FactionEvent`s.
+pub fn run_factions(
+    clock: Res<GameClock>,
+    universe: Res<UniverseSettings>,
+    territory: Res<Territory>,
+    borders: Res<Borders>,
+    mut factions: ResMut<Factions>,
+    mut events: EventWriter<FactionEvent>
+) {
+    let now = clock.seconds;
+    let mut rng = Rng::new(random::derive(random::derive(universe.galaxy.seed, FACTION_KEY), now.to_bits()));
+    // Turns missed by a long tick are taken once, rather than all at once on stale borders.
+    if now >= factions.next_expansion {
+        for event in factions.expand(&territory, &borders, &mut rng) {
+            events.send(event);
+        }
+        factions.next_expansion = factions.next_expansion.max(now - EXPANSION_SECONDS) + EXPANSION_SECONDS;
+    }
+    if now >= factions.next_diplomacy {
+        for event in factions.negotiate(&borders, now, &mut rng) {
+            events.send(event);
+        }
+        factions.next_diplomacy = factions.next_diplomacy.max(now - DIPLOMACY_SECONDS) + DIPLOMACY_SECONDS;
+    }
+}
+
+/// Applies `FactionEvent`s to the factions in the order they were sent, and redraws the borders when systems change hands.
+pub fn apply_faction_events(
+    clock: Res<GameClock>,
+    territory: Option<Res<Territory>>,
+    mut events: EventReader<FactionEvent>,
+    mut factions: ResMut<Factions>,
+    mut borders: ResMut<Borders>
+) {
+    let mut moved = false;
+    for event in events.iter() {
+        if factions.apply(event, clock.seconds) {
+            moved |= matches!(event, FactionEvent::Founded(_) | FactionEvent::Settled { .. } | FactionEvent::Captured { .. });
+            match event {
+                FactionEvent::OpinionChanged { .. } => {}
+                _                                   => info!("{}", event)
+            }
+            if let FactionEvent::Captured { from, .. } = event {
+                if !factions.factions.contains_key(from) {
+                    info!("{} fell", from);
+                }
+            }
+        }
+    }
+    if let (true, Some(territory)) = (moved, territory) {
+        *borders = factions.borders(&territory);
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum FactionSystem {
+    Run,
+    Apply
+}
+
+/// Factions holding the star systems of the generated universe, expanding and warring over game time.
+/// Needs the `ClockPlugin` and `UniversePlugin`.
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Factions>()
+            .init_resource::<Borders>()
+            .add_event::<FactionEvent>()
+            .add_startup_system(found_factions.system())
+            .add_system_to_stage(SimulationStage, run_factions.system()
+                                 .label(FactionSystem::Run)
+                                 .after(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, apply_faction_events.system()
+                                 .label(FactionSystem::Apply)
+                                 .after(FactionSystem::Run));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::galaxy::Sector;
+    use super::super::length;
+
+    fn star(index: u32) -> StarId {
+        StarId { sector: Sector::new(0, 0, 0), index }
+    }
+
+    /// Five systems in a line, three light years apart.
+    fn territory() -> Territory {
+        let systems = (0..5).map(|index| (star(index), Position::scaled(3.0 * index as f64, 0.0, 0.0, length::Scale::LightYear))).collect();
+        Territory::new(systems, Position::scaled(6.0, 0.0, 0.0, length::Scale::LightYear), Length::ly(10.0))
+    }
+
+    fn faction(id: u32, capital: u32) -> Faction {
+        Faction { id: FactionId(id), name: faction_name(id as u64), capital: star(capital), expansionism: 0.5, aggression: 0.5 }
+    }
+
+    /// Faction 0 holding systems 0 and 1, at war with faction 1 holding 3 and 4.
+    fn at_war() -> Factions {
+        let mut factions = Factions::default();
+        let (a, b) = (FactionId(0), FactionId(1));
+        for event in [FactionEvent::Founded(faction(0, 0)), FactionEvent::Settled { faction: a, star: star(1) },
+                      FactionEvent::Founded(faction(1, 4)), FactionEvent::Settled { faction: b, star: star(3) },
+                      FactionEvent::WarDeclared { aggressor: b, defender: a }].iter() {
+            assert!(factions.apply(event, 0.0));
+        }
+        factions
+    }
+
+    #[test]
+    fn capitals_fall_last() {
+        let mut factions = at_war();
+        let (a, b) = (FactionId(0), FactionId(1));
+        assert!(!factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(0) }, 0.0));
+        assert!(factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(1) }, 0.0));
+        assert_eq!(factions.owners[&star(1)], b);
+        assert!(factions.relation(a, b).opinion < 0.0);
+        assert!(factions.is_capital(star(0)));
+    }
+
+    #[test]
+    fn factions_losing_their_last_system_are_gone() {
+        let mut factions = at_war();
+        let (a, b) = (FactionId(0), FactionId(1));
+        assert!(factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(1) }, 0.0));
+        assert!(factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(0) }, 0.0));
+        assert!(!factions.factions.contains_key(&a));
+        assert!(factions.relations.is_empty());
+        assert_eq!(factions.systems(b).count(), 4);
+        assert!(!factions.at_war(a, b));
+        assert!(!factions.apply(&FactionEvent::Settled { faction: a, star: star(2) }, 0.0));
+    }
+
+    #[test]
+    fn captured_capitals_give_no_capital_influence() {
+        let mut factions = at_war();
+        let (a, b) = (FactionId(0), FactionId(1));
+        let territory = territory();
+        factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(1) }, 0.0);
+        factions.apply(&FactionEvent::Captured { faction: b, from: a, star: star(0) }, 0.0);
+        assert!(!factions.is_capital(star(0)));
+        assert!(factions.is_capital(star(4)));
+
+        // Held as an ordinary system, the old capital weighs the same as the one beside it.
+        let mut ordinary = factions.clone();
+        ordinary.owners.remove(&star(0));
+        ordinary.owners.remove(&star(1));
+        let with = factions.borders(&territory).0[&star(2)].of(b);
+        let without = ordinary.borders(&territory).0[&star(2)].of(b);
+        let x = |light_years: f64| light_years / INFLUENCE_RANGE_LY;
+        assert!((with - without - (1.0 - x(6.0) * x(6.0)) - (1.0 - x(3.0) * x(3.0))).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn expansion_never_takes_a_capital_before_the_rest() {
+        let factions = at_war();
+        let territory = territory();
+        let borders = factions.borders(&territory);
+        for seed in 0..100 {
+            for event in factions.expand(&territory, &borders, &mut Rng::new(seed)) {
+                assert_ne!(event, FactionEvent::Captured { faction: FactionId(1), from: FactionId(0), star: star(0) });
+            }
+        }
+    }
+}
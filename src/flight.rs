@@ -0,0 +1,216 @@
+use bevy::math::{const_dvec3, DQuat, DVec3};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::clock::{ClockSystem, GameClock, SimulationStage};
+use super::floating_origin::WorldPosition;
+use super::length::Length;
+use super::mass::Mass;
+use super::orbit::{Orbit, GRAVITATIONAL_CONSTANT};
+use super::astronomy::Orbiting;
+use super::planet::Planet;
+use super::scene;
+use super::ship;
+use super::ship::Ship;
+use super::star::Star;
+use super::universe::{StarIndex, UniverseSettings};
+
+/// The direction a ship's main engines push it, in its own frame.
+pub const FORWARD: DVec3 = const_dvec3!([0.0, 0.0, -1.0]);
+/// Bodies further away than this are left out of a ship's gravity.
+pub const GRAVITY_RANGE_METERS: f64 = 1.0e15;
+/// The radius of the player's starting orbit around the nearest star.
+pub const START_ORBIT_METERS: f64 = 1.495_978_707e11;
+
+/// In meters per second, relative to the galaxy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Velocity(pub DVec3);
+
+/// Which way a ship points and how fast it turns.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attitude {
+    /// From the ship's frame to the galaxy's.
+    pub rotation: DQuat,
+    /// Radians per second about each axis of the ship's frame.
+    pub angular_velocity: DVec3
+}
+
+impl Default for Attitude {
+    fn default() -> Attitude {
+        Attitude { rotation: DQuat::IDENTITY, angular_velocity: DVec3::ZERO }
+    }
+}
+
+impl Attitude {
+    /// Turns the ship to point its engines along a direction in the galaxy's frame, without turning rates.
+    pub fn facing(direction: DVec3) -> Attitude {
+        Attitude { rotation: DQuat::from_rotation_arc(FORWARD, direction.normalize()), angular_velocity: DVec3::ZERO }
+    }
+
+    pub fn forward(&self) -> DVec3 {
+        self.rotation * FORWARD
+    }
+}
+
+/// What a ship's pilot, human or autopilot, is asking of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShipControls {
+    /// The main engines, from 0 to 1.
+    pub throttle: f64,
+    /// Thrusters firing to translate along each axis of the ship's frame, from -1 to 1.
+    pub translation: DVec3,
+    /// Thrusters firing to turn about each axis of the ship's frame, from -1 to 1.
+    pub rotation: DVec3
+}
+
+/// The ship the player flies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerShip;
+
+/// A body whose gravity pulls on ships.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gravitating {
+    pub position: WorldPosition,
+    pub mass: Mass
+}
+
+/// The bodies near enough to ships to pull on them, gathered each tick.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GravitySources(pub Vec<Gravitating>);
+
+/// The acceleration on a light body at a position due to the bodies, in m s⁻².
+pub fn gravity_at(position: WorldPosition, bodies: &[Gravitating]) -> DVec3 {
+    bodies.iter().fold(DVec3::ZERO, |acceleration, body| {
+        let offset = body.position.relative_to(position);
+        let distance_squared = offset.length_squared();
+        if distance_squared > 0.0 && distance_squared < GRAVITY_RANGE_METERS * GRAVITY_RANGE_METERS {
+            acceleration + offset * (GRAVITATIONAL_CONSTANT * body.mass.in_kilograms() / (distance_squared * distance_squared.sqrt()))
+        } else {
+            acceleration
+        }
+    })
+}
+
+/// The velocity of a body relative to the galaxy at a time in seconds since the epoch,
+/// through its chain of orbits. Bodies that do not orbit anything are taken to be at rest.
+pub fn body_velocity(entity: Entity, seconds: f64, orbits: &Query<(&Orbit, &Orbiting)>) -> DVec3 {
+    match orbits.get(entity) {
+        Ok((orbit, parent)) => orbit.velocity_at(seconds) + body_velocity(parent.0, seconds, orbits),
+        Err(_)              => DVec3::ZERO
+    }
+}
+
+/// Fires a ship's engines and thrusters for `seconds`, burning its fuel,
+/// and returns the acceleration they gave it in the galaxy's frame, in m s⁻².
+pub fn fire(ship: &mut Ship, attitude: &mut Attitude, controls: &ShipControls, seconds: f64) -> DVec3 {
+    let throttle = controls.throttle.clamp(0.0, 1.0);
+    let translation = controls.translation.clamp(DVec3::splat(-1.0), DVec3::splat(1.0));
+    let rotation = controls.rotation.clamp(DVec3::splat(-1.0), DVec3::splat(1.0));
+    let wanted = ship.mass_flow() * throttle
+        + ship.thruster_mass_flow() * (translation.abs().dot(DVec3::ONE) + rotation.abs().dot(DVec3::ONE));
+    if wanted <= 0.0 || seconds <= 0.0 {
+        return DVec3::ZERO;
+    }
+    // Short of fuel, everything fires for the fraction of the time it lasts.
+    let burnt = ship.consume_fuel(Mass::kg(wanted * seconds));
+    let fraction = burnt.in_kilograms() / (wanted * seconds);
+    let mass = ship.total_mass();
+    attitude.angular_velocity += rotation * (ship.angular_acceleration() * fraction * seconds);
+    let thrust = FORWARD * (ship.thrust().in_newtons() * throttle) + translation * ship.thruster_thrust().in_newtons();
+    attitude.rotation * thrust * (fraction / mass.in_kilograms())
+}
+
+/// Gathers the stars and planets within gravity range of any ship.
+pub fn gather_gravitating(
+    ships: Query<&WorldPosition, With<Ship>>,
+    stars: Query<(&Star, &WorldPosition)>,
+    planets: Query<(&Planet, &WorldPosition)>,
+    mut sources: ResMut<GravitySources>
+) {
+    sources.0.clear();
+    let near = |position: &WorldPosition| ships.iter().any(|ship| ship.relative_to(*position).length() < GRAVITY_RANGE_METERS);
+    sources.0.extend(stars.iter()
+                     .filter(|(_, position)| near(position))
+                     .map(|(star, position)| Gravitating { position: *position, mass: star.mass }));
+    sources.0.extend(planets.iter()
+                     .filter(|(_, position)| near(position))
+                     .map(|(planet, position)| Gravitating { position: *position, mass: planet.mass }));
+}
+
+/// Moves ships under their own thrust and gravity, velocity first so the integration is symplectic.
+pub fn fly_ships(
+    clock: Res<GameClock>,
+    sources: Res<GravitySources>,
+    mut ships: Query<(&mut Ship, &ShipControls, &mut Attitude, &mut Velocity, &mut WorldPosition)>
+) {
+    let dt = clock.tick_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+    for (mut ship, controls, mut attitude, mut velocity, mut position) in ships.iter_mut() {
+        let thrust = if *controls == ShipControls::default() { DVec3::ZERO } else { fire(&mut ship, &mut attitude, controls, dt) };
+        velocity.0 += (thrust + gravity_at(*position, &sources.0)) * dt;
+        position.translate(velocity.0 * dt);
+        let spin = attitude.angular_velocity;
+        if spin.length_squared() > 0.0 {
+            attitude.rotation = (attitude.rotation * DQuat::from_axis_angle(spin.normalize(), spin.length() * dt)).normalize();
+        }
+    }
+}
+
+/// Puts the player's ship, of the starter design, in a circular orbit about the star nearest the start.
+pub fn spawn_player_ship(
+    mut commands: Commands,
+    settings: Res<UniverseSettings>,
+    index: Res<StarIndex>,
+    stars: Query<(&Star, &WorldPosition)>,
+    players: Query<(), With<PlayerShip>>
+) {
+    if players.iter().next().is_some() {
+        return;
+    }
+    let design = match Ship::load(scene::asset_path(ship::STARTER_SHIP)) {
+        Ok(design) => design,
+        Err(error) => {
+            error!("could not load the starter ship {}: {}", ship::STARTER_SHIP, error);
+            return;
+        }
+    };
+    let (star, star_position) = match index.0.nearest(settings.start, 1).first().and_then(|nearest| stars.get(*nearest.item).ok()) {
+        Some(star) => star,
+        None       => return
+    };
+    let orbit = Orbit::circular(Length::m(START_ORBIT_METERS), star.mass);
+    let mut position = *star_position;
+    position.translate(orbit.position_at(0.0));
+    let velocity = orbit.velocity_at(0.0);
+    commands.spawn()
+        .insert(design)
+        .insert(PlayerShip)
+        .insert(ShipControls::default())
+        .insert(Attitude::facing(velocity))
+        .insert(Velocity(velocity))
+        .insert(position);
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum FlightSystem {
+    Gravity,
+    Fly
+}
+
+/// Newtonian ship flight under thrust and gravity, in the simulation's fixed ticks. Needs the `ClockPlugin` and `UniversePlugin`.
+pub struct FlightPlugin;
+
+impl Plugin for FlightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GravitySources>()
+            .add_startup_system_to_stage(StartupStage::PostStartup, spawn_player_ship.system())
+            .add_system_to_stage(SimulationStage, gather_gravitating.system()
+                                 .label(FlightSystem::Gravity)
+                                 .after(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, fly_ships.system()
+                                 .label(FlightSystem::Fly)
+                                 .after(FlightSystem::Gravity));
+    }
+}
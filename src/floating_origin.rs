@@ -0,0 +1,213 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use serde::{Deserialize, Serialize};
+
+use super::length;
+use super::length::Length;
+use super::position::Position;
+
+/// The side of a grid cell. An f64 offset within a cell this size keeps better than a millimetre of precision,
+/// and the i64 cell indices reach far beyond the observable universe.
+pub const CELL_SIZE_METERS: f64 = 1.0e12;
+
+/// A large-scale position: an integer grid cell plus a double precision offset within it.
+/// Entities with one have their render-space `Transform` rebuilt around the floating origin every frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldPosition {
+    pub cell: [i64; 3],
+    /// Meters from the cell's corner, normally within [0, CELL_SIZE_METERS) on each axis.
+    pub offset: DVec3
+}
+
+impl WorldPosition {
+    pub fn new(cell: [i64; 3], offset: DVec3) -> WorldPosition {
+        WorldPosition { cell, offset }.normalized()
+    }
+
+    pub fn from_meters(meters: DVec3) -> WorldPosition {
+        WorldPosition::new([0, 0, 0], meters)
+    }
+
+    pub fn from_position(position: Position) -> WorldPosition {
+        WorldPosition::from_meters(position.in_meters())
+    }
+
+    /// Loses precision at galactic distances, as `Position` is a plain f64 per axis.
+    pub fn to_position(self, scale: length::Scale) -> Position {
+        let cell = DVec3::new(self.cell[0] as f64, self.cell[1] as f64, self.cell[2] as f64);
+        Position::from_meters(cell * CELL_SIZE_METERS + self.offset, scale)
+    }
+
+    /// Moves whole cells out of the offset and into the cell index.
+    pub fn normalized(self) -> WorldPosition {
+        let carry = (self.offset / CELL_SIZE_METERS).floor();
+        WorldPosition {
+            cell: [self.cell[0] + carry.x as i64,
+                   self.cell[1] + carry.y as i64,
+                   self.cell[2] + carry.z as i64],
+            offset: self.offset - carry * CELL_SIZE_METERS
+        }
+    }
+
+    pub fn translate(&mut self, meters: DVec3) {
+        *self = WorldPosition::new(self.cell, self.offset + meters);
+    }
+
+    /// The vector from `origin` to here, in meters.
+    /// Whole cells are subtracted as integers first, so nearby positions stay precise however far they are from the galactic centre.
+    pub fn relative_to(self, origin: WorldPosition) -> DVec3 {
+        let cells = DVec3::new((self.cell[0] - origin.cell[0]) as f64,
+                               (self.cell[1] - origin.cell[1]) as f64,
+                               (self.cell[2] - origin.cell[2]) as f64);
+        cells * CELL_SIZE_METERS + (self.offset - origin.offset)
+    }
+
+    pub fn distance(self, other: WorldPosition) -> Length {
+        Length::meters(self.relative_to(other).length())
+    }
+}
+
+/// The size of an entity's mesh units in meters, so it keeps its true size as the `RenderScale` changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldScale(pub DVec3);
+
+impl WorldScale {
+    pub fn uniform(meters: f64) -> WorldScale {
+        WorldScale(DVec3::splat(meters))
+    }
+}
+
+/// Marks the entity, normally the camera, that render space is centred on.
+pub struct FloatingOrigin;
+
+/// How many meters one unit of render space represents.
+/// Zooming out to interstellar scales raises this so that everything visible stays within f32's comfortable range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderScale {
+    pub meters_per_unit: f64
+}
+
+impl Default for RenderScale {
+    fn default() -> RenderScale {
+        RenderScale { meters_per_unit: 1.0 }
+    }
+}
+
+/// Where render space is currently centred, updated every frame from the `FloatingOrigin` entity.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderOrigin(pub WorldPosition);
+
+/// Converts a world position into render space, for a given origin and scale.
+pub fn render_translation(position: WorldPosition, origin: WorldPosition, scale: RenderScale) -> Vec3 {
+    let relative = position.relative_to(origin) / scale.meters_per_unit;
+    Vec3::new(relative.x as f32, relative.y as f32, relative.z as f32)
+}
+
+/// Recentres render space on the floating origin, rewriting the translation of every top level entity with a `WorldPosition`,
+/// and the scale of those with a `WorldScale`.
+/// Children keep their transforms relative to their parents.
+pub fn rebase_transforms(
+    scale: Res<RenderScale>,
+    mut origin: ResMut<RenderOrigin>,
+    origins: Query<&WorldPosition, With<FloatingOrigin>>,
+    mut positioned: Query<(&WorldPosition, Option<&WorldScale>, &mut Transform), Without<Parent>>
+) {
+    if let Ok(position) = origins.single() {
+        origin.0 = *position;
+    }
+    for (position, world_scale, mut transform) in positioned.iter_mut() {
+        transform.translation = render_translation(*position, origin.0, *scale);
+        if let Some(WorldScale(meters)) = world_scale {
+            let units = *meters / scale.meters_per_unit;
+            transform.scale = Vec3::new(units.x as f32, units.y as f32, units.z as f32);
+        }
+    }
+}
+
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<RenderScale>()
+            .init_resource::<RenderOrigin>()
+            .add_system_to_stage(CoreStage::PostUpdate,
+                                 rebase_transforms.system().before(TransformSystem::TransformPropagate));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Far out in the galaxy, about a hundred thousand light years from the origin.
+    const FAR: i64 = 1_000_000_000;
+
+    #[test]
+    fn whole_cells_carry_out_of_the_offset() {
+        let position = WorldPosition::new([5, 0, -5], DVec3::new(2.5 * CELL_SIZE_METERS, -0.25 * CELL_SIZE_METERS, CELL_SIZE_METERS));
+        assert_eq!(position.cell, [7, -1, -4]);
+        assert_eq!(position.offset, DVec3::new(0.5 * CELL_SIZE_METERS, 0.75 * CELL_SIZE_METERS, 0.0));
+        assert_eq!(position.normalized(), position);
+        assert_eq!(WorldPosition::from_meters(DVec3::new(-1.0, 0.0, 0.0)).cell, [-1, 0, 0]);
+
+        let mut moved = WorldPosition::new([FAR, FAR, FAR], DVec3::splat(CELL_SIZE_METERS - 0.5));
+        moved.translate(DVec3::splat(1.0));
+        assert_eq!(moved.cell, [FAR + 1; 3]);
+        assert!((moved.offset - DVec3::splat(0.5)).length() < 1.0e-3);
+        moved.translate(DVec3::splat(-1.0));
+        assert_eq!(moved.cell, [FAR; 3]);
+        assert!((moved.offset - DVec3::splat(CELL_SIZE_METERS - 0.5)).length() < 1.0e-3);
+    }
+
+    #[test]
+    fn nearby_positions_stay_precise_however_far_out() {
+        // A millimetre apart across a cell boundary, where plain f64 meters would be a hundred kilometres coarse;
+        // offsets near the far side of a cell are good to about a tenth of a millimetre.
+        let a = WorldPosition::new([FAR, -FAR, FAR], DVec3::new(CELL_SIZE_METERS - 0.0005, 3.0, 4.0));
+        let b = WorldPosition::new([FAR + 1, -FAR, FAR], DVec3::new(0.0005, 3.0, 4.0));
+        assert!((b.relative_to(a) - DVec3::new(0.001, 0.0, 0.0)).length() < 1.0e-4);
+        assert!((a.relative_to(b) + b.relative_to(a)).length() < 1.0e-9);
+        assert!((b.distance(a).in_meters() - 0.001).abs() < 1.0e-4);
+
+        // Many cells apart, the separation keeps f64's relative precision.
+        let light_years = DVec3::new(3.0, -2.0, 1.0) * length::LIGHT_YEARS_TO_METERS;
+        let mut c = a;
+        c.translate(light_years);
+        assert!((c.relative_to(a) - light_years).length() < 1.0e-12 * light_years.length());
+        assert_eq!(a.relative_to(a), DVec3::ZERO);
+    }
+
+    #[test]
+    fn render_space_is_centred_on_the_floating_origin() {
+        let eye = WorldPosition::new([FAR, FAR, -FAR], DVec3::new(123_456.789, 0.5, CELL_SIZE_METERS - 1.0));
+        let mut near = eye;
+        near.translate(DVec3::new(10.0, -2.0, 3.0));
+        let mut far = eye;
+        far.translate(DVec3::new(0.0, 0.0, 5.0e9));
+
+        let mut world = World::default();
+        world.insert_resource(RenderScale::default());
+        world.insert_resource(RenderOrigin::default());
+        let camera = world.spawn().insert_bundle((eye, FloatingOrigin, Transform::default())).id();
+        let object = world.spawn().insert_bundle((near, WorldScale::uniform(2.0), Transform::default())).id();
+        let planet = world.spawn().insert_bundle((far, Transform::default())).id();
+        let child = world.spawn().insert_bundle((far, Transform::from_xyz(1.0, 0.0, 0.0), Parent(object))).id();
+        let mut stage = SystemStage::single(rebase_transforms.system());
+        stage.run(&mut world);
+
+        assert_eq!(world.get_resource::<RenderOrigin>().unwrap().0, eye);
+        assert_eq!(world.get::<Transform>(camera).unwrap().translation, Vec3::ZERO);
+        let translation = world.get::<Transform>(object).unwrap().translation;
+        assert!((translation - Vec3::new(10.0, -2.0, 3.0)).length() < 1.0e-6, "{:?}", translation);
+        assert_eq!(world.get::<Transform>(object).unwrap().scale, Vec3::splat(2.0));
+        assert_eq!(world.get::<Transform>(child).unwrap().translation, Vec3::new(1.0, 0.0, 0.0));
+
+        // Zoomed out, render units grow so distant things stay in f32's range, and sizes shrink with them.
+        world.insert_resource(RenderScale { meters_per_unit: 1.0e6 });
+        stage.run(&mut world);
+        assert!((world.get::<Transform>(planet).unwrap().translation - Vec3::new(0.0, 0.0, 5000.0)).length() < 1.0e-3);
+        assert_eq!(world.get::<Transform>(object).unwrap().scale, Vec3::splat(2.0e-6));
+        assert_eq!(render_translation(near, eye, RenderScale { meters_per_unit: 1.0e6 }), world.get::<Transform>(object).unwrap().translation);
+    }
+}
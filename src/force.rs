@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use serde::{Deserialize, Serialize};
+
+use super::mass::Mass;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Scale {
+    Newton,
+    Kilonewton,
+    Meganewton,
+    Giganewton,
+    PoundForce,
+    KilogramForce
+}
+
+impl Default for Scale {
+    fn default() -> Scale {
+        Scale::Newton
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Force {
+    newtons: f64,
+    scale: Scale
+}
+
+/// Standard gravity, in m s⁻², by definition (CGPM 1901).
+pub const STANDARD_GRAVITY: f64 = 9.806_65;
+
+pub const KILONEWTONS_TO_NEWTONS:     f64 = 1.0e3;
+pub const MEGANEWTONS_TO_NEWTONS:     f64 = 1.0e6;
+pub const GIGANEWTONS_TO_NEWTONS:     f64 = 1.0e9;
+pub const POUNDS_FORCE_TO_NEWTONS:    f64 = 4.448_221_615_260_5;
+pub const KILOGRAMS_FORCE_TO_NEWTONS: f64 = STANDARD_GRAVITY;
+
+pub const ZERO: Force = Force { newtons: 0.0,           scale: Scale::Newton };
+pub const MAX:  Force = Force { newtons: f64::MAX, scale: Scale::Newton };
+
+impl Force {
+    pub fn scaled(force: f64, scale: Scale) -> Force {
+        match scale {
+            Scale::Newton        => Force { newtons: force,                              scale },
+            Scale::Kilonewton    => Force { newtons: force * KILONEWTONS_TO_NEWTONS,     scale },
+            Scale::Meganewton    => Force { newtons: force * MEGANEWTONS_TO_NEWTONS,     scale },
+            Scale::Giganewton    => Force { newtons: force * GIGANEWTONS_TO_NEWTONS,     scale },
+            Scale::PoundForce    => Force { newtons: force * POUNDS_FORCE_TO_NEWTONS,    scale },
+            Scale::KilogramForce => Force { newtons: force * KILOGRAMS_FORCE_TO_NEWTONS, scale }
+        }
+    }
+
+    pub fn new(newtons: f64) -> Force {
+        Force::newtons(newtons)
+    }
+
+    pub fn newtons(newtons: f64) -> Force {
+        Force::scaled(newtons, Scale::Newton)
+    }
+
+    pub fn kilonewtons(kilonewtons: f64) -> Force {
+        Force::scaled(kilonewtons, Scale::Kilonewton)
+    }
+
+    pub fn meganewtons(meganewtons: f64) -> Force {
+        Force::scaled(meganewtons, Scale::Meganewton)
+    }
+
+    pub fn giganewtons(giganewtons: f64) -> Force {
+        Force::scaled(giganewtons, Scale::Giganewton)
+    }
+
+    pub fn pounds_force(pounds_force: f64) -> Force {
+        Force::scaled(pounds_force, Scale::PoundForce)
+    }
+
+    pub fn kilograms_force(kilograms_force: f64) -> Force {
+        Force::scaled(kilograms_force, Scale::KilogramForce)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn N(newtons: f64) -> Force {
+        Force::newtons(newtons)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn kN(kilonewtons: f64) -> Force {
+        Force::kilonewtons(kilonewtons)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn MN(meganewtons: f64) -> Force {
+        Force::meganewtons(meganewtons)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn GN(giganewtons: f64) -> Force {
+        Force::giganewtons(giganewtons)
+    }
+
+    pub fn lbf(pounds_force: f64) -> Force {
+        Force::pounds_force(pounds_force)
+    }
+
+    pub fn kgf(kilograms_force: f64) -> Force {
+        Force::kilograms_force(kilograms_force)
+    }
+
+    pub fn range(range: std::ops::Range<f64>, scale: Scale) -> std::ops::Range<Force> {
+        Force::scaled(range.start, scale) .. Force::scaled(range.end, scale)
+    }
+
+    pub fn scale(self) -> Scale {
+        self.scale
+    }
+
+    /// The same force, displayed in another scale.
+    pub fn to_scale(self, scale: Scale) -> Force {
+        Force { newtons: self.newtons, scale }
+    }
+
+    pub fn in_newtons(self) -> f64 {
+        self.newtons
+    }
+
+    /// The acceleration this force gives a mass, in m s⁻².
+    pub fn acceleration_of(self, mass: Mass) -> f64 {
+        self.newtons / mass.in_kilograms()
+    }
+}
+
+impl PartialOrd for Force {
+    fn partial_cmp(&self, other: &Force) -> Option<Ordering> {
+        self.newtons.partial_cmp(&other.newtons)
+    }
+}
+
+// Arithmetic keeps the scale of the left-hand side.
+
+impl Add for Force {
+    type Output = Force;
+    fn add(self, other: Force) -> Force {
+        Force { newtons: self.newtons + other.newtons, scale: self.scale }
+    }
+}
+
+impl Sub for Force {
+    type Output = Force;
+    fn sub(self, other: Force) -> Force {
+        Force { newtons: self.newtons - other.newtons, scale: self.scale }
+    }
+}
+
+impl Mul<f64> for Force {
+    type Output = Force;
+    fn mul(self, factor: f64) -> Force {
+        Force { newtons: self.newtons * factor, scale: self.scale }
+    }
+}
+
+impl Div<f64> for Force {
+    type Output = Force;
+    fn div(self, divisor: f64) -> Force {
+        Force { newtons: self.newtons / divisor, scale: self.scale }
+    }
+}
+
+impl Div for Force {
+    type Output = f64;
+    fn div(self, other: Force) -> f64 {
+        self.newtons / other.newtons
+    }
+}
+
+impl std::iter::Sum for Force {
+    fn sum<I: Iterator<Item = Force>>(iter: I) -> Force {
+        iter.fold(ZERO, |total, force| total + force)
+    }
+}
+
+impl From<Force> for f64 {
+    fn from(force: Force) -> f64 {
+        let Force { newtons, scale } = force;
+        match scale {
+            Scale::Newton        => newtons,
+            Scale::Kilonewton    => newtons / KILONEWTONS_TO_NEWTONS,
+            Scale::Meganewton    => newtons / MEGANEWTONS_TO_NEWTONS,
+            Scale::Giganewton    => newtons / GIGANEWTONS_TO_NEWTONS,
+            Scale::PoundForce    => newtons / POUNDS_FORCE_TO_NEWTONS,
+            Scale::KilogramForce => newtons / KILOGRAMS_FORCE_TO_NEWTONS
+        }
+    }
+}
+
+impl fmt::Display for Force {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value: f64 = Force::into(*self);
+        let unit = match self.scale {
+            Scale::Newton        => "N",
+            Scale::Kilonewton    => "kN",
+            Scale::Meganewton    => "MN",
+            Scale::Giganewton    => "GN",
+            Scale::PoundForce    => "lbf",
+            Scale::KilogramForce => "kgf"
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            None            => write!(f, "{} {}", value, unit)
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::length;
+use super::length::Length;
+use super::position::Position;
+use super::random;
+use super::random::Rng;
+use super::star::{SpectralType, Star};
+
+/// Stars are generated lazily, one cubic sector at a time.
+pub const SECTOR_SIZE_PARSECS: f64 = 10.0;
+pub const SECTOR_VOLUME_CUBIC_PARSECS: f64 = SECTOR_SIZE_PARSECS * SECTOR_SIZE_PARSECS * SECTOR_SIZE_PARSECS;
+
+/// A cubic sector of the galaxy, indexed from the galactic centre.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Sector {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64
+}
+
+impl Sector {
+    pub fn new(x: i64, y: i64, z: i64) -> Sector {
+        Sector { x, y, z }
+    }
+
+    pub fn containing(position: Position) -> Sector {
+        Sector {
+            x: (position.x.in_parsecs() / SECTOR_SIZE_PARSECS).floor() as i64,
+            y: (position.y.in_parsecs() / SECTOR_SIZE_PARSECS).floor() as i64,
+            z: (position.z.in_parsecs() / SECTOR_SIZE_PARSECS).floor() as i64
+        }
+    }
+
+    /// The corner of the sector nearest negative infinity on every axis.
+    pub fn corner(self) -> Position {
+        Position::scaled(self.x as f64 * SECTOR_SIZE_PARSECS,
+                         self.y as f64 * SECTOR_SIZE_PARSECS,
+                         self.z as f64 * SECTOR_SIZE_PARSECS,
+                         length::Scale::Parsec)
+    }
+
+    pub fn center(self) -> Position {
+        let half = SECTOR_SIZE_PARSECS / 2.0;
+        self.corner() + Position::scaled(half, half, half, length::Scale::Parsec)
+    }
+}
+
+/// Identifies a generated star by its sector and its index within that sector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StarId {
+    pub sector: Sector,
+    pub index: u32
+}
+
+/// Written as the sector's coordinates, then the index.
+impl fmt::Display for StarId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}#{}", self.sector.x, self.sector.y, self.sector.z, self.index)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GalaxyStar {
+    pub id: StarId,
+    /// Everything else about the star and its system is generated from this.
+    pub seed: u64,
+    pub position: Position,
+    pub spectral_type: SpectralType
+}
+
+impl GalaxyStar {
+    /// The star's physical properties, generated from its seed.
+    pub fn star(&self) -> Star {
+        let mut rng = Rng::new(random::derive(self.seed, STAR_KEY));
+        let mut star = Star::main_sequence(self.spectral_type, rng.next_f64());
+        star.age = rng.next_f64() * star.main_sequence_lifetime().min(MAX_STELLAR_AGE);
+        star
+    }
+
+    /// The seed of the star's planetary system, from which each body's seed derives.
+    pub fn system_seed(&self) -> u64 {
+        random::derive(self.seed, SYSTEM_KEY)
+    }
+}
+
+/// Keys for deriving the seeds of a star's properties and its planetary system from the star's own seed.
+pub const STAR_KEY: u64 = 1;
+pub const SYSTEM_KEY: u64 = 2;
+/// No star is older than the galaxy, in years.
+pub const MAX_STELLAR_AGE: f64 = 1.3e10;
+
+/// A seeded model of a spiral galaxy: an exponential disk with logarithmic spiral arms and a Gaussian bulge.
+/// The defaults approximate The Milky Way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Galaxy {
+    pub seed: u64,
+    /// Where the disk density is normalised to `local_density`, i.e. the Sun's distance from the galactic centre.
+    pub solar_radius: Length,
+    /// Stars per cubic parsec in the mid-plane at `solar_radius`, averaged over the arms.
+    pub local_density: f64,
+    pub disk_scale_length: Length,
+    pub disk_scale_height: Length,
+    pub disk_radius: Length,
+    /// Stars per cubic parsec at the very centre of the bulge.
+    pub bulge_density: f64,
+    pub bulge_radius: Length,
+    pub arms: u32,
+    pub arm_pitch_degrees: f64,
+    /// How much denser the arms are than the disk average; 0.0 gives no arms and 1.0 gives empty inter-arm gaps.
+    pub arm_contrast: f64
+}
+
+impl Galaxy {
+    pub fn new(seed: u64) -> Galaxy {
+        Galaxy {
+            seed,
+            solar_radius:      Length::kpc( 8.2),
+            local_density:     0.14,
+            disk_scale_length: Length::kpc( 2.6),
+            disk_scale_height: Length::kpc( 0.3),
+            disk_radius:       Length::kpc(15.0),
+            bulge_density:     5.0,
+            bulge_radius:      Length::kpc( 0.7),
+            arms:              4,
+            arm_pitch_degrees: 12.0,
+            arm_contrast:      0.5
+        }
+    }
+
+    /// A position in the solar neighbourhood, in the mid-plane at `solar_radius`.
+    pub fn solar_position(&self) -> Position {
+        Position::new(self.solar_radius, length::ZERO, length::ZERO)
+    }
+
+    /// The stellar density at a position, in stars per cubic parsec.
+    pub fn density(&self, position: Position) -> f64 {
+        let x = position.x.in_parsecs();
+        let y = position.y.in_parsecs();
+        let z = position.z.in_parsecs();
+        let radius = libm::hypot(x, z);
+        let disk = if radius > self.disk_radius.in_parsecs() {
+            0.0
+        } else {
+            self.local_density
+                * libm::exp(-(radius - self.solar_radius.in_parsecs()) / self.disk_scale_length.in_parsecs())
+                * libm::exp(-y.abs() / self.disk_scale_height.in_parsecs())
+                * self.arm_factor(radius, libm::atan2(z, x))
+        };
+        let bulge_distance = (x * x + y * y + z * z).sqrt() / self.bulge_radius.in_parsecs();
+        let bulge = self.bulge_density * libm::exp(-bulge_distance * bulge_distance);
+        disk + bulge
+    }
+
+    /// The density multiplier of the spiral arms, which fade out inside the bulge.
+    fn arm_factor(&self, radius_parsecs: f64, angle: f64) -> f64 {
+        let bulge_radius = self.bulge_radius.in_parsecs();
+        if radius_parsecs < bulge_radius * 0.01 {
+            return 1.0;
+        }
+        let winding = libm::log(radius_parsecs / bulge_radius) / libm::tan(self.arm_pitch_degrees.to_radians());
+        let relative = radius_parsecs / bulge_radius;
+        let fade = 1.0 - libm::exp(-relative * relative);
+        1.0 + self.arm_contrast * fade * libm::cos(self.arms as f64 * (angle - winding))
+    }
+
+    pub fn expected_star_count(&self, sector: Sector) -> f64 {
+        self.density(sector.center()) * SECTOR_VOLUME_CUBIC_PARSECS
+    }
+
+    /// The seed of a sector, which each of its stars' seeds derives from.
+    pub fn sector_seed(&self, sector: Sector) -> u64 {
+        random::derive(random::derive(random::derive(self.seed, sector.x as u64), sector.y as u64), sector.z as u64)
+    }
+
+    /// The stars of one sector, generated lazily.
+    /// The same galaxy seed always gives the same stars, whatever order sectors are visited in.
+    pub fn sector_stars(&self, sector: Sector) -> SectorStars {
+        let seed = self.sector_seed(sector);
+        let count = Rng::new(seed).poisson(self.expected_star_count(sector)) as u32;
+        SectorStars { sector, seed, index: 0, count }
+    }
+
+    /// Any star of the galaxy by its id, without generating anything else; `None` if the sector has fewer stars.
+    pub fn star(&self, id: StarId) -> Option<GalaxyStar> {
+        let stars = self.sector_stars(id.sector);
+        if id.index < stars.count { Some(stars.star(id.index)) } else { None }
+    }
+
+    /// Every sector that overlaps a sphere.
+    pub fn sectors_within(&self, center: Position, radius: Length) -> impl Iterator<Item = Sector> {
+        let low = Sector::containing(center - Position::new(radius, radius, radius));
+        let high = Sector::containing(center + Position::new(radius, radius, radius));
+        (low.x..=high.x).flat_map(move |x| {
+            (low.y..=high.y).flat_map(move |y| {
+                (low.z..=high.z).map(move |z| Sector::new(x, y, z))
+            })
+        })
+    }
+
+    /// Every star within a sphere, generated lazily sector by sector so memory use stays bounded.
+    pub fn stars_within(&self, center: Position, radius: Length) -> impl Iterator<Item = GalaxyStar> + '_ {
+        self.sectors_within(center, radius)
+            .flat_map(move |sector| self.sector_stars(sector))
+            .filter(move |star| star.position.distance(center) <= radius)
+    }
+}
+
+/// A lazy iterator over the stars of a sector.
+#[derive(Clone, Debug)]
+pub struct SectorStars {
+    sector: Sector,
+    seed: u64,
+    index: u32,
+    count: u32
+}
+
+impl SectorStars {
+    /// Any star of the sector, without generating the ones before it.
+    pub fn star(&self, index: u32) -> GalaxyStar {
+        let seed = random::derive(self.seed, index as u64);
+        let mut rng = Rng::new(seed);
+        let corner = self.sector.corner();
+        let offset = Position::scaled(rng.range_f64(0.0..SECTOR_SIZE_PARSECS),
+                                      rng.range_f64(0.0..SECTOR_SIZE_PARSECS),
+                                      rng.range_f64(0.0..SECTOR_SIZE_PARSECS),
+                                      length::Scale::Parsec);
+        GalaxyStar {
+            id: StarId { sector: self.sector, index },
+            seed,
+            position: corner + offset,
+            spectral_type: SpectralType::sample_main_sequence(rng.next_f64())
+        }
+    }
+}
+
+impl Iterator for SectorStars {
+    type Item = GalaxyStar;
+
+    fn next(&mut self) -> Option<GalaxyStar> {
+        if self.index < self.count {
+            let star = self.star(self.index);
+            self.index += 1;
+            Some(star)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SectorStars {}
@@ -0,0 +1,404 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use super::autopilot::{Autopilot, AutopilotCommand, AutopilotOrder};
+use super::camera::{CameraController, CameraSystem, FocusCamera};
+use super::clock::GameClock;
+use super::flight::{PlayerShip, ShipControls};
+use super::floating_origin::WorldPosition;
+use super::galaxy::StarId;
+use super::length;
+use super::market::{Market, DOCKING_RANGE_METERS};
+use super::mission::{AbandonMission, AcceptMission, MissionLog, MissionStatus};
+use super::route::{PlotRoute, RouteOptions};
+use super::save::{LoadGame, SaveGame, SaveSlot};
+use super::star_catalogue::{browser_origin, CatalogueBrowser, StarCatalogue};
+use super::survey::{Scan, Scanning};
+use super::universe::{StarIndex, UniverseSettings};
+
+/// The entity the player has selected, if any.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Selection(pub Option<Entity>);
+
+/// Keys for the player's actions.
+pub struct GameplayBindings {
+    /// Selects the star nearest the camera's focus, other than the one already selected.
+    pub select_nearest_star: KeyCode,
+    /// Follows the selected entity with the camera.
+    pub follow_selection: KeyCode,
+    pub clear_selection: KeyCode,
+    pub quick_save: KeyCode,
+    pub quick_load: KeyCode,
+    pub pause: KeyCode,
+    pub warp_up: KeyCode,
+    pub warp_down: KeyCode,
+    /// Follows the player's ship with the camera.
+    pub follow_ship: KeyCode,
+    pub throttle_up: KeyCode,
+    pub throttle_down: KeyCode,
+    pub cut_throttle: KeyCode,
+    pub pitch_up: KeyCode,
+    pub pitch_down: KeyCode,
+    pub yaw_left: KeyCode,
+    pub yaw_right: KeyCode,
+    pub roll_left: KeyCode,
+    pub roll_right: KeyCode,
+    /// Translation with the thrusters.
+    pub thrust_up: KeyCode,
+    pub thrust_down: KeyCode,
+    pub thrust_left: KeyCode,
+    pub thrust_right: KeyCode,
+    pub thrust_forward: KeyCode,
+    pub thrust_back: KeyCode,
+    /// The throttle's change per second held.
+    pub throttle_rate: f64,
+    pub circularise: KeyCode,
+    /// Matches velocity with the selection.
+    pub match_velocity: KeyCode,
+    /// Meets the selected planet.
+    pub rendezvous: KeyCode,
+    pub cancel_autopilot: KeyCode,
+    /// Plots a route from the star nearest the ship to the selected star.
+    pub plot_route: KeyCode,
+    /// Takes up the first mission offered by the market the ship is docked at.
+    pub accept_mission: KeyCode,
+    /// Gives up the earliest mission taken up.
+    pub abandon_mission: KeyCode,
+    /// Scans the selection with the ship's sensors, or stops scanning it.
+    pub scan: KeyCode,
+    /// Opens and closes the star catalogue.
+    pub toggle_catalogue: KeyCode,
+    pub catalogue_next_page: KeyCode,
+    pub catalogue_previous_page: KeyCode,
+    /// Cycles what the catalogue is sorted by, and reverses it.
+    pub catalogue_sort: KeyCode,
+    pub catalogue_reverse: KeyCode,
+    /// Cycles the spectral type the catalogue shows.
+    pub catalogue_filter: KeyCode,
+    /// Selects the next star on the catalogue's page.
+    pub catalogue_select: KeyCode
+}
+
+impl Default for GameplayBindings {
+    fn default() -> GameplayBindings {
+        GameplayBindings {
+            select_nearest_star: KeyCode::N,
+            follow_selection: KeyCode::F,
+            clear_selection: KeyCode::Escape,
+            quick_save: KeyCode::F5,
+            quick_load: KeyCode::F9,
+            pause: KeyCode::Space,
+            warp_up: KeyCode::Period,
+            warp_down: KeyCode::Comma,
+            follow_ship: KeyCode::Home,
+            throttle_up: KeyCode::T,
+            throttle_down: KeyCode::G,
+            cut_throttle: KeyCode::X,
+            pitch_up: KeyCode::K,
+            pitch_down: KeyCode::I,
+            yaw_left: KeyCode::J,
+            yaw_right: KeyCode::L,
+            roll_left: KeyCode::U,
+            roll_right: KeyCode::O,
+            thrust_up: KeyCode::Up,
+            thrust_down: KeyCode::Down,
+            thrust_left: KeyCode::Left,
+            thrust_right: KeyCode::Right,
+            thrust_forward: KeyCode::PageUp,
+            thrust_back: KeyCode::PageDown,
+            throttle_rate: 0.5,
+            circularise: KeyCode::C,
+            match_velocity: KeyCode::V,
+            rendezvous: KeyCode::R,
+            cancel_autopilot: KeyCode::Back,
+            plot_route: KeyCode::P,
+            accept_mission: KeyCode::M,
+            abandon_mission: KeyCode::B,
+            scan: KeyCode::Y,
+            toggle_catalogue: KeyCode::F3,
+            catalogue_next_page: KeyCode::RBracket,
+            catalogue_previous_page: KeyCode::LBracket,
+            catalogue_sort: KeyCode::Slash,
+            catalogue_reverse: KeyCode::Apostrophe,
+            catalogue_filter: KeyCode::Semicolon,
+            catalogue_select: KeyCode::Backslash
+        }
+    }
+}
+
+pub fn select_star(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    index: Res<StarIndex>,
+    mut selection: ResMut<Selection>,
+    mut focus: EventWriter<FocusCamera>,
+    cameras: Query<&CameraController>
+) {
+    if keys.just_pressed(bindings.clear_selection) {
+        selection.0 = None;
+    }
+    if keys.just_pressed(bindings.select_nearest_star) {
+        let center = match cameras.iter().next() {
+            Some(controller) => controller.focus.to_position(length::Scale::Parsec),
+            None             => return
+        };
+        let current = selection.0;
+        if let Some(nearest) = index.0.nearest_filtered(center, 1, |entity| Some(*entity) != current).first() {
+            selection.0 = Some(*nearest.item);
+            focus.send(FocusCamera { target: *nearest.item, follow: false });
+        }
+    }
+    if keys.just_pressed(bindings.follow_selection) {
+        if let Some(target) = selection.0 {
+            focus.send(FocusCamera { target, follow: true });
+        }
+    }
+}
+
+/// The quick save slot.
+pub const QUICK_SAVE: &str = "quicksave";
+
+pub fn quick_save(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    mut saves: EventWriter<SaveGame>,
+    mut loads: EventWriter<LoadGame>
+) {
+    if keys.just_pressed(bindings.quick_save) {
+        saves.send(SaveGame(SaveSlot::Named(QUICK_SAVE.to_string())));
+    }
+    if keys.just_pressed(bindings.quick_load) {
+        loads.send(LoadGame(SaveSlot::Named(QUICK_SAVE.to_string())));
+    }
+}
+
+pub fn control_clock(keys: Res<Input<KeyCode>>, bindings: Res<GameplayBindings>, mut clock: ResMut<GameClock>) {
+    if keys.just_pressed(bindings.pause) {
+        clock.paused = !clock.paused;
+    }
+    if keys.just_pressed(bindings.warp_up) {
+        clock.warp_up();
+    }
+    if keys.just_pressed(bindings.warp_down) {
+        clock.warp_down();
+    }
+}
+
+/// Flies the player's ship from the keyboard. Touching the controls takes them back from the autopilot.
+pub fn pilot_ship(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    bindings: Res<GameplayBindings>,
+    mut ships: Query<(Entity, &mut ShipControls, Option<&Autopilot>), With<PlayerShip>>
+) {
+    let (entity, mut controls, autopilot) = match ships.single_mut() {
+        Ok(ship) => ship,
+        Err(_)   => return
+    };
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        (keys.pressed(positive) as i32 - keys.pressed(negative) as i32) as f64
+    };
+    let throttle = axis(bindings.throttle_up, bindings.throttle_down);
+    let rotation = DVec3::new(axis(bindings.pitch_up, bindings.pitch_down),
+                              axis(bindings.yaw_left, bindings.yaw_right),
+                              axis(bindings.roll_left, bindings.roll_right));
+    let translation = DVec3::new(axis(bindings.thrust_right, bindings.thrust_left),
+                                 axis(bindings.thrust_up, bindings.thrust_down),
+                                 axis(bindings.thrust_back, bindings.thrust_forward));
+    let cut = keys.just_pressed(bindings.cut_throttle);
+    if autopilot.is_some() {
+        if throttle == 0.0 && rotation == DVec3::ZERO && translation == DVec3::ZERO && !cut {
+            return;
+        }
+        commands.entity(entity).remove::<Autopilot>();
+        controls.throttle = 0.0;
+    }
+    controls.throttle = if cut { 0.0 } else { (controls.throttle + throttle * bindings.throttle_rate * time.delta_seconds_f64()).clamp(0.0, 1.0) };
+    controls.rotation = rotation;
+    controls.translation = translation;
+}
+
+pub fn command_autopilot(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    selection: Res<Selection>,
+    ships: Query<Entity, With<PlayerShip>>,
+    mut commands: EventWriter<AutopilotCommand>,
+    mut focus: EventWriter<FocusCamera>
+) {
+    let ship = match ships.single() {
+        Ok(ship) => ship,
+        Err(_)   => return
+    };
+    if keys.just_pressed(bindings.follow_ship) {
+        focus.send(FocusCamera { target: ship, follow: true });
+    }
+    let order = if keys.just_pressed(bindings.circularise) {
+        Some(AutopilotOrder::Circularise)
+    } else if keys.just_pressed(bindings.cancel_autopilot) {
+        Some(AutopilotOrder::Cancel)
+    } else if keys.just_pressed(bindings.match_velocity) {
+        selection.0.map(|target| AutopilotOrder::MatchVelocity { target })
+    } else if keys.just_pressed(bindings.rendezvous) {
+        selection.0.map(|target| AutopilotOrder::Rendezvous { target })
+    } else {
+        None
+    };
+    if let Some(order) = order {
+        commands.send(AutopilotCommand { ship, order });
+    }
+}
+
+pub fn plot_route(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    selection: Res<Selection>,
+    index: Res<StarIndex>,
+    ships: Query<(Entity, &WorldPosition), With<PlayerShip>>,
+    stars: Query<&StarId>,
+    mut plots: EventWriter<PlotRoute>
+) {
+    if !keys.just_pressed(bindings.plot_route) {
+        return;
+    }
+    let (ship, position) = match ships.single() {
+        Ok(ship) => ship,
+        Err(_)   => return
+    };
+    let nearest = index.0.nearest(position.to_position(length::Scale::LightYear), 1);
+    let from = nearest.first().and_then(|nearest| stars.get(*nearest.item).ok());
+    let to = selection.0.and_then(|entity| stars.get(entity).ok());
+    if let (Some(from), Some(to)) = (from, to) {
+        plots.send(PlotRoute { ship, from: *from, to: *to, options: RouteOptions::default() });
+    }
+}
+
+pub fn manage_missions(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    log: Res<MissionLog>,
+    ships: Query<&WorldPosition, With<PlayerShip>>,
+    markets: Query<(Entity, &WorldPosition), With<Market>>,
+    mut accepts: EventWriter<AcceptMission>,
+    mut abandons: EventWriter<AbandonMission>
+) {
+    if keys.just_pressed(bindings.accept_mission) {
+        let docked = ships.single().ok().and_then(|position| markets.iter()
+            .find(|(_, market)| market.distance(*position).in_meters() <= DOCKING_RANGE_METERS));
+        if let Some(offer) = docked.and_then(|(market, _)| log.offers(market).next()) {
+            accepts.send(AcceptMission(offer.id));
+        }
+    }
+    if keys.just_pressed(bindings.abandon_mission) {
+        if let Some(mission) = log.with_status(MissionStatus::Active).next() {
+            abandons.send(AbandonMission(mission.id));
+        }
+    }
+}
+
+pub fn scan_selection(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    selection: Res<Selection>,
+    ships: Query<(Entity, Option<&Scanning>), With<PlayerShip>>,
+    mut scans: EventWriter<Scan>
+) {
+    if !keys.just_pressed(bindings.scan) {
+        return;
+    }
+    if let Ok((ship, scanning)) = ships.single() {
+        let target = match (selection.0, scanning) {
+            (Some(target), Some(scanning)) if scanning.target == target => None,
+            (target, _)                                                 => target
+        };
+        scans.send(Scan { ship, target });
+    }
+}
+
+pub fn browse_catalogue(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<GameplayBindings>,
+    (catalogue, settings): (Res<StarCatalogue>, Res<UniverseSettings>),
+    mut browser: ResMut<CatalogueBrowser>,
+    mut selection: ResMut<Selection>,
+    ships: Query<&WorldPosition, With<PlayerShip>>,
+    mut focus: EventWriter<FocusCamera>
+) {
+    if keys.just_pressed(bindings.toggle_catalogue) {
+        browser.open = !browser.open;
+    }
+    if !browser.open {
+        return;
+    }
+    if keys.just_pressed(bindings.catalogue_sort) {
+        browser.sort = browser.sort.next();
+        browser.page = 0;
+    }
+    if keys.just_pressed(bindings.catalogue_reverse) {
+        browser.descending = !browser.descending;
+        browser.page = 0;
+    }
+    if keys.just_pressed(bindings.catalogue_filter) {
+        browser.cycle_spectral_type(&catalogue);
+    }
+    if keys.just_pressed(bindings.catalogue_next_page) {
+        browser.page += 1;
+        browser.cursor = 0;
+    }
+    if keys.just_pressed(bindings.catalogue_previous_page) {
+        browser.page = browser.page.saturating_sub(1);
+        browser.cursor = 0;
+    }
+    // Only queried again when something shown would change, so the browser is left unchanged while idle.
+    let from = browser_origin(ships.single().ok(), &settings);
+    if browser.is_changed() || catalogue.is_changed() || browser.is_stale(from) {
+        browser.refresh(&catalogue, from);
+    }
+    if keys.just_pressed(bindings.catalogue_select) {
+        let chosen = browser.shown.as_ref().filter(|(_, page)| !page.entries.is_empty()).map(|(_, page)| {
+            let row = if selection.0.is_some() && selection.0 == page.entries.get(browser.cursor).and_then(|(entry, _)| entry.entity) {
+                (browser.cursor + 1) % page.entries.len()
+            } else {
+                browser.cursor.min(page.entries.len() - 1)
+            };
+            (row, page.entries[row].0.entity)
+        });
+        if let Some((row, entity)) = chosen {
+            browser.cursor = row;
+            if let Some(target) = entity {
+                selection.0 = Some(target);
+                focus.send(FocusCamera { target, follow: false });
+            }
+        }
+    }
+}
+
+/// Forgets a selection whose entity has gone.
+pub fn validate_selection(mut selection: ResMut<Selection>, entities: Query<&WorldPosition>) {
+    if let Some(entity) = selection.0 {
+        if entities.get(entity).is_err() {
+            selection.0 = None;
+        }
+    }
+}
+
+/// The player's interactions with the universe. Needs the `UniversePlugin`, `CameraPlugin`, `SavePlugin`, `AutopilotPlugin`, `RoutePlugin`, `MissionPlugin`, `SurveyPlugin` and `StarCataloguePlugin`.
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Selection>()
+            .init_resource::<GameplayBindings>()
+            .add_system(validate_selection.system())
+            .add_system(quick_save.system())
+            .add_system(control_clock.system())
+            .add_system(pilot_ship.system())
+            .add_system(command_autopilot.system().before(CameraSystem::Focus))
+            .add_system(plot_route.system())
+            .add_system(manage_missions.system())
+            .add_system(scan_selection.system())
+            .add_system(browse_catalogue.system().before(CameraSystem::Focus))
+            .add_system(select_star.system().before(CameraSystem::Focus));
+    }
+}
@@ -0,0 +1,442 @@
+use bevy::math::DVec3;
+use std::fmt;
+
+use super::length::Length;
+use super::mass::Mass;
+use super::orbit::{Orbit, GRAVITATIONAL_CONSTANT};
+
+/// A point mass. Positions are in meters and velocities in meters per second, in any inertial frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Body {
+    pub mass: Mass,
+    pub position: DVec3,
+    pub velocity: DVec3
+}
+
+impl Body {
+    pub fn new(mass: Mass, position: DVec3, velocity: DVec3) -> Body {
+        Body { mass, position, velocity }
+    }
+
+    /// A body on an orbit around `parent`, at a time in seconds since the epoch.
+    pub fn on_orbit(mass: Mass, orbit: &Orbit, seconds: f64, parent: &Body) -> Body {
+        let (position, velocity) = orbit.state_at(seconds);
+        Body { mass, position: parent.position + position, velocity: parent.velocity + velocity }
+    }
+
+    /// μ = GM, in m³ s⁻².
+    pub fn gravitational_parameter(&self) -> f64 {
+        GRAVITATIONAL_CONSTANT * self.mass.in_kilograms()
+    }
+}
+
+/// How to advance an `NBody` system.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    /// Kick-drift-kick leapfrog, equivalent to velocity Verlet. Second order and symplectic,
+    /// so energy errors stay bounded over long runs; the default for whole systems.
+    Leapfrog,
+    /// Dormand–Prince 5(4) with adaptive steps, keeping each step's relative error under `tolerance`.
+    /// Better for close encounters, where a fixed step is either too coarse or too slow.
+    DormandPrince { tolerance: f64 }
+}
+
+/// Why an integration stopped short.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntegrationError {
+    /// The positions or velocities stopped being finite, trying to step on from this time in seconds.
+    NotFinite { time: f64 },
+    /// The leapfrog was asked for steps of no time, or not a number of seconds at all.
+    InvalidStep { max_step: f64 }
+}
+
+impl fmt::Display for IntegrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrationError::NotFinite { time }       => write!(f, "the bodies' motion stopped being finite at {} s", time),
+            IntegrationError::InvalidStep { max_step } => write!(f, "the leapfrog cannot take steps of {} s", max_step)
+        }
+    }
+}
+
+impl std::error::Error for IntegrationError {}
+
+/// Bodies moving under their mutual gravity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NBody {
+    pub bodies: Vec<Body>,
+    /// Plummer softening, which keeps accelerations finite when bodies pass through each other.
+    pub softening: Length,
+    /// Seconds simulated so far.
+    pub time: f64,
+    /// The step the adaptive integrator will try next, in seconds.
+    pub adaptive_step: Option<f64>
+}
+
+impl NBody {
+    pub fn new(bodies: Vec<Body>) -> NBody {
+        NBody { bodies, softening: Length::m(0.0), time: 0.0, adaptive_step: None }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    fn parameters(&self) -> Vec<f64> {
+        self.bodies.iter().map(Body::gravitational_parameter).collect()
+    }
+
+    /// The acceleration of each body at the given positions, in m s⁻².
+    fn accelerations_at(&self, mu: &[f64], positions: &[DVec3]) -> Vec<DVec3> {
+        let softening = self.softening.in_meters();
+        let softening = softening * softening;
+        let mut accelerations = vec![DVec3::ZERO; positions.len()];
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let separation = positions[j] - positions[i];
+                let distance_squared = separation.length_squared() + softening;
+                let factor = separation / (distance_squared * distance_squared.sqrt());
+                accelerations[i] += factor * mu[j];
+                accelerations[j] -= factor * mu[i];
+            }
+        }
+        accelerations
+    }
+
+    pub fn accelerations(&self) -> Vec<DVec3> {
+        let positions: Vec<DVec3> = self.bodies.iter().map(|body| body.position).collect();
+        self.accelerations_at(&self.parameters(), &positions)
+    }
+
+    /// The gravitational acceleration the bodies exert at a point, such as a massless ship.
+    pub fn acceleration_at(&self, point: DVec3) -> DVec3 {
+        let softening = self.softening.in_meters();
+        self.bodies.iter()
+            .map(|body| {
+                let separation = body.position - point;
+                let distance_squared = separation.length_squared() + softening * softening;
+                separation * (body.gravitational_parameter() / (distance_squared * distance_squared.sqrt()))
+            })
+            .fold(DVec3::ZERO, |sum, acceleration| sum + acceleration)
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.bodies.iter().map(|body| 0.5 * body.mass.in_kilograms() * body.velocity.length_squared()).sum()
+    }
+
+    pub fn potential_energy(&self) -> f64 {
+        let softening = self.softening.in_meters();
+        let mut energy = 0.0;
+        for (i, a) in self.bodies.iter().enumerate() {
+            for b in self.bodies[(i + 1)..].iter() {
+                let distance = ((b.position - a.position).length_squared() + softening * softening).sqrt();
+                energy -= GRAVITATIONAL_CONSTANT * a.mass.in_kilograms() * b.mass.in_kilograms() / distance;
+            }
+        }
+        energy
+    }
+
+    /// Total energy in joules, conserved by the true motion.
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Total momentum in kg m s⁻¹.
+    pub fn momentum(&self) -> DVec3 {
+        self.bodies.iter().fold(DVec3::ZERO, |sum, body| sum + body.velocity * body.mass.in_kilograms())
+    }
+
+    /// Total angular momentum about the origin, in kg m² s⁻¹.
+    pub fn angular_momentum(&self) -> DVec3 {
+        self.bodies.iter().fold(DVec3::ZERO, |sum, body| sum + body.position.cross(body.velocity) * body.mass.in_kilograms())
+    }
+
+    pub fn center_of_mass(&self) -> DVec3 {
+        let total: f64 = self.bodies.iter().map(|body| body.mass.in_kilograms()).sum();
+        self.bodies.iter().fold(DVec3::ZERO, |sum, body| sum + body.position * body.mass.in_kilograms()) / total
+    }
+
+    /// Shifts to the centre of mass frame, so the system does not drift.
+    pub fn to_center_of_mass_frame(&mut self) {
+        let total: f64 = self.bodies.iter().map(|body| body.mass.in_kilograms()).sum();
+        let center = self.center_of_mass();
+        let velocity = self.momentum() / total;
+        for body in self.bodies.iter_mut() {
+            body.position -= center;
+            body.velocity -= velocity;
+        }
+    }
+
+    /// One kick-drift-kick leapfrog step of `dt` seconds.
+    pub fn leapfrog_step(&mut self, dt: f64) {
+        let mu = self.parameters();
+        let mut positions: Vec<DVec3> = self.bodies.iter().map(|body| body.position).collect();
+        let accelerations = self.accelerations_at(&mu, &positions);
+        for (body, acceleration) in self.bodies.iter_mut().zip(accelerations) {
+            body.velocity += acceleration * (dt / 2.0);
+            body.position += body.velocity * dt;
+        }
+        positions.iter_mut().zip(self.bodies.iter()).for_each(|(position, body)| *position = body.position);
+        let accelerations = self.accelerations_at(&mu, &positions);
+        for (body, acceleration) in self.bodies.iter_mut().zip(accelerations) {
+            body.velocity += acceleration * (dt / 2.0);
+        }
+        self.time += dt;
+    }
+
+    /// The derivative of the state, as (velocities, accelerations).
+    fn derivative(&self, mu: &[f64], positions: &[DVec3], velocities: &[DVec3]) -> (Vec<DVec3>, Vec<DVec3>) {
+        (velocities.to_vec(), self.accelerations_at(mu, positions))
+    }
+
+    /// Tries one Dormand–Prince step of `dt` seconds, returning the new positions and velocities and the error estimate,
+    /// which is NaN if they are not finite.
+    fn dormand_prince_trial(&self, mu: &[f64], dt: f64) -> (Vec<DVec3>, Vec<DVec3>, f64) {
+        const C: [[f64; 6]; 6] = [
+            [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+            [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+            [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+            [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+            [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0]
+        ];
+        // The fifth order weights less the embedded fourth order ones.
+        const E: [f64; 7] = [
+            71.0 / 57600.0, 0.0, -71.0 / 16695.0, 71.0 / 1920.0, -17253.0 / 339200.0, 22.0 / 525.0, -1.0 / 40.0
+        ];
+        let positions: Vec<DVec3> = self.bodies.iter().map(|body| body.position).collect();
+        let velocities: Vec<DVec3> = self.bodies.iter().map(|body| body.velocity).collect();
+        let mut stages: Vec<(Vec<DVec3>, Vec<DVec3>)> = vec![self.derivative(mu, &positions, &velocities)];
+        for row in C.iter() {
+            let mut stage_positions = positions.clone();
+            let mut stage_velocities = velocities.clone();
+            for (weight, (dx, dv)) in row.iter().zip(stages.iter()) {
+                if *weight != 0.0 {
+                    for i in 0..positions.len() {
+                        stage_positions[i] += dx[i] * (dt * weight);
+                        stage_velocities[i] += dv[i] * (dt * weight);
+                    }
+                }
+            }
+            if stages.len() == C.len() {
+                // The last row is the fifth order solution itself; its derivative gives the error estimate.
+                stages.push(self.derivative(mu, &stage_positions, &stage_velocities));
+                let mut error: f64 = 0.0;
+                for i in 0..positions.len() {
+                    let (mut dx, mut dv) = (DVec3::ZERO, DVec3::ZERO);
+                    for (weight, (sx, sv)) in E.iter().zip(stages.iter()) {
+                        dx += sx[i] * (dt * weight);
+                        dv += sv[i] * (dt * weight);
+                    }
+                    let scale_x = positions[i].length().max(stage_positions[i].length()).max(1.0);
+                    let scale_v = velocities[i].length().max(stage_velocities[i].length()).max(1e-9);
+                    error = error.max(dx.length() / scale_x).max(dv.length() / scale_v);
+                }
+                // `max` passes over NaN, so a state that is no longer finite has to be caught on its own.
+                if stage_positions.iter().chain(stage_velocities.iter()).any(|vector| !vector.is_finite()) {
+                    error = f64::NAN;
+                }
+                return (stage_positions, stage_velocities, error);
+            }
+            stages.push(self.derivative(mu, &stage_positions, &stage_velocities));
+        }
+        unreachable!()
+    }
+
+    /// Advances `duration` seconds with adaptive Dormand–Prince steps, returning how many steps were taken.
+    /// Stops with an error, leaving the bodies as they were after the last good step, if the state stops being finite.
+    pub fn dormand_prince(&mut self, duration: f64, tolerance: f64) -> Result<usize, IntegrationError> {
+        if duration.is_nan() || duration <= 0.0 {
+            return Ok(0);
+        }
+        let mu = self.parameters();
+        let end = self.time + duration;
+        let mut step = match self.adaptive_step {
+            Some(step) if step > 0.0 && step.is_finite() => step.min(duration),
+            _                                            => duration
+        };
+        let mut steps = 0;
+        while self.time < end {
+            // The last step is cut short to land on the end; the step it was cut from is still the one to try next time.
+            let last = step >= end - self.time;
+            let dt = if last { end - self.time } else { step };
+            let (positions, velocities, error) = self.dormand_prince_trial(&mu, dt);
+            if !error.is_finite() {
+                return Err(IntegrationError::NotFinite { time: self.time });
+            }
+            let accepted = error <= tolerance || dt <= duration * 1e-12;
+            if accepted {
+                for ((body, position), velocity) in self.bodies.iter_mut().zip(positions).zip(velocities) {
+                    body.position = position;
+                    body.velocity = velocity;
+                }
+                self.time = if last { end } else { self.time + dt };
+                steps += 1;
+            }
+            if !(last && accepted) {
+                // The usual controller: aim a little under the tolerance, and never change the step too abruptly.
+                let factor = if error == 0.0 { 5.0 } else { (0.9 * (tolerance / error).powf(0.2)).clamp(0.2, 5.0) };
+                step = dt * factor;
+            }
+        }
+        self.adaptive_step = Some(step);
+        Ok(steps)
+    }
+
+    /// Advances `duration` seconds. The leapfrog takes fixed steps of at most `max_step` seconds, which must be positive.
+    pub fn advance(&mut self, integrator: Integrator, duration: f64, max_step: f64) -> Result<(), IntegrationError> {
+        if duration.is_nan() || duration <= 0.0 {
+            return Ok(());
+        }
+        match integrator {
+            Integrator::Leapfrog => {
+                if max_step.is_nan() || max_step <= 0.0 {
+                    return Err(IntegrationError::InvalidStep { max_step });
+                }
+                let steps = (duration / max_step).ceil().max(1.0);
+                for _ in 0..steps as u64 {
+                    self.leapfrog_step(duration / steps);
+                }
+                Ok(())
+            }
+            Integrator::DormandPrince { tolerance } => self.dormand_prince(duration, tolerance).map(|_| ())
+        }
+    }
+}
+
+/// The radius of a body's sphere of influence, inside which patched conics treat it as the only attractor.
+pub fn sphere_of_influence(orbit: &Orbit, mass: Mass) -> Length {
+    orbit.semi_major_axis * (mass.in_kilograms() / orbit.central_mass.in_kilograms()).powf(0.4)
+}
+
+/// A body that patched conics can switch to: its state, and its sphere of influence.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attractor {
+    pub body: Body,
+    /// `None` for the root of the hierarchy, which dominates everywhere else.
+    pub sphere_of_influence: Option<Length>
+}
+
+/// The attractor whose sphere of influence contains the point, preferring the smallest; otherwise the root.
+pub fn dominant_attractor(attractors: &[Attractor], point: DVec3) -> Option<usize> {
+    attractors.iter()
+        .enumerate()
+        .filter_map(|(index, attractor)| match attractor.sphere_of_influence {
+            Some(radius) if (point - attractor.body.position).length() <= radius.in_meters() => Some((index, radius.in_meters())),
+            Some(_) => None,
+            None => Some((index, f64::INFINITY))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(index, _)| index)
+}
+
+/// Patched conics: the Keplerian orbit of a light body about whichever attractor dominates where it is,
+/// at a time in seconds since the epoch. `None` if there is no attractor or the body is unbound.
+pub fn patched_conic(attractors: &[Attractor], position: DVec3, velocity: DVec3, seconds: f64) -> Option<(usize, Orbit)> {
+    let index = dominant_attractor(attractors, position)?;
+    let attractor = &attractors[index].body;
+    let orbit = Orbit::from_state(position - attractor.position, velocity - attractor.velocity, attractor.mass, seconds)?;
+    Some((index, orbit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::length;
+
+    /// The sun and a light body on an eccentric orbit one AU across, in the sun's rest frame.
+    fn two_body() -> (NBody, Orbit) {
+        let sun = Body::new(Mass::Msol(1.0), DVec3::ZERO, DVec3::ZERO);
+        let orbit = Orbit {
+            eccentricity: 0.3,
+            inclination: 0.2,
+            ascending_node: 1.0,
+            argument_of_periapsis: 0.5,
+            mean_anomaly_at_epoch: 2.0,
+            ..Orbit::circular(Length::AU(1.0), sun.mass)
+        };
+        let probe = Body::on_orbit(Mass::kg(1.0), &orbit, 0.0, &sun);
+        (NBody::new(vec![sun, probe]), orbit)
+    }
+
+    fn relative_drift(before: f64, after: f64) -> f64 {
+        ((after - before) / before).abs()
+    }
+
+    fn drifts(system: &NBody, energy: f64, angular_momentum: DVec3) -> (f64, f64) {
+        (relative_drift(energy, system.total_energy()),
+         (system.angular_momentum() - angular_momentum).length() / angular_momentum.length())
+    }
+
+    #[test]
+    fn leapfrog_conserves_energy_and_angular_momentum() {
+        let (mut system, orbit) = two_body();
+        let (energy, angular_momentum) = (system.total_energy(), system.angular_momentum());
+        system.advance(Integrator::Leapfrog, 3.0 * orbit.period(), orbit.period() / 5_000.0).unwrap();
+        let (energy_drift, angular_momentum_drift) = drifts(&system, energy, angular_momentum);
+        assert!(energy_drift < 1e-5);
+        assert!(angular_momentum_drift < 1e-10);
+    }
+
+    #[test]
+    fn dormand_prince_conserves_energy_and_angular_momentum() {
+        let (mut system, orbit) = two_body();
+        let (energy, angular_momentum) = (system.total_energy(), system.angular_momentum());
+        system.dormand_prince(3.0 * orbit.period(), 1e-10).unwrap();
+        let (energy_drift, angular_momentum_drift) = drifts(&system, energy, angular_momentum);
+        assert!(energy_drift < 1e-8);
+        assert!(angular_momentum_drift < 1e-8);
+    }
+
+    #[test]
+    fn orbits_agree_with_kepler_after_several_periods() {
+        let (system, orbit) = two_body();
+        let duration = 3.0 * orbit.period();
+        let expected = orbit.position_at(duration);
+        let integrators = [(Integrator::Leapfrog, 1e-4), (Integrator::DormandPrince { tolerance: 1e-10 }, 1e-7)];
+        for (integrator, tolerance) in integrators.iter() {
+            let mut system = system.clone();
+            system.advance(*integrator, duration, orbit.period() / 5_000.0).unwrap();
+            let position = system.bodies[1].position - system.bodies[0].position;
+            assert!((position - expected).length() < tolerance * length::AU_TO_METERS, "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn zero_durations_leave_the_system_as_it_was() {
+        let (mut system, _) = two_body();
+        let before = system.clone();
+        assert_eq!(system.dormand_prince(0.0, 1e-10), Ok(0));
+        assert_eq!(system.dormand_prince(-10.0, 1e-10), Ok(0));
+        system.advance(Integrator::Leapfrog, 0.0, 60.0).unwrap();
+        assert_eq!(system, before);
+        // A zero step is never kept for the next run to loop on.
+        system.adaptive_step = Some(0.0);
+        assert!(system.dormand_prince(3600.0, 1e-10).unwrap() > 0);
+        assert!(system.adaptive_step.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn non_finite_motion_is_an_error() {
+        let (mut system, _) = two_body();
+        system.bodies[1].velocity = DVec3::new(f64::NAN, 0.0, 0.0);
+        assert_eq!(system.dormand_prince(3600.0, 1e-10), Err(IntegrationError::NotFinite { time: 0.0 }));
+    }
+
+    #[test]
+    fn leapfrog_steps_must_take_time() {
+        let (mut system, _) = two_body();
+        let before = system.clone();
+        assert_eq!(system.advance(Integrator::Leapfrog, 3600.0, 0.0), Err(IntegrationError::InvalidStep { max_step: 0.0 }));
+        assert_eq!(system.advance(Integrator::Leapfrog, 3600.0, -60.0), Err(IntegrationError::InvalidStep { max_step: -60.0 }));
+        assert!(matches!(system.advance(Integrator::Leapfrog, 3600.0, f64::NAN), Err(IntegrationError::InvalidStep { .. })));
+        assert_eq!(system, before);
+        // The adaptive integrator chooses its own steps.
+        assert_eq!(system.advance(Integrator::DormandPrince { tolerance: 1e-10 }, 3600.0, 0.0), Ok(()));
+        assert_eq!(system.time, 3600.0);
+    }
+}
@@ -0,0 +1,572 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY};
+use super::mass;
+use super::mass::Mass;
+use super::planet::{Composition, Planet, PlanetKind};
+use super::power;
+use super::power::Power;
+use super::random;
+use super::random::Rng;
+use super::scene;
+
+/// The recipes factories know, relative to the assets directory.
+pub const RECIPES: &str = "industry/recipes.ron";
+
+/// The raw materials bodies yield.
+pub const METAL_ORE: &str = "metal ore";
+pub const SILICATES: &str = "silicates";
+pub const RARE_EARTHS: &str = "rare earths";
+pub const WATER_ICE: &str = "water ice";
+pub const HYDROGEN: &str = "hydrogen";
+pub const HELIUM_3: &str = "helium-3";
+
+/// The fraction of a body's mass near enough its surface, or the top of its atmosphere, to extract.
+pub const ACCESSIBLE_FRACTION: f64 = 1.0e-9;
+/// The most batches one factory finishes in a tick, however long the tick.
+pub const MAX_BATCHES_PER_TICK: usize = 1000;
+/// Seconds throughput is averaged over, so batches finishing now and then still show as a steady rate.
+pub const THROUGHPUT_SECONDS: f64 = SECONDS_PER_DAY;
+
+const DEPOSITS_KEY: u64 = 0x6465_706f_7369_7473;
+
+/// Anything that can be mined, made or traded, by name. Amounts are always masses.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Commodity(pub String);
+
+impl Commodity {
+    pub fn new(name: &str) -> Commodity {
+        Commodity(name.to_string())
+    }
+}
+
+impl fmt::Display for Commodity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Deposit {
+    pub commodity: Commodity,
+    pub reserves: Mass,
+    /// How readily it gives up its reserves, scaling an extractor's rate; about one is typical.
+    pub richness: f64
+}
+
+/// What can be extracted from a body.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Deposits(pub Vec<Deposit>);
+
+/// A body's deposits, in proportion to what it is made of. Gas giants only give up what can be skimmed from their atmospheres.
+pub fn generate_deposits(planet: &Planet, composition: &Composition, seed: u64) -> Deposits {
+    let mut rng = Rng::new(random::derive(seed, DEPOSITS_KEY));
+    let accessible = planet.mass * ACCESSIBLE_FRACTION;
+    let solid = planet.kind != PlanetKind::GasGiant;
+    let candidates = [
+        (METAL_ORE,   if solid { composition.metals } else { 0.0 }),
+        (RARE_EARTHS, if solid { composition.metals * 1.0e-3 } else { 0.0 }),
+        (SILICATES,   if solid { composition.silicates } else { 0.0 }),
+        (WATER_ICE,   if solid { composition.ices } else { 0.0 }),
+        (HYDROGEN,    composition.gases),
+        (HELIUM_3,    composition.gases * 1.0e-5)
+    ];
+    Deposits(candidates.iter()
+             .filter(|(_, fraction)| *fraction > 0.0)
+             .map(|(name, fraction)| Deposit {
+                 commodity: Commodity::new(name),
+                 reserves: (accessible * *fraction).to_scale(mass::Scale::Tonne),
+                 richness: libm::exp(0.5 * rng.gaussian())
+             })
+             .collect())
+}
+
+/// Turns inputs into outputs, a batch at a time, drawing power while it works.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    pub inputs: Vec<(Commodity, Mass)>,
+    pub outputs: Vec<(Commodity, Mass)>,
+    pub power: Power,
+    /// Seconds a batch takes.
+    pub duration: f64
+}
+
+impl Recipe {
+    pub fn input_mass(&self) -> Mass {
+        self.inputs.iter().map(|(_, amount)| *amount).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    pub fn output_mass(&self) -> Mass {
+        self.outputs.iter().map(|(_, amount)| *amount).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    /// The energy a batch takes, in joules.
+    pub fn energy(&self) -> f64 {
+        self.power.in_watts() * self.duration
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recipes(pub Vec<Recipe>);
+
+impl Recipes {
+    /// Parses recipes, refusing any whose batches take no time, which would finish without end.
+    pub fn from_ron(text: &str) -> Result<Recipes, ron::Error> {
+        let recipes: Recipes = ron::de::from_str(text)?;
+        match recipes.0.iter().find(|recipe| recipe.duration.is_nan() || recipe.duration <= 0.0) {
+            Some(recipe) => Err(serde::de::Error::custom(
+                format!("recipe {} takes {} seconds a batch, but must take some time", recipe.name, recipe.duration))),
+            None => Ok(recipes)
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recipes, ron::Error> {
+        Recipes::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Recipe> {
+        self.0.iter().find(|recipe| recipe.name == name)
+    }
+}
+
+/// Why a facility is or is not working.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+    Running,
+    /// Has not run yet.
+    Idle,
+    NoPower,
+    Starved(Commodity),
+    /// Its deposit has run out, or was never there.
+    Exhausted,
+    UnknownRecipe(String)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Extractor {
+    /// Into the body's `Deposits`.
+    pub deposit: usize,
+    /// Extracted a day from a deposit of richness one.
+    pub per_day: Mass,
+    pub power: Power,
+    pub status: Status
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Factory {
+    pub recipe: String,
+    /// Seconds into the current batch, whose inputs are already used, if there is one.
+    pub batch: Option<f64>,
+    pub status: Status
+}
+
+/// The goods stored at a site.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stockpile(pub BTreeMap<Commodity, Mass>);
+
+impl Stockpile {
+    pub fn amount(&self, commodity: &Commodity) -> Mass {
+        self.0.get(commodity).copied().unwrap_or(mass::ZERO)
+    }
+
+    pub fn add(&mut self, commodity: &Commodity, amount: Mass) {
+        let total = self.amount(commodity) + amount;
+        self.0.insert(commodity.clone(), total.to_scale(mass::Scale::Tonne));
+    }
+
+    /// Takes up to `amount` of a commodity, and returns how much there was.
+    pub fn remove(&mut self, commodity: &Commodity, amount: Mass) -> Mass {
+        let have = self.amount(commodity);
+        let taken = if have < amount { have } else { amount };
+        if taken > mass::ZERO {
+            self.0.insert(commodity.clone(), have - taken);
+        }
+        taken
+    }
+
+    /// The first of `amounts` the stockpile is short of, if any.
+    pub fn shortage(&self, amounts: &[(Commodity, Mass)]) -> Option<Commodity> {
+        amounts.iter().find(|(commodity, amount)| self.amount(commodity) < *amount).map(|(commodity, _)| commodity.clone())
+    }
+
+    /// Takes all of `amounts`, or none of them if any is short.
+    pub fn take(&mut self, amounts: &[(Commodity, Mass)]) -> Result<(), Commodity> {
+        if let Some(short) = self.shortage(amounts) {
+            return Err(short);
+        }
+        for (commodity, amount) in amounts {
+            let left = self.amount(commodity) - *amount;
+            self.0.insert(commodity.clone(), left);
+        }
+        Ok(())
+    }
+}
+
+/// The rates a commodity has lately moved through a site, per day.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Throughput {
+    pub produced: Mass,
+    pub consumed: Mass
+}
+
+impl Default for Throughput {
+    fn default() -> Throughput {
+        Throughput { produced: mass::ZERO.to_scale(mass::Scale::Tonne), consumed: mass::ZERO.to_scale(mass::Scale::Tonne) }
+    }
+}
+
+impl Throughput {
+    pub fn net(&self) -> Mass {
+        self.produced - self.consumed
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FacilityRef {
+    Extractor(usize),
+    Factory(usize)
+}
+
+/// A facility that is not running, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bottleneck {
+    pub facility: FacilityRef,
+    pub status: Status
+}
+
+/// The extractors and factories on a body, sharing a power supply and a stockpile.
+/// Power goes to extractors first, then factories, in the order they were built.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Industry {
+    pub power: Power,
+    pub extractors: Vec<Extractor>,
+    pub factories: Vec<Factory>,
+    pub stockpile: Stockpile,
+    pub throughput: BTreeMap<Commodity, Throughput>,
+    /// Drawn over the last tick.
+    pub power_used: Power
+}
+
+impl Industry {
+    pub fn new(power: Power) -> Industry {
+        Industry { power, ..Default::default() }
+    }
+
+    /// Runs the site for `seconds`, extracting from `deposits` and making goods by `recipes`.
+    pub fn step(&mut self, deposits: &mut Deposits, recipes: &Recipes, seconds: f64) {
+        if seconds <= 0.0 {
+            return;
+        }
+        let mut flows: BTreeMap<Commodity, (f64, f64)> = BTreeMap::new();
+        let mut available = self.power.in_watts();
+
+        for extractor in self.extractors.iter_mut() {
+            let deposit = match deposits.0.get_mut(extractor.deposit) {
+                Some(deposit) if deposit.reserves > mass::ZERO => deposit,
+                _ => {
+                    extractor.status = Status::Exhausted;
+                    continue;
+                }
+            };
+            if extractor.power.in_watts() > available {
+                extractor.status = Status::NoPower;
+                continue;
+            }
+            available -= extractor.power.in_watts();
+            let wanted = extractor.per_day * (deposit.richness * seconds / SECONDS_PER_DAY);
+            let amount = if wanted < deposit.reserves { wanted } else { deposit.reserves };
+            deposit.reserves = deposit.reserves - amount;
+            self.stockpile.add(&deposit.commodity, amount);
+            flows.entry(deposit.commodity.clone()).or_default().0 += amount.in_kilograms();
+            extractor.status = Status::Running;
+        }
+
+        for factory in self.factories.iter_mut() {
+            let recipe = match recipes.get(&factory.recipe) {
+                Some(recipe) => recipe,
+                None => {
+                    factory.status = Status::UnknownRecipe(factory.recipe.clone());
+                    continue;
+                }
+            };
+            if factory.batch.is_none() {
+                if let Some(short) = self.stockpile.shortage(&recipe.inputs) {
+                    factory.status = Status::Starved(short);
+                    continue;
+                }
+            }
+            if recipe.power.in_watts() > available {
+                factory.status = Status::NoPower;
+                continue;
+            }
+            available -= recipe.power.in_watts();
+            factory.status = Status::Running;
+            let mut remaining = seconds;
+            for _ in 0..MAX_BATCHES_PER_TICK {
+                let progress = match factory.batch {
+                    Some(progress) => progress,
+                    None => match self.stockpile.take(&recipe.inputs) {
+                        Ok(()) => {
+                            for (commodity, amount) in recipe.inputs.iter() {
+                                flows.entry(commodity.clone()).or_default().1 += amount.in_kilograms();
+                            }
+                            0.0
+                        }
+                        Err(short) => {
+                            factory.status = Status::Starved(short);
+                            break;
+                        }
+                    }
+                };
+                if progress + remaining < recipe.duration {
+                    factory.batch = Some(progress + remaining);
+                    break;
+                }
+                remaining -= recipe.duration - progress;
+                factory.batch = None;
+                for (commodity, amount) in recipe.outputs.iter() {
+                    self.stockpile.add(commodity, *amount);
+                    flows.entry(commodity.clone()).or_default().0 += amount.in_kilograms();
+                }
+            }
+        }
+
+        self.power_used = (self.power - Power::watts(available)).to_scale(power::Scale::Megawatt);
+        let per_day = SECONDS_PER_DAY / seconds;
+        let blend = (seconds / THROUGHPUT_SECONDS).min(1.0);
+        for commodity in self.throughput.keys().cloned().collect::<Vec<Commodity>>() {
+            flows.entry(commodity).or_default();
+        }
+        for (commodity, (produced, consumed)) in flows {
+            let throughput = self.throughput.entry(commodity).or_default();
+            throughput.produced = throughput.produced + (Mass::kg(produced * per_day) - throughput.produced) * blend;
+            throughput.consumed = throughput.consumed + (Mass::kg(consumed * per_day) - throughput.consumed) * blend;
+        }
+    }
+
+    /// The power every facility would draw running at once.
+    pub fn power_demand(&self, recipes: &Recipes) -> Power {
+        (self.extractors.iter().map(|extractor| extractor.power).sum::<Power>()
+         + self.factories.iter().filter_map(|factory| recipes.get(&factory.recipe)).map(|recipe| recipe.power).sum())
+            .to_scale(power::Scale::Megawatt)
+    }
+
+    /// Every facility that did not run last tick.
+    pub fn bottlenecks(&self) -> Vec<Bottleneck> {
+        let extractors = self.extractors.iter().enumerate()
+            .map(|(index, extractor)| (FacilityRef::Extractor(index), &extractor.status));
+        let factories = self.factories.iter().enumerate()
+            .map(|(index, factory)| (FacilityRef::Factory(index), &factory.status));
+        extractors.chain(factories)
+            .filter(|(_, status)| **status != Status::Running)
+            .map(|(facility, status)| Bottleneck { facility, status: status.clone() })
+            .collect()
+    }
+
+    pub fn throughput(&self, commodity: &Commodity) -> Throughput {
+        self.throughput.get(commodity).copied().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Industry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} extractors, {} factories, {:.1} of {:.1}", self.extractors.len(), self.factories.len(),
+               self.power_used, self.power.to_scale(power::Scale::Megawatt))?;
+        for (commodity, amount) in self.stockpile.0.iter() {
+            write!(f, ", {} {:.1}", commodity, amount)?;
+        }
+        Ok(())
+    }
+}
+
+/// Something that can be built on a body.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Facility {
+    Extractor { deposit: usize, per_day: Mass, power: Power },
+    Factory { recipe: String },
+    PowerPlant(Power)
+}
+
+/// Asks for a facility on a body, starting an industry there if it has none.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildFacility {
+    pub body: Entity,
+    pub facility: Facility
+}
+
+pub fn build_facilities(
+    mut commands: Commands,
+    mut requests: EventReader<BuildFacility>,
+    mut industries: Query<Option<&mut Industry>, With<Deposits>>
+) {
+    // Several requests for a body without an industry in one frame build up the same new one.
+    let mut started: BTreeMap<Entity, Industry> = BTreeMap::new();
+    for request in requests.iter() {
+        let mut existing = match industries.get_mut(request.body) {
+            Ok(existing) => existing,
+            Err(_)       => {
+                warn!("cannot build on {:?}, which has no deposits", request.body);
+                continue;
+            }
+        };
+        let industry = match existing.as_mut() {
+            Some(industry) => &mut **industry,
+            None           => started.entry(request.body).or_default()
+        };
+        match &request.facility {
+            Facility::Extractor { deposit, per_day, power } => industry.extractors.push(Extractor {
+                deposit: *deposit, per_day: *per_day, power: *power, status: Status::Idle
+            }),
+            Facility::Factory { recipe } => industry.factories.push(Factory {
+                recipe: recipe.clone(), batch: None, status: Status::Idle
+            }),
+            Facility::PowerPlant(output) => industry.power = industry.power + *output
+        }
+    }
+    for (body, industry) in started {
+        commands.entity(body).insert(industry);
+    }
+}
+
+pub fn run_industry(clock: Res<GameClock>, recipes: Res<Recipes>, mut sites: Query<(&mut Industry, &mut Deposits)>) {
+    let dt = clock.tick_seconds();
+    for (mut industry, mut deposits) in sites.iter_mut() {
+        industry.step(&mut deposits, &recipes, dt);
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum IndustrySystem {
+    Run
+}
+
+/// Extraction and production on the bodies of the universe, with recipes from `RECIPES`. Needs the `ClockPlugin`.
+pub struct IndustryPlugin;
+
+impl Plugin for IndustryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let recipes = Recipes::load(scene::asset_path(RECIPES)).unwrap_or_else(|error| {
+            error!("could not load the recipes {}: {}", RECIPES, error);
+            Recipes::default()
+        });
+        app.insert_resource(recipes)
+            .add_event::<BuildFacility>()
+            .add_system(build_facilities.system())
+            .add_system_to_stage(SimulationStage, run_industry.system()
+                                 .label(IndustrySystem::Run)
+                                 .after(ClockSystem::Advance));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/industry/recipes.ron");
+
+    fn recipe(duration: f64) -> String {
+        format!("([(name: \"Idling\", inputs: [], outputs: [(\"steel\", (grams: 1e6, scale: Tonne))], \
+                 power: (watts: 1e6, scale: Megawatt), duration: {:?})])", duration)
+    }
+
+    fn tonnes(amount: Mass) -> f64 {
+        amount.in_kilograms() / 1000.0
+    }
+
+    fn ore(reserves: Mass) -> Deposits {
+        Deposits(vec![Deposit { commodity: Commodity::new(METAL_ORE), reserves, richness: 1.0 }])
+    }
+
+    fn smelter(power: Power) -> Industry {
+        let mut industry = Industry::new(power);
+        industry.extractors.push(Extractor {
+            deposit: 0, per_day: Mass::tonnes(20.0), power: Power::megawatts(10.0), status: Status::Idle
+        });
+        industry.factories.push(Factory { recipe: "Smelting".to_string(), batch: None, status: Status::Idle });
+        industry
+    }
+
+    #[test]
+    fn the_shipped_recipes_load() {
+        let recipes = Recipes::load(RECIPES_PATH).unwrap();
+        assert!(recipes.get("Smelting").is_some());
+        assert!(recipes.0.iter().all(|recipe| recipe.duration > 0.0));
+    }
+
+    #[test]
+    fn recipes_taking_no_time_are_refused() {
+        assert!(Recipes::from_ron(&recipe(60.0)).is_ok());
+        assert!(Recipes::from_ron(&recipe(0.0)).is_err());
+        assert!(Recipes::from_ron(&recipe(-60.0)).is_err());
+        assert!(Recipes::from_ron(&recipe(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn extractors_fill_the_stockpile_and_factories_use_it() {
+        let recipes = Recipes::load(RECIPES_PATH).unwrap();
+        let smelting = recipes.get("Smelting").unwrap().clone();
+        let mut deposits = ore(Mass::tonnes(1.0e6));
+        let mut industry = smelter(Power::gigawatts(1.0));
+
+        // The day's ore is extracted before the factory looks for it, so a batch finishes and the next starts.
+        industry.step(&mut deposits, &recipes, smelting.duration);
+        assert_eq!(industry.factories[0].status, Status::Running);
+        assert_eq!(industry.factories[0].batch, Some(0.0));
+        assert!(industry.bottlenecks().is_empty());
+        assert_eq!(industry.stockpile.amount(&Commodity::new(METAL_ORE)).in_grams(), 0.0);
+        let steel = industry.stockpile.amount(&Commodity::new("steel"));
+        assert!((tonnes(steel) - tonnes(smelting.output_mass())).abs() < 1.0e-9);
+        assert!((tonnes(deposits.0[0].reserves) - (1.0e6 - 20.0)).abs() < 1.0e-6);
+        assert!(industry.throughput(&Commodity::new("steel")).produced > mass::ZERO);
+    }
+
+    #[test]
+    fn batches_carry_over_between_ticks() {
+        let recipes = Recipes::load(RECIPES_PATH).unwrap();
+        let duration = recipes.get("Smelting").unwrap().duration;
+        let mut deposits = ore(mass::ZERO);
+        let mut industry = smelter(Power::gigawatts(1.0));
+        industry.stockpile.add(&Commodity::new(METAL_ORE), Mass::tonnes(10.0));
+
+        industry.step(&mut deposits, &recipes, duration * 0.75);
+        assert_eq!(industry.factories[0].batch, Some(duration * 0.75));
+        assert_eq!(industry.stockpile.amount(&Commodity::new("steel")), mass::ZERO);
+        industry.step(&mut deposits, &recipes, duration * 0.5);
+        assert!(industry.stockpile.amount(&Commodity::new("steel")) > mass::ZERO);
+        assert_eq!(industry.factories[0].status, Status::Starved(Commodity::new(METAL_ORE)));
+        assert_eq!(industry.extractors[0].status, Status::Exhausted);
+    }
+
+    #[test]
+    fn power_goes_to_extractors_first() {
+        let recipes = Recipes::load(RECIPES_PATH).unwrap();
+        let mut deposits = ore(Mass::tonnes(1.0e6));
+        let mut industry = smelter(Power::megawatts(20.0));
+        industry.stockpile.add(&Commodity::new(METAL_ORE), Mass::tonnes(10.0));
+
+        industry.step(&mut deposits, &recipes, 3600.0);
+        assert_eq!(industry.extractors[0].status, Status::Running);
+        assert_eq!(industry.factories[0].status, Status::NoPower);
+        assert_eq!(industry.bottlenecks(), vec![Bottleneck { facility: FacilityRef::Factory(0), status: Status::NoPower }]);
+        assert!((industry.power_used.in_watts() - 10.0e6).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn the_industry_system_runs_each_tick() {
+        let mut app = App::build();
+        app.insert_resource(GameClock::default())
+            .insert_resource(Recipes::load(RECIPES_PATH).unwrap())
+            .add_system(run_industry.system());
+        let body = app.world_mut().spawn().insert_bundle((smelter(Power::gigawatts(1.0)), ore(Mass::tonnes(1.0e6)))).id();
+        app.app.update();
+        let industry = app.world().get::<Industry>(body).unwrap();
+        assert_eq!(industry.extractors[0].status, Status::Running);
+        assert!(industry.stockpile.amount(&Commodity::new(METAL_ORE)) > mass::ZERO);
+    }
+}
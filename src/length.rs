@@ -1,6 +1,11 @@
+use std::cmp::Ordering;
+use std::convert::From;
 use std::default::Default;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Scale {
     Millimeter, Centimeter, Meter, Kilometer, Megameter,
     Inch, Hand, Foot, Cubit, Yard, Mile, NauticalMile,
@@ -38,9 +43,9 @@ pub const LIGHT_MINUTES_TO_METERS:  f64 = LIGHT_SECONDS_TO_METERS * 60.0;
 pub const LIGHT_HOURS_TO_METERS:    f64 = LIGHT_MINUTES_TO_METERS * 60.0;
 pub const LIGHT_DAYS_TO_METERS:     f64 = LIGHT_HOURS_TO_METERS   * 24.0;
 pub const LIGHT_WEEKS_TO_METERS:    f64 = LIGHT_DAYS_TO_METERS    *  7.0;
-pub const LIGHT_YEARS_TO_METERS:    f64 = 9_460_730_000_000.0;
+pub const LIGHT_YEARS_TO_METERS:    f64 = 9_460_730_472_580_800.0; // IAU, a Julian year of light travel
 
-#[derive(Clone, Copy, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Length {
     /// We see that f64's maximum is approximately 1.8*10^308.
     /// If we use this for meters, we convert this to 1.9*10^292 light years.
@@ -84,6 +89,55 @@ impl Length {
         Length { meters: length_in_meters, scale: scale }
     }
 
+    pub fn meters(meters: f64) -> Length {
+        Length::scaled(meters, Scale::Meter)
+    }
+
+    pub fn kilometers(kilometers: f64) -> Length {
+        Length::scaled(kilometers, Scale::Kilometer)
+    }
+
+    pub fn astronomical_units(astronomical_units: f64) -> Length {
+        Length::scaled(astronomical_units, Scale::AstronomicalUnit)
+    }
+
+    pub fn parsecs(parsecs: f64) -> Length {
+        Length::scaled(parsecs, Scale::Parsec)
+    }
+
+    pub fn kiloparsecs(kiloparsecs: f64) -> Length {
+        Length::scaled(kiloparsecs, Scale::Kiloparsec)
+    }
+
+    pub fn light_years(light_years: f64) -> Length {
+        Length::scaled(light_years, Scale::LightYear)
+    }
+
+    pub fn m(m: f64) -> Length {
+        Length::meters(m)
+    }
+
+    pub fn km(km: f64) -> Length {
+        Length::kilometers(km)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn AU(au: f64) -> Length {
+        Length::astronomical_units(au)
+    }
+
+    pub fn pc(pc: f64) -> Length {
+        Length::parsecs(pc)
+    }
+
+    pub fn kpc(kpc: f64) -> Length {
+        Length::kiloparsecs(kpc)
+    }
+
+    pub fn ly(ly: f64) -> Length {
+        Length::light_years(ly)
+    }
+
     pub fn solar_radii(solar_radii: f64) -> Length {
         Length::scaled(solar_radii, Scale::SolarRadius)
     }
@@ -96,4 +150,200 @@ impl Length {
     pub fn range(range: std::ops::Range<f64>, scale: Scale) -> std::ops::Range<Length> {
         Length::scaled(range.start, scale) .. Length::scaled(range.end, scale)
     }
+
+    pub fn scale(self) -> Scale {
+        self.scale
+    }
+
+    /// The same length, displayed in another scale.
+    pub fn to_scale(self, scale: Scale) -> Length {
+        Length { meters: self.meters, scale }
+    }
+
+    pub fn in_meters(self) -> f64 {
+        self.meters
+    }
+
+    pub fn in_parsecs(self) -> f64 {
+        self.meters / PARSECS_TO_METERS
+    }
+
+    pub fn in_light_years(self) -> f64 {
+        self.meters / LIGHT_YEARS_TO_METERS
+    }
+
+    /// Seconds for light to cross it.
+    pub fn light_time(self) -> f64 {
+        self.meters / LIGHT_SECONDS_TO_METERS
+    }
+
+    /// The same length, displayed in whichever astronomical scale reads most naturally.
+    pub fn auto_scale(self) -> Length {
+        let meters = self.meters.abs();
+        let scale = if meters < KILOMETERS_TO_METERS {
+            Scale::Meter
+        } else if meters < 0.1 * AU_TO_METERS {
+            Scale::Kilometer
+        } else if meters < 0.1 * LIGHT_YEARS_TO_METERS {
+            Scale::AstronomicalUnit
+        } else if meters < 100.0 * LIGHT_YEARS_TO_METERS {
+            Scale::LightYear
+        } else if meters < KILOPARSECS_TO_METERS {
+            Scale::Parsec
+        } else if meters < MEGAPARSECS_TO_METERS {
+            Scale::Kiloparsec
+        } else {
+            Scale::Megaparsec
+        };
+        self.to_scale(scale)
+    }
+
+    pub fn abs(self) -> Length {
+        Length { meters: self.meters.abs(), scale: self.scale }
+    }
+
+    pub fn min(self, other: Length) -> Length {
+        if other.meters < self.meters { other } else { self }
+    }
+
+    pub fn max(self, other: Length) -> Length {
+        if other.meters > self.meters { other } else { self }
+    }
+}
+
+impl PartialOrd for Length {
+    fn partial_cmp(&self, other: &Length) -> Option<Ordering> {
+        self.meters.partial_cmp(&other.meters)
+    }
+}
+
+// Arithmetic keeps the scale of the left-hand side, so a sum of light years still reads in light years.
+
+impl Add for Length {
+    type Output = Length;
+    fn add(self, other: Length) -> Length {
+        Length { meters: self.meters + other.meters, scale: self.scale }
+    }
+}
+
+impl Sub for Length {
+    type Output = Length;
+    fn sub(self, other: Length) -> Length {
+        Length { meters: self.meters - other.meters, scale: self.scale }
+    }
+}
+
+impl Mul<f64> for Length {
+    type Output = Length;
+    fn mul(self, factor: f64) -> Length {
+        Length { meters: self.meters * factor, scale: self.scale }
+    }
+}
+
+impl Div<f64> for Length {
+    type Output = Length;
+    fn div(self, divisor: f64) -> Length {
+        Length { meters: self.meters / divisor, scale: self.scale }
+    }
+}
+
+impl Div for Length {
+    type Output = f64;
+    fn div(self, other: Length) -> f64 {
+        self.meters / other.meters
+    }
+}
+
+impl From<f64> for Length {
+    fn from(value: f64) -> Self {
+        Length::meters(value)
+    }
+}
+
+impl From<Length> for f64 {
+    fn from(length: Length) -> f64 {
+        let Length { meters, scale } = length;
+        match scale {
+            Scale::Millimeter       => meters * MILLIMETERS_TO_METERS,
+            Scale::Centimeter       => meters * CENTIMETERS_TO_METERS,
+            Scale::Meter            => meters,
+            Scale::Kilometer        => meters / KILOMETERS_TO_METERS,
+            Scale::Megameter        => meters / MEGAMETERS_TO_METERS,
+            Scale::Inch             => meters / INCHES_TO_METERS,
+            Scale::Hand             => meters / HANDS_TO_METERS,
+            Scale::Foot             => meters / FEET_TO_METERS,
+            Scale::Cubit            => meters / CUBITS_TO_METERS,
+            Scale::Yard             => meters / YARDS_TO_METERS,
+            Scale::Mile             => meters / MILES_TO_METERS,
+            Scale::NauticalMile     => meters / NAUTICAL_MILES_TO_METERS,
+            Scale::EarthRadius      => meters / EARTH_RADII_TO_METERS,
+            Scale::SolarRadius      => meters / SOLAR_RADII_TO_METERS,
+            Scale::AstronomicalUnit => meters / AU_TO_METERS,
+            Scale::Parsec           => meters / PARSECS_TO_METERS,
+            Scale::Kiloparsec       => meters / KILOPARSECS_TO_METERS,
+            Scale::Megaparsec       => meters / MEGAPARSECS_TO_METERS,
+            Scale::LightSecond      => meters / LIGHT_SECONDS_TO_METERS,
+            Scale::LightMinute      => meters / LIGHT_MINUTES_TO_METERS,
+            Scale::LightHour        => meters / LIGHT_HOURS_TO_METERS,
+            Scale::LightDay         => meters / LIGHT_DAYS_TO_METERS,
+            Scale::LightWeek        => meters / LIGHT_WEEKS_TO_METERS,
+            Scale::LightYear        => meters / LIGHT_YEARS_TO_METERS,
+        }
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value: f64 = Length::into(*self);
+        let unit = match self.scale {
+            Scale::Millimeter       => "mm",
+            Scale::Centimeter       => "cm",
+            Scale::Meter            => "m",
+            Scale::Kilometer        => "km",
+            Scale::Megameter        => "Mm",
+            Scale::Inch             => "in",
+            Scale::Hand             => "hh",
+            Scale::Foot             => "ft",
+            Scale::Cubit            => "cubits",
+            Scale::Yard             => "yd",
+            Scale::Mile             => "mi",
+            Scale::NauticalMile     => "nmi",
+            Scale::EarthRadius      => "R♁",
+            Scale::SolarRadius      => "R☉",
+            Scale::AstronomicalUnit => "AU",
+            Scale::Parsec           => "pc",
+            Scale::Kiloparsec       => "kpc",
+            Scale::Megaparsec       => "Mpc",
+            Scale::LightSecond      => "ls",
+            Scale::LightMinute      => "lmin",
+            Scale::LightHour        => "lh",
+            Scale::LightDay         => "ld",
+            Scale::LightWeek        => "lw",
+            Scale::LightYear        => "ly",
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            None            => write!(f, "{} {}", value, unit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_years_convert_through_meters() {
+        assert_eq!(Length::ly(1.0).in_meters(), 9_460_730_472_580_800.0);
+        assert!((Length::ly(1.0).in_meters() / LIGHT_DAYS_TO_METERS - 365.25).abs() < 1e-9);
+        assert!((Length::pc(1.0).in_light_years() - 3.261_563_777).abs() < 1e-9);
+        assert!((Length::ly(4.2465).in_light_years() - 4.2465).abs() < 1e-12);
+    }
+
+    #[test]
+    fn light_crosses_an_astronomical_unit_in_about_eight_minutes() {
+        assert_eq!(Length::scaled(1.0, Scale::LightSecond).light_time(), 1.0);
+        assert!((Length::AU(1.0).light_time() - 499.004_784).abs() < 1e-6);
+        assert!((Length::ly(1.0).light_time() - 365.25 * 86_400.0).abs() < 1e-6);
+    }
 }
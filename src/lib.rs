@@ -1,7 +1,39 @@
+pub mod astronomy;
+pub mod autopilot;
+pub mod behaviour;
 pub mod camera;
+pub mod clock;
+pub mod colony;
+pub mod combat;
+pub mod faction;
+pub mod floating_origin;
+pub mod flight;
+pub mod force;
+pub mod galaxy;
+pub mod gameplay;
+pub mod gravity;
+pub mod industry;
 pub mod length;
+pub mod market;
 pub mod mass;
+pub mod mission;
+pub mod orbit;
+pub mod planet;
+pub mod plugins;
+pub mod position;
 pub mod power;
+pub mod radiation;
+pub mod random;
+pub mod route;
+pub mod save;
+pub mod scene;
+pub mod sensors;
+pub mod ship;
+pub mod spatial;
 pub mod star;
-pub mod startup;
+pub mod star_catalogue;
+pub mod survey;
 pub mod temperature;
+pub mod thermal;
+pub mod ui;
+pub mod universe;
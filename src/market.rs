@@ -0,0 +1,692 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::path::Path;
+
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY};
+use super::flight::PlayerShip;
+use super::floating_origin::WorldPosition;
+use super::industry::{Commodity, Industry, IndustrySystem, Stockpile};
+use super::length;
+use super::mass;
+use super::mass::Mass;
+use super::scene;
+use super::ship::Ship;
+
+/// What markets trade and at what base prices, relative to the assets directory.
+pub const COMMODITIES: &str = "industry/commodities.ron";
+/// A market prices goods to hold this many days of what is used locally.
+pub const STOCK_DAYS: f64 = 30.0;
+/// Prices never leave these multiples of the base price, however glutted or short a market is.
+pub const MIN_PRICE_FACTOR: f64 = 0.1;
+pub const MAX_PRICE_FACTOR: f64 = 10.0;
+/// Prices are recorded once a day, for a year.
+pub const PRICE_SAMPLE_SECONDS: f64 = SECONDS_PER_DAY;
+pub const HISTORY_LENGTH: usize = 365;
+/// How near a ship must be to a body to trade at its market.
+pub const DOCKING_RANGE_METERS: f64 = 1.0e9;
+/// What the player starts with.
+pub const STARTING_CREDITS: f64 = 100_000.0;
+/// The speed NPC traders average between markets, in m s⁻¹.
+pub const TRADER_SPEED: f64 = 1.0e5;
+/// NPC traders only work markets this near each other, which keeps them within a star system.
+pub const MAX_ROUTE_METERS: f64 = 1.0e13;
+/// The cost of hauling cargo, in credits per tonne per astronomical unit.
+pub const HAULAGE_COST: f64 = 1.0;
+/// NPC traders never buy more than this fraction of a market's stock at once.
+pub const MAX_BUY_FRACTION: f64 = 0.5;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommodityInfo {
+    pub commodity: Commodity,
+    /// Credits per tonne at a market holding just the stock it wants.
+    pub base_price: f64,
+    /// The price elasticity of demand, as a magnitude: how much less is wanted as the price goes up.
+    pub elasticity: f64
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalogue(pub Vec<CommodityInfo>);
+
+impl Catalogue {
+    pub fn from_ron(text: &str) -> Result<Catalogue, ron::Error> {
+        ron::de::from_str(text)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Catalogue, ron::Error> {
+        Catalogue::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, commodity: &Commodity) -> Option<&CommodityInfo> {
+        self.0.iter().find(|info| info.commodity == *commodity)
+    }
+}
+
+fn tonnes(amount: Mass) -> f64 {
+    amount.in_kilograms() / 1000.0
+}
+
+/// Credits per tonne for a market holding `stock` and wanting `target`. Demand falling as the price
+/// rises with elasticity ε, the price that clears the stock goes as (target / stock)^(1/ε).
+pub fn price(base_price: f64, elasticity: f64, stock: Mass, target: Mass) -> f64 {
+    let (low, high) = (base_price * MIN_PRICE_FACTOR, base_price * MAX_PRICE_FACTOR);
+    if target <= mass::ZERO {
+        low
+    } else if stock <= mass::ZERO {
+        high
+    } else {
+        (base_price * (target / stock).powf(1.0 / elasticity)).clamp(low, high)
+    }
+}
+
+/// A commodity's place in a market.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Listing {
+    pub base_price: f64,
+    pub elasticity: f64,
+    /// Used up locally each day, by people rather than factories.
+    pub demand: Mass,
+    /// The stock the market prices to hold: its days of local demand and of its factories' use.
+    pub target: Mass,
+    /// Credits per tonne, as of the last tick.
+    pub price: f64,
+    /// A price a day, oldest first.
+    pub history: VecDeque<f64>
+}
+
+impl Listing {
+    pub fn new(info: &CommodityInfo) -> Listing {
+        Listing {
+            base_price: info.base_price,
+            elasticity: info.elasticity,
+            demand: mass::ZERO.to_scale(mass::Scale::Tonne),
+            target: mass::ZERO.to_scale(mass::Scale::Tonne),
+            price: info.base_price * MIN_PRICE_FACTOR,
+            history: VecDeque::new()
+        }
+    }
+
+    pub fn price_at(&self, stock: Mass) -> f64 {
+        price(self.base_price, self.elasticity, stock, self.target)
+    }
+}
+
+/// Which way goods go, from the customer's side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TradeError {
+    NoMarket,
+    NoAccount,
+    NotListed(Commodity),
+    NotDocked,
+    InsufficientStock { commodity: Commodity, stock: Mass },
+    InsufficientCredits { needed: f64, available: f64 },
+    NoCargoSpace { space: Mass },
+    NotInCargo { commodity: Commodity, carried: Mass }
+}
+
+impl fmt::Display for TradeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TradeError::NoMarket                                 => write!(f, "there is no market there"),
+            TradeError::NoAccount                                => write!(f, "the ship has no account to trade with"),
+            TradeError::NotListed(commodity)                     => write!(f, "the market does not trade {}", commodity),
+            TradeError::NotDocked                                => write!(f, "the ship is too far from the market"),
+            TradeError::InsufficientStock { commodity, stock }   => write!(f, "the market has only {:.1} of {}", stock, commodity),
+            TradeError::InsufficientCredits { needed, available } => write!(f, "that costs {:.0} credits but there are only {:.0}", needed, available),
+            TradeError::NoCargoSpace { space }                   => write!(f, "the holds have room for only {:.1}", space),
+            TradeError::NotInCargo { commodity, carried }        => write!(f, "the holds have only {:.1} of {}", carried, commodity)
+        }
+    }
+}
+
+impl std::error::Error for TradeError {}
+
+/// Money, of a ship's owner or a trader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Credits(pub f64);
+
+/// What a ship's holds carry, by commodity. NPC traders, which have no `Ship`, keep only this.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest(pub Stockpile);
+
+impl Manifest {
+    /// Stows up to `amount` of a commodity in a ship's holds, and returns how much fitted.
+    /// Going through here and `unstow` keeps the holds' `cargo` masses adding up to the manifest.
+    pub fn stow(&mut self, ship: &mut Ship, commodity: &Commodity, amount: Mass) -> Mass {
+        let stowed = ship.load_cargo(amount);
+        self.0.add(commodity, stowed);
+        stowed
+    }
+
+    /// Takes up to `amount` of a commodity out of a ship's holds, and returns how much there was.
+    pub fn unstow(&mut self, ship: &mut Ship, commodity: &Commodity, amount: Mass) -> Mass {
+        let taken = self.0.remove(commodity, amount);
+        ship.unload_cargo(taken);
+        taken
+    }
+}
+
+/// Trade at a body, out of the stockpile of its `Industry`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Market {
+    pub listings: BTreeMap<Commodity, Listing>,
+    /// When the next price is recorded, in seconds since the epoch.
+    pub next_sample: f64
+}
+
+impl Market {
+    /// A market in every commodity of the catalogue, with no local demand.
+    pub fn new(catalogue: &Catalogue) -> Market {
+        Market {
+            listings: catalogue.0.iter().map(|info| (info.commodity.clone(), Listing::new(info))).collect(),
+            next_sample: 0.0
+        }
+    }
+
+    pub fn with_demand(mut self, commodity: &Commodity, per_day: Mass) -> Market {
+        if let Some(listing) = self.listings.get_mut(commodity) {
+            listing.demand = per_day.to_scale(mass::Scale::Tonne);
+        }
+        self
+    }
+
+    pub fn price(&self, commodity: &Commodity) -> Option<f64> {
+        self.listings.get(commodity).map(|listing| listing.price)
+    }
+
+    /// The credits for `amount` bought from or sold into `stock`, priced at the stock halfway through the trade.
+    pub fn quote(&self, stock: &Stockpile, commodity: &Commodity, amount: Mass, side: Side) -> Result<f64, TradeError> {
+        let listing = self.listings.get(commodity).ok_or_else(|| TradeError::NotListed(commodity.clone()))?;
+        let halfway = match side {
+            Side::Buy  => stock.amount(commodity) - amount * 0.5,
+            Side::Sell => stock.amount(commodity) + amount * 0.5
+        };
+        Ok(listing.price_at(halfway) * tonnes(amount))
+    }
+
+    /// Sells `amount` out of `stock` to a customer with `credits`, and returns what it cost them.
+    pub fn buy(&self, stock: &mut Stockpile, commodity: &Commodity, amount: Mass, credits: &mut Credits) -> Result<f64, TradeError> {
+        let cost = self.quote(stock, commodity, amount, Side::Buy)?;
+        if stock.amount(commodity) < amount {
+            return Err(TradeError::InsufficientStock { commodity: commodity.clone(), stock: stock.amount(commodity) });
+        }
+        if credits.0 < cost {
+            return Err(TradeError::InsufficientCredits { needed: cost, available: credits.0 });
+        }
+        stock.remove(commodity, amount);
+        credits.0 -= cost;
+        Ok(cost)
+    }
+
+    /// Buys `amount` into `stock` from a customer with `credits`, and returns what it paid them.
+    pub fn sell(&self, stock: &mut Stockpile, commodity: &Commodity, amount: Mass, credits: &mut Credits) -> Result<f64, TradeError> {
+        let proceeds = self.quote(stock, commodity, amount, Side::Sell)?;
+        stock.add(commodity, amount);
+        credits.0 += proceeds;
+        Ok(proceeds)
+    }
+
+    /// Uses up local demand for `seconds`, reprices everything, and records the day's prices when one is due.
+    pub fn update(&mut self, industry: &mut Industry, seconds: f64, now: f64) {
+        for (commodity, listing) in self.listings.iter_mut() {
+            industry.stockpile.remove(commodity, listing.demand * (seconds / SECONDS_PER_DAY));
+            listing.target = ((listing.demand + industry.throughput(commodity).consumed) * STOCK_DAYS).to_scale(mass::Scale::Tonne);
+            listing.price = listing.price_at(industry.stockpile.amount(commodity));
+        }
+        if now - self.next_sample > HISTORY_LENGTH as f64 * PRICE_SAMPLE_SECONDS {
+            self.next_sample = now;
+        }
+        while now >= self.next_sample {
+            for listing in self.listings.values_mut() {
+                listing.history.push_back(listing.price);
+                if listing.history.len() > HISTORY_LENGTH {
+                    listing.history.pop_front();
+                }
+            }
+            self.next_sample += PRICE_SAMPLE_SECONDS;
+        }
+    }
+}
+
+impl fmt::Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prices: Vec<String> = self.listings.iter()
+            .map(|(commodity, listing)| format!("{} {:.0}", commodity, listing.price))
+            .collect();
+        write!(f, "{}", prices.join(", "))
+    }
+}
+
+pub fn update_markets(clock: Res<GameClock>, mut markets: Query<(&mut Market, &mut Industry)>) {
+    for (mut market, mut industry) in markets.iter_mut() {
+        market.update(&mut industry, clock.tick_seconds(), clock.seconds);
+    }
+}
+
+/// A trader between markets, abstracted from flight: it is at a market, or on its way to one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trader {
+    pub capacity: Mass,
+    pub at: Entity,
+    pub voyage: Option<Voyage>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Voyage {
+    pub to: Entity,
+    /// In seconds since the epoch.
+    pub arrival: f64
+}
+
+/// A cargo worth carrying from one market to another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeRoute {
+    pub to: Entity,
+    pub commodity: Commodity,
+    pub amount: Mass,
+    /// Credits spent running the ship there.
+    pub haulage: f64,
+    /// Credits, after buying, selling and hauling.
+    pub profit: f64,
+    /// Seconds.
+    pub travel_time: f64
+}
+
+impl TradeRoute {
+    /// What the route is worth per second of the trader's time.
+    pub fn score(&self) -> f64 {
+        self.profit / self.travel_time.max(1.0)
+    }
+}
+
+/// The best route from market `from` for a trader with `capacity` and `credits`: the most profit per second,
+/// with hauling costs and travel times growing with distance. Ties go to the first found.
+pub fn best_route(
+    from: (Entity, &Market, &Stockpile, WorldPosition),
+    markets: &[(Entity, &Market, &Stockpile, WorldPosition)],
+    capacity: Mass,
+    credits: f64
+) -> Option<TradeRoute> {
+    let (at, market, stock, position) = from;
+    let mut best: Option<TradeRoute> = None;
+    for (to, other, other_stock, other_position) in markets.iter() {
+        let distance = other_position.distance(position).in_meters();
+        if *to == at || distance > MAX_ROUTE_METERS {
+            continue;
+        }
+        let travel_time = distance / TRADER_SPEED;
+        let haulage_per_tonne = HAULAGE_COST * distance / length::AU_TO_METERS;
+        for commodity in market.listings.keys().filter(|commodity| other.listings.contains_key(*commodity)) {
+            let available = stock.amount(commodity) * MAX_BUY_FRACTION;
+            let mut amount = if available < capacity { available } else { capacity };
+            let mut cost = match market.quote(stock, commodity, amount, Side::Buy) {
+                Ok(cost) if amount > mass::ZERO => cost,
+                _                                => continue
+            };
+            let outlay = cost + haulage_per_tonne * tonnes(amount);
+            if outlay > credits {
+                amount = amount * (credits / outlay);
+                cost = market.quote(stock, commodity, amount, Side::Buy).unwrap_or(f64::INFINITY);
+            }
+            let proceeds = other.quote(other_stock, commodity, amount, Side::Sell).unwrap_or(0.0);
+            let haulage = haulage_per_tonne * tonnes(amount);
+            let route = TradeRoute { to: *to, commodity: commodity.clone(), amount, haulage, profit: proceeds - cost - haulage, travel_time };
+            let better = match &best {
+                Some(best) => route.score() > best.score(),
+                None       => true
+            };
+            if route.profit > 0.0 && better {
+                best = Some(route);
+            }
+        }
+    }
+    best
+}
+
+/// Where a trader with nothing worth loading at market `from` should go empty, and how long it takes to get there:
+/// the market whose best route pays most per second of the whole trip, the empty leg included.
+pub fn best_reposition(
+    from: (Entity, &Market, &Stockpile, WorldPosition),
+    markets: &[(Entity, &Market, &Stockpile, WorldPosition)],
+    capacity: Mass,
+    credits: f64
+) -> Option<(Entity, f64)> {
+    let (at, _, _, position) = from;
+    let mut best: Option<(Entity, f64, f64)> = None;
+    for market in markets.iter() {
+        let distance = market.3.distance(position).in_meters();
+        if market.0 == at || distance > MAX_ROUTE_METERS {
+            continue;
+        }
+        let travel_time = distance / TRADER_SPEED;
+        if let Some(route) = best_route(*market, markets, capacity, credits) {
+            let score = route.profit / (travel_time + route.travel_time).max(1.0);
+            let better = match best {
+                Some((_, _, best)) => score > best,
+                None               => true
+            };
+            if better {
+                best = Some((market.0, travel_time, score));
+            }
+        }
+    }
+    best.map(|(to, travel_time, _)| (to, travel_time))
+}
+
+/// Where a trader left holding `amount` of a commodity at market `from` should take it: the market in reach
+/// paying most for it per second of the trip, and how long it takes to get there. None if no market in reach trades it.
+pub fn best_outlet(
+    from: (Entity, &Market, &Stockpile, WorldPosition),
+    markets: &[(Entity, &Market, &Stockpile, WorldPosition)],
+    commodity: &Commodity,
+    amount: Mass
+) -> Option<(Entity, f64)> {
+    let (at, _, _, position) = from;
+    let mut best: Option<(Entity, f64, f64)> = None;
+    for (to, market, stock, other_position) in markets.iter() {
+        let distance = other_position.distance(position).in_meters();
+        if *to == at || distance > MAX_ROUTE_METERS {
+            continue;
+        }
+        let travel_time = distance / TRADER_SPEED;
+        if let Ok(proceeds) = market.quote(stock, commodity, amount, Side::Sell) {
+            let score = proceeds / travel_time.max(1.0);
+            let better = match best {
+                Some((_, _, best)) => score > best,
+                None               => true
+            };
+            if better {
+                best = Some((*to, travel_time, score));
+            }
+        }
+    }
+    best.map(|(to, travel_time, _)| (to, travel_time))
+}
+
+/// Sells traders' cargoes where they arrive, and sends idle ones off along the best route from where they are.
+/// Cargo a market will not take goes on to the best market that will, or is written off if none in reach trades it.
+pub fn run_traders(
+    clock: Res<GameClock>,
+    mut traders: Query<(&mut Trader, &mut Manifest, &mut Credits)>,
+    mut markets: Query<(Entity, &Market, &mut Industry, &WorldPosition)>
+) {
+    let mut sites: Vec<_> = markets.iter_mut().collect();
+    let site = |sites: &[(Entity, &Market, Mut<Industry>, &WorldPosition)], entity: Entity| {
+        sites.iter().position(|(site, _, _, _)| *site == entity)
+    };
+    for (mut trader, mut manifest, mut credits) in traders.iter_mut() {
+        if let Some(voyage) = trader.voyage {
+            if clock.seconds < voyage.arrival {
+                continue;
+            }
+            trader.at = voyage.to;
+            trader.voyage = None;
+            if let Some(at) = site(&sites, trader.at) {
+                let (_, market, industry, _) = &mut sites[at];
+                for (commodity, amount) in std::mem::take(&mut manifest.0.0) {
+                    if market.sell(&mut industry.stockpile, &commodity, amount, &mut credits).is_err() {
+                        manifest.0.add(&commodity, amount);
+                    }
+                }
+            }
+        }
+        let at = match site(&sites, trader.at) {
+            Some(at) => at,
+            None     => continue
+        };
+        let (route, destination) = {
+            let all: Vec<(Entity, &Market, &Stockpile, WorldPosition)> = sites.iter()
+                .map(|(entity, market, industry, position)| (*entity, *market, &industry.stockpile, **position))
+                .collect();
+            let mut outlet = None;
+            for (commodity, amount) in manifest.0.0.clone() {
+                if amount <= mass::ZERO {
+                    continue;
+                }
+                match best_outlet(all[at], &all, &commodity, amount) {
+                    Some(found) => outlet = outlet.or(Some(found)),
+                    None        => {
+                        info!("a trader wrote off {:.1} of {}, which no market in reach trades", amount, commodity);
+                        manifest.0.remove(&commodity, amount);
+                    }
+                }
+            }
+            match outlet {
+                Some(outlet) => (None, Some(outlet)),
+                None         => match best_route(all[at], &all, trader.capacity, credits.0) {
+                    Some(route) => (Some(route), None),
+                    None        => (None, best_reposition(all[at], &all, trader.capacity, credits.0))
+                }
+            }
+        };
+        if let Some((to, travel_time)) = destination {
+            trader.voyage = Some(Voyage { to, arrival: clock.seconds + travel_time });
+        }
+        if let Some(route) = route {
+            let (_, market, industry, _) = &mut sites[at];
+            if market.buy(&mut industry.stockpile, &route.commodity, route.amount, &mut credits).is_ok() {
+                credits.0 -= route.haulage;
+                manifest.0.add(&route.commodity, route.amount);
+                trader.voyage = Some(Voyage { to: route.to, arrival: clock.seconds + route.travel_time });
+            }
+        }
+    }
+}
+
+/// Asks to trade a ship's cargo at a market.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeOrder {
+    pub ship: Entity,
+    pub market: Entity,
+    pub commodity: Commodity,
+    pub amount: Mass,
+    pub side: Side
+}
+
+/// How a `TradeOrder` went, with the credits paid or received.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeResult {
+    pub order: TradeOrder,
+    pub result: Result<f64, TradeError>
+}
+
+fn trade(
+    order: &TradeOrder,
+    ship: &mut Ship,
+    manifest: &mut Manifest,
+    credits: &mut Credits,
+    position: WorldPosition,
+    market: Option<(&Market, &mut Industry, WorldPosition)>
+) -> Result<f64, TradeError> {
+    let (market, industry, market_position) = market.ok_or(TradeError::NoMarket)?;
+    if market_position.distance(position).in_meters() > DOCKING_RANGE_METERS {
+        return Err(TradeError::NotDocked);
+    }
+    match order.side {
+        Side::Buy => {
+            if ship.cargo_space() < order.amount {
+                return Err(TradeError::NoCargoSpace { space: ship.cargo_space() });
+            }
+            let cost = market.buy(&mut industry.stockpile, &order.commodity, order.amount, credits)?;
+            manifest.stow(ship, &order.commodity, order.amount);
+            Ok(cost)
+        }
+        Side::Sell => {
+            let carried = manifest.0.amount(&order.commodity);
+            if carried < order.amount {
+                return Err(TradeError::NotInCargo { commodity: order.commodity.clone(), carried });
+            }
+            let proceeds = market.sell(&mut industry.stockpile, &order.commodity, order.amount, credits)?;
+            manifest.unstow(ship, &order.commodity, order.amount);
+            Ok(proceeds)
+        }
+    }
+}
+
+pub fn execute_trade_orders(
+    mut orders: EventReader<TradeOrder>,
+    mut ships: Query<(&mut Ship, &mut Manifest, &mut Credits, &WorldPosition)>,
+    mut markets: Query<(&Market, &mut Industry, &WorldPosition)>,
+    mut results: EventWriter<TradeResult>
+) {
+    for order in orders.iter() {
+        let result = match ships.get_mut(order.ship) {
+            Ok((mut ship, mut manifest, mut credits, position)) => {
+                let mut market = markets.get_mut(order.market).ok();
+                let market = market.as_mut().map(|(market, industry, position)| (*market, &mut **industry, **position));
+                trade(order, &mut ship, &mut manifest, &mut credits, *position, market)
+            }
+            Err(_) => Err(TradeError::NoAccount)
+        };
+        if let Err(error) = &result {
+            info!("trade refused: {}", error);
+        }
+        results.send(TradeResult { order: order.clone(), result });
+    }
+}
+
+/// Gives the player's ship an account and an empty manifest.
+pub fn open_accounts(mut commands: Commands, ships: Query<Entity, (With<PlayerShip>, Without<Credits>)>) {
+    for ship in ships.iter() {
+        commands.entity(ship)
+            .insert(Credits(STARTING_CREDITS))
+            .insert(Manifest::default());
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum MarketSystem {
+    Update,
+    Traders,
+    Trade
+}
+
+/// Markets on bodies with industry, NPC traders between them, and trading by ships.
+/// Prices move only with the game clock, so scripted runs are repeatable. Needs the `ClockPlugin` and `IndustryPlugin`.
+pub struct MarketPlugin;
+
+impl Plugin for MarketPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let catalogue = Catalogue::load(scene::asset_path(COMMODITIES)).unwrap_or_else(|error| {
+            error!("could not load the commodities {}: {}", COMMODITIES, error);
+            Catalogue::default()
+        });
+        app.insert_resource(catalogue)
+            .add_event::<TradeOrder>()
+            .add_event::<TradeResult>()
+            .add_system(open_accounts.system())
+            .add_system_to_stage(SimulationStage, update_markets.system()
+                                 .label(MarketSystem::Update)
+                                 .after(ClockSystem::Advance)
+                                 .after(IndustrySystem::Run))
+            .add_system_to_stage(SimulationStage, run_traders.system()
+                                 .label(MarketSystem::Traders)
+                                 .after(MarketSystem::Update))
+            .add_system_to_stage(SimulationStage, execute_trade_orders.system()
+                                 .label(MarketSystem::Trade)
+                                 .after(MarketSystem::Traders));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::DVec3;
+    use super::super::ship::courier;
+
+    const CATALOGUE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/industry/commodities.ron");
+
+    fn steel() -> Commodity {
+        Commodity::new("steel")
+    }
+
+    /// A market `x` meters along, trading steel only if asked to.
+    fn spawn_market(world: &mut World, x: f64, trades_steel: bool) -> Entity {
+        let mut market = Market::new(&Catalogue::load(CATALOGUE_PATH).unwrap());
+        if !trades_steel {
+            market.listings.remove(&steel());
+        }
+        world.spawn()
+            .insert_bundle((market, Industry::default(), WorldPosition::from_meters(DVec3::new(x, 0.0, 0.0))))
+            .id()
+    }
+
+    /// A trader arriving now at `to` with ten tonnes of steel.
+    fn spawn_trader(world: &mut World, from: Entity, to: Entity) -> Entity {
+        let mut manifest = Manifest::default();
+        manifest.0.add(&steel(), Mass::tonnes(10.0));
+        let trader = Trader { capacity: Mass::tonnes(100.0), at: from, voyage: Some(Voyage { to, arrival: 0.0 }) };
+        world.spawn().insert_bundle((trader, manifest, Credits(0.0))).id()
+    }
+
+    fn world() -> World {
+        let mut world = World::default();
+        world.insert_resource(GameClock::default());
+        world
+    }
+
+    #[test]
+    fn traders_take_cargo_a_market_refuses_to_one_that_buys_it() {
+        let mut world = world();
+        let home = spawn_market(&mut world, 0.0, false);
+        let refusing = spawn_market(&mut world, 1.0e10, false);
+        let buying = spawn_market(&mut world, 3.0e10, true);
+        let trader = spawn_trader(&mut world, home, refusing);
+        let mut stage = SystemStage::single(run_traders.system());
+
+        stage.run(&mut world);
+        let voyage = world.get::<Trader>(trader).unwrap().voyage.unwrap();
+        assert_eq!(world.get::<Trader>(trader).unwrap().at, refusing);
+        assert_eq!(voyage.to, buying);
+        assert!((voyage.arrival - 2.0e10 / TRADER_SPEED).abs() < 1.0e-6);
+        assert_eq!(world.get::<Manifest>(trader).unwrap().0.amount(&steel()), Mass::tonnes(10.0).to_scale(mass::Scale::Tonne));
+
+        world.get_resource_mut::<GameClock>().unwrap().seconds = voyage.arrival;
+        stage.run(&mut world);
+        assert_eq!(world.get::<Trader>(trader).unwrap().at, buying);
+        assert_eq!(world.get::<Manifest>(trader).unwrap().0.amount(&steel()).in_grams(), 0.0);
+        assert!(world.get::<Credits>(trader).unwrap().0 > 0.0);
+        assert!(world.get::<Industry>(buying).unwrap().stockpile.amount(&steel()) > mass::ZERO);
+    }
+
+    #[test]
+    fn cargo_no_market_in_reach_trades_is_written_off() {
+        let mut world = world();
+        let home = spawn_market(&mut world, 0.0, false);
+        let refusing = spawn_market(&mut world, 1.0e10, false);
+        spawn_market(&mut world, 2.0 * MAX_ROUTE_METERS, true);
+        let trader = spawn_trader(&mut world, home, refusing);
+
+        SystemStage::single(run_traders.system()).run(&mut world);
+        let trader = world.entity(trader);
+        assert_eq!(trader.get::<Trader>().unwrap().at, refusing);
+        assert_eq!(trader.get::<Trader>().unwrap().voyage, None);
+        assert_eq!(trader.get::<Manifest>().unwrap().0.amount(&steel()).in_grams(), 0.0);
+        assert_eq!(trader.get::<Credits>().unwrap().0, 0.0);
+    }
+
+    #[test]
+    fn manifests_keep_the_holds_in_step() {
+        let mut ship = courier();
+        let capacity = ship.cargo_space();
+        let mut manifest = Manifest::default();
+
+        let stowed = manifest.stow(&mut ship, &steel(), capacity * 2.0);
+        assert_eq!(stowed.in_grams(), capacity.in_grams());
+        assert_eq!(manifest.0.amount(&steel()).in_grams(), ship.cargo().in_grams());
+        assert_eq!(ship.cargo_space().in_grams(), 0.0);
+
+        let taken = manifest.unstow(&mut ship, &steel(), capacity * 0.25);
+        assert_eq!(taken.in_grams(), (capacity * 0.25).in_grams());
+        assert_eq!(manifest.0.amount(&steel()).in_grams(), ship.cargo().in_grams());
+        assert_eq!(manifest.unstow(&mut ship, &Commodity::new("ceramics"), capacity).in_grams(), 0.0);
+        assert_eq!(manifest.0.amount(&steel()).in_grams(), ship.cargo().in_grams());
+    }
+}
@@ -1,10 +1,13 @@
+use std::cmp::Ordering;
 use std::default::Default;
 use std::convert::{From, Into};
 use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Scale {
-    Gram, Kilogram,
+    Gram, Kilogram, Tonne,
     Ounce, Pound,
     LunarMass, EarthMass, JovianMass, SolarMass
 }
@@ -15,7 +18,7 @@ impl Default for Scale {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Mass {
     /// We see that f64's maximum is approximately 1.8*10^308.
     /// If we use this for grams, we convert into 9.05*10^274 solar masses.
@@ -27,6 +30,7 @@ pub struct Mass {
 
 pub const GRAMS_TO_KILOGRAMS:     f64 = 1_000.0;
 pub const KILOGRAMS_TO_GRAMS:     f64 = 1.0 / GRAMS_TO_KILOGRAMS;
+pub const TONNES_TO_GRAMS:        f64 = 1_000_000.0;
 pub const OUNCES_TO_GRAMS:        f64 = 28.34952;
 pub const POUNDS_TO_GRAMS:        f64 = 453.5924;
 pub const LUNAR_MASSES_TO_GRAMS:  f64 = 7.342e22 * 1_000.0;
@@ -34,6 +38,7 @@ pub const EARTH_MASSES_TO_GRAMS:  f64 = 5.9722e24 * 1_000.0;
 pub const JOVIAN_MASSES_TO_GRAMS: f64 = 1.89813e27 * 1_000.0;
 pub const SOLAR_MASSES_TO_GRAMS:  f64 = 1.98847e30 * 1_000.0;
 
+pub const ZERO: Mass = Mass { grams: 0.0, scale: Scale::Gram };
 pub const MIN: Mass = Mass { grams: std::f64::MIN, scale: Scale::Gram };
 pub const MAX: Mass = Mass { grams: std::f64::MAX, scale: Scale::Gram };
 
@@ -46,6 +51,7 @@ impl Mass {
         match scale {
             Scale::Gram        => Mass::grams(mass),
             Scale::Kilogram    => Mass::kilograms(mass),
+            Scale::Tonne       => Mass::tonnes(mass),
             Scale::Ounce       => Mass::ounces(mass),
             Scale::Pound       => Mass::pounds(mass),
             Scale::LunarMass   => Mass::lunar_masses(mass),
@@ -64,11 +70,18 @@ impl Mass {
 
     pub fn kilograms(kilograms: f64) -> Mass {
         Mass {
-            grams: kilograms / GRAMS_TO_KILOGRAMS,
+            grams: kilograms * GRAMS_TO_KILOGRAMS,
             scale: Scale::Kilogram
         }
     }
 
+    pub fn tonnes(tonnes: f64) -> Mass {
+        Mass {
+            grams: tonnes * TONNES_TO_GRAMS,
+            scale: Scale::Tonne
+        }
+    }
+
     pub fn ounces(ounces: f64) -> Mass {
         Mass {
             grams: ounces * OUNCES_TO_GRAMS,
@@ -119,6 +132,10 @@ impl Mass {
         Mass::kilograms(kg)
     }
 
+    pub fn t(t: f64) -> Mass {
+        Mass::tonnes(t)
+    }
+
     pub fn oz(oz: f64) -> Mass {
         Mass::ounces(oz)
     }
@@ -150,6 +167,80 @@ impl Mass {
     pub fn range(range: std::ops::Range<f64>, scale: Scale) -> std::ops::Range<Mass> {
         Mass::scaled(range.start, scale) .. Mass::scaled(range.end, scale)
     }
+
+    pub fn scale(self) -> Scale {
+        self.scale
+    }
+
+    /// The same mass, displayed in another scale.
+    pub fn to_scale(self, scale: Scale) -> Mass {
+        Mass { grams: self.grams, scale }
+    }
+
+    pub fn in_grams(self) -> f64 {
+        self.grams
+    }
+
+    pub fn in_kilograms(self) -> f64 {
+        self.grams / GRAMS_TO_KILOGRAMS
+    }
+
+    pub fn in_earth_masses(self) -> f64 {
+        self.grams / EARTH_MASSES_TO_GRAMS
+    }
+
+    pub fn in_solar_masses(self) -> f64 {
+        self.grams / SOLAR_MASSES_TO_GRAMS
+    }
+}
+
+impl PartialOrd for Mass {
+    fn partial_cmp(&self, other: &Mass) -> Option<Ordering> {
+        self.grams.partial_cmp(&other.grams)
+    }
+}
+
+// Arithmetic keeps the scale of the left-hand side.
+
+impl Add for Mass {
+    type Output = Mass;
+    fn add(self, other: Mass) -> Mass {
+        Mass { grams: self.grams + other.grams, scale: self.scale }
+    }
+}
+
+impl Sub for Mass {
+    type Output = Mass;
+    fn sub(self, other: Mass) -> Mass {
+        Mass { grams: self.grams - other.grams, scale: self.scale }
+    }
+}
+
+impl Mul<f64> for Mass {
+    type Output = Mass;
+    fn mul(self, factor: f64) -> Mass {
+        Mass { grams: self.grams * factor, scale: self.scale }
+    }
+}
+
+impl Div<f64> for Mass {
+    type Output = Mass;
+    fn div(self, divisor: f64) -> Mass {
+        Mass { grams: self.grams / divisor, scale: self.scale }
+    }
+}
+
+impl Div for Mass {
+    type Output = f64;
+    fn div(self, other: Mass) -> f64 {
+        self.grams / other.grams
+    }
+}
+
+impl std::iter::Sum for Mass {
+    fn sum<I: Iterator<Item = Mass>>(iter: I) -> Mass {
+        iter.fold(ZERO, |total, mass| total + mass)
+    }
 }
 
 
@@ -164,12 +255,13 @@ impl Into<f64> for Mass {
         match self.scale {
             Scale::Gram        => self.grams,
             Scale::Kilogram    => self.grams / GRAMS_TO_KILOGRAMS,
-            Scale::Ounce       => self.grams * OUNCES_TO_GRAMS,
-            Scale::Pound       => self.grams * POUNDS_TO_GRAMS,
-            Scale::LunarMass   => self.grams * LUNAR_MASSES_TO_GRAMS,
-            Scale::EarthMass   => self.grams * EARTH_MASSES_TO_GRAMS,
-            Scale::JovianMass  => self.grams * JOVIAN_MASSES_TO_GRAMS,
-            Scale::SolarMass   => self.grams * SOLAR_MASSES_TO_GRAMS
+            Scale::Tonne       => self.grams / TONNES_TO_GRAMS,
+            Scale::Ounce       => self.grams / OUNCES_TO_GRAMS,
+            Scale::Pound       => self.grams / POUNDS_TO_GRAMS,
+            Scale::LunarMass   => self.grams / LUNAR_MASSES_TO_GRAMS,
+            Scale::EarthMass   => self.grams / EARTH_MASSES_TO_GRAMS,
+            Scale::JovianMass  => self.grams / JOVIAN_MASSES_TO_GRAMS,
+            Scale::SolarMass   => self.grams / SOLAR_MASSES_TO_GRAMS
         }
     }
 }
@@ -177,15 +269,44 @@ impl Into<f64> for Mass {
 impl fmt::Display for Mass {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let value: f64 = Mass::into(*self);
-        match self.scale {
-            Scale::Gram        => write!(f, "{} g", value),
-            Scale::Kilogram    => write!(f, "{} kg", value),
-            Scale::Ounce       => write!(f, "{} oz", value),
-            Scale::Pound       => write!(f, "{} lb", value),
-            Scale::LunarMass   => write!(f, "{} M☽", value),
-            Scale::EarthMass   => write!(f, "{} M♁", value),
-            Scale::JovianMass  => write!(f, "{} M♃", value),
-            Scale::SolarMass   => write!(f, "{} M☉", value)
+        let unit = match self.scale {
+            Scale::Gram        => "g",
+            Scale::Kilogram    => "kg",
+            Scale::Tonne       => "t",
+            Scale::Ounce       => "oz",
+            Scale::Pound       => "lb",
+            Scale::LunarMass   => "M☽",
+            Scale::EarthMass   => "M♁",
+            Scale::JovianMass  => "M♃",
+            Scale::SolarMass   => "M☉"
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            None            => write!(f, "{} {}", value, unit)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs()
+    }
+
+    #[test]
+    fn kilograms_are_a_thousand_grams() {
+        assert!(close(Mass::kg(2.5).in_grams(), 2_500.0));
+        assert!(close(Mass::kg(2.5).into(), 2.5));
+        assert!(close(Mass::kg(2.5).in_kilograms(), 2.5));
+    }
+
+    #[test]
+    fn conversions_round_trip_through_grams() {
+        assert!(close(Mass::lb(1.0).in_grams(), POUNDS_TO_GRAMS));
+        assert!(close(Mass::lb(3.0).into(), 3.0));
+        assert!(close(Mass::Msol(1.0).into(), 1.0));
+        assert!(close(Mass::kg(5.9722e24).to_scale(Scale::EarthMass).into(), 1.0));
+    }
+}
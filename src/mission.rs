@@ -0,0 +1,840 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+
+use super::behaviour::{Blackboard, Role, Value};
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY};
+use super::combat::{CombatEvent, CombatSystem};
+use super::flight::PlayerShip;
+use super::floating_origin::WorldPosition;
+use super::galaxy::StarId;
+use super::industry::Commodity;
+use super::length;
+use super::mass;
+use super::mass::Mass;
+use super::market::{Credits, Manifest, Market, DOCKING_RANGE_METERS};
+use super::random;
+use super::random::Rng;
+use super::scene;
+use super::sensors::Contacts;
+use super::ship::Ship;
+use super::star::{SpectralType, Star};
+use super::universe::UniverseSettings;
+
+/// The mission templates, under the assets directory.
+pub const MISSIONS: &str = "missions/missions.ron";
+/// How often markets post fresh missions, replacing those not taken up.
+pub const OFFER_INTERVAL_SECONDS: f64 = SECONDS_PER_DAY;
+pub const OFFERS_PER_MARKET: usize = 3;
+/// How near a star a ship must come to survey it.
+pub const SURVEY_RANGE_METERS: f64 = 10.0 * length::AU_TO_METERS;
+/// How near an escort must be to its charge when the charge arrives.
+pub const ESCORT_RANGE_METERS: f64 = DOCKING_RANGE_METERS * 2.0;
+const MISSION_KEY: u64 = 0x6d69_7373_696f_6e73;
+
+/// When a template is offered. All of a template's triggers must hold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// The player has completed at least this many missions.
+    Completed(u32),
+    /// Each market offers it with this probability.
+    Chance(f64),
+    /// The market's station can see a pirate.
+    Hostiles
+}
+
+/// What a template asks for, filled in from the universe when it is offered.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ObjectiveTemplate {
+    /// Carry consigned cargo, one of the commodities in an amount in the range of tonnes, to another market.
+    Deliver { commodities: Vec<Commodity>, tonnes: (f64, f64) },
+    /// Visit a star of one of the spectral types within this many light years.
+    Survey { spectral_types: Vec<SpectralType>, light_years: f64 },
+    /// See a trader docked at the market safely to another.
+    Escort,
+    /// Destroy a pirate the market can see.
+    Bounty,
+    /// Come back to the market that gave the mission.
+    Return
+}
+
+/// How a mission can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Failure {
+    /// Its time limit ran out.
+    Deadline,
+    /// Cargo to be delivered is no longer aboard.
+    CargoLost,
+    /// A ship to be escorted or destroyed is gone, other than by the player's hand.
+    TargetLost,
+    /// The player's ship is gone.
+    ShipLost
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Deadline   => write!(f, "out of time"),
+            Failure::CargoLost  => write!(f, "cargo lost"),
+            Failure::TargetLost => write!(f, "target lost"),
+            Failure::ShipLost   => write!(f, "ship lost")
+        }
+    }
+}
+
+/// Credits paid on completion, with more for the distances involved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Reward {
+    pub credits: f64,
+    /// For each AU between the market and where a delivery or escort is bound.
+    #[serde(default)]
+    pub per_au: f64,
+    /// For each light year to a star to be surveyed.
+    #[serde(default)]
+    pub per_light_year: f64
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MissionTemplate {
+    pub name: String,
+    /// Carried out in order.
+    pub objectives: Vec<ObjectiveTemplate>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    pub reward: Reward,
+    #[serde(default)]
+    pub failures: Vec<Failure>,
+    /// Seconds from accepting it to complete it in, if it fails on `Deadline`.
+    pub time_limit: f64
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MissionTemplates(pub Vec<MissionTemplate>);
+
+impl MissionTemplates {
+    pub fn from_ron(text: &str) -> Result<MissionTemplates, ron::Error> {
+        ron::de::from_str(text)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MissionTemplates, ron::Error> {
+        MissionTemplates::from_ron(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// An objective as offered, naming what it refers to by `T`: entities in the game, bodies in a save.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Objective<T> {
+    Deliver { commodity: Commodity, amount: Mass, to: T },
+    Survey { star: T, id: StarId },
+    Escort { ship: T, to: T },
+    Bounty { ship: T },
+    Return { to: T }
+}
+
+impl<T> Objective<T> {
+    pub fn map<U>(&self, f: &mut impl FnMut(&T) -> Option<U>) -> Option<Objective<U>> {
+        Some(match self {
+            Objective::Deliver { commodity, amount, to } => Objective::Deliver { commodity: commodity.clone(), amount: *amount, to: f(to)? },
+            Objective::Survey { star, id }               => Objective::Survey { star: f(star)?, id: *id },
+            Objective::Escort { ship, to }               => Objective::Escort { ship: f(ship)?, to: f(to)? },
+            Objective::Bounty { ship }                   => Objective::Bounty { ship: f(ship)? },
+            Objective::Return { to }                     => Objective::Return { to: f(to)? }
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Goal<T> {
+    pub objective: Objective<T>,
+    pub done: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MissionStatus {
+    Offered,
+    Active,
+    Completed,
+    Failed(Failure),
+    Abandoned
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mission<T> {
+    pub id: u64,
+    pub name: String,
+    /// The market that offers it, and pays for it.
+    pub giver: T,
+    /// What it asks, in words.
+    pub briefing: String,
+    pub objectives: Vec<Goal<T>>,
+    pub reward: f64,
+    pub failures: Vec<Failure>,
+    pub time_limit: f64,
+    pub offered_at: f64,
+    /// When it fails on `Deadline`, once accepted.
+    pub deadline: Option<f64>,
+    pub status: MissionStatus
+}
+
+impl<T> Mission<T> {
+    /// The same mission naming what it refers to by `U`, or `None` if anything it refers to cannot be.
+    pub fn map<U>(&self, f: &mut impl FnMut(&T) -> Option<U>) -> Option<Mission<U>> {
+        Some(Mission {
+            id: self.id,
+            name: self.name.clone(),
+            giver: f(&self.giver)?,
+            briefing: self.briefing.clone(),
+            objectives: self.objectives.iter()
+                .map(|goal| Some(Goal { objective: goal.objective.map(f)?, done: goal.done }))
+                .collect::<Option<_>>()?,
+            reward: self.reward,
+            failures: self.failures.clone(),
+            time_limit: self.time_limit,
+            offered_at: self.offered_at,
+            deadline: self.deadline,
+            status: self.status
+        })
+    }
+
+    /// The first objective not yet done.
+    pub fn current(&self) -> Option<&Objective<T>> {
+        self.objectives.iter().find(|goal| !goal.done).map(|goal| &goal.objective)
+    }
+}
+
+impl<T> fmt::Display for Mission<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let done = self.objectives.iter().filter(|goal| goal.done).count();
+        write!(f, "#{} {}: {} ({}/{}, {:.0} credits)", self.id, self.name, self.briefing, done, self.objectives.len(), self.reward)
+    }
+}
+
+/// Every mission offered to the player, taken up, or finished.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MissionLog<T = Entity> {
+    pub missions: Vec<Mission<T>>,
+    pub next_id: u64,
+    /// When markets next post missions.
+    pub next_offer: f64,
+    pub completed: u32
+}
+
+impl<T> Default for MissionLog<T> {
+    fn default() -> MissionLog<T> {
+        MissionLog { missions: Vec::new(), next_id: 1, next_offer: 0.0, completed: 0 }
+    }
+}
+
+impl<T> MissionLog<T> {
+    /// The log naming what it refers to by `U`, leaving out missions referring to anything that cannot be.
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> Option<U>) -> MissionLog<U> {
+        MissionLog {
+            missions: self.missions.iter().filter_map(|mission| mission.map(&mut f)).collect(),
+            next_id: self.next_id,
+            next_offer: self.next_offer,
+            completed: self.completed
+        }
+    }
+
+    pub fn with_status(&self, status: MissionStatus) -> impl Iterator<Item = &Mission<T>> {
+        self.missions.iter().filter(move |mission| mission.status == status)
+    }
+}
+
+impl MissionLog<Entity> {
+    /// What a market offers.
+    pub fn offers(&self, giver: Entity) -> impl Iterator<Item = &Mission<Entity>> {
+        self.with_status(MissionStatus::Offered).filter(move |mission| mission.giver == giver)
+    }
+}
+
+/// Why a mission could not be taken up.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MissionError {
+    NotOffered,
+    NoShip,
+    /// The player must be docked at the market offering it.
+    NotDocked,
+    NoCargoSpace { needed: Mass, space: Mass }
+}
+
+impl fmt::Display for MissionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissionError::NotOffered                     => write!(f, "not on offer"),
+            MissionError::NoShip                         => write!(f, "no ship"),
+            MissionError::NotDocked                      => write!(f, "not docked at the market offering it"),
+            MissionError::NoCargoSpace { needed, space } => write!(f, "needs {} of cargo space, only {} free", needed, space)
+        }
+    }
+}
+
+/// Asks to take up an offered mission.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AcceptMission(pub u64);
+
+/// Asks to give up an active mission.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbandonMission(pub u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MissionEvent {
+    Accepted { id: u64 },
+    Refused { id: u64, error: MissionError },
+    Progressed { id: u64, objective: usize },
+    Completed { id: u64, reward: f64 },
+    Failed { id: u64, failure: Failure },
+    Abandoned { id: u64 }
+}
+
+impl fmt::Display for MissionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissionEvent::Accepted { id }              => write!(f, "mission {} accepted", id),
+            MissionEvent::Refused { id, error }        => write!(f, "mission {} refused: {}", id, error),
+            MissionEvent::Progressed { id, objective } => write!(f, "mission {} objective {} done", id, objective + 1),
+            MissionEvent::Completed { id, reward }     => write!(f, "mission {} completed, {:.0} credits paid", id, reward),
+            MissionEvent::Failed { id, failure }       => write!(f, "mission {} failed: {}", id, failure),
+            MissionEvent::Abandoned { id }             => write!(f, "mission {} abandoned", id)
+        }
+    }
+}
+
+/// What a market can see about it when it posts missions.
+struct Surroundings<'a> {
+    markets: &'a [(Entity, WorldPosition)],
+    stars: &'a [(Entity, StarId, SpectralType, WorldPosition)],
+    /// Ships flown by behaviour trees, with their roles and where they are.
+    agents: &'a [(Entity, Role, WorldPosition)]
+}
+
+fn au_between(a: WorldPosition, b: WorldPosition) -> f64 {
+    a.distance(b).in_meters() / length::AU_TO_METERS
+}
+
+/// Fills a template in for a market, or `None` if there is nothing about for some objective of it.
+fn instantiate(
+    template: &MissionTemplate,
+    (giver, position, contacts): (Entity, WorldPosition, Option<&Contacts>),
+    surroundings: &Surroundings,
+    rng: &mut Rng
+) -> Option<(Vec<Goal<Entity>>, Vec<String>, f64)> {
+    let mut goals = Vec::new();
+    let mut briefing = Vec::new();
+    let mut reward = template.reward.credits;
+    let pick = |count: usize, rng: &mut Rng| if count == 0 { None } else { Some(rng.below(count as u64) as usize) };
+    let elsewhere: Vec<&(Entity, WorldPosition)> = surroundings.markets.iter().filter(|(market, _)| *market != giver).collect();
+    for objective in template.objectives.iter() {
+        let filled = match objective {
+            ObjectiveTemplate::Deliver { commodities, tonnes } => {
+                let (to, to_position) = *elsewhere[pick(elsewhere.len(), rng)?];
+                let commodity = commodities[pick(commodities.len(), rng)?].clone();
+                let amount = Mass::t(rng.range_f64(tonnes.0..tonnes.1.max(tonnes.0 + f64::EPSILON)).round().max(1.0));
+                reward += template.reward.per_au * au_between(position, to_position);
+                briefing.push(format!("deliver {} of {} {:.2} AU", amount, commodity.0, au_between(position, to_position)));
+                Objective::Deliver { commodity, amount, to }
+            }
+            ObjectiveTemplate::Survey { spectral_types, light_years } => {
+                let candidates: Vec<&(Entity, StarId, SpectralType, WorldPosition)> = surroundings.stars.iter()
+                    .filter(|(_, _, spectral_type, star_position)| {
+                        spectral_types.contains(spectral_type)
+                            && star_position.distance(position).in_meters() > SURVEY_RANGE_METERS
+                            && star_position.distance(position).in_light_years() <= *light_years
+                    })
+                    .collect();
+                let (star, id, spectral_type, star_position) = *candidates[pick(candidates.len(), rng)?];
+                let distance = star_position.distance(position).in_light_years();
+                reward += template.reward.per_light_year * distance;
+                briefing.push(format!("survey the {:?} star {} {:.1} ly away", spectral_type, id, distance));
+                Objective::Survey { star, id }
+            }
+            ObjectiveTemplate::Escort => {
+                let traders: Vec<&(Entity, Role, WorldPosition)> = surroundings.agents.iter()
+                    .filter(|(_, role, ship_position)| *role == Role::Trader && ship_position.distance(position).in_meters() <= DOCKING_RANGE_METERS)
+                    .collect();
+                let (ship, _, _) = *traders[pick(traders.len(), rng)?];
+                let (to, to_position) = *elsewhere[pick(elsewhere.len(), rng)?];
+                reward += template.reward.per_au * au_between(position, to_position);
+                briefing.push(format!("escort {:?} {:.2} AU", ship, au_between(position, to_position)));
+                Objective::Escort { ship, to }
+            }
+            ObjectiveTemplate::Bounty => {
+                let seen = contacts?;
+                let (ship, _, _) = surroundings.agents.iter()
+                    .filter(|(ship, role, _)| *role == Role::Pirate && seen.is_detected(*ship))
+                    .min_by(|(_, _, a), (_, _, b)| a.distance(position).partial_cmp(&b.distance(position)).unwrap_or(Ordering::Equal))?;
+                briefing.push(format!("destroy the pirate {:?}", ship));
+                Objective::Bounty { ship: *ship }
+            }
+            ObjectiveTemplate::Return => {
+                briefing.push("return".to_string());
+                Objective::Return { to: giver }
+            }
+        };
+        goals.push(Goal { objective: filled, done: false });
+    }
+    Some((goals, briefing, reward.round()))
+}
+
+type Giver<'a> = (Entity, &'a WorldPosition, Option<&'a Contacts>);
+
+/// Has every market post missions once every `OFFER_INTERVAL_SECONDS`, filled in from what is about it,
+/// withdrawing those it posted before that were not taken up.
+pub fn offer_missions(
+    clock: Res<GameClock>,
+    universe: Res<UniverseSettings>,
+    templates: Res<MissionTemplates>,
+    mut log: ResMut<MissionLog>,
+    markets: Query<Giver, With<Market>>,
+    stars: Query<(Entity, &StarId, &Star, &WorldPosition)>,
+    agents: Query<(Entity, &Role, &WorldPosition)>
+) {
+    let now = clock.seconds;
+    if now < log.next_offer {
+        return;
+    }
+    log.next_offer = now + OFFER_INTERVAL_SECONDS;
+    log.missions.retain(|mission| mission.status != MissionStatus::Offered);
+    let mut givers: Vec<_> = markets.iter().collect();
+    givers.sort_by_key(|(entity, _, _)| *entity);
+    let positions: Vec<(Entity, WorldPosition)> = givers.iter().map(|(entity, position, _)| (*entity, **position)).collect();
+    let mut stars: Vec<(Entity, StarId, SpectralType, WorldPosition)> = stars.iter()
+        .map(|(entity, id, star, position)| (entity, *id, star.spectral_type, *position))
+        .collect();
+    stars.sort_by_key(|(_, id, _, _)| *id);
+    let mut agents: Vec<(Entity, Role, WorldPosition)> = agents.iter().map(|(entity, role, position)| (entity, *role, *position)).collect();
+    agents.sort_by_key(|(entity, _, _)| *entity);
+    let surroundings = Surroundings { markets: &positions, stars: &stars, agents: &agents };
+    let completed = log.completed;
+    for (number, (giver, position, contacts)) in givers.iter().enumerate() {
+        let mut rng = Rng::new(random::derive(random::derive(random::derive(universe.galaxy.seed, MISSION_KEY), now.to_bits()), number as u64));
+        let mut offered = 0;
+        for template in templates.0.iter() {
+            if offered >= OFFERS_PER_MARKET {
+                break;
+            }
+            let triggered = template.triggers.iter().all(|trigger| match trigger {
+                Trigger::Completed(count)   => completed >= *count,
+                Trigger::Chance(probability) => rng.chance(*probability),
+                Trigger::Hostiles           => matches!(contacts, Some(contacts) if agents.iter()
+                    .any(|(ship, role, _)| *role == Role::Pirate && contacts.is_detected(*ship)))
+            });
+            if !triggered {
+                continue;
+            }
+            if let Some((objectives, briefing, reward)) = instantiate(template, (*giver, **position, *contacts), &surroundings, &mut rng) {
+                let id = log.next_id;
+                log.next_id += 1;
+                log.missions.push(Mission {
+                    id,
+                    name: template.name.clone(),
+                    giver: *giver,
+                    briefing: briefing.join(", then "),
+                    objectives,
+                    reward,
+                    failures: template.failures.clone(),
+                    time_limit: template.time_limit,
+                    offered_at: now,
+                    deadline: None,
+                    status: MissionStatus::Offered
+                });
+                offered += 1;
+            }
+        }
+    }
+}
+
+type PlayerAccount<'a> = (Entity, &'a WorldPosition, &'a mut Ship, &'a mut Manifest);
+
+/// Takes up and gives up missions as the player asks. Cargo to be delivered is loaded when a delivery is taken up,
+/// and traders to be escorted are sent on their way.
+pub fn accept_missions(
+    clock: Res<GameClock>,
+    mut log: ResMut<MissionLog>,
+    (mut accepts, mut abandons): (EventReader<AcceptMission>, EventReader<AbandonMission>),
+    mut players: Query<PlayerAccount, With<PlayerShip>>,
+    positions: Query<&WorldPosition, Without<PlayerShip>>,
+    mut blackboards: Query<&mut Blackboard>,
+    mut events: EventWriter<MissionEvent>
+) {
+    for AcceptMission(id) in accepts.iter() {
+        let mission = match log.missions.iter_mut().find(|mission| mission.id == *id && mission.status == MissionStatus::Offered) {
+            Some(mission) => mission,
+            None          => {
+                events.send(MissionEvent::Refused { id: *id, error: MissionError::NotOffered });
+                continue;
+            }
+        };
+        let (_, position, mut ship, mut manifest) = match players.single_mut() {
+            Ok(player) => player,
+            Err(_)     => {
+                events.send(MissionEvent::Refused { id: *id, error: MissionError::NoShip });
+                continue;
+            }
+        };
+        if !matches!(positions.get(mission.giver), Ok(giver) if giver.distance(*position).in_meters() <= DOCKING_RANGE_METERS) {
+            events.send(MissionEvent::Refused { id: *id, error: MissionError::NotDocked });
+            continue;
+        }
+        let consigned = mission.objectives.iter()
+            .map(|goal| match &goal.objective {
+                Objective::Deliver { amount, .. } => *amount,
+                _                                 => mass::ZERO
+            })
+            .sum::<Mass>()
+            .to_scale(mass::Scale::Tonne);
+        if consigned > ship.cargo_space() {
+            events.send(MissionEvent::Refused { id: *id, error: MissionError::NoCargoSpace { needed: consigned, space: ship.cargo_space() } });
+            continue;
+        }
+        for goal in mission.objectives.iter() {
+            match &goal.objective {
+                Objective::Deliver { commodity, amount, .. } => {
+                    manifest.stow(&mut ship, commodity, *amount);
+                }
+                Objective::Escort { ship, to } => {
+                    if let Ok(mut blackboard) = blackboards.get_mut(*ship) {
+                        blackboard.set("destination", Value::Entity(*to));
+                    }
+                }
+                _ => {}
+            }
+        }
+        mission.status = MissionStatus::Active;
+        mission.deadline = Some(clock.seconds + mission.time_limit);
+        events.send(MissionEvent::Accepted { id: *id });
+    }
+    for AbandonMission(id) in abandons.iter() {
+        if let Some(mission) = log.missions.iter_mut().find(|mission| mission.id == *id && mission.status == MissionStatus::Active) {
+            mission.status = MissionStatus::Abandoned;
+            events.send(MissionEvent::Abandoned { id: *id });
+        }
+    }
+}
+
+type PlayerMissionShip<'a> = (Entity, &'a WorldPosition, &'a mut Ship, &'a mut Manifest, &'a mut Credits);
+
+/// Checks the player's active missions: their current objectives, which are carried out in order, and their failures.
+/// Completed missions are paid for at once.
+pub fn track_missions(
+    clock: Res<GameClock>,
+    mut log: ResMut<MissionLog>,
+    mut combat: EventReader<CombatEvent>,
+    mut players: Query<PlayerMissionShip, With<PlayerShip>>,
+    positions: Query<&WorldPosition, Without<PlayerShip>>,
+    mut events: EventWriter<MissionEvent>
+) {
+    let now = clock.seconds;
+    let mut player = players.single_mut().ok();
+    let destroyed: Vec<(Entity, Entity)> = combat.iter()
+        .filter_map(|event| match event {
+            CombatEvent::Destroyed { ship, by } => Some((*ship, *by)),
+            _                                   => None
+        })
+        .collect();
+    let near = |a: Option<&WorldPosition>, b: Entity, meters: f64| matches!((a, positions.get(b)), (Some(a), Ok(b)) if a.distance(*b).in_meters() <= meters);
+    let mut completed = 0;
+    for mission in log.missions.iter_mut().filter(|mission| mission.status == MissionStatus::Active) {
+        let position = player.as_ref().map(|(_, position, ..)| **position);
+        // Bounties are claimed by the shot that destroys the target, whenever in the mission it falls.
+        for goal in mission.objectives.iter_mut() {
+            if let Objective::Bounty { ship } = goal.objective {
+                let by_player = destroyed.iter().any(|(target, by)| *target == ship && matches!(&player, Some((entity, ..)) if entity == by));
+                if by_player && !goal.done {
+                    goal.done = true;
+                }
+            }
+        }
+        let failure = mission.failures.iter().copied().find(|failure| match failure {
+            Failure::Deadline   => matches!(mission.deadline, Some(deadline) if now > deadline),
+            Failure::ShipLost   => player.is_none(),
+            Failure::CargoLost  => mission.objectives.iter().any(|goal| match &goal.objective {
+                Objective::Deliver { commodity, amount, .. } if !goal.done => {
+                    !matches!(&player, Some((_, _, _, manifest, _)) if manifest.0.amount(commodity) >= *amount * 0.999)
+                }
+                _ => false
+            }),
+            Failure::TargetLost => mission.objectives.iter().any(|goal| match &goal.objective {
+                Objective::Escort { ship, .. } | Objective::Bounty { ship } if !goal.done => positions.get(*ship).is_err(),
+                _                                                                        => false
+            })
+        });
+        if let Some(failure) = failure {
+            mission.status = MissionStatus::Failed(failure);
+            events.send(MissionEvent::Failed { id: mission.id, failure });
+            continue;
+        }
+        let index = match mission.objectives.iter().position(|goal| !goal.done) {
+            Some(index) => index,
+            None        => mission.objectives.len()
+        };
+        let done = match mission.objectives.get(index).map(|goal| &goal.objective) {
+            Some(Objective::Deliver { commodity, amount, to }) if near(position.as_ref(), *to, DOCKING_RANGE_METERS) => {
+                match player.as_mut() {
+                    Some((_, _, ship, manifest, _)) => {
+                        manifest.unstow(ship, commodity, *amount);
+                        true
+                    }
+                    None => false
+                }
+            }
+            Some(Objective::Survey { star, .. })   => near(position.as_ref(), *star, SURVEY_RANGE_METERS),
+            Some(Objective::Escort { ship, to })   => {
+                near(position.as_ref(), *ship, ESCORT_RANGE_METERS)
+                    && matches!((positions.get(*ship), positions.get(*to)), (Ok(a), Ok(b)) if a.distance(*b).in_meters() <= DOCKING_RANGE_METERS)
+            }
+            Some(Objective::Return { to })         => near(position.as_ref(), *to, DOCKING_RANGE_METERS),
+            _                                      => false
+        };
+        if done {
+            mission.objectives[index].done = true;
+            events.send(MissionEvent::Progressed { id: mission.id, objective: index });
+        }
+        if mission.objectives.iter().all(|goal| goal.done) {
+            mission.status = MissionStatus::Completed;
+            if let Some((_, _, _, _, credits)) = player.as_mut() {
+                credits.0 += mission.reward;
+            }
+            completed += 1;
+            events.send(MissionEvent::Completed { id: mission.id, reward: mission.reward });
+        }
+    }
+    log.completed += completed;
+}
+
+pub fn log_mission_events(mut events: EventReader<MissionEvent>) {
+    for event in events.iter() {
+        info!("{}", event);
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum MissionSystem {
+    Offer,
+    Track
+}
+
+/// Missions posted by markets from data-defined templates, filled in from the universe about them, and the player's
+/// log of those taken up. Needs the `ClockPlugin`, `UniversePlugin` and `MarketPlugin`; bounties and escorts need
+/// the `BehaviourPlugin`, `SensorPlugin` and `CombatPlugin` for their pirates and traders.
+pub struct MissionPlugin;
+
+impl Plugin for MissionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let templates = MissionTemplates::load(scene::asset_path(MISSIONS)).unwrap_or_else(|error| {
+            error!("could not load the missions {}: {}", MISSIONS, error);
+            MissionTemplates::default()
+        });
+        app.insert_resource(templates)
+            .init_resource::<MissionLog>()
+            .add_event::<AcceptMission>()
+            .add_event::<AbandonMission>()
+            .add_event::<MissionEvent>()
+            .add_system(accept_missions.system())
+            .add_system(log_mission_events.system())
+            .add_system_to_stage(SimulationStage, offer_missions.system()
+                                 .label(MissionSystem::Offer)
+                                 .after(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, track_missions.system()
+                                 .label(MissionSystem::Track)
+                                 .after(CombatSystem::Resolve)
+                                 .after(MissionSystem::Offer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::{Events, ManualEventReader};
+    use bevy::math::DVec3;
+    use super::super::astronomy::Orbiting;
+    use super::super::flight::{Attitude, Velocity};
+    use super::super::planet;
+    use super::super::save;
+    use super::super::ship::courier;
+    use super::super::universe::UniverseSeed;
+
+
+    fn steel() -> Commodity {
+        Commodity::new("steel")
+    }
+
+    /// Steel carried an AU, then a return to the market that gave it.
+    fn consignments() -> MissionTemplates {
+        MissionTemplates(vec![MissionTemplate {
+            name: "Consignment".to_string(),
+            objectives: vec![ObjectiveTemplate::Deliver { commodities: vec![steel()], tonnes: (2.0, 5.0) }, ObjectiveTemplate::Return],
+            triggers: vec![Trigger::Chance(1.0)],
+            reward: Reward { credits: 500.0, per_au: 100.0, per_light_year: 0.0 },
+            failures: vec![Failure::Deadline, Failure::CargoLost, Failure::ShipLost],
+            time_limit: 10.0 * SECONDS_PER_DAY
+        }])
+    }
+
+    /// A star with two of its planets as markets an AU apart, and the player's courier docked at the first.
+    fn courier_at_a_market() -> (World, [Entity; 2], Entity) {
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        let (galaxy_star, planets) = settings.galaxy.stars_within(settings.start, settings.radius)
+            .map(|galaxy_star| {
+                let planets = planet::generate_planets(&galaxy_star.star(), galaxy_star.system_seed());
+                (galaxy_star, planets)
+            })
+            .find(|(_, planets)| planets.len() >= 2)
+            .unwrap();
+        let mut world = World::default();
+        let star = world.spawn()
+            .insert_bundle((galaxy_star.id, galaxy_star.star(), WorldPosition::from_position(galaxy_star.position)))
+            .id();
+        let mut markets = [star; 2];
+        for (index, (planet, orbit)) in planets.into_iter().take(2).enumerate() {
+            let position = WorldPosition::from_meters(DVec3::new(index as f64 * length::AU_TO_METERS, 0.0, 0.0));
+            markets[index] = world.spawn().insert_bundle((planet, orbit, Orbiting(star), Market::default(), position)).id();
+        }
+        let player = world.spawn()
+            .insert_bundle((courier(), WorldPosition::default(), Velocity(DVec3::ZERO), Attitude::default()))
+            .insert_bundle((PlayerShip, Manifest::default(), Credits(0.0)))
+            .id();
+        world.insert_resource(GameClock::default());
+        world.insert_resource(settings);
+        world.insert_resource(consignments());
+        world.insert_resource(MissionLog::<Entity>::default());
+        world.insert_resource(Events::<AcceptMission>::default());
+        world.insert_resource(Events::<AbandonMission>::default());
+        world.insert_resource(Events::<MissionEvent>::default());
+        world.insert_resource(Events::<CombatEvent>::default());
+        (world, markets, player)
+    }
+
+    fn run(world: &mut World) {
+        SystemStage::single_threaded()
+            .with_system(offer_missions.system().label(MissionSystem::Offer))
+            .with_system(accept_missions.system().after(MissionSystem::Offer))
+            .with_system(track_missions.system().label(MissionSystem::Track))
+            .run(world);
+    }
+
+    fn accept(world: &mut World, id: u64) {
+        world.get_resource_mut::<Events<AcceptMission>>().unwrap().send(AcceptMission(id));
+        run(world);
+    }
+
+    fn events(world: &World) -> Vec<MissionEvent> {
+        let events = world.get_resource::<Events<MissionEvent>>().unwrap();
+        ManualEventReader::<MissionEvent>::default().iter(events).cloned().collect()
+    }
+
+    fn move_player(world: &mut World, player: Entity, to: Entity) {
+        let position = *world.get::<WorldPosition>(to).unwrap();
+        *world.get_mut::<WorldPosition>(player).unwrap() = position;
+    }
+
+    fn offered_by(world: &World, giver: Entity) -> Mission<Entity> {
+        world.get_resource::<MissionLog>().unwrap().offers(giver).next().cloned().unwrap()
+    }
+
+    fn status(world: &World, id: u64) -> MissionStatus {
+        world.get_resource::<MissionLog>().unwrap().missions.iter().find(|mission| mission.id == id).unwrap().status
+    }
+
+    #[test]
+    fn offers_depend_only_on_the_seed_and_the_time() {
+        let (mut a, markets, _) = courier_at_a_market();
+        let (mut b, _, _) = courier_at_a_market();
+        run(&mut a);
+        run(&mut b);
+        let log = a.get_resource::<MissionLog>().unwrap().clone();
+        assert_eq!(log.offers(markets[0]).count(), 1);
+        assert_eq!(log.offers(markets[1]).count(), 1);
+        assert_eq!(&log, b.get_resource::<MissionLog>().unwrap());
+
+        // Offers not taken up are withdrawn for new ones a day on.
+        a.get_resource_mut::<GameClock>().unwrap().seconds += OFFER_INTERVAL_SECONDS;
+        run(&mut a);
+        let later = a.get_resource::<MissionLog>().unwrap();
+        assert_eq!(later.with_status(MissionStatus::Offered).count(), 2);
+        assert!(later.missions.iter().all(|mission| mission.offered_at == OFFER_INTERVAL_SECONDS));
+    }
+
+    #[test]
+    fn missions_are_taken_up_only_where_they_are_offered() {
+        let (mut world, markets, player) = courier_at_a_market();
+        run(&mut world);
+        let elsewhere = offered_by(&world, markets[1]);
+        accept(&mut world, elsewhere.id);
+        assert_eq!(events(&world), vec![MissionEvent::Refused { id: elsewhere.id, error: MissionError::NotDocked }]);
+        assert_eq!(status(&world, elsewhere.id), MissionStatus::Offered);
+
+        let here = offered_by(&world, markets[0]);
+        accept(&mut world, here.id);
+        assert!(events(&world).contains(&MissionEvent::Accepted { id: here.id }));
+        assert_eq!(status(&world, here.id), MissionStatus::Active);
+        let consigned = match &here.objectives[0].objective {
+            Objective::Deliver { amount, .. } => *amount,
+            objective                         => panic!("expected a delivery, not {:?}", objective)
+        };
+        assert_eq!(world.get::<Manifest>(player).unwrap().0.amount(&steel()).in_grams(), consigned.in_grams());
+        assert_eq!(world.get::<Ship>(player).unwrap().cargo().in_grams(), consigned.in_grams());
+    }
+
+    #[test]
+    fn deliveries_are_paid_for_on_return() {
+        let (mut world, markets, player) = courier_at_a_market();
+        run(&mut world);
+        let mission = offered_by(&world, markets[0]);
+        accept(&mut world, mission.id);
+
+        move_player(&mut world, player, markets[1]);
+        run(&mut world);
+        assert!(events(&world).contains(&MissionEvent::Progressed { id: mission.id, objective: 0 }));
+        assert_eq!(world.get::<Manifest>(player).unwrap().0.amount(&steel()).in_grams(), 0.0);
+        assert_eq!(world.get::<Ship>(player).unwrap().cargo().in_grams(), 0.0);
+
+        move_player(&mut world, player, markets[0]);
+        run(&mut world);
+        assert!(events(&world).contains(&MissionEvent::Completed { id: mission.id, reward: mission.reward }));
+        assert_eq!(status(&world, mission.id), MissionStatus::Completed);
+        assert_eq!(world.get::<Credits>(player).unwrap().0, mission.reward);
+        assert_eq!(world.get_resource::<MissionLog>().unwrap().completed, 1);
+    }
+
+    #[test]
+    fn missions_fail_when_the_cargo_is_lost_or_time_runs_out() {
+        let (mut world, markets, player) = courier_at_a_market();
+        run(&mut world);
+        let mission = offered_by(&world, markets[0]);
+        accept(&mut world, mission.id);
+        world.get_mut::<Manifest>(player).unwrap().0 = Default::default();
+        run(&mut world);
+        assert_eq!(status(&world, mission.id), MissionStatus::Failed(Failure::CargoLost));
+
+        let (mut world, markets, _) = courier_at_a_market();
+        run(&mut world);
+        let mission = offered_by(&world, markets[0]);
+        accept(&mut world, mission.id);
+        world.get_resource_mut::<GameClock>().unwrap().seconds += mission.time_limit + 1.0;
+        run(&mut world);
+        assert_eq!(status(&world, mission.id), MissionStatus::Failed(Failure::Deadline));
+    }
+
+    #[test]
+    fn missions_survive_a_save() {
+        let (mut world, markets, _) = courier_at_a_market();
+        run(&mut world);
+        let mission = offered_by(&world, markets[0]);
+        accept(&mut world, mission.id);
+        let log = world.get_resource::<MissionLog>().unwrap().clone();
+
+        let data = save::decode(&save::encode(&save::capture(&mut world)).unwrap()).unwrap();
+        let mut restored = World::default();
+        save::restore(&mut restored, data.clone());
+        assert_eq!(save::capture(&mut restored).missions, data.missions);
+
+        // The same missions, naming the restored planets.
+        let mut planets: Vec<Entity> = restored.query_filtered::<Entity, With<Market>>().iter(&restored).collect();
+        planets.sort();
+        let renamed = log.map(|entity| markets.iter().position(|market| market == entity).map(|index| planets[index]));
+        assert_eq!(restored.get_resource::<MissionLog>().unwrap(), &renamed);
+    }
+}
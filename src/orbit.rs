@@ -0,0 +1,161 @@
+use bevy::math::DVec3;
+use std::f64::consts::TAU;
+use serde::{Deserialize, Serialize};
+
+use super::length::Length;
+use super::mass::Mass;
+
+/// Newton's gravitational constant, in m³ kg⁻¹ s⁻² (CODATA 2018).
+pub const GRAVITATIONAL_CONSTANT: f64 = 6.674_30e-11;
+
+/// A Keplerian orbit around a central mass.
+/// The reference plane is x-z with +y as north, matching the galactic disk; angles are in radians.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Orbit {
+    pub semi_major_axis: Length,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    /// The mean anomaly at time zero of the universe's clock.
+    pub mean_anomaly_at_epoch: f64,
+    pub central_mass: Mass
+}
+
+impl Orbit {
+    pub fn circular(radius: Length, central_mass: Mass) -> Orbit {
+        Orbit {
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            mean_anomaly_at_epoch: 0.0,
+            central_mass
+        }
+    }
+
+    /// The bound orbit of a light body with a position in meters and a velocity in meters per second
+    /// relative to a central mass, at a time in seconds since the epoch. `None` if it is unbound.
+    pub fn from_state(position: DVec3, velocity: DVec3, central_mass: Mass, seconds: f64) -> Option<Orbit> {
+        let mu = GRAVITATIONAL_CONSTANT * central_mass.in_kilograms();
+        // Work in the classical frame, with north along +z.
+        let r = DVec3::new(position.x, -position.z, position.y);
+        let v = DVec3::new(velocity.x, -velocity.z, velocity.y);
+        let energy = v.length_squared() / 2.0 - mu / r.length();
+        let angular_momentum = r.cross(v);
+        if energy >= 0.0 || angular_momentum.length() == 0.0 {
+            return None;
+        }
+        let normal = angular_momentum.normalize();
+        let eccentricity_vector = (r * (v.length_squared() - mu / r.length()) - v * r.dot(v)) / mu;
+        let eccentricity = eccentricity_vector.length();
+        let node = DVec3::new(-angular_momentum.y, angular_momentum.x, 0.0);
+        let (ascending_node, node_direction) = if node.length() > 1e-12 * angular_momentum.length() {
+            (node.y.atan2(node.x).rem_euclid(TAU), node.normalize())
+        } else {
+            (0.0, DVec3::X)
+        };
+        // Circular orbits put periapsis at the ascending node.
+        let periapsis_direction = if eccentricity > 1e-12 { eccentricity_vector / eccentricity } else { node_direction };
+        let angle = |from: DVec3, to: DVec3| from.cross(to).dot(normal).atan2(from.dot(to)).rem_euclid(TAU);
+        let true_anomaly = angle(periapsis_direction, r);
+        let eccentric = 2.0 * ((1.0 - eccentricity).sqrt() * (true_anomaly / 2.0).sin())
+            .atan2((1.0 + eccentricity).sqrt() * (true_anomaly / 2.0).cos());
+        let mut orbit = Orbit {
+            semi_major_axis: Length::m(-mu / (2.0 * energy)),
+            eccentricity,
+            inclination: normal.z.clamp(-1.0, 1.0).acos(),
+            ascending_node,
+            argument_of_periapsis: angle(node_direction, periapsis_direction),
+            mean_anomaly_at_epoch: 0.0,
+            central_mass
+        };
+        let mean_anomaly = eccentric - eccentricity * eccentric.sin();
+        orbit.mean_anomaly_at_epoch = (mean_anomaly - orbit.mean_motion() * seconds).rem_euclid(TAU);
+        Some(orbit)
+    }
+
+    /// μ = GM, in m³ s⁻².
+    pub fn gravitational_parameter(&self) -> f64 {
+        GRAVITATIONAL_CONSTANT * self.central_mass.in_kilograms()
+    }
+
+    /// Radians per second.
+    pub fn mean_motion(&self) -> f64 {
+        let a = self.semi_major_axis.in_meters();
+        (self.gravitational_parameter() / (a * a * a)).sqrt()
+    }
+
+    /// Seconds per orbit.
+    pub fn period(&self) -> f64 {
+        TAU / self.mean_motion()
+    }
+
+    pub fn periapsis(&self) -> Length {
+        self.semi_major_axis * (1.0 - self.eccentricity)
+    }
+
+    pub fn apoapsis(&self) -> Length {
+        self.semi_major_axis * (1.0 + self.eccentricity)
+    }
+
+    pub fn mean_anomaly(&self, seconds: f64) -> f64 {
+        (self.mean_anomaly_at_epoch + self.mean_motion() * seconds).rem_euclid(TAU)
+    }
+
+    pub fn true_anomaly(&self, seconds: f64) -> f64 {
+        let eccentric = eccentric_anomaly(self.mean_anomaly(seconds), self.eccentricity);
+        let e = self.eccentricity;
+        2.0 * libm::atan2((1.0 + e).sqrt() * libm::sin(eccentric / 2.0), (1.0 - e).sqrt() * libm::cos(eccentric / 2.0))
+    }
+
+    /// Position in meters and velocity in meters per second, relative to the central mass.
+    /// Uses `libm`, so that where generated planets start is the same on every platform.
+    pub fn state_at(&self, seconds: f64) -> (DVec3, DVec3) {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis.in_meters();
+        let mu = self.gravitational_parameter();
+        let nu = self.true_anomaly(seconds);
+        let p = a * (1.0 - e * e);
+        let (sin_nu, cos_nu) = libm::sincos(nu);
+        let r = p / (1.0 + e * cos_nu);
+        let position = DVec3::new(r * cos_nu, r * sin_nu, 0.0);
+        let velocity = DVec3::new(-sin_nu, e + cos_nu, 0.0) * (mu / p).sqrt();
+        (self.rotate_to_reference(position), self.rotate_to_reference(velocity))
+    }
+
+    pub fn position_at(&self, seconds: f64) -> DVec3 {
+        self.state_at(seconds).0
+    }
+
+    pub fn velocity_at(&self, seconds: f64) -> DVec3 {
+        self.state_at(seconds).1
+    }
+
+    /// Rotates from the perifocal frame (periapsis along +x, orbit normal along +z) into the reference frame.
+    fn rotate_to_reference(&self, perifocal: DVec3) -> DVec3 {
+        let (sin_o, cos_o) = libm::sincos(self.ascending_node);
+        let (sin_i, cos_i) = libm::sincos(self.inclination);
+        let (sin_w, cos_w) = libm::sincos(self.argument_of_periapsis);
+        let x = (cos_o * cos_w - sin_o * sin_w * cos_i) * perifocal.x + (-cos_o * sin_w - sin_o * cos_w * cos_i) * perifocal.y;
+        let y = (sin_o * cos_w + cos_o * sin_w * cos_i) * perifocal.x + (-sin_o * sin_w + cos_o * cos_w * cos_i) * perifocal.y;
+        let z = (sin_w * sin_i) * perifocal.x + (cos_w * sin_i) * perifocal.y;
+        // The classical elements put north along +z; we put it along +y.
+        DVec3::new(x, z, -y)
+    }
+}
+
+/// Solves Kepler's equation M = E - e sin E for the eccentric anomaly E, by Newton's method.
+pub fn eccentric_anomaly(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let mut eccentric = if eccentricity < 0.8 { mean_anomaly } else { std::f64::consts::PI };
+    for _ in 0..50 {
+        let (sin_e, cos_e) = libm::sincos(eccentric);
+        let step = (eccentric - eccentricity * sin_e - mean_anomaly) / (1.0 - eccentricity * cos_e);
+        eccentric -= step;
+        if step.abs() < 1e-12 {
+            break;
+        }
+    }
+    eccentric
+}
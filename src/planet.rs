@@ -0,0 +1,148 @@
+use std::f64::consts::TAU;
+use serde::{Deserialize, Serialize};
+
+use super::length::Length;
+use super::mass::Mass;
+use super::orbit::Orbit;
+use super::random;
+use super::random::Rng;
+use super::star::Star;
+
+pub const EARTH_MASSES_PER_JOVIAN_MASS: f64 = 317.8;
+pub const EARTH_RADII_PER_JOVIAN_RADIUS: f64 = 11.21;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlanetKind {
+    Rocky,
+    SuperEarth,
+    IceGiant,
+    GasGiant
+}
+
+impl PlanetKind {
+    /// Typical masses, in Earth masses.
+    pub fn mass_range(self) -> std::ops::Range<f64> {
+        match self {
+            PlanetKind::Rocky      =>  0.05 ..    1.5,
+            PlanetKind::SuperEarth =>  1.5  ..   10.0,
+            PlanetKind::IceGiant   => 10.0  ..   40.0,
+            PlanetKind::GasGiant   => 50.0  .. 4000.0
+        }
+    }
+
+    /// The radius of a planet of this kind, from the mass-radius relations of Chen & Kipping (2017).
+    pub fn radius(self, mass: Mass) -> Length {
+        let earth_masses = mass.in_earth_masses();
+        let earth_radii = match self {
+            PlanetKind::Rocky | PlanetKind::SuperEarth => libm::pow(earth_masses, 0.28),
+            PlanetKind::IceGiant                       => libm::pow(earth_masses, 0.59),
+            PlanetKind::GasGiant                       => EARTH_RADII_PER_JOVIAN_RADIUS
+        };
+        Length::scaled(earth_radii, super::length::Scale::EarthRadius)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Planet {
+    pub kind: PlanetKind,
+    pub mass: Mass,
+    pub radius: Length
+}
+
+/// What a body is made of, as fractions of its mass summing to one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Composition {
+    pub metals: f64,
+    pub silicates: f64,
+    /// Water, ammonia and methane ices.
+    pub ices: f64,
+    /// Hydrogen and helium.
+    pub gases: f64
+}
+
+impl Composition {
+    /// Typical of a body of a kind, varied by its seed.
+    pub fn generate(kind: PlanetKind, seed: u64) -> Composition {
+        let (metals, silicates, ices, gases) = match kind {
+            PlanetKind::Rocky      => (0.30, 0.68, 0.02, 0.0),
+            PlanetKind::SuperEarth => (0.28, 0.62, 0.09, 0.01),
+            PlanetKind::IceGiant   => (0.05, 0.15, 0.65, 0.15),
+            PlanetKind::GasGiant   => (0.01, 0.02, 0.07, 0.90)
+        };
+        let mut rng = Rng::new(random::derive(seed, COMPOSITION_KEY));
+        let mut vary = |fraction: f64| fraction * rng.range_f64(0.7..1.3);
+        Composition { metals: vary(metals), silicates: vary(silicates), ices: vary(ices), gases: vary(gases) }.normalized()
+    }
+
+    pub fn normalized(self) -> Composition {
+        let total = self.metals + self.silicates + self.ices + self.gases;
+        if total > 0.0 {
+            Composition { metals: self.metals / total, silicates: self.silicates / total, ices: self.ices / total, gases: self.gases / total }
+        } else {
+            self
+        }
+    }
+}
+
+/// Where water freezes in a star's protoplanetary disk, beyond which giants form.
+pub fn frost_line(star: &Star) -> Length {
+    Length::AU(2.7 * star.luminosity.in_solar_luminosities().sqrt())
+}
+
+/// Keys for deriving, from a system's seed, the seed of its layout and the seeds of its bodies.
+pub const LAYOUT_KEY: u64 = 1;
+pub const BODIES_KEY: u64 = 2;
+/// For deriving a body's composition from its own seed, apart from its orbit and size.
+pub const COMPOSITION_KEY: u64 = 3;
+
+/// The seed of a system's body, addressable without generating the bodies before it.
+pub fn body_seed(system_seed: u64, index: u32) -> u64 {
+    random::derive(random::derive(system_seed, BODIES_KEY), index as u64)
+}
+
+/// The number of planets in a system and their semi-major axes, innermost first.
+pub fn system_layout(star: &Star, system_seed: u64) -> Vec<Length> {
+    let mut rng = Rng::new(random::derive(system_seed, LAYOUT_KEY));
+    let count = rng.poisson(3.5).min(12);
+    let mut semi_major_axis = Length::AU(rng.range_f64(0.04..0.4) * star.luminosity.in_solar_luminosities().sqrt().max(0.1));
+    (0..count)
+        .map(|_| {
+            let this = semi_major_axis;
+            semi_major_axis = semi_major_axis * rng.range_f64(1.4..2.2);
+            this
+        })
+        .collect()
+}
+
+/// One planet of a system, from its own seed.
+pub fn generate_planet(star: &Star, semi_major_axis: Length, seed: u64) -> (Planet, Orbit) {
+    let mut rng = Rng::new(seed);
+    let kind = if semi_major_axis < frost_line(star) {
+        if rng.chance(0.7) { PlanetKind::Rocky } else { PlanetKind::SuperEarth }
+    } else if rng.chance(0.45) {
+        PlanetKind::GasGiant
+    } else {
+        PlanetKind::IceGiant
+    };
+    let masses = kind.mass_range();
+    let mass = Mass::Mearth(masses.start * libm::pow(masses.end / masses.start, rng.next_f64()));
+    let planet = Planet { kind, mass, radius: kind.radius(mass) };
+    let orbit = Orbit {
+        semi_major_axis,
+        eccentricity: rng.next_f64() * rng.next_f64() * 0.2,
+        inclination: rng.next_f64() * 3f64.to_radians(),
+        ascending_node: rng.next_f64() * TAU,
+        argument_of_periapsis: rng.next_f64() * TAU,
+        mean_anomaly_at_epoch: rng.next_f64() * TAU,
+        central_mass: star.mass + mass
+    };
+    (planet, orbit)
+}
+
+/// Generates a star's planets and their orbits, innermost first.
+pub fn generate_planets(star: &Star, system_seed: u64) -> Vec<(Planet, Orbit)> {
+    system_layout(star, system_seed).into_iter()
+        .enumerate()
+        .map(|(index, semi_major_axis)| generate_planet(star, semi_major_axis, body_seed(system_seed, index as u32)))
+        .collect()
+}
@@ -0,0 +1,82 @@
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+use super::astronomy::AstronomyPlugin;
+use super::autopilot::AutopilotPlugin;
+use super::behaviour::BehaviourPlugin;
+use super::camera::CameraPlugin;
+use super::clock::{ClockPlugin, Lockstep};
+use super::colony::ColonyPlugin;
+use super::combat::CombatPlugin;
+use super::faction::FactionPlugin;
+use super::flight::FlightPlugin;
+use super::floating_origin::FloatingOriginPlugin;
+use super::gameplay::GameplayPlugin;
+use super::industry::IndustryPlugin;
+use super::market::MarketPlugin;
+use super::mission::MissionPlugin;
+use super::radiation::RadiationPlugin;
+use super::route::RoutePlugin;
+use super::save::SavePlugin;
+use super::scene::ScenePlugin;
+use super::sensors::SensorPlugin;
+use super::ship::ShipPlugin;
+use super::star_catalogue::StarCataloguePlugin;
+use super::survey::SurveyPlugin;
+use super::thermal::ThermalPlugin;
+use super::ui::UiPlugin;
+use super::universe::{UniversePlugin, UniverseSettings};
+
+/// The game's logic, with nothing that needs a window or renderer.
+pub struct SimulationPlugins;
+
+impl PluginGroup for SimulationPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group.add(ClockPlugin)
+            .add(AstronomyPlugin)
+            .add(UniversePlugin)
+            .add(ShipPlugin)
+            .add(FlightPlugin)
+            .add(AutopilotPlugin)
+            .add(RoutePlugin)
+            .add(RadiationPlugin)
+            .add(ThermalPlugin)
+            .add(IndustryPlugin)
+            .add(MarketPlugin)
+            .add(FactionPlugin)
+            .add(ColonyPlugin)
+            .add(SensorPlugin)
+            .add(CombatPlugin)
+            .add(BehaviourPlugin)
+            .add(MissionPlugin)
+            .add(SurveyPlugin)
+            .add(StarCataloguePlugin);
+    }
+}
+
+/// An app that simulates the universe without a display, for servers, tests and profiling.
+/// Each update runs exactly one tick, so a number of updates always simulates the same span.
+pub fn headless_app(settings: UniverseSettings) -> AppBuilder {
+    let mut app = App::build();
+    app.insert_resource(settings)
+        .insert_resource(Lockstep)
+        .add_plugins(MinimalPlugins)
+        .add_plugins(SimulationPlugins);
+    app
+}
+
+/// Everything the game needs on top of Bevy's `DefaultPlugins`.
+/// Binaries wanting only some subsystems can add the plugins one by one instead.
+pub struct SapphireStarPlugins;
+
+impl PluginGroup for SapphireStarPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        SimulationPlugins.build(group);
+        group.add(FloatingOriginPlugin)
+            .add(CameraPlugin)
+            .add(ScenePlugin)
+            .add(UiPlugin)
+            .add(SavePlugin)
+            .add(GameplayPlugin);
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::math::DVec3;
+use std::ops::{Add, Sub};
+use serde::{Deserialize, Serialize};
+
+use super::length;
+use super::length::Length;
+
+/// A point in space, relative to the galactic centre.
+/// The galactic disk lies in the x-z plane, with +y towards the north galactic pole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: Length,
+    pub y: Length,
+    pub z: Length
+}
+
+pub const ORIGIN: Position = Position { x: length::ZERO, y: length::ZERO, z: length::ZERO };
+
+impl Position {
+    pub fn new(x: Length, y: Length, z: Length) -> Position {
+        Position { x, y, z }
+    }
+
+    pub fn scaled(x: f64, y: f64, z: f64, scale: length::Scale) -> Position {
+        Position {
+            x: Length::scaled(x, scale),
+            y: Length::scaled(y, scale),
+            z: Length::scaled(z, scale)
+        }
+    }
+
+    pub fn from_meters(meters: DVec3, scale: length::Scale) -> Position {
+        Position {
+            x: Length::meters(meters.x).to_scale(scale),
+            y: Length::meters(meters.y).to_scale(scale),
+            z: Length::meters(meters.z).to_scale(scale)
+        }
+    }
+
+    pub fn in_meters(self) -> DVec3 {
+        DVec3::new(self.x.in_meters(), self.y.in_meters(), self.z.in_meters())
+    }
+
+    pub fn distance(self, other: Position) -> Length {
+        Length::meters((self.in_meters() - other.in_meters()).length()).to_scale(self.x.scale())
+    }
+
+    /// The distance from the galactic rotation axis, in the plane of the disk.
+    pub fn galactic_radius(self) -> Length {
+        Length::meters(self.x.in_meters().hypot(self.z.in_meters())).to_scale(self.x.scale())
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+    fn add(self, other: Position) -> Position {
+        Position { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl Sub for Position {
+    type Output = Position;
+    fn sub(self, other: Position) -> Position {
+        Position { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
@@ -1,6 +1,10 @@
+use std::cmp::Ordering;
 use std::default::Default;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Scale {
     Watt,
     Kilowatt,
@@ -18,7 +22,7 @@ impl Default for Scale {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Power {
     watts: f64,
     scale: Scale
@@ -127,4 +131,106 @@ impl Power {
     pub fn range(range: std::ops::Range<f64>, scale: Scale) -> std::ops::Range<Power> {
         Power::scaled(range.start, scale) .. Power::scaled(range.end, scale)
     }
+
+    pub fn scale(self) -> Scale {
+        self.scale
+    }
+
+    /// The same power, displayed in another scale.
+    pub fn to_scale(self, scale: Scale) -> Power {
+        Power { watts: self.watts, scale }
+    }
+
+    pub fn in_watts(self) -> f64 {
+        self.watts
+    }
+
+    pub fn in_solar_luminosities(self) -> f64 {
+        self.watts / SOLAR_LUMINOSITY_TO_WATTS
+    }
+}
+
+impl PartialOrd for Power {
+    fn partial_cmp(&self, other: &Power) -> Option<Ordering> {
+        self.watts.partial_cmp(&other.watts)
+    }
+}
+
+// Arithmetic keeps the scale of the left-hand side.
+
+impl Add for Power {
+    type Output = Power;
+    fn add(self, other: Power) -> Power {
+        Power { watts: self.watts + other.watts, scale: self.scale }
+    }
+}
+
+impl Sub for Power {
+    type Output = Power;
+    fn sub(self, other: Power) -> Power {
+        Power { watts: self.watts - other.watts, scale: self.scale }
+    }
+}
+
+impl Mul<f64> for Power {
+    type Output = Power;
+    fn mul(self, factor: f64) -> Power {
+        Power { watts: self.watts * factor, scale: self.scale }
+    }
+}
+
+impl Div<f64> for Power {
+    type Output = Power;
+    fn div(self, divisor: f64) -> Power {
+        Power { watts: self.watts / divisor, scale: self.scale }
+    }
+}
+
+impl Div for Power {
+    type Output = f64;
+    fn div(self, other: Power) -> f64 {
+        self.watts / other.watts
+    }
+}
+
+impl std::iter::Sum for Power {
+    fn sum<I: Iterator<Item = Power>>(iter: I) -> Power {
+        iter.fold(ZERO, |total, power| total + power)
+    }
+}
+
+impl From<Power> for f64 {
+    fn from(power: Power) -> f64 {
+        let Power { watts, scale } = power;
+        match scale {
+            Scale::Watt            => watts,
+            Scale::Kilowatt        => watts / KILOWATTS_TO_WATTS,
+            Scale::Megawatt        => watts / MEGAWATTS_TO_WATTS,
+            Scale::Gigawatt        => watts / GIGAWATTS_TO_WATTS,
+            Scale::Terawatt        => watts / TERAWATTS_TO_WATTS,
+            Scale::Petawatt        => watts / PETAWATTS_TO_WATTS,
+            Scale::Horsepower      => watts / HORSEPOWER_TO_WATTS,
+            Scale::SolarLuminosity => watts / SOLAR_LUMINOSITY_TO_WATTS
+        }
+    }
+}
+
+impl fmt::Display for Power {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value: f64 = Power::into(*self);
+        let unit = match self.scale {
+            Scale::Watt            => "W",
+            Scale::Kilowatt        => "kW",
+            Scale::Megawatt        => "MW",
+            Scale::Gigawatt        => "GW",
+            Scale::Terawatt        => "TW",
+            Scale::Petawatt        => "PW",
+            Scale::Horsepower      => "hp",
+            Scale::SolarLuminosity => "L☉"
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            None            => write!(f, "{} {}", value, unit)
+        }
+    }
 }
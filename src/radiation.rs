@@ -0,0 +1,488 @@
+use bevy::prelude::*;
+use std::f64::consts::PI;
+use std::fmt;
+
+use super::astronomy::Orbiting;
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY};
+use super::flight::FlightSystem;
+use super::floating_origin::WorldPosition;
+use super::galaxy::{Galaxy, StarId};
+use super::length;
+use super::length::Length;
+use super::orbit::Orbit;
+use super::planet::Planet;
+use super::power::{Power, Scale};
+use super::random;
+use super::random::Rng;
+use super::ship::Ship;
+use super::star::{SpectralType, Star};
+use super::temperature::Temperature;
+use super::universe::{StarIndex, UniverseSettings};
+
+/// In J s.
+pub const PLANCK_CONSTANT: f64 = 6.626_070_15e-34;
+/// In J K⁻¹.
+pub const BOLTZMANN_CONSTANT: f64 = 1.380_649e-23;
+/// In W m⁻² K⁻⁴.
+pub const STEFAN_BOLTZMANN_CONSTANT: f64 = 5.670_374_419e-8;
+/// Light shorter than this is X-rays, in meters.
+pub const X_RAY_WAVELENGTH: f64 = 10.0e-9;
+/// Light shorter than this, and longer than X-rays, is ultraviolet, in meters.
+pub const ULTRAVIOLET_WAVELENGTH: f64 = 400.0e-9;
+
+/// The fraction of the sunlight falling on a hull that it absorbs as heat.
+pub const HULL_ABSORPTIVITY: f64 = 0.3;
+/// The fraction of X-rays falling on a hull that get through to the crew.
+pub const HULL_TRANSMISSION: f64 = 1.0e-3;
+/// A crew member's cross-section, in m², and mass, in kg, for working out their dose.
+pub const CREW_CROSS_SECTION: f64 = 0.7;
+pub const CREW_MASS: f64 = 70.0;
+/// The most starlight a hull can take, in W m⁻², about fifteen times the Sun's at the Earth.
+pub const MAX_HULL_FLUX: f64 = 2.0e4;
+/// The highest dose rate a crew can work under, in Sv s⁻¹: a millisievert an hour.
+pub const MAX_DOSE_RATE: f64 = 1.0e-3 / 3600.0;
+/// Stars further away than this are left out of a ship's exposure.
+pub const EXPOSURE_RANGE_METERS: f64 = 1.0e15;
+
+/// The fraction of a blackbody's emission at wavelengths shorter than `wavelength` meters.
+pub fn blackbody_fraction_below(temperature: Temperature, wavelength: f64) -> f64 {
+    let kelvin = temperature.in_kelvin() as f64;
+    if kelvin <= 0.0 {
+        return 0.0;
+    }
+    let x = PLANCK_CONSTANT * length::LIGHT_SECONDS_TO_METERS / (wavelength * BOLTZMANN_CONSTANT * kelvin);
+    let normalisation = 15.0 / PI.powi(4);
+    if x < 2.0 {
+        // The series about long wavelengths, for the emission longer than `wavelength`.
+        let longer = normalisation * x.powi(3)
+            * (1.0 / 3.0 - x / 8.0 + x.powi(2) / 60.0 - x.powi(4) / 5040.0 + x.powi(6) / 272_160.0 - x.powi(8) / 13_305_600.0);
+        (1.0 - longer).clamp(0.0, 1.0)
+    } else {
+        let shorter: f64 = (1..=16).map(|n| {
+            let n = n as f64;
+            (-n * x).exp() * (x.powi(3) / n + 3.0 * x.powi(2) / n.powi(2) + 6.0 * x / n.powi(3) + 6.0 / n.powi(4))
+        }).sum();
+        (normalisation * shorter).clamp(0.0, 1.0)
+    }
+}
+
+/// The fraction of a star's light from its corona or winds as X-rays, which its photosphere alone would not give.
+/// Cool stars with deep convective zones have the most active coronae.
+pub fn coronal_x_ray_fraction(spectral_type: SpectralType) -> f64 {
+    match spectral_type {
+        SpectralType::O | SpectralType::B | SpectralType::WR => 1.0e-7,
+        SpectralType::A                                      => 1.0e-8,
+        SpectralType::F | SpectralType::G                    => 1.0e-6,
+        SpectralType::K                                      => 1.0e-5,
+        SpectralType::M                                      => 1.0e-3,
+        SpectralType::L                                      => 1.0e-5,
+        _                                                    => 0.0
+    }
+}
+
+/// How a star's light divides between the bands that matter to ships.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spectrum {
+    pub ultraviolet: f64,
+    pub x_ray: f64
+}
+
+impl Spectrum {
+    pub fn of(star: &Star) -> Spectrum {
+        let x_ray = blackbody_fraction_below(star.temperature, X_RAY_WAVELENGTH) + coronal_x_ray_fraction(star.spectral_type);
+        let ultraviolet = blackbody_fraction_below(star.temperature, ULTRAVIOLET_WAVELENGTH)
+            - blackbody_fraction_below(star.temperature, X_RAY_WAVELENGTH);
+        Spectrum { ultraviolet, x_ray }
+    }
+}
+
+/// The flux through a sphere of radius `distance` about a source, in W m⁻².
+pub fn flux_at(luminosity: Power, distance: Length) -> f64 {
+    luminosity.in_watts() / (4.0 * PI * distance.in_meters().powi(2))
+}
+
+/// The distance from a source at which its flux falls to `flux` W m⁻².
+pub fn distance_for_flux(luminosity: Power, flux: f64) -> Length {
+    Length::m((luminosity.in_watts() / (4.0 * PI * flux)).sqrt())
+}
+
+/// The distance at which the flux is that averaged over time round an orbit, ∝ 1 / (a² √(1 − e²)): a (1 − e²)^¼.
+pub fn mean_flux_distance(orbit: &Orbit) -> Length {
+    orbit.semi_major_axis * (1.0 - orbit.eccentricity.powi(2)).powf(0.25)
+}
+
+/// Starlight falling on a surface facing the star, in W m⁻².
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Irradiation {
+    pub total: f64,
+    pub ultraviolet: f64,
+    pub x_ray: f64
+}
+
+impl Irradiation {
+    /// From a star at a distance, with `flare` watts of X-rays on top of its usual light.
+    pub fn from_star(star: &Star, flare: Power, distance: Length) -> Irradiation {
+        let spectrum = Spectrum::of(star);
+        let total = flux_at(star.luminosity + flare, distance);
+        Irradiation {
+            total,
+            ultraviolet: flux_at(star.luminosity, distance) * spectrum.ultraviolet,
+            x_ray: flux_at(star.luminosity, distance) * spectrum.x_ray + flux_at(flare, distance)
+        }
+    }
+
+    /// The heat a hull of `radius` absorbs, with its cross-section to the light.
+    pub fn heat_load(&self, radius: Length) -> Power {
+        Power::watts(self.total * PI * radius.in_meters().powi(2) * HULL_ABSORPTIVITY).to_scale(Scale::Megawatt)
+    }
+
+    /// The dose rate the crew behind a hull get, in Sv s⁻¹. X-rays weigh one sievert to the gray.
+    pub fn dose_rate(&self) -> f64 {
+        self.x_ray * HULL_TRANSMISSION * CREW_CROSS_SECTION / CREW_MASS
+    }
+}
+
+impl std::ops::Add for Irradiation {
+    type Output = Irradiation;
+    fn add(self, other: Irradiation) -> Irradiation {
+        Irradiation {
+            total: self.total + other.total,
+            ultraviolet: self.ultraviolet + other.ultraviolet,
+            x_ray: self.x_ray + other.x_ray
+        }
+    }
+}
+
+/// How close a ship can safely come to a star.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SafeApproach {
+    /// Nearer than this, the hull takes more starlight than it can bear.
+    pub heat: Length,
+    /// Nearer than this, the crew's dose rate goes over the working limit.
+    pub dose: Length
+}
+
+impl SafeApproach {
+    pub fn of(star: &Star, flare: Power) -> SafeApproach {
+        let x_ray = star.luminosity * Spectrum::of(star).x_ray + flare;
+        SafeApproach {
+            heat: distance_for_flux(star.luminosity + flare, MAX_HULL_FLUX),
+            dose: distance_for_flux(x_ray, MAX_DOSE_RATE * CREW_MASS / (HULL_TRANSMISSION * CREW_CROSS_SECTION))
+        }
+    }
+
+    /// The further of the two limits, as near as a ship can safely come.
+    pub fn distance(&self) -> Length {
+        if self.heat > self.dose { self.heat } else { self.dose }
+    }
+}
+
+impl fmt::Display for SafeApproach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let heat = self.heat.to_scale(length::Scale::AstronomicalUnit);
+        let dose = self.dose.to_scale(length::Scale::AstronomicalUnit);
+        match f.precision() {
+            Some(precision) => write!(f, "heat {:.*}, dose {:.*}", precision, heat, precision, dose),
+            None            => write!(f, "heat {}, dose {}", heat, dose)
+        }
+    }
+}
+
+/// Flares per day, on average, for stars of a type. Only stars with convective envelopes flare.
+pub fn flare_rate(spectral_type: SpectralType) -> f64 {
+    match spectral_type {
+        SpectralType::M => 0.5,
+        SpectralType::K => 0.05,
+        _               => 0.0
+    }
+}
+
+/// The smallest and largest flares we bother with, in joules. Between them, energies follow a power law.
+pub const MIN_FLARE_ENERGY: f64 = 1.0e24;
+pub const MAX_FLARE_ENERGY: f64 = 1.0e28;
+/// The index of the flare frequency distribution, dN/dE ∝ E^-α.
+pub const FLARE_POWER_LAW_INDEX: f64 = 1.8;
+/// How long the smallest flares last, in seconds; bigger ones last longer.
+pub const MIN_FLARE_SECONDS: f64 = 600.0;
+
+const FLARE_KEY: u64 = 0x666c_6172_6573;
+
+/// A star flaring, in X-rays that rise at once and then fade linearly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flaring {
+    pub start: f64,
+    pub duration: f64,
+    pub peak: Power
+}
+
+impl Flaring {
+    /// A flare with its energy drawn from the power law by a uniform sample in [0, 1).
+    pub fn sample(start: f64, uniform: f64) -> Flaring {
+        let ratio = (MAX_FLARE_ENERGY / MIN_FLARE_ENERGY).powf(1.0 - FLARE_POWER_LAW_INDEX);
+        let energy = MIN_FLARE_ENERGY * libm::pow(1.0 - uniform * (1.0 - ratio), 1.0 / (1.0 - FLARE_POWER_LAW_INDEX));
+        let duration = MIN_FLARE_SECONDS * libm::pow(energy / MIN_FLARE_ENERGY, 0.3);
+        Flaring { start, duration, peak: Power::watts(2.0 * energy / duration).to_scale(Scale::SolarLuminosity) }
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.peak.in_watts() * self.duration / 2.0
+    }
+
+    /// The extra X-rays at a time in seconds since the epoch.
+    pub fn luminosity_at(&self, seconds: f64) -> Power {
+        let fade = 1.0 - (seconds - self.start) / self.duration;
+        if (0.0..=1.0).contains(&fade) { self.peak * fade } else { super::power::ZERO }
+    }
+
+    pub fn is_over(&self, seconds: f64) -> bool {
+        seconds >= self.start + self.duration
+    }
+}
+
+/// Sent when a star starts to flare.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StellarFlare {
+    pub star: Entity,
+    pub flare: Flaring
+}
+
+/// The seed of a star's flares on one tick, so flares replay identically.
+pub fn flare_seed(galaxy: &Galaxy, id: StarId, tick: u64) -> u64 {
+    random::derive(random::derive(random::derive(galaxy.sector_seed(id.sector), id.index as u64), FLARE_KEY), tick)
+}
+
+/// Starts and ends flares on cool stars.
+pub fn flare_stars(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    universe: Res<UniverseSettings>,
+    stars: Query<(Entity, &Star, &StarId, Option<&Flaring>)>,
+    mut flares: EventWriter<StellarFlare>
+) {
+    let dt = clock.tick_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+    for (entity, star, id, flaring) in stars.iter() {
+        let rate = flare_rate(star.spectral_type);
+        if rate <= 0.0 {
+            continue;
+        }
+        match flaring {
+            Some(flaring) if !flaring.is_over(clock.seconds) => continue,
+            Some(_)                                          => { commands.entity(entity).remove::<Flaring>(); }
+            None                                             => {}
+        }
+        let mut rng = Rng::new(flare_seed(&universe.galaxy, *id, clock.ticks));
+        if rng.poisson(rate * dt / SECONDS_PER_DAY) > 0 {
+            let flare = Flaring::sample(clock.seconds, rng.next_f64());
+            commands.entity(entity).insert(flare);
+            flares.send(StellarFlare { star: entity, flare });
+        }
+    }
+}
+
+/// The starlight a ship takes and what it does to the ship and crew.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RadiationExposure {
+    pub irradiation: Irradiation,
+    pub heat_load: Power,
+    /// In Sv s⁻¹.
+    pub dose_rate: f64,
+    /// The crew's total dose so far, in sieverts.
+    pub dose: f64
+}
+
+impl fmt::Display for RadiationExposure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "starlight {:.1} W/m²  heat {:.3}  dose {:.3} mSv/h, {:.1} mSv in all",
+               self.irradiation.total, self.heat_load, self.dose_rate * 3.6e6, self.dose * 1.0e3)
+    }
+}
+
+/// The starlight falling at a position from the stars about it.
+pub fn irradiation_at(position: WorldPosition, index: &StarIndex, stars: &Query<(&Star, &WorldPosition, Option<&Flaring>)>, seconds: f64) -> Irradiation {
+    index.0.within(position.to_position(length::Scale::LightYear), Length::m(EXPOSURE_RANGE_METERS)).iter()
+        .filter_map(|neighbour| stars.get(*neighbour.item).ok())
+        .fold(Irradiation::default(), |irradiation, (star, star_position, flaring)| {
+            let flare = flaring.map_or(super::power::ZERO, |flaring| flaring.luminosity_at(seconds));
+            irradiation + Irradiation::from_star(star, flare, star_position.distance(position))
+        })
+}
+
+pub fn expose_ships(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    index: Res<StarIndex>,
+    stars: Query<(&Star, &WorldPosition, Option<&Flaring>)>,
+    mut ships: Query<(Entity, &Ship, &WorldPosition, Option<&mut RadiationExposure>)>
+) {
+    let dt = clock.tick_seconds();
+    for (entity, ship, position, exposure) in ships.iter_mut() {
+        let irradiation = irradiation_at(*position, &index, &stars, clock.seconds);
+        let dose_rate = irradiation.dose_rate();
+        let heat_load = irradiation.heat_load(ship.hull.radius);
+        match exposure {
+            Some(mut exposure) => {
+                exposure.irradiation = irradiation;
+                exposure.heat_load = heat_load;
+                exposure.dose_rate = dose_rate;
+                exposure.dose += dose_rate * dt;
+            }
+            None => {
+                commands.entity(entity).insert(RadiationExposure { irradiation, heat_load, dose_rate, dose: dose_rate * dt });
+            }
+        }
+    }
+}
+
+/// Gives each planet the mean starlight over its orbit, from its star at rest.
+pub fn irradiate_planets(
+    mut commands: Commands,
+    planets: Query<(Entity, &Orbit, &Orbiting), With<Planet>>,
+    stars: Query<&Star>
+) {
+    for (entity, orbit, parent) in planets.iter() {
+        if let Ok(star) = stars.get(parent.0) {
+            commands.entity(entity).insert(Irradiation::from_star(star, super::power::ZERO, mean_flux_distance(orbit)));
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum RadiationSystem {
+    Flare,
+    Expose
+}
+
+/// Starlight, flares and the hazards they bring to ships and planets. Needs the `ClockPlugin` and `UniversePlugin`.
+pub struct RadiationPlugin;
+
+impl Plugin for RadiationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<StellarFlare>()
+            .add_startup_system_to_stage(StartupStage::PostStartup, irradiate_planets.system())
+            .add_system_to_stage(SimulationStage, flare_stars.system()
+                                 .label(RadiationSystem::Flare)
+                                 .after(ClockSystem::Advance))
+            .add_system_to_stage(SimulationStage, expose_ships.system()
+                                 .label(RadiationSystem::Expose)
+                                 .after(RadiationSystem::Flare)
+                                 .after(FlightSystem::Fly));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mass::Mass;
+    use super::super::star::LuminosityClass;
+
+    fn sun() -> Star {
+        Star {
+            spectral_type: SpectralType::G,
+            luminosity_class: LuminosityClass::V,
+            mass: Mass::solar_masses(1.0),
+            radius: Length::km(695_700.0),
+            luminosity: Power::solar_luminosity(1.0),
+            temperature: Temperature::kelvin(5772.0),
+            age: 4.6e9
+        }
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * a.abs().max(b.abs())
+    }
+
+    #[test]
+    fn blackbodies_divide_their_light_as_plancks_law_says() {
+        let sun = Temperature::kelvin(5772.0);
+        // A quarter of the light is shorter than Wien's peak, and half shorter than 4110 µm K over the temperature.
+        assert!((blackbody_fraction_below(sun, 2.897_772e-3 / 5772.0) - 0.250_05).abs() < 1.0e-4);
+        assert!((blackbody_fraction_below(sun, 4.110e-3 / 5772.0) - 0.500_48).abs() < 1.0e-4);
+        // About an eighth of the Sun's light is ultraviolet, and next to none of it X-rays.
+        assert!((blackbody_fraction_below(sun, ULTRAVIOLET_WAVELENGTH) - 0.121_77).abs() < 1.0e-4);
+        assert!(blackbody_fraction_below(sun, X_RAY_WAVELENGTH) < 1.0e-100);
+        // Far into the infrared, where the series about long wavelengths takes over.
+        assert!((blackbody_fraction_below(Temperature::kelvin(3000.0), 20.0e-6) - 0.999_35).abs() < 1.0e-4);
+        assert_eq!(blackbody_fraction_below(Temperature::kelvin(0.0), 1.0e-6), 0.0);
+
+        // The two series meet where x = hc / λkT = 2.
+        let meeting = PLANCK_CONSTANT * length::LIGHT_SECONDS_TO_METERS / (2.0 * BOLTZMANN_CONSTANT * 5772.0);
+        let (shorter, longer) = (blackbody_fraction_below(sun, meeting * 0.999_999), blackbody_fraction_below(sun, meeting * 1.000_001));
+        assert!(shorter < longer && longer - shorter < 1.0e-5);
+        let fractions: Vec<f64> = (0..100).map(|step| blackbody_fraction_below(sun, 1.0e-8 * 1.1_f64.powi(step))).collect();
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn starlight_falls_off_with_the_square_of_distance() {
+        let sun = sun();
+        let au = Length::astronomical_units(1.0);
+        assert!((flux_at(sun.luminosity, au) - 1368.3).abs() < 0.1);
+        assert!(close(flux_at(sun.luminosity, au * 2.0), flux_at(sun.luminosity, au) / 4.0, 1.0e-12));
+        assert!(close(distance_for_flux(sun.luminosity, 1368.3).in_meters(), au.in_meters(), 1.0e-4));
+
+        let near = Irradiation::from_star(&sun, super::super::power::ZERO, au);
+        let far = Irradiation::from_star(&sun, super::super::power::ZERO, au * 10.0);
+        assert!(close(near.ultraviolet, near.total * Spectrum::of(&sun).ultraviolet, 1.0e-12));
+        assert!(close(near.dose_rate(), far.dose_rate() * 100.0, 1.0e-12));
+        assert!(close(near.heat_load(Length::m(10.0)).in_watts(), near.total * PI * 100.0 * HULL_ABSORPTIVITY, 1.0e-12));
+
+        // A flare's X-rays add to the dose, and to the heat.
+        let flare = Power::watts(1.0e22);
+        let flaring = Irradiation::from_star(&sun, flare, au);
+        assert!(close(flaring.x_ray - near.x_ray, flux_at(flare, au), 1.0e-9));
+        assert!(close(flaring.dose_rate() - near.dose_rate(), flux_at(flare, au) * HULL_TRANSMISSION * CREW_CROSS_SECTION / CREW_MASS, 1.0e-9));
+        assert_eq!(flaring.ultraviolet, near.ultraviolet);
+
+        // At the limits of a safe approach the hull and crew take just what they can bear.
+        let approach = SafeApproach::of(&sun, flare);
+        assert!(close(Irradiation::from_star(&sun, flare, approach.heat).total, MAX_HULL_FLUX, 1.0e-9));
+        assert!(close(Irradiation::from_star(&sun, flare, approach.dose).dose_rate(), MAX_DOSE_RATE, 1.0e-9));
+        assert_eq!(approach.distance(), if approach.heat > approach.dose { approach.heat } else { approach.dose });
+    }
+
+    #[test]
+    fn orbits_get_their_mean_starlight_at_the_mean_flux_distance() {
+        let sun = sun();
+        for eccentricity in [0.0, 0.2, 0.6, 0.9].iter() {
+            let orbit = Orbit { eccentricity: *eccentricity, ..Orbit::circular(Length::astronomical_units(1.0), sun.mass) };
+            let samples = 100_000;
+            let mean = (0..samples)
+                .map(|sample| flux_at(sun.luminosity, Length::m(orbit.position_at(orbit.period() * sample as f64 / samples as f64).length())))
+                .sum::<f64>() / samples as f64;
+            assert!(close(mean, flux_at(sun.luminosity, mean_flux_distance(&orbit)), 1.0e-6), "e = {}", eccentricity);
+        }
+    }
+
+    #[test]
+    fn flares_follow_their_power_law_and_fade() {
+        let smallest = Flaring::sample(100.0, 0.0);
+        assert!(close(smallest.energy(), MIN_FLARE_ENERGY, 1.0e-9));
+        assert!(close(smallest.duration, MIN_FLARE_SECONDS, 1.0e-9));
+        let largest = Flaring::sample(100.0, 1.0 - 1.0e-15);
+        assert!(close(largest.energy(), MAX_FLARE_ENERGY, 1.0e-9));
+
+        // The chance of a flare above an energy, from dN/dE ∝ E^-α between the limits.
+        let above = |energy: f64| {
+            let power = |energy: f64| libm::pow(energy, 1.0 - FLARE_POWER_LAW_INDEX);
+            (power(energy) - power(MAX_FLARE_ENERGY)) / (power(MIN_FLARE_ENERGY) - power(MAX_FLARE_ENERGY))
+        };
+        let mut rng = Rng::new(11);
+        let flares: Vec<Flaring> = (0..20000).map(|_| Flaring::sample(0.0, rng.next_f64())).collect();
+        for energy in [1.0e25, 1.0e26, 1.0e27].iter() {
+            let share = flares.iter().filter(|flare| flare.energy() > *energy).count() as f64 / flares.len() as f64;
+            let expected = above(*energy);
+            assert!((share - expected).abs() < 4.0 * (expected * (1.0 - expected) / flares.len() as f64).sqrt() + 1.0e-4,
+                    "{} of flares above {:e} J, not {}", share, energy, expected);
+        }
+
+        let flare = Flaring::sample(100.0, 0.5);
+        assert_eq!(flare.luminosity_at(99.0), super::super::power::ZERO);
+        assert_eq!(flare.luminosity_at(100.0), flare.peak);
+        assert!(close(flare.luminosity_at(100.0 + flare.duration / 2.0).in_watts(), flare.peak.in_watts() / 2.0, 1.0e-12));
+        assert!(!flare.is_over(100.0 + flare.duration * 0.99) && flare.is_over(100.0 + flare.duration));
+        assert_eq!(flare.luminosity_at(100.0 + flare.duration * 1.01), super::super::power::ZERO);
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+/// A small, fast and fully deterministic random number generator (SplitMix64).
+/// We use our own rather than an external crate so that generated content never changes underneath us.
+/// Anything generated from it uses `libm` rather than the platform's maths library for transcendental functions,
+/// so a seed gives bit-identical results on every platform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u64
+}
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The SplitMix64 finaliser, a good 64-bit mixing function.
+pub fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Combines a seed with a key into a new, well mixed seed.
+pub fn derive(seed: u64, key: u64) -> u64 {
+    mix(seed ^ mix(key.wrapping_add(GOLDEN_GAMMA)))
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix(self.state)
+    }
+
+    /// A uniform sample in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    pub fn range_f64(&mut self, range: Range<f64>) -> f64 {
+        range.start + (range.end - range.start) * self.next_f64()
+    }
+
+    /// A uniform integer in [0, bound).
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    /// A standard normal sample, via Box-Muller.
+    pub fn gaussian(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64(); // (0, 1], so the logarithm is finite.
+        let v = self.next_f64();
+        (-2.0 * libm::log(u)).sqrt() * libm::cos(std::f64::consts::TAU * v)
+    }
+
+    /// A Poisson distributed count with the given mean.
+    /// Small means use Knuth's method, large means the normal approximation.
+    pub fn poisson(&mut self, mean: f64) -> u64 {
+        if mean <= 0.0 {
+            0
+        } else if mean < 30.0 {
+            let limit = libm::exp(-mean);
+            let mut count = 0;
+            let mut product = self.next_f64();
+            while product > limit {
+                count += 1;
+                product *= self.next_f64();
+            }
+            count
+        } else {
+            (mean + mean.sqrt() * self.gaussian()).round().max(0.0) as u64
+        }
+    }
+}
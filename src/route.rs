@@ -0,0 +1,572 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+use super::clock::{SECONDS_PER_DAY, SECONDS_PER_YEAR};
+use super::galaxy::{Galaxy, GalaxyStar, StarId};
+use super::length;
+use super::length::Length;
+use super::mass;
+use super::mass::Mass;
+use super::planet;
+use super::planet::{Planet, PlanetKind};
+use super::position::Position;
+use super::ship::Ship;
+use super::spatial::Octree;
+use super::star::{SpectralType, Star};
+use super::universe::UniverseSettings;
+
+/// In m s⁻¹, exactly, by the definition of the meter.
+pub const SPEED_OF_LIGHT: f64 = length::LIGHT_SECONDS_TO_METERS;
+/// Time spent skimming a gas giant or brown dwarf to fill the tanks, in seconds.
+pub const REFUEL_SECONDS: f64 = 10.0 * SECONDS_PER_DAY;
+
+/// Whether a ship can fill its tanks at a star: brown dwarfs and systems with a gas giant have hydrogen to skim.
+pub fn can_refuel(star: &Star, planets: &[Planet]) -> bool {
+    matches!(star.spectral_type, SpectralType::L | SpectralType::T | SpectralType::Y)
+        || planets.iter().any(|planet| planet.kind == PlanetKind::GasGiant)
+}
+
+/// A star a route can pass through.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Waypoint {
+    pub id: StarId,
+    pub position: Position,
+    pub refuel: bool
+}
+
+impl Waypoint {
+    /// Generates the star's planets to see whether it has fuel.
+    pub fn from_galaxy_star(galaxy_star: &GalaxyStar) -> Waypoint {
+        let star = galaxy_star.star();
+        let planets: Vec<Planet> = planet::generate_planets(&star, galaxy_star.system_seed()).into_iter()
+            .map(|(planet, _)| planet)
+            .collect();
+        Waypoint { id: galaxy_star.id, position: galaxy_star.position, refuel: can_refuel(&star, &planets) }
+    }
+}
+
+/// The stars routes are planned over, indexed by position for finding the reachable neighbours of each.
+pub struct StarGraph {
+    pub waypoints: Vec<Waypoint>,
+    index: Octree<usize>,
+    ids: HashMap<StarId, usize>
+}
+
+impl StarGraph {
+    pub fn new(waypoints: Vec<Waypoint>, center: Position, half_size: Length) -> StarGraph {
+        let mut index = Octree::new(center, half_size);
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            index.insert(waypoint.position, i);
+        }
+        let ids = waypoints.iter().enumerate().map(|(i, waypoint)| (waypoint.id, i)).collect();
+        StarGraph { waypoints, index, ids }
+    }
+
+    /// Every star of the galaxy within a sphere.
+    pub fn around(galaxy: &Galaxy, center: Position, radius: Length) -> StarGraph {
+        let waypoints = galaxy.stars_within(center, radius).map(|star| Waypoint::from_galaxy_star(&star)).collect();
+        StarGraph::new(waypoints, center, radius)
+    }
+
+    pub fn len(&self) -> usize {
+        self.waypoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+
+    pub fn find(&self, id: StarId) -> Option<usize> {
+        self.ids.get(&id).copied()
+    }
+
+    /// The waypoints within `range` of waypoint `from`, with their distances.
+    pub fn neighbours(&self, from: usize, range: Length) -> Vec<(usize, Length)> {
+        self.index.within(self.waypoints[from].position, range).into_iter()
+            .filter(|neighbour| *neighbour.item != from)
+            .map(|neighbour| (*neighbour.item, neighbour.distance))
+            .collect()
+    }
+}
+
+/// A sub-light trip from rest to rest: constant proper acceleration up to a cruise rapidity, a coast,
+/// and the same deceleration. Trips too short to reach cruise turn round halfway.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trip {
+    /// Seconds in the universe's frame.
+    pub universe_time: f64,
+    /// Seconds aboard the ship.
+    pub ship_time: f64,
+    /// The greatest rapidity reached; the speed is c tanh of it.
+    pub peak_rapidity: f64
+}
+
+impl Trip {
+    /// `distance` in meters, `acceleration` in m s⁻² as felt aboard.
+    pub fn new(distance: f64, acceleration: f64, cruise_rapidity: f64) -> Trip {
+        let c = SPEED_OF_LIGHT;
+        // Half of cosh φ − 1 = 2 sinh²(φ/2), written so short trips keep their precision.
+        let boost_distance = 2.0 * c * c / acceleration * (cruise_rapidity / 2.0).sinh().powi(2);
+        if 2.0 * boost_distance >= distance {
+            let peak_rapidity = 2.0 * ((acceleration * distance).sqrt() / (2.0 * c)).asinh();
+            Trip {
+                universe_time: 2.0 * c / acceleration * peak_rapidity.sinh(),
+                ship_time: 2.0 * c / acceleration * peak_rapidity,
+                peak_rapidity
+            }
+        } else {
+            let coast = distance - 2.0 * boost_distance;
+            let speed = c * cruise_rapidity.tanh();
+            Trip {
+                universe_time: 2.0 * c / acceleration * cruise_rapidity.sinh() + coast / speed,
+                ship_time: 2.0 * c / acceleration * cruise_rapidity + coast / (speed * cruise_rapidity.cosh()),
+                peak_rapidity: cruise_rapidity
+            }
+        }
+    }
+
+    pub fn peak_speed(&self) -> f64 {
+        SPEED_OF_LIGHT * self.peak_rapidity.tanh()
+    }
+
+    /// The ratio of starting to final mass, by the relativistic rocket equation.
+    pub fn mass_ratio(&self, exhaust_velocity: f64) -> f64 {
+        (2.0 * self.peak_rapidity * SPEED_OF_LIGHT / exhaust_velocity).exp()
+    }
+
+    /// Dust impacts, per light year crossed, carry energy that grows as the square of the Lorentz factor.
+    pub fn risk(&self, distance: Length) -> f64 {
+        distance.in_meters() / length::LIGHT_YEARS_TO_METERS * self.peak_rapidity.cosh().powi(2)
+    }
+}
+
+/// What a route minimises.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteCost {
+    UniverseTime,
+    ShipTime,
+    Fuel,
+    Risk
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Search {
+    Dijkstra,
+    /// Dijkstra guided by an admissible straight-line estimate of the cost still to come; the same routes, found sooner.
+    AStar
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteOptions {
+    pub cost: RouteCost,
+    pub search: Search,
+    /// As a fraction of the speed of light. Capped at what a full load of fuel allows for one leg.
+    pub cruise_speed: f64,
+    /// The longest leg the ship's crew and life support can endure.
+    pub range: Length
+}
+
+impl Default for RouteOptions {
+    fn default() -> RouteOptions {
+        RouteOptions {
+            cost: RouteCost::UniverseTime,
+            search: Search::AStar,
+            cruise_speed: 0.1,
+            range: Length::ly(10.0)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leg {
+    pub from: StarId,
+    pub to: StarId,
+    pub distance: Length,
+    /// Whether the ship fills its tanks at `from` before leaving.
+    pub refuel: bool,
+    pub trip: Trip,
+    pub fuel: Mass
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    pub legs: Vec<Leg>,
+    /// Seconds in the universe's frame, refuelling stops included.
+    pub universe_time: f64,
+    /// Seconds aboard the ship, refuelling stops included.
+    pub ship_time: f64,
+    pub fuel: Mass,
+    pub risk: f64
+}
+
+impl Route {
+    pub fn distance(&self) -> Length {
+        self.legs.iter().fold(length::ZERO, |total, leg| total + leg.distance).to_scale(length::Scale::LightYear)
+    }
+
+    pub fn refuels(&self) -> usize {
+        self.legs.iter().filter(|leg| leg.refuel).count()
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} legs, {:.2}, {} refuelling stops, {:.2} years in the universe and {:.2} aboard, {:.2} of fuel",
+               self.legs.len(), self.distance(), self.refuels(),
+               self.universe_time / SECONDS_PER_YEAR, self.ship_time / SECONDS_PER_YEAR, self.fuel)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RouteError {
+    UnknownStar(StarId),
+    /// The cruise speed is not a positive fraction of the speed of light.
+    InvalidCruiseSpeed(f64),
+    /// The ship has no engines, or no fuel to carry.
+    CannotFly,
+    Unreachable
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteError::UnknownStar(id)           => write!(f, "star {:?} is not on the map", id),
+            RouteError::InvalidCruiseSpeed(speed) => write!(f, "cannot cruise at {}c", speed),
+            RouteError::CannotFly                 => write!(f, "the ship cannot make interstellar trips"),
+            RouteError::Unreachable               => write!(f, "no route is within the ship's range and fuel")
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// A search state: a star, and how many full-speed legs the fuel aboard is good for.
+/// Shorter legs burn less, so counting every leg as full is conservative.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct State {
+    waypoint: usize,
+    legs: u32
+}
+
+struct Frontier {
+    estimate: f64,
+    cost: f64,
+    state: State
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Frontier) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Frontier) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    /// Reversed, so the heap pops the lowest estimate first.
+    fn cmp(&self, other: &Frontier) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The cheapest route for a ship between two stars, refuelling on the way where it must.
+pub fn plan_route(graph: &StarGraph, from: StarId, to: StarId, ship: &Ship, options: &RouteOptions) -> Result<Route, RouteError> {
+    let start = graph.find(from).ok_or(RouteError::UnknownStar(from))?;
+    let goal = graph.find(to).ok_or(RouteError::UnknownStar(to))?;
+    if options.cruise_speed.is_nan() || options.cruise_speed <= 0.0 {
+        return Err(RouteError::InvalidCruiseSpeed(options.cruise_speed));
+    }
+    let exhaust_velocity = ship.exhaust_velocity();
+    let acceleration = ship.acceleration();
+    let empty = ship.total_mass() - ship.fuel();
+    let full = empty + ship.fuel_capacity();
+    if exhaust_velocity <= 0.0 || acceleration <= 0.0 || full <= empty {
+        return Err(RouteError::CannotFly);
+    }
+    // Each full-speed leg takes the same ratio of masses, so the legs the tanks hold is a count.
+    let full_rapidity = exhaust_velocity / SPEED_OF_LIGHT * (full / empty).ln();
+    let cruise_rapidity = options.cruise_speed.clamp(0.0, 1.0 - f64::EPSILON).atanh().min(full_rapidity / 2.0);
+    let leg_log_ratio = 2.0 * cruise_rapidity * SPEED_OF_LIGHT / exhaust_velocity;
+    let legs_in = |mass: Mass| ((mass / empty).ln() / leg_log_ratio + 1e-9).floor() as u32;
+    let (max_legs, start_legs) = (legs_in(full), legs_in(ship.total_mass()));
+
+    let cost_of = |trip: &Trip, distance: Length, refuel: bool| match options.cost {
+        RouteCost::UniverseTime => trip.universe_time + if refuel { REFUEL_SECONDS } else { 0.0 },
+        RouteCost::ShipTime     => trip.ship_time + if refuel { REFUEL_SECONDS } else { 0.0 },
+        RouteCost::Fuel         => trip.mass_ratio(exhaust_velocity).ln(),
+        RouteCost::Risk         => trip.risk(distance)
+    };
+    let goal_position = graph.waypoints[goal].position;
+    let estimate = |waypoint: usize| {
+        let remaining = graph.waypoints[waypoint].position.distance(goal_position);
+        match (options.search, options.cost) {
+            (Search::Dijkstra, _) | (_, RouteCost::Fuel) => 0.0,
+            (_, RouteCost::UniverseTime) => remaining.in_meters() / (SPEED_OF_LIGHT * cruise_rapidity.tanh()),
+            (_, RouteCost::ShipTime)     => remaining.in_meters() / (SPEED_OF_LIGHT * cruise_rapidity.sinh()),
+            (_, RouteCost::Risk)         => remaining.in_meters() / length::LIGHT_YEARS_TO_METERS
+        }
+    };
+
+    let initial = State { waypoint: start, legs: start_legs };
+    let mut costs: HashMap<State, f64> = HashMap::new();
+    let mut previous: HashMap<State, (State, usize, Length, bool, Trip)> = HashMap::new();
+    let mut frontier = BinaryHeap::new();
+    costs.insert(initial, 0.0);
+    frontier.push(Frontier { estimate: estimate(start), cost: 0.0, state: initial });
+    let mut arrived = None;
+    while let Some(Frontier { cost, state, .. }) = frontier.pop() {
+        if state.waypoint == goal {
+            arrived = Some(state);
+            break;
+        }
+        if cost > costs.get(&state).copied().unwrap_or(f64::INFINITY) {
+            continue;
+        }
+        // Stopping to refuel where the ship can is a choice, which the search makes by cost like any other.
+        let can_refuel = graph.waypoints[state.waypoint].refuel && state.legs < max_legs;
+        for refuel in [false, true].iter().copied().filter(|refuel| !refuel || can_refuel) {
+            let legs = if refuel { max_legs } else { state.legs };
+            if legs == 0 {
+                continue;
+            }
+            for (next, distance) in graph.neighbours(state.waypoint, options.range) {
+                let trip = Trip::new(distance.in_meters(), acceleration, cruise_rapidity);
+                let next_state = State { waypoint: next, legs: legs - 1 };
+                let next_cost = cost + cost_of(&trip, distance, refuel);
+                if next_cost < costs.get(&next_state).copied().unwrap_or(f64::INFINITY) {
+                    costs.insert(next_state, next_cost);
+                    previous.insert(next_state, (state, state.waypoint, distance, refuel, trip));
+                    frontier.push(Frontier { estimate: next_cost + estimate(next), cost: next_cost, state: next_state });
+                }
+            }
+        }
+    }
+
+    let mut state = arrived.ok_or(RouteError::Unreachable)?;
+    let mut hops = Vec::new();
+    while let Some((before, from, distance, refuel, trip)) = previous.get(&state).copied() {
+        hops.push((from, state.waypoint, distance, refuel, trip));
+        state = before;
+    }
+    hops.reverse();
+
+    // Replay the legs to size each one's fuel from the mass it starts with.
+    let mut mass = ship.total_mass();
+    let mut route = Route { legs: Vec::new(), universe_time: 0.0, ship_time: 0.0, fuel: mass::ZERO, risk: 0.0 };
+    for (from, to, distance, refuel, trip) in hops {
+        if refuel {
+            mass = full;
+            route.universe_time += REFUEL_SECONDS;
+            route.ship_time += REFUEL_SECONDS;
+        }
+        let fuel = mass - mass / trip.mass_ratio(exhaust_velocity);
+        mass = mass - fuel;
+        route.universe_time += trip.universe_time;
+        route.ship_time += trip.ship_time;
+        route.fuel = route.fuel + fuel;
+        route.risk += trip.risk(distance);
+        route.legs.push(Leg {
+            from: graph.waypoints[from].id,
+            to: graph.waypoints[to].id,
+            distance: distance.to_scale(length::Scale::LightYear),
+            refuel,
+            trip,
+            fuel: fuel.to_scale(mass::Scale::Tonne)
+        });
+    }
+    route.fuel = route.fuel.to_scale(mass::Scale::Tonne);
+    Ok(route)
+}
+
+/// Asks for a route for a ship from the star nearest it to another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotRoute {
+    pub ship: Entity,
+    pub from: StarId,
+    pub to: StarId,
+    pub options: RouteOptions
+}
+
+/// The route a ship has been given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlottedRoute(pub Route);
+
+/// The stars of the generated universe, as a graph for routes to be planned over.
+pub struct StarMap(pub StarGraph);
+
+pub fn build_star_map(mut commands: Commands, settings: Res<UniverseSettings>) {
+    commands.insert_resource(StarMap(StarGraph::around(&settings.galaxy, settings.start, settings.radius)));
+}
+
+pub fn plot_routes(
+    mut commands: Commands,
+    mut requests: EventReader<PlotRoute>,
+    map: Res<StarMap>,
+    ships: Query<&Ship>
+) {
+    for request in requests.iter() {
+        let ship = match ships.get(request.ship) {
+            Ok(ship) => ship,
+            Err(_)   => continue
+        };
+        match plan_route(&map.0, request.from, request.to, ship, &request.options) {
+            Ok(route) => {
+                info!("route: {}", route);
+                commands.entity(request.ship).insert(PlottedRoute(route));
+            }
+            Err(error) => warn!("{}", error)
+        }
+    }
+}
+
+/// Interstellar route planning over the stars of the generated universe. Needs the `UniversePlugin`.
+pub struct RoutePlugin;
+
+impl Plugin for RoutePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PlotRoute>()
+            .add_startup_system(build_star_map.system())
+            .add_system(plot_routes.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::galaxy::Sector;
+    use super::super::ship::courier;
+
+    fn id(index: u32) -> StarId {
+        StarId { sector: Sector::new(0, 0, 0), index }
+    }
+
+    /// Stars along a line, `spacing` light years apart, that can refuel where `refuel` says so.
+    fn line(spacing: f64, refuel: &[bool]) -> StarGraph {
+        let waypoints: Vec<Waypoint> = refuel.iter().enumerate()
+            .map(|(index, refuel)| Waypoint {
+                id: id(index as u32),
+                position: Position::scaled(index as f64 * spacing, 0.0, 0.0, length::Scale::LightYear),
+                refuel: *refuel
+            })
+            .collect();
+        StarGraph::new(waypoints, Position::scaled(0.0, 0.0, 0.0, length::Scale::LightYear), Length::ly(100.0))
+    }
+
+    /// The courier with a torch drive, whose exhaust is near a tenth of the speed of light.
+    fn torch_ship() -> Ship {
+        let mut ship = courier();
+        ship.engines[0].specific_impulse = 3.0e6;
+        ship
+    }
+
+    /// Slow enough that a full load of fuel is good for three legs.
+    fn options(cost: RouteCost, search: Search) -> RouteOptions {
+        RouteOptions { cost, search, cruise_speed: 0.0075, range: Length::ly(5.0) }
+    }
+
+    #[test]
+    fn cruise_speeds_must_be_positive() {
+        let graph = line(4.0, &[false, false]);
+        for speed in [0.0, -0.1, f64::NAN].iter() {
+            let options = RouteOptions { cruise_speed: *speed, ..RouteOptions::default() };
+            match plan_route(&graph, id(0), id(1), &torch_ship(), &options) {
+                Err(RouteError::InvalidCruiseSpeed(_)) => {}
+                other                                   => panic!("cruise speed {} planned {:?}", speed, other)
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_stars_and_grounded_ships_are_refused() {
+        let graph = line(4.0, &[false, false]);
+        let options = RouteOptions::default();
+        assert_eq!(plan_route(&graph, id(0), id(9), &torch_ship(), &options), Err(RouteError::UnknownStar(id(9))));
+        let mut grounded = torch_ship();
+        grounded.engines.clear();
+        assert_eq!(plan_route(&graph, id(0), id(1), &grounded, &options), Err(RouteError::CannotFly));
+    }
+
+    #[test]
+    fn legs_stay_within_range_and_fuel() {
+        let options = options(RouteCost::UniverseTime, Search::AStar);
+        let route = plan_route(&line(4.0, &[false; 4]), id(0), id(3), &torch_ship(), &options).unwrap();
+        assert_eq!(route.legs.len(), 3);
+        assert!(route.legs.iter().all(|leg| leg.distance <= options.range && !leg.refuel));
+        assert!(route.fuel <= torch_ship().fuel_capacity());
+        // A fourth leg needs more fuel than the tanks hold, and a gap wider than the range cannot be crossed at all.
+        assert_eq!(plan_route(&line(4.0, &[false; 5]), id(0), id(4), &torch_ship(), &options), Err(RouteError::Unreachable));
+        assert_eq!(plan_route(&line(6.0, &[false; 2]), id(0), id(1), &torch_ship(), &options), Err(RouteError::Unreachable));
+    }
+
+    #[test]
+    fn routes_refuel_where_they_must() {
+        let mut refuel = [false; 7];
+        refuel[3] = true;
+        let route = plan_route(&line(4.0, &refuel), id(0), id(6), &torch_ship(), &options(RouteCost::UniverseTime, Search::AStar)).unwrap();
+        assert_eq!(route.legs.len(), 6);
+        assert_eq!(route.refuels(), 1);
+        assert!(route.legs[3].refuel && route.legs[3].from == id(3));
+        let flying: f64 = route.legs.iter().map(|leg| leg.trip.universe_time).sum();
+        assert!((route.universe_time - flying - REFUEL_SECONDS).abs() < 1.0);
+    }
+
+    #[test]
+    fn routes_pass_fuel_they_do_not_need() {
+        let route = plan_route(&line(4.0, &[false, true, false, false]), id(0), id(3), &torch_ship(),
+                               &options(RouteCost::UniverseTime, Search::AStar)).unwrap();
+        assert_eq!(route.legs.len(), 3);
+        assert_eq!(route.refuels(), 0);
+        let flying: f64 = route.legs.iter().map(|leg| leg.trip.universe_time).sum();
+        assert!((route.universe_time - flying).abs() < 1.0);
+    }
+
+    #[test]
+    fn a_star_finds_routes_as_cheap_as_dijkstra() {
+        let settings = UniverseSettings::default();
+        let graph = StarGraph::around(&settings.galaxy, settings.start, Length::ly(15.0));
+        let from = graph.waypoints[0].id;
+        for cost in [RouteCost::UniverseTime, RouteCost::ShipTime, RouteCost::Fuel, RouteCost::Risk].iter() {
+            let options = RouteOptions { range: Length::ly(8.0), ..options(*cost, Search::Dijkstra) };
+            for waypoint in graph.waypoints.iter().skip(1) {
+                let dijkstra = plan_route(&graph, from, waypoint.id, &torch_ship(), &options);
+                let a_star = plan_route(&graph, from, waypoint.id, &torch_ship(), &RouteOptions { search: Search::AStar, ..options });
+                match (dijkstra, a_star) {
+                    (Ok(dijkstra), Ok(a_star)) => {
+                        let total = |route: &Route| match cost {
+                            RouteCost::UniverseTime => route.universe_time,
+                            RouteCost::ShipTime     => route.ship_time,
+                            RouteCost::Fuel         => route.legs.iter().map(|leg| leg.trip.mass_ratio(torch_ship().exhaust_velocity()).ln()).sum(),
+                            RouteCost::Risk         => route.risk
+                        };
+                        assert!((total(&dijkstra) - total(&a_star)).abs() <= 1e-9 * total(&dijkstra), "{:?} to {}", cost, waypoint.id);
+                    }
+                    (dijkstra, a_star) => assert_eq!(dijkstra, a_star)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn trips_obey_relativity() {
+        let cruise = 0.5f64.atanh();
+        for light_years in [0.001, 1.0, 100.0].iter() {
+            let distance = light_years * length::LIGHT_YEARS_TO_METERS;
+            let trip = Trip::new(distance, 10.0, cruise);
+            assert!(trip.peak_speed() <= 0.5 * SPEED_OF_LIGHT * (1.0 + 1e-12));
+            assert!(trip.universe_time > distance / SPEED_OF_LIGHT);
+            assert!(trip.ship_time < trip.universe_time);
+        }
+        // A short trip turns round halfway: a quarter of the distance takes half the time at low speeds.
+        let short = Trip::new(1.0e9, 10.0, cruise);
+        let shorter = Trip::new(0.25e9, 10.0, cruise);
+        assert!((short.universe_time / shorter.universe_time - 2.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,677 @@
+use bevy::app::Events;
+use bevy::prelude::*;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::astronomy::Orbiting;
+use super::behaviour::{Agent, Blackboard, Role, Value};
+use super::clock::GameClock;
+use super::colony::Colony;
+use super::faction::{FactionSettings, Factions, Territory};
+use super::flight::{Attitude, PlayerShip, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::galaxy::{Galaxy, StarId};
+use super::gameplay::Selection;
+use super::industry::{Deposits, Industry};
+use super::length;
+use super::length::Length;
+use super::mass::Mass;
+use super::market::{Credits, Manifest, Market, Trader, Voyage};
+use super::mission::MissionLog;
+use super::orbit::Orbit;
+use super::planet::{Composition, Planet};
+use super::position::Position;
+use super::ship::Ship;
+use super::spatial::Octree;
+use super::star::Star;
+use super::survey::Codex;
+use super::universe::{StarIndex, UniverseSeed, UniverseSettings};
+
+/// Identifies a save file, ahead of anything that might be mistaken for one.
+pub const MAGIC: [u8; 8] = *b"SAPPHIRE";
+/// The version of the save format this build writes. Bump it, and read the old version in `read_payload`, when `SaveData`
+/// changes incompatibly.
+pub const SAVE_VERSION: u32 = 2;
+/// The magic, the version, and the CRC-32 of the compressed payload.
+pub const HEADER_LENGTH: usize = 16;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    /// The file is not a save at all.
+    NotASave,
+    /// The file ends before its header does.
+    Truncated,
+    /// The save was written by a newer build than this one.
+    NewerVersion { found: u32, supported: u32 },
+    /// The payload does not match its checksum, or will not decompress.
+    Corrupt(String),
+    Serialize(ron::Error),
+    Deserialize(ron::Error),
+    InvalidSlotName(String)
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error)                         => write!(f, "could not access save: {}", error),
+            SaveError::NotASave                          => write!(f, "not a save file"),
+            SaveError::Truncated                         => write!(f, "save file is truncated"),
+            SaveError::NewerVersion { found, supported } =>
+                write!(f, "save is from a newer version ({}) than this game supports ({})", found, supported),
+            SaveError::Corrupt(reason)                   => write!(f, "save is corrupt: {}", reason),
+            SaveError::Serialize(error)                  => write!(f, "could not write save: {}", error),
+            SaveError::Deserialize(error)                => write!(f, "could not read save: {}", error),
+            SaveError::InvalidSlotName(name)             => write!(f, "invalid save slot name: {:?}", name)
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> SaveError {
+        SaveError::Io(error)
+    }
+}
+
+/// A body in a save, by its index in `SaveData::stars`, `SaveData::planets` or `SaveData::ships`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BodyRef {
+    Star(usize),
+    Planet(usize),
+    Ship(usize)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedStar {
+    pub id: StarId,
+    pub star: Star,
+    pub position: WorldPosition
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlanet {
+    pub planet: Planet,
+    pub orbit: Orbit,
+    pub parent: BodyRef,
+    #[serde(default)]
+    pub composition: Option<Composition>,
+    #[serde(default)]
+    pub deposits: Option<Deposits>,
+    #[serde(default)]
+    pub industry: Option<Industry>,
+    #[serde(default)]
+    pub market: Option<Market>,
+    #[serde(default)]
+    pub colony: Option<Colony>
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedShip {
+    pub ship: Ship,
+    pub position: WorldPosition,
+    pub velocity: Velocity,
+    pub attitude: Attitude,
+    pub player: bool,
+    #[serde(default)]
+    pub credits: Option<Credits>,
+    #[serde(default)]
+    pub manifest: Option<Manifest>
+}
+
+/// A blackboard entry, with entities as bodies in the save.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SavedValue {
+    Body(BodyRef),
+    Number(f64)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedAgent {
+    pub ship: BodyRef,
+    pub behaviour: String,
+    pub role: Option<Role>,
+    /// Entries for bodies no longer in the game are left out.
+    pub blackboard: BTreeMap<String, SavedValue>,
+    pub task: Option<String>
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedTrader {
+    pub capacity: Mass,
+    pub at: BodyRef,
+    /// Where the trader is bound, and when it arrives.
+    pub voyage: Option<(BodyRef, f64)>,
+    pub credits: Credits,
+    pub manifest: Manifest
+}
+
+/// Everything needed to resume a game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub galaxy: Galaxy,
+    pub start: Position,
+    pub radius: Length,
+    pub clock: GameClock,
+    pub stars: Vec<SavedStar>,
+    pub planets: Vec<SavedPlanet>,
+    #[serde(default)]
+    pub ships: Vec<SavedShip>,
+    #[serde(default)]
+    pub traders: Vec<SavedTrader>,
+    #[serde(default)]
+    pub agents: Vec<SavedAgent>,
+    #[serde(default)]
+    pub missions: Option<MissionLog<BodyRef>>,
+    #[serde(default)]
+    pub codex: Option<Codex>,
+    /// Saves from before factions found them afresh.
+    #[serde(default)]
+    pub factions: Option<Factions>,
+    #[serde(default)]
+    pub selection: Option<BodyRef>
+}
+
+/// Seconds since the epoch, as version 1 kept them before the game clock.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct UniverseTime {
+    seconds: f64
+}
+
+/// `SaveData` as version 1 wrote it, with the universe time where the game clock now is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct SaveDataVersion1 {
+    galaxy: Galaxy,
+    start: Position,
+    radius: Length,
+    time: UniverseTime,
+    stars: Vec<SavedStar>,
+    planets: Vec<SavedPlanet>,
+    #[serde(default)]
+    selection: Option<BodyRef>
+}
+
+/// The game clock keeps the universe time's seconds and defaults the rest.
+impl From<SaveDataVersion1> for SaveData {
+    fn from(old: SaveDataVersion1) -> SaveData {
+        let mut clock = GameClock::default();
+        clock.seconds = old.time.seconds;
+        SaveData {
+            galaxy: old.galaxy,
+            start: old.start,
+            radius: old.radius,
+            clock,
+            stars: old.stars,
+            planets: old.planets,
+            ships: Vec::new(),
+            traders: Vec::new(),
+            agents: Vec::new(),
+            missions: None,
+            codex: None,
+            factions: None,
+            selection: old.selection
+        }
+    }
+}
+
+/// Reads a save's RON payload in the shape the version that wrote it used.
+/// Fields added with `#[serde(default)]` need no case of their own.
+fn read_payload(version: u32, text: &str) -> Result<SaveData, SaveError> {
+    match version {
+        1 => ron::de::from_str::<SaveDataVersion1>(text).map(SaveData::from).map_err(SaveError::Deserialize),
+        _ => ron::de::from_str(text).map_err(SaveError::Deserialize)
+    }
+}
+
+/// Encodes a save: the header, then the RON payload compressed with deflate.
+pub fn encode(data: &SaveData) -> Result<Vec<u8>, SaveError> {
+    let text = ron::ser::to_string(data).map_err(SaveError::Serialize)?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    let payload = encoder.finish()?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LENGTH + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&SAVE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Decodes a save, checking its integrity and migrating it from older versions.
+pub fn decode(bytes: &[u8]) -> Result<SaveData, SaveError> {
+    if bytes.len() < MAGIC.len() {
+        return Err(if MAGIC.starts_with(bytes) { SaveError::Truncated } else { SaveError::NotASave });
+    }
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(SaveError::NotASave);
+    }
+    if bytes.len() < HEADER_LENGTH {
+        return Err(SaveError::Truncated);
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    let checksum = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+    if version > SAVE_VERSION {
+        return Err(SaveError::NewerVersion { found: version, supported: SAVE_VERSION });
+    }
+    if version == 0 {
+        return Err(SaveError::Corrupt("version 0".to_string()));
+    }
+    let payload = &bytes[HEADER_LENGTH..];
+    let found = crc32fast::hash(payload);
+    if found != checksum {
+        return Err(SaveError::Corrupt(format!("checksum is {:08x}, expected {:08x}", found, checksum)));
+    }
+    let mut text = String::new();
+    DeflateDecoder::new(payload).read_to_string(&mut text)
+        .map_err(|error| SaveError::Corrupt(error.to_string()))?;
+    read_payload(version, &text)
+}
+
+/// Writes through a temporary file, so a crash mid-save leaves the previous save intact.
+pub fn write_save<P: AsRef<Path>>(path: P, data: &SaveData) -> Result<(), SaveError> {
+    let path = path.as_ref();
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, encode(data)?)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+pub fn read_save<P: AsRef<Path>>(path: P) -> Result<SaveData, SaveError> {
+    decode(&std::fs::read(path)?)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SaveSlot {
+    Named(String),
+    /// Autosaves rotate through a fixed number of numbered slots.
+    Autosave(u32)
+}
+
+impl SaveSlot {
+    pub fn file_name(&self) -> Result<String, SaveError> {
+        match self {
+            SaveSlot::Named(name) => {
+                let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ');
+                if valid { Ok(format!("{}.sav", name)) } else { Err(SaveError::InvalidSlotName(name.clone())) }
+            }
+            SaveSlot::Autosave(number) => Ok(format!("autosave-{}.sav", number))
+        }
+    }
+}
+
+/// Where saves are kept.
+pub struct SaveDirectory(pub PathBuf);
+
+impl Default for SaveDirectory {
+    fn default() -> SaveDirectory {
+        SaveDirectory(PathBuf::from("saves"))
+    }
+}
+
+impl SaveDirectory {
+    pub fn path(&self, slot: &SaveSlot) -> Result<PathBuf, SaveError> {
+        Ok(self.0.join(slot.file_name()?))
+    }
+}
+
+pub struct AutosaveSettings {
+    /// Real seconds between autosaves; `None` turns autosaving off.
+    pub interval: Option<f64>,
+    pub slots: u32
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> AutosaveSettings {
+        AutosaveSettings { interval: Some(300.0), slots: 3 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AutosaveState {
+    pub elapsed: f64,
+    pub next_slot: u32
+}
+
+pub struct SaveGame(pub SaveSlot);
+pub struct LoadGame(pub SaveSlot);
+
+/// Captures the game's state from the world.
+pub fn capture(world: &mut World) -> SaveData {
+    let settings = world.get_resource::<UniverseSettings>().cloned().unwrap_or_default();
+    let clock = world.get_resource::<GameClock>().cloned().unwrap_or_default();
+
+    let mut stars: Vec<(Entity, SavedStar)> = world.query::<(Entity, &StarId, &Star, &WorldPosition)>()
+        .iter(world)
+        .map(|(entity, id, star, position)| (entity, SavedStar { id: *id, star: *star, position: *position }))
+        .collect();
+    stars.sort_by_key(|(_, saved)| saved.id);
+    let planets: Vec<(Entity, SavedPlanet, Entity)> = world.query::<(Entity, &Planet, &Orbit, &Orbiting, Option<&Composition>, Option<&Deposits>, Option<&Industry>, Option<&Market>, Option<&Colony>)>()
+        .iter(world)
+        .map(|(entity, planet, orbit, parent, composition, deposits, industry, market, colony)| (entity, SavedPlanet {
+            planet: *planet,
+            orbit: *orbit,
+            parent: BodyRef::Planet(0),
+            composition: composition.copied(),
+            deposits: deposits.cloned(),
+            industry: industry.cloned(),
+            market: market.cloned(),
+            colony: colony.cloned()
+        }, parent.0))
+        .collect();
+    let ships: Vec<(Entity, SavedShip)> = world.query::<(Entity, &Ship, &WorldPosition, &Velocity, &Attitude, Option<&PlayerShip>, Option<&Credits>, Option<&Manifest>)>()
+        .iter(world)
+        .map(|(entity, ship, position, velocity, attitude, player, credits, manifest)| (entity, SavedShip {
+            ship: ship.clone(),
+            position: *position,
+            velocity: *velocity,
+            attitude: *attitude,
+            player: player.is_some(),
+            credits: credits.copied(),
+            manifest: manifest.cloned()
+        }))
+        .collect();
+
+    let mut refs: HashMap<Entity, BodyRef> = HashMap::new();
+    refs.extend(stars.iter().enumerate().map(|(index, (entity, _))| (*entity, BodyRef::Star(index))));
+    refs.extend(planets.iter().enumerate().map(|(index, (entity, _, _))| (*entity, BodyRef::Planet(index))));
+    refs.extend(ships.iter().enumerate().map(|(index, (entity, _))| (*entity, BodyRef::Ship(index))));
+    let selection = world.get_resource::<Selection>().and_then(|selection| selection.0).and_then(|entity| refs.get(&entity).copied());
+    let missions = world.get_resource::<MissionLog>().map(|log| log.map(|entity| refs.get(entity).copied()));
+    let traders: Vec<SavedTrader> = world.query::<(&Trader, &Credits, &Manifest)>()
+        .iter(world)
+        .filter_map(|(trader, credits, manifest)| Some(SavedTrader {
+            capacity: trader.capacity,
+            at: *refs.get(&trader.at)?,
+            voyage: match trader.voyage {
+                Some(voyage) => Some((*refs.get(&voyage.to)?, voyage.arrival)),
+                None         => None
+            },
+            credits: *credits,
+            manifest: manifest.clone()
+        }))
+        .collect();
+    let agents: Vec<SavedAgent> = world.query::<(Entity, &Agent, &Blackboard, Option<&Role>)>()
+        .iter(world)
+        .filter_map(|(entity, agent, blackboard, role)| Some(SavedAgent {
+            ship: *refs.get(&entity)?,
+            behaviour: agent.behaviour.clone(),
+            role: role.copied(),
+            blackboard: blackboard.values.iter()
+                .filter_map(|(key, value)| Some((key.clone(), match value {
+                    Value::Entity(entity) => SavedValue::Body(*refs.get(entity)?),
+                    Value::Number(number) => SavedValue::Number(*number)
+                })))
+                .collect(),
+            task: blackboard.task.clone()
+        }))
+        .collect();
+
+    SaveData {
+        galaxy: settings.galaxy,
+        start: settings.start,
+        radius: settings.radius,
+        clock,
+        stars: stars.into_iter().map(|(_, saved)| saved).collect(),
+        planets: planets.into_iter()
+            .filter_map(|(_, saved, parent)| refs.get(&parent).map(|parent| SavedPlanet { parent: *parent, ..saved }))
+            .collect(),
+        ships: ships.into_iter().map(|(_, saved)| saved).collect(),
+        traders,
+        agents,
+        missions,
+        codex: world.get_resource::<Codex>().cloned(),
+        factions: world.get_resource::<Factions>().cloned(),
+        selection
+    }
+}
+
+/// Replaces the game's state in the world with a save's.
+pub fn restore(world: &mut World, data: SaveData) {
+    let old: Vec<Entity> = world.query_filtered::<Entity, Or<(With<StarId>, With<Planet>, With<Ship>, With<Trader>)>>().iter(world).collect();
+    for entity in old {
+        world.despawn(entity);
+    }
+
+    let mut index = Octree::new(data.start, Length::pc(16.0));
+    let stars: Vec<Entity> = data.stars.iter()
+        .map(|saved| {
+            let entity = world.spawn().insert_bundle((saved.id, saved.star, saved.position)).id();
+            index.insert(saved.position.to_position(length::Scale::Parsec), entity);
+            entity
+        })
+        .collect();
+    let planets: Vec<Entity> = data.planets.iter()
+        .map(|saved| {
+            let mut planet = world.spawn();
+            planet.insert_bundle((saved.planet, saved.orbit, WorldPosition::default()));
+            if let Some(composition) = saved.composition {
+                planet.insert(composition);
+            }
+            if let Some(deposits) = &saved.deposits {
+                planet.insert(deposits.clone());
+            }
+            if let Some(industry) = &saved.industry {
+                planet.insert(industry.clone());
+            }
+            if let Some(market) = &saved.market {
+                planet.insert(market.clone());
+            }
+            if let Some(colony) = &saved.colony {
+                planet.insert(colony.clone());
+            }
+            planet.id()
+        })
+        .collect();
+    let ships: Vec<Entity> = data.ships.iter()
+        .map(|saved| {
+            let mut ship = world.spawn();
+            ship.insert_bundle((saved.ship.clone(), saved.position, saved.velocity, saved.attitude, ShipControls::default()));
+            if saved.player {
+                ship.insert(PlayerShip);
+            }
+            if let Some(credits) = saved.credits {
+                ship.insert(credits);
+            }
+            if let Some(manifest) = &saved.manifest {
+                ship.insert(manifest.clone());
+            }
+            ship.id()
+        })
+        .collect();
+    let entity = |body: BodyRef| match body {
+        BodyRef::Star(index)   => stars.get(index).copied(),
+        BodyRef::Planet(index) => planets.get(index).copied(),
+        BodyRef::Ship(index)   => ships.get(index).copied()
+    };
+    for (saved, planet) in data.planets.iter().zip(planets.iter()) {
+        match entity(saved.parent) {
+            Some(parent) => { world.entity_mut(*planet).insert(Orbiting(parent)); }
+            None         => { world.despawn(*planet); }
+        }
+    }
+    for saved in data.traders.iter() {
+        let at = match entity(saved.at) {
+            Some(at) => at,
+            None     => continue
+        };
+        let voyage = saved.voyage.and_then(|(to, arrival)| Some(Voyage { to: entity(to)?, arrival }));
+        world.spawn().insert_bundle((Trader { capacity: saved.capacity, at, voyage }, saved.credits, saved.manifest.clone()));
+    }
+    for saved in data.agents.iter() {
+        let ship = match entity(saved.ship) {
+            Some(ship) => ship,
+            None       => continue
+        };
+        let values = saved.blackboard.iter()
+            .filter_map(|(key, value)| Some((key.clone(), match value {
+                SavedValue::Body(body)     => Value::Entity(entity(*body)?),
+                SavedValue::Number(number) => Value::Number(*number)
+            })))
+            .collect();
+        let mut agent = world.entity_mut(ship);
+        agent.insert_bundle((Agent { behaviour: saved.behaviour.clone() }, Blackboard { values, task: saved.task.clone() }));
+        if let Some(role) = saved.role {
+            agent.insert(role);
+        }
+    }
+    let missions = data.missions.as_ref().map_or_else(MissionLog::default, |log| log.map(|body| entity(*body)));
+    let selection = data.selection.and_then(entity);
+
+    let territory = Territory::around(&data.galaxy, data.start, data.radius);
+    let factions = match data.factions {
+        Some(factions) => factions,
+        None           => {
+            let count = world.get_resource::<FactionSettings>().copied().unwrap_or_default().count;
+            let mut factions = Factions::default();
+            for event in Factions::found(&territory, data.galaxy.seed, count) {
+                factions.apply(&event, data.clock.seconds);
+            }
+            factions
+        }
+    };
+
+    world.insert_resource(UniverseSeed(data.galaxy.seed));
+    world.insert_resource(UniverseSettings { galaxy: data.galaxy, start: data.start, radius: data.radius });
+    world.insert_resource(data.clock);
+    world.insert_resource(StarIndex(index));
+    world.insert_resource(factions.borders(&territory));
+    world.insert_resource(factions);
+    world.insert_resource(territory);
+    world.insert_resource(missions);
+    world.insert_resource(data.codex.unwrap_or_default());
+    if let Some(mut current) = world.get_resource_mut::<Selection>() {
+        current.0 = selection;
+    }
+}
+
+fn save_to(world: &mut World, slot: &SaveSlot) -> Result<PathBuf, SaveError> {
+    let path = world.get_resource::<SaveDirectory>().map_or_else(|| SaveDirectory::default().path(slot), |directory| directory.path(slot))?;
+    write_save(&path, &capture(world))?;
+    Ok(path)
+}
+
+fn load_from(world: &mut World, slot: &SaveSlot) -> Result<PathBuf, SaveError> {
+    let path = world.get_resource::<SaveDirectory>().map_or_else(|| SaveDirectory::default().path(slot), |directory| directory.path(slot))?;
+    let data = read_save(&path)?;
+    restore(world, data);
+    Ok(path)
+}
+
+pub fn handle_save_requests(world: &mut World) {
+    let saves: Vec<SaveSlot> = world.get_resource_mut::<Events<SaveGame>>()
+        .map_or_else(Vec::new, |mut events| events.drain().map(|request| request.0).collect());
+    let loads: Vec<SaveSlot> = world.get_resource_mut::<Events<LoadGame>>()
+        .map_or_else(Vec::new, |mut events| events.drain().map(|request| request.0).collect());
+    for slot in saves {
+        match save_to(world, &slot) {
+            Ok(path)   => info!("saved to {}", path.display()),
+            Err(error) => error!("{}", error)
+        }
+    }
+    if let Some(slot) = loads.last() {
+        match load_from(world, slot) {
+            Ok(path)   => info!("loaded {}", path.display()),
+            Err(error) => error!("{}", error)
+        }
+    }
+}
+
+pub fn autosave(world: &mut World) {
+    let delta_seconds = world.get_resource::<Time>().map_or(0.0, |time| time.delta_seconds_f64());
+    let (interval, slots) = match world.get_resource::<AutosaveSettings>() {
+        Some(AutosaveSettings { interval: Some(interval), slots }) if *slots > 0 => (*interval, *slots),
+        _ => return
+    };
+    let slot = {
+        let mut state = world.get_resource_or_insert_with(AutosaveState::default);
+        state.elapsed += delta_seconds;
+        if state.elapsed < interval {
+            return;
+        }
+        state.elapsed = 0.0;
+        let slot = state.next_slot % slots;
+        state.next_slot = (slot + 1) % slots;
+        SaveSlot::Autosave(slot)
+    };
+    if let Err(error) = save_to(world, &slot) {
+        error!("autosave failed: {}", error);
+    }
+}
+
+/// Saving and loading through `SaveGame` and `LoadGame` events, and periodic autosaves.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SaveDirectory>()
+            .init_resource::<AutosaveSettings>()
+            .init_resource::<AutosaveState>()
+            .add_event::<SaveGame>()
+            .add_event::<LoadGame>()
+            .add_system(handle_save_requests.exclusive_system().at_start())
+            .add_system(autosave.exclusive_system().at_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written by version 1, ten hour long ticks into a game.
+    const VERSION_1_SAVE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/saves/version-1.sav");
+
+    #[test]
+    fn version_1_saves_load_with_their_time_on_the_game_clock() {
+        let data = read_save(VERSION_1_SAVE).unwrap();
+        assert_eq!(data.clock.seconds, 36_000.0);
+        assert_eq!(data.clock.tick, GameClock::default().tick);
+        assert_eq!(data.stars.len(), 11);
+        assert_eq!(data.planets.len(), 26);
+        assert!(data.ships.is_empty() && data.factions.is_none());
+    }
+
+    #[test]
+    fn saves_round_trip() {
+        let mut data = read_save(VERSION_1_SAVE).unwrap();
+        data.clock.warp = 100.0;
+        data.selection = Some(BodyRef::Planet(3));
+        let bytes = encode(&data).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[8..12].try_into().unwrap()), SAVE_VERSION);
+        assert_eq!(decode(&bytes).unwrap(), data);
+    }
+
+    #[test]
+    fn damaged_saves_are_refused() {
+        let bytes = encode(&read_save(VERSION_1_SAVE).unwrap()).unwrap();
+        assert!(matches!(decode(b"SAPP"), Err(SaveError::Truncated)));
+        assert!(matches!(decode(b"not a save at all"), Err(SaveError::NotASave)));
+        assert!(matches!(decode(&bytes[..12]), Err(SaveError::Truncated)));
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        assert!(matches!(decode(&newer), Err(SaveError::NewerVersion { .. })));
+        let mut flipped = bytes;
+        let last = flipped.len() - 1;
+        flipped[last] ^= 0x01;
+        assert!(matches!(decode(&flipped), Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn slot_names_stay_inside_the_save_directory() {
+        assert_eq!(SaveSlot::Named("Sol run-2".to_string()).file_name().unwrap(), "Sol run-2.sav");
+        assert_eq!(SaveSlot::Autosave(1).file_name().unwrap(), "autosave-1.sav");
+        assert!(SaveSlot::Named("../escape".to_string()).file_name().is_err());
+        assert!(SaveSlot::Named(String::new()).file_name().is_err());
+    }
+}
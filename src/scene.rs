@@ -0,0 +1,287 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::camera;
+use super::camera::CameraController;
+use super::flight::Attitude;
+use super::floating_origin::{RenderScale, WorldPosition, WorldScale};
+use super::planet::{Planet, PlanetKind};
+use super::ship::Ship;
+use super::star::Star;
+use super::temperature::Temperature;
+use super::universe::UniverseSettings;
+
+/// The scene the `ScenePlugin` loads when no `SceneDescription` has been inserted, relative to `assets`.
+pub const STARTUP_SCENE: &str = "scenes/startup.ron";
+
+/// Stars and planets are drawn at least this many render units across, so they stay visible from light years away.
+pub const MIN_APPARENT_SIZE_UNITS: f64 = 0.02;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Plane { size: f32 },
+    Cube { size: f32 },
+    Sphere { radius: f32 }
+}
+
+/// Positions are in meters from the start of the universe.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneObject {
+    pub shape: Shape,
+    pub color: (f32, f32, f32),
+    pub position: (f64, f64, f64)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneLight {
+    pub position: (f64, f64, f64),
+    #[serde(default = "SceneLight::default_intensity")]
+    pub intensity: f32,
+    #[serde(default = "SceneLight::default_range")]
+    pub range: f32
+}
+
+impl SceneLight {
+    fn default_intensity() -> f32 {
+        200.0
+    }
+
+    fn default_range() -> f32 {
+        20.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneCamera {
+    pub focus: (f64, f64, f64),
+    pub eye: (f64, f64, f64)
+}
+
+/// A scene described by data, normally loaded from a RON file under `assets/scenes`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub camera: SceneCamera,
+    #[serde(default)]
+    pub objects: Vec<SceneObject>,
+    #[serde(default)]
+    pub lights: Vec<SceneLight>
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(ron::Error)
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error)    => write!(f, "could not read scene: {}", error),
+            SceneError::Parse(error) => write!(f, "could not parse scene: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl Default for SceneDescription {
+    fn default() -> SceneDescription {
+        SceneDescription {
+            camera: SceneCamera { focus: (0.0, 0.0, 0.0), eye: (5.0, 5.0, 5.0) },
+            objects: Vec::new(),
+            lights: Vec::new()
+        }
+    }
+}
+
+impl SceneDescription {
+    pub fn from_ron(text: &str) -> Result<SceneDescription, SceneError> {
+        ron::de::from_str(text).map_err(SceneError::Parse)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SceneDescription, SceneError> {
+        SceneDescription::from_ron(&std::fs::read_to_string(path).map_err(SceneError::Io)?)
+    }
+}
+
+/// Where Bevy looks for assets: under the crate when run by cargo, otherwise beside the executable.
+pub fn asset_path<P: AsRef<Path>>(relative: P) -> PathBuf {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)))
+        .unwrap_or_default();
+    root.join("assets").join(relative)
+}
+
+fn meters((x, y, z): (f64, f64, f64)) -> DVec3 {
+    DVec3::new(x, y, z)
+}
+
+pub fn spawn_scene(
+    mut commands: Commands,
+    scene: Res<SceneDescription>,
+    universe: Option<Res<UniverseSettings>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    let anchor = universe.map_or_else(WorldPosition::default, |universe| WorldPosition::from_position(universe.start));
+    let at = |position: (f64, f64, f64)| {
+        let mut world = anchor;
+        world.translate(meters(position));
+        world
+    };
+
+    let mut focus = anchor;
+    focus.translate(meters(scene.camera.focus));
+    let eye = meters(scene.camera.eye) - meters(scene.camera.focus);
+    // An eye on the focus has no direction to look from, so it looks level.
+    let pitch = if eye.length() > 0.0 { (eye.y / eye.length()).asin() } else { 0.0 };
+    camera::spawn_camera(&mut commands, CameraController::orbiting(focus, eye.x.atan2(eye.z), pitch, eye.length()));
+
+    for object in scene.objects.iter() {
+        let mesh = match object.shape {
+            Shape::Plane { size }    => Mesh::from(shape::Plane { size }),
+            Shape::Cube { size }     => Mesh::from(shape::Cube { size }),
+            Shape::Sphere { radius } => Mesh::from(shape::Icosphere { radius, subdivisions: 4 })
+        };
+        let (red, green, blue) = object.color;
+        commands.spawn_bundle(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(Color::rgb(red, green, blue).into()),
+            ..Default::default()
+        })
+            .insert(at(object.position))
+            .insert(WorldScale::uniform(1.0));
+    }
+    for light in scene.lights.iter() {
+        commands.spawn_bundle(LightBundle {
+            light: Light { intensity: light.intensity, range: light.range, ..Default::default() },
+            ..Default::default()
+        })
+            .insert(at(light.position));
+    }
+}
+
+/// The approximate colour of a blackbody, after Tanner Helland's fit to the CIE data.
+pub fn blackbody_color(temperature: Temperature) -> Color {
+    let t = (temperature.in_kelvin() / 100.0).clamp(10.0, 400.0);
+    let red = if t <= 66.0 { 255.0 } else { 329.7 * (t - 60.0).powf(-0.133_2) };
+    let green = if t <= 66.0 { 99.47 * t.ln() - 161.12 } else { 288.12 * (t - 60.0).powf(-0.075_5) };
+    let blue = if t >= 66.0 { 255.0 } else if t <= 19.0 { 0.0 } else { 138.52 * (t - 10.0).ln() - 305.04 };
+    Color::rgb(red.clamp(0.0, 255.0) / 255.0,
+               green.clamp(0.0, 255.0) / 255.0,
+               blue.clamp(0.0, 255.0) / 255.0)
+}
+
+pub fn planet_color(kind: PlanetKind) -> Color {
+    match kind {
+        PlanetKind::Rocky      => Color::rgb(0.55, 0.45, 0.35),
+        PlanetKind::SuperEarth => Color::rgb(0.35, 0.5, 0.4),
+        PlanetKind::IceGiant   => Color::rgb(0.5, 0.7, 0.9),
+        PlanetKind::GasGiant   => Color::rgb(0.8, 0.65, 0.45)
+    }
+}
+
+/// The true radius of a body, or a minimum apparent size if that would be too small to see.
+pub fn visible_radius(radius: f64, scale: RenderScale) -> f64 {
+    radius.max(scale.meters_per_unit * MIN_APPARENT_SIZE_UNITS)
+}
+
+/// A unit mesh scaled to a body's radius.
+pub struct BodyVisual {
+    pub radius: f64
+}
+
+pub fn add_star_visuals(
+    mut commands: Commands,
+    stars: Query<(Entity, &Star), Added<Star>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    for (entity, star) in stars.iter() {
+        let color = blackbody_color(star.temperature);
+        commands.entity(entity)
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Icosphere { radius: 1.0, subdivisions: 3 })),
+                material: materials.add(StandardMaterial { base_color: color, emissive: color, unlit: true, ..Default::default() }),
+                ..Default::default()
+            })
+            .insert(BodyVisual { radius: star.radius.in_meters() })
+            .insert(WorldScale::uniform(star.radius.in_meters()));
+    }
+}
+
+pub fn add_planet_visuals(
+    mut commands: Commands,
+    planets: Query<(Entity, &Planet), Added<Planet>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    for (entity, planet) in planets.iter() {
+        commands.entity(entity)
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Icosphere { radius: 1.0, subdivisions: 3 })),
+                material: materials.add(planet_color(planet.kind).into()),
+                ..Default::default()
+            })
+            .insert(BodyVisual { radius: planet.radius.in_meters() })
+            .insert(WorldScale::uniform(planet.radius.in_meters()));
+    }
+}
+
+/// Ships are drawn as a box twice as long as it is wide, pointing along their engines' thrust.
+pub fn add_ship_visuals(
+    mut commands: Commands,
+    ships: Query<(Entity, &Ship), Added<Ship>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    for (entity, ship) in ships.iter() {
+        commands.entity(entity)
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(1.0, 1.0, 2.0))),
+                material: materials.add(Color::rgb(0.8, 0.8, 0.85).into()),
+                ..Default::default()
+            })
+            .insert(BodyVisual { radius: ship.hull.radius.in_meters() })
+            .insert(WorldScale::uniform(ship.hull.radius.in_meters()));
+    }
+}
+
+pub fn orient_ship_visuals(mut ships: Query<(&Attitude, &mut Transform)>) {
+    for (attitude, mut transform) in ships.iter_mut() {
+        let rotation = attitude.rotation;
+        transform.rotation = Quat::from_xyzw(rotation.x as f32, rotation.y as f32, rotation.z as f32, rotation.w as f32);
+    }
+}
+
+pub fn scale_body_visuals(scale: Res<RenderScale>, mut bodies: Query<(&BodyVisual, &mut WorldScale)>) {
+    for (visual, mut world_scale) in bodies.iter_mut() {
+        *world_scale = WorldScale::uniform(visible_radius(visual.radius, *scale));
+    }
+}
+
+/// Draws the `SceneDescription` resource, and the stars, planets and ships of the universe.
+/// Without a `SceneDescription` the `STARTUP_SCENE` is loaded.
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        if app.world().get_resource::<SceneDescription>().is_none() {
+            let scene = SceneDescription::load(asset_path(STARTUP_SCENE)).unwrap_or_else(|error| {
+                error!("{}", error);
+                SceneDescription::default()
+            });
+            app.insert_resource(scene);
+        }
+        app.add_startup_system(spawn_scene.system())
+            .add_system(add_star_visuals.system())
+            .add_system(add_planet_visuals.system())
+            .add_system(add_ship_visuals.system())
+            .add_system(orient_ship_visuals.system())
+            .add_system(scale_body_visuals.system());
+    }
+}
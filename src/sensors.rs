@@ -0,0 +1,416 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use std::collections::{BTreeMap, VecDeque};
+
+use super::clock::{ClockSystem, GameClock, SimulationStage, SECONDS_PER_DAY, SECONDS_PER_YEAR};
+use super::flight::{FlightSystem, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::length;
+use super::length::Length;
+use super::market::Market;
+use super::planet::Planet;
+use super::power;
+use super::power::Power;
+use super::ship::{Ship, Subsystem};
+use super::thermal::ShipThermal;
+
+/// A sensor's range is how far it detects something giving off this many watts.
+pub const REFERENCE_SIGNATURE_WATTS: f64 = 1.0e6;
+/// Of a station, from its reactors, docks and the ships about it.
+pub const STATION_SIGNATURE_WATTS: f64 = 1.0e9;
+/// Of a station, for telling it apart when it is not on a body.
+pub const STATION_RADIUS_METERS: f64 = 500.0;
+/// A station's own sensors.
+pub const STATION_SENSOR_RANGE_METERS: f64 = 1.5e12;
+pub const STATION_SENSOR_RESOLUTION: f64 = 1.0e-6;
+/// The least time between the states kept of anything's past; the latest is always kept as well.
+pub const SAMPLE_SECONDS: f64 = 60.0;
+/// How far back anything's past is kept, which is the furthest, in light time, it can be seen from.
+pub const HISTORY_SECONDS: f64 = SECONDS_PER_DAY;
+/// An observer forgets a contact it has not seen for this long.
+pub const FORGET_SECONDS: f64 = SECONDS_PER_YEAR;
+
+/// How far a sensor of `range` detects something giving off `signature`; its flux falls with the square of distance.
+pub fn detection_range(range: Length, signature: Power) -> Length {
+    range * (signature.in_watts().max(0.0) / REFERENCE_SIGNATURE_WATTS).sqrt()
+}
+
+/// How far a sensor of `resolution` radians tells apart something of `radius`; further off, it is smaller than
+/// what the sensor resolves.
+pub fn resolvable_range(resolution: f64, radius: Length) -> Length {
+    radius * (2.0 / resolution)
+}
+
+/// Whether a sensor picks out something of `radius` giving off `signature` at `distance`. Once it is too small
+/// to resolve, its light is spread over the whole of what the sensor resolves, so it has to be brighter to stand out:
+/// beyond its resolvable range, what it needs grows with the fourth power of distance rather than the square.
+pub fn detectable(sensing: &Sensing, signature: Power, radius: Length, distance: Length) -> bool {
+    let reach = detection_range(sensing.range, signature);
+    let resolvable = resolvable_range(sensing.resolution, radius);
+    distance <= reach && (distance <= resolvable || distance / reach <= resolvable / distance)
+}
+
+/// What something gives off for others to detect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature(pub Power);
+
+impl Signature {
+    /// The heat a ship radiates, and the plume of its drive as it thrusts.
+    pub fn of_ship(ship: &Ship, thermal: Option<&ShipThermal>, controls: Option<&ShipControls>) -> Signature {
+        let throttle = controls.map_or(0.0, |controls| controls.throttle.clamp(0.0, 1.0));
+        let heat = thermal.map_or_else(|| ship.waste_heat(throttle), |thermal| thermal.heat_out);
+        let plume: Power = ship.engines.iter().map(|engine| engine.power).sum::<Power>() * (throttle * ship.working(Subsystem::Engines));
+        Signature((heat + plume).to_scale(power::Scale::Megawatt))
+    }
+}
+
+/// How well something sees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sensing {
+    /// Against `REFERENCE_SIGNATURE_WATTS`.
+    pub range: Length,
+    /// The smallest angle it tells apart, in radians.
+    pub resolution: f64
+}
+
+impl Sensing {
+    pub fn of_ship(ship: &Ship) -> Sensing {
+        Sensing { range: ship.sensor_range(), resolution: ship.sensor_resolution() }
+    }
+
+    pub fn station() -> Sensing {
+        Sensing { range: Length::m(STATION_SENSOR_RANGE_METERS), resolution: STATION_SENSOR_RESOLUTION }
+    }
+}
+
+/// Something's state at a moment, as the light leaving it then shows it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    pub position: WorldPosition,
+    /// In m s⁻¹.
+    pub velocity: DVec3,
+    pub signature: Power
+}
+
+/// Something's recent past, for observers far enough away to be seeing it late.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History(pub VecDeque<Sample>);
+
+impl History {
+    /// Keeps a sample, thinning those before it to one every `SAMPLE_SECONDS` and dropping any older than `HISTORY_SECONDS`.
+    pub fn record(&mut self, sample: Sample) {
+        let thin = match (self.0.len().checked_sub(2).and_then(|index| self.0.get(index)), self.0.back()) {
+            (Some(before), Some(_)) => sample.time - before.time < SAMPLE_SECONDS,
+            _                       => false
+        };
+        if thin {
+            self.0.pop_back();
+        }
+        self.0.push_back(sample);
+        while matches!(self.0.front(), Some(oldest) if oldest.time < sample.time - HISTORY_SECONDS) {
+            self.0.pop_front();
+        }
+    }
+
+    /// The state at `time`, between the samples either side of it; carried on from the latest if it is later,
+    /// and `None` if it is earlier than anything kept.
+    pub fn at(&self, time: f64) -> Option<Sample> {
+        // The first sample after `time`, by bisection.
+        let (mut index, mut end) = (0, self.0.len());
+        while index < end {
+            let middle = (index + end) / 2;
+            if self.0[middle].time <= time { index = middle + 1; } else { end = middle; }
+        }
+        if index == 0 {
+            return None;
+        }
+        let before = self.0[index - 1];
+        let after = match self.0.get(index) {
+            Some(after) => *after,
+            None        => {
+                let mut position = before.position;
+                position.translate(before.velocity * (time - before.time));
+                return Some(Sample { time, position, ..before });
+            }
+        };
+        // Cubic Hermite interpolation, exact for constant acceleration.
+        let span = after.time - before.time;
+        let s = (time - before.time) / span;
+        let offset = after.position.relative_to(before.position);
+        let h10 = s * (1.0 - s) * (1.0 - s);
+        let h01 = s * s * (3.0 - 2.0 * s);
+        let h11 = s * s * (s - 1.0);
+        let mut position = before.position;
+        position.translate(before.velocity * (h10 * span) + offset * h01 + after.velocity * (h11 * span));
+        Some(Sample {
+            time,
+            position,
+            velocity: before.velocity.lerp(after.velocity, s),
+            signature: if s < 0.5 { before.signature } else { after.signature }
+        })
+    }
+}
+
+/// What an observer knows of something: as it was when the light it saw left it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub position: WorldPosition,
+    pub velocity: DVec3,
+    pub signature: Power,
+    /// When the light it was last seen by left it.
+    pub seen_at: f64,
+    /// How far off its position may be, from the observer's resolution.
+    pub uncertainty: Length,
+    /// Whether it is in sight now, rather than remembered.
+    pub detected: bool
+}
+
+impl Contact {
+    /// How old the observer's picture of it is, in seconds.
+    pub fn age(&self, now: f64) -> f64 {
+        now - self.seen_at
+    }
+
+    /// Where it would be now had it kept its course since it was seen.
+    pub fn projected_position(&self, now: f64) -> WorldPosition {
+        let mut position = self.position;
+        position.translate(self.velocity * self.age(now));
+        position
+    }
+}
+
+/// Everything an observer has seen, with what it last knew of those it no longer sees.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contacts(pub BTreeMap<Entity, Contact>);
+
+impl Contacts {
+    pub fn is_detected(&self, entity: Entity) -> bool {
+        matches!(self.0.get(&entity), Some(contact) if contact.detected)
+    }
+
+    pub fn detected(&self) -> impl Iterator<Item = (&Entity, &Contact)> {
+        self.0.iter().filter(|(_, contact)| contact.detected)
+    }
+}
+
+type EmittingShip<'a> = (Entity, &'a Ship, Option<&'a ShipThermal>, Option<&'a ShipControls>, Option<&'a mut Signature>);
+
+/// Updates the signatures of ships, and gives stations theirs.
+pub fn emit_signatures(
+    mut commands: Commands,
+    mut ships: Query<EmittingShip>,
+    stations: Query<Entity, (With<Market>, Without<Signature>)>
+) {
+    for (entity, ship, thermal, controls, signature) in ships.iter_mut() {
+        let emitted = Signature::of_ship(ship, thermal, controls);
+        match signature {
+            Some(mut signature) => *signature = emitted,
+            None                => { commands.entity(entity).insert(emitted); }
+        }
+    }
+    for entity in stations.iter() {
+        commands.entity(entity).insert(Signature(Power::scaled(STATION_SIGNATURE_WATTS / power::MEGAWATTS_TO_WATTS, power::Scale::Megawatt)));
+    }
+}
+
+type Emitter<'a> = (Entity, &'a Signature, &'a WorldPosition, Option<&'a Velocity>, Option<&'a mut History>);
+
+/// Keeps the past of everything with a signature.
+pub fn record_histories(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut emitters: Query<Emitter>
+) {
+    for (entity, signature, position, velocity, history) in emitters.iter_mut() {
+        let sample = Sample { time: clock.seconds, position: *position, velocity: velocity.map_or(DVec3::ZERO, |velocity| velocity.0), signature: signature.0 };
+        match history {
+            Some(mut history) => history.record(sample),
+            None              => {
+                let mut history = History::default();
+                history.record(sample);
+                commands.entity(entity).insert(history);
+            }
+        }
+    }
+}
+
+/// Where something was when the light reaching `observer` now left it, found by iterating on the light time.
+pub fn seen_from(observer: WorldPosition, history: &History, now: f64) -> Option<Sample> {
+    let mut sample = history.at(now)?;
+    for _ in 0..4 {
+        sample = history.at(now - sample.position.distance(observer).light_time())?;
+    }
+    Some(sample)
+}
+
+type Observer<'a> = (Entity, &'a WorldPosition, Option<&'a Ship>, Option<&'a Market>, Option<&'a mut Contacts>);
+/// Ships, and stations, which are at markets.
+type SeesAnything = Or<(With<Ship>, With<Market>)>;
+
+/// Lets every ship and station see what its sensors pick out, as it was a light time ago,
+/// and remember the last of what it has lost sight of.
+pub fn detect(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    emitters: Query<(Entity, &History, Option<&Ship>, Option<&Planet>)>,
+    mut observers: Query<Observer, SeesAnything>
+) {
+    let now = clock.seconds;
+    for (entity, position, ship, market, contacts) in observers.iter_mut() {
+        let sensing = match (ship, market) {
+            (Some(ship), _) => Sensing::of_ship(ship),
+            (None, Some(_)) => Sensing::station(),
+            (None, None)    => continue
+        };
+        let mut seen = Contacts::default();
+        let mut contacts = match contacts {
+            Some(contacts) => contacts,
+            None           => {
+                if sensing.range > length::ZERO {
+                    commands.entity(entity).insert(Contacts::default());
+                }
+                continue;
+            }
+        };
+        for (other, history, other_ship, planet) in emitters.iter() {
+            if other == entity {
+                continue;
+            }
+            let radius = match (other_ship, planet) {
+                (Some(ship), _)      => ship.hull.radius,
+                (None, Some(planet)) => planet.radius,
+                (None, None)         => Length::m(STATION_RADIUS_METERS)
+            };
+            let sample = match seen_from(*position, history, now) {
+                Some(sample) => sample,
+                None         => continue
+            };
+            let distance = sample.position.distance(*position);
+            if detectable(&sensing, sample.signature, radius, distance) {
+                seen.0.insert(other, Contact {
+                    position: sample.position,
+                    velocity: sample.velocity,
+                    signature: sample.signature,
+                    seen_at: sample.time,
+                    uncertainty: distance * sensing.resolution,
+                    detected: true
+                });
+            }
+        }
+        for (other, contact) in contacts.0.iter() {
+            if !seen.0.contains_key(other) && contact.age(now) < FORGET_SECONDS {
+                seen.0.insert(*other, Contact { detected: false, ..*contact });
+            }
+        }
+        if *contacts != seen {
+            *contacts = seen;
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum SensorSystem {
+    Record,
+    Detect
+}
+
+/// Ships and stations seeing one another by what they give off, as they were when the light left them.
+/// Needs the `ClockPlugin` and `FlightPlugin`.
+pub struct SensorPlugin;
+
+impl Plugin for SensorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(SimulationStage, emit_signatures.system()
+                                .after(ClockSystem::Advance)
+                                .before(SensorSystem::Record))
+            .add_system_to_stage(SimulationStage, record_histories.system()
+                                 .label(SensorSystem::Record)
+                                 .after(FlightSystem::Fly))
+            .add_system_to_stage(SimulationStage, detect.system()
+                                 .label(SensorSystem::Detect)
+                                 .after(SensorSystem::Record));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ship::courier;
+
+    fn sample(time: f64, velocity: f64) -> Sample {
+        Sample {
+            time,
+            position: WorldPosition::from_meters(DVec3::new(velocity * time, 0.0, 0.0)),
+            velocity: DVec3::new(velocity, 0.0, 0.0),
+            signature: Power::megawatts(1.0)
+        }
+    }
+
+    #[test]
+    fn histories_give_the_state_at_any_time_kept() {
+        let mut history = History::default();
+        for step in 0..=100 {
+            history.record(sample(step as f64 * 10.0, 1000.0));
+        }
+        assert_eq!(history.at(-1.0), None);
+        for time in [0.0, 5.0, 333.0, 990.0, 1000.0, 1234.0].iter() {
+            let at = history.at(*time).unwrap();
+            assert!((at.position.relative_to(WorldPosition::default()).x - 1000.0 * time).abs() < 1.0e-6, "at {}", time);
+        }
+        assert!(history.0.len() < 30);
+    }
+
+    #[test]
+    fn light_shows_things_as_they_were() {
+        let mut history = History::default();
+        for step in 0..=1000 {
+            history.record(sample(step as f64, 1.0e5));
+        }
+        let observer = WorldPosition::from_meters(DVec3::new(-length::LIGHT_SECONDS_TO_METERS * 100.0, 0.0, 0.0));
+        let seen = seen_from(observer, &history, 1000.0).unwrap();
+        let delay = seen.position.distance(observer).light_time();
+        assert!((seen.time + delay - 1000.0).abs() < 1.0e-6);
+        assert!(seen.time < 900.0);
+    }
+
+    #[test]
+    fn things_too_small_to_resolve_must_be_brighter_to_see() {
+        let sensing = Sensing::of_ship(&courier());
+        let radius = Length::m(12.0);
+        let bright = Power::megawatts(100.0);
+        let resolvable = resolvable_range(sensing.resolution, radius);
+        assert!(detection_range(sensing.range, bright) > resolvable * 1000.0);
+        assert!(detectable(&sensing, bright, radius, resolvable));
+        assert!(detectable(&sensing, bright, radius, Length::m(1.0e9)));
+        assert!(!detectable(&sensing, bright, radius, Length::m(1.0e11)));
+        assert!(detectable(&sensing, bright, Length::m(12.0e3), Length::m(1.0e11)));
+        assert!(!detectable(&sensing, Power::watts(1.0), Length::m(12.0e3), Length::m(1.0e11)));
+    }
+
+    #[test]
+    fn ships_see_each_other_within_reach() {
+        let mut world = World::default();
+        world.insert_resource(GameClock::default());
+        let at = |x: f64| WorldPosition::from_meters(DVec3::new(x, 0.0, 0.0));
+        let observer = world.spawn().insert_bundle((courier(), at(0.0))).id();
+        let near = world.spawn().insert_bundle((courier(), at(1.0e3))).id();
+        let unresolved = world.spawn().insert_bundle((courier(), at(3.0e9))).id();
+        let far = world.spawn().insert_bundle((courier(), at(3.0e10))).id();
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(emit_signatures.system().before(SensorSystem::Record))
+            .add_system(record_histories.system().label(SensorSystem::Record))
+            .add_system(detect.system().after(SensorSystem::Record));
+        // Nothing is seen until light has had time to arrive from what has been recorded.
+        for _ in 0..4 {
+            stage.run(&mut world);
+            world.get_resource_mut::<GameClock>().unwrap().seconds += SAMPLE_SECONDS;
+        }
+        let contacts = world.get::<Contacts>(observer).unwrap();
+        assert!(contacts.is_detected(near));
+        assert!(contacts.is_detected(unresolved));
+        assert!(!contacts.is_detected(far));
+        assert_eq!(contacts.0.len(), 2);
+        assert!(!contacts.is_detected(observer));
+    }
+}
@@ -0,0 +1,755 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use super::force;
+use super::force::Force;
+use super::length;
+use super::length::Length;
+use super::mass;
+use super::mass::Mass;
+use super::power;
+use super::power::Power;
+use super::radiation::STEFAN_BOLTZMANN_CONSTANT;
+use super::temperature;
+use super::temperature::Temperature;
+
+/// The design the player starts with, relative to the assets directory.
+pub const STARTER_SHIP: &str = "ships/courier.ron";
+/// Of the hull's structure, in J kg⁻¹ K⁻¹, about that of aluminium.
+pub const HULL_SPECIFIC_HEAT: f64 = 900.0;
+/// The fraction of a reactor's heat it turns into electricity; the rest is waste.
+pub const REACTOR_EFFICIENCY: f64 = 0.5;
+/// The fraction of an engine's power that stays in the ship as heat rather than leaving in the exhaust.
+pub const ENGINE_HEAT_FRACTION: f64 = 0.01;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hull {
+    pub name: String,
+    pub mass: Mass,
+    /// Of the sphere the ship's mass is treated as spread through, and the lever arm of its thrusters.
+    pub radius: Length,
+    /// The most acceleration the structure takes, in m s⁻².
+    pub max_acceleration: f64
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Engine {
+    pub name: String,
+    pub mass: Mass,
+    pub thrust: Force,
+    /// Seconds; the exhaust velocity divided by standard gravity.
+    pub specific_impulse: f64,
+    /// Drawn while the engine fires.
+    pub power: Power
+}
+
+impl Engine {
+    /// In m s⁻¹.
+    pub fn exhaust_velocity(&self) -> f64 {
+        self.specific_impulse * force::STANDARD_GRAVITY
+    }
+}
+
+/// Reaction control thrusters, for turning and small translations. They burn the main fuel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Thrusters {
+    pub name: String,
+    pub mass: Mass,
+    /// Along any one axis.
+    pub thrust: Force,
+    pub specific_impulse: f64
+}
+
+impl Thrusters {
+    pub fn exhaust_velocity(&self) -> f64 {
+        self.specific_impulse * force::STANDARD_GRAVITY
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FuelTank {
+    /// Empty.
+    pub mass: Mass,
+    pub capacity: Mass,
+    pub fuel: Mass
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reactor {
+    pub name: String,
+    pub mass: Mass,
+    pub output: Power
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CargoHold {
+    /// Empty.
+    pub mass: Mass,
+    pub capacity: Mass,
+    pub cargo: Mass
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sensor {
+    pub name: String,
+    pub mass: Mass,
+    /// How far it detects something giving off a megawatt.
+    pub range: Length,
+    /// The smallest angle it tells apart, in radians; none given is perfect.
+    #[serde(default)]
+    pub resolution: f64,
+    /// Drawn continuously.
+    pub power: Power
+}
+
+/// Panels that reject the ship's heat by radiating it to space.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Radiator {
+    pub name: String,
+    pub mass: Mass,
+    /// Of radiating surface, both faces together, in m².
+    pub area: f64,
+    pub emissivity: f64,
+    /// The hottest the coolant through them can run.
+    pub max_temperature: Temperature
+}
+
+/// Thermal mass that soaks up heat faster than the radiators can reject it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeatSink {
+    pub name: String,
+    pub mass: Mass,
+    /// In J K⁻¹.
+    pub heat_capacity: f64
+}
+
+/// How a weapon delivers its energy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    /// A gun or mass driver; its rounds hit with their kinetic energy.
+    Kinetic {
+        /// In m s⁻¹, relative to the ship.
+        muzzle_velocity: f64,
+        projectile: Mass
+    },
+    /// A pulsed laser, spreading by diffraction from its aperture.
+    Laser {
+        beam: Power,
+        /// Seconds.
+        pulse: f64,
+        aperture: Length,
+        wavelength: Length
+    },
+    /// Guided missiles that steer onto their target with a drive of their own.
+    Missile {
+        /// In m s⁻¹.
+        delta_v: f64,
+        /// In m s⁻².
+        acceleration: f64,
+        /// In joules.
+        warhead: f64,
+        magazine: u32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    pub mass: Mass,
+    pub kind: WeaponKind,
+    /// The furthest it is fired at.
+    pub range: Length,
+    /// The spread of its aim, in radians.
+    pub accuracy: f64,
+    /// Seconds between shots.
+    pub reload: f64,
+    /// Drawn while it reloads.
+    pub power: Power,
+    /// The game time it can next fire, in seconds.
+    #[serde(default)]
+    pub ready_at: f64
+}
+
+/// A field that takes hits on its charge and recharges from the reactors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shield {
+    pub name: String,
+    pub mass: Mass,
+    /// In joules.
+    pub capacity: f64,
+    pub charge: f64,
+    /// Drawn while it recharges, all of which goes into the charge.
+    pub recharge: Power
+}
+
+/// Plating that stops hits up to a threshold, and wears away as it does.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Armour {
+    pub name: String,
+    pub mass: Mass,
+    /// The energy of the heaviest hit it stops whole when intact, in joules.
+    pub threshold: f64,
+    /// The energy it absorbs before it is shot away, in joules.
+    pub capacity: f64,
+    pub integrity: f64
+}
+
+/// The parts of a ship that take damage together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Subsystem {
+    /// The structure and everything else in it; the ship is lost when it is destroyed.
+    Hull,
+    Engines,
+    Thrusters,
+    Reactors,
+    Sensors,
+    Radiators,
+    Weapons,
+    Shields
+}
+
+pub const SUBSYSTEMS: [Subsystem; 8] = [
+    Subsystem::Hull,
+    Subsystem::Engines,
+    Subsystem::Thrusters,
+    Subsystem::Reactors,
+    Subsystem::Sensors,
+    Subsystem::Radiators,
+    Subsystem::Weapons,
+    Subsystem::Shields
+];
+
+/// Why a ship design cannot fly as built.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DesignError {
+    NoEngines,
+    NoFuelTanks,
+    /// The reactors cannot run every engine and sensor at once.
+    InsufficientPower { supply: Power, demand: Power },
+    OverfilledTank { tank: usize, fuel: Mass, capacity: Mass },
+    OverloadedHold { hold: usize, cargo: Mass, capacity: Mass },
+    /// Full thrust on the empty ship would break the hull.
+    ExceedsHullLimit { acceleration: f64, limit: f64 },
+    /// At full thrust the radiators, at their hottest, cannot reject the waste heat.
+    InsufficientCooling { waste_heat: Power, rejection: Power }
+}
+
+impl fmt::Display for DesignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesignError::NoEngines                                 => write!(f, "the ship has no engines"),
+            DesignError::NoFuelTanks                               => write!(f, "the ship has no fuel tanks"),
+            DesignError::InsufficientPower { supply, demand }      =>
+                write!(f, "the reactors supply {:.1} but the ship needs {:.1}", supply, demand),
+            DesignError::OverfilledTank { tank, fuel, capacity }   =>
+                write!(f, "fuel tank {} holds {:.1} of {:.1}", tank, fuel, capacity),
+            DesignError::OverloadedHold { hold, cargo, capacity }  =>
+                write!(f, "cargo hold {} holds {:.1} of {:.1}", hold, cargo, capacity),
+            DesignError::ExceedsHullLimit { acceleration, limit }  =>
+                write!(f, "full thrust gives {:.1} m/s², more than the hull's {:.1} m/s²", acceleration, limit),
+            DesignError::InsufficientCooling { waste_heat, rejection } =>
+                write!(f, "full thrust makes {:.1} of heat but the radiators reject {:.1}", waste_heat, rejection)
+        }
+    }
+}
+
+impl std::error::Error for DesignError {}
+
+/// The reactors' supply against what the ship draws with everything running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerBudget {
+    pub supply: Power,
+    pub demand: Power
+}
+
+impl PowerBudget {
+    pub fn surplus(&self) -> Power {
+        self.supply - self.demand
+    }
+}
+
+/// Δv = vₑ ln(m₀ / m₁), the Tsiolkovsky rocket equation; in m s⁻¹.
+pub fn delta_v(exhaust_velocity: f64, initial_mass: Mass, final_mass: Mass) -> f64 {
+    exhaust_velocity * (initial_mass / final_mass).ln()
+}
+
+/// The propellant needed for a change in velocity, by the rocket equation.
+pub fn propellant_for(delta_v: f64, exhaust_velocity: f64, initial_mass: Mass) -> Mass {
+    initial_mass * (1.0 - (-delta_v / exhaust_velocity).exp())
+}
+
+/// A ship, from its parts. Everything else about it is derived.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ship {
+    pub name: String,
+    pub hull: Hull,
+    #[serde(default)]
+    pub engines: Vec<Engine>,
+    #[serde(default)]
+    pub thrusters: Vec<Thrusters>,
+    #[serde(default)]
+    pub fuel_tanks: Vec<FuelTank>,
+    #[serde(default)]
+    pub reactors: Vec<Reactor>,
+    #[serde(default)]
+    pub cargo_holds: Vec<CargoHold>,
+    #[serde(default)]
+    pub sensors: Vec<Sensor>,
+    #[serde(default)]
+    pub radiators: Vec<Radiator>,
+    #[serde(default)]
+    pub heat_sinks: Vec<HeatSink>,
+    #[serde(default)]
+    pub weapons: Vec<Weapon>,
+    #[serde(default)]
+    pub shields: Vec<Shield>,
+    #[serde(default)]
+    pub armour: Vec<Armour>,
+    /// The fraction of each subsystem destroyed, missing when it is intact.
+    #[serde(default)]
+    pub damage: BTreeMap<Subsystem, f64>
+}
+
+impl Ship {
+    pub fn from_ron(text: &str) -> Result<Ship, ron::Error> {
+        ron::de::from_str(text)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ship, ron::Error> {
+        Ship::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    /// Everything but fuel and cargo.
+    pub fn dry_mass(&self) -> Mass {
+        (self.hull.mass
+            + self.engines.iter().map(|engine| engine.mass).sum()
+            + self.thrusters.iter().map(|thrusters| thrusters.mass).sum()
+            + self.fuel_tanks.iter().map(|tank| tank.mass).sum()
+            + self.reactors.iter().map(|reactor| reactor.mass).sum()
+            + self.cargo_holds.iter().map(|hold| hold.mass).sum()
+            + self.sensors.iter().map(|sensor| sensor.mass).sum()
+            + self.radiators.iter().map(|radiator| radiator.mass).sum()
+            + self.heat_sinks.iter().map(|sink| sink.mass).sum()
+            + self.weapons.iter().map(|weapon| weapon.mass).sum()
+            + self.shields.iter().map(|shield| shield.mass).sum()
+            + self.armour.iter().map(|armour| armour.mass).sum())
+            .to_scale(mass::Scale::Tonne)
+    }
+
+    /// The fraction of a subsystem still working.
+    pub fn working(&self, subsystem: Subsystem) -> f64 {
+        1.0 - self.damage.get(&subsystem).copied().unwrap_or(0.0).clamp(0.0, 1.0)
+    }
+
+    /// The mass of a subsystem's parts, which is how likely a hit is to land on it.
+    pub fn subsystem_mass(&self, subsystem: Subsystem) -> Mass {
+        match subsystem {
+            Subsystem::Hull      => self.hull.mass
+                + self.fuel_tanks.iter().map(|tank| tank.mass).sum()
+                + self.cargo_holds.iter().map(|hold| hold.mass).sum()
+                + self.heat_sinks.iter().map(|sink| sink.mass).sum(),
+            Subsystem::Engines   => self.engines.iter().map(|engine| engine.mass).sum(),
+            Subsystem::Thrusters => self.thrusters.iter().map(|thrusters| thrusters.mass).sum(),
+            Subsystem::Reactors  => self.reactors.iter().map(|reactor| reactor.mass).sum(),
+            Subsystem::Sensors   => self.sensors.iter().map(|sensor| sensor.mass).sum(),
+            Subsystem::Radiators => self.radiators.iter().map(|radiator| radiator.mass).sum(),
+            Subsystem::Weapons   => self.weapons.iter().map(|weapon| weapon.mass).sum(),
+            Subsystem::Shields   => self.shields.iter().map(|shield| shield.mass).sum()
+        }
+    }
+
+    pub fn fuel(&self) -> Mass {
+        self.fuel_tanks.iter().map(|tank| tank.fuel).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    pub fn fuel_capacity(&self) -> Mass {
+        self.fuel_tanks.iter().map(|tank| tank.capacity).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    pub fn cargo(&self) -> Mass {
+        self.cargo_holds.iter().map(|hold| hold.cargo).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    pub fn total_mass(&self) -> Mass {
+        self.dry_mass() + self.fuel() + self.cargo()
+    }
+
+    /// What the engines give as they are, less any damage.
+    pub fn thrust(&self) -> Force {
+        (self.engines.iter().map(|engine| engine.thrust).sum::<Force>() * self.working(Subsystem::Engines))
+            .to_scale(force::Scale::Kilonewton)
+    }
+
+    /// The exhaust velocity of all engines firing together, in m s⁻¹: total thrust over total mass flow.
+    pub fn exhaust_velocity(&self) -> f64 {
+        let thrust: f64 = self.engines.iter().map(|engine| engine.thrust.in_newtons()).sum();
+        let mass_flow: f64 = self.engines.iter().map(|engine| engine.thrust.in_newtons() / engine.exhaust_velocity()).sum();
+        if mass_flow > 0.0 { thrust / mass_flow } else { 0.0 }
+    }
+
+    /// Propellant burnt per second at full thrust, in kg s⁻¹.
+    pub fn mass_flow(&self) -> f64 {
+        let exhaust_velocity = self.exhaust_velocity();
+        if exhaust_velocity > 0.0 { self.thrust().in_newtons() / exhaust_velocity } else { 0.0 }
+    }
+
+    /// The change in velocity the remaining fuel gives, in m s⁻¹.
+    pub fn delta_v(&self) -> f64 {
+        let total = self.total_mass();
+        delta_v(self.exhaust_velocity(), total, total - self.fuel())
+    }
+
+    /// At full thrust, in m s⁻².
+    pub fn acceleration(&self) -> f64 {
+        self.thrust().acceleration_of(self.total_mass())
+    }
+
+    /// At full thrust with the tanks dry, the most the ship ever accelerates, in m s⁻².
+    pub fn max_acceleration(&self) -> f64 {
+        self.thrust().acceleration_of(self.dry_mass() + self.cargo())
+    }
+
+    /// The reaction control thrust along any one axis.
+    pub fn thruster_thrust(&self) -> Force {
+        (self.thrusters.iter().map(|thrusters| thrusters.thrust).sum::<Force>() * self.working(Subsystem::Thrusters))
+            .to_scale(force::Scale::Kilonewton)
+    }
+
+    /// Propellant the reaction control thrusters burn per second firing along one axis, in kg s⁻¹.
+    pub fn thruster_mass_flow(&self) -> f64 {
+        self.thrusters.iter().map(|thrusters| thrusters.thrust.in_newtons() / thrusters.exhaust_velocity()).sum::<f64>()
+            * self.working(Subsystem::Thrusters)
+    }
+
+    /// In kg m², treating the ship as a uniform sphere of the hull's radius.
+    pub fn moment_of_inertia(&self) -> f64 {
+        0.4 * self.total_mass().in_kilograms() * self.hull.radius.in_meters().powi(2)
+    }
+
+    /// With the thrusters firing as a couple about one axis, in rad s⁻².
+    pub fn angular_acceleration(&self) -> f64 {
+        let inertia = self.moment_of_inertia();
+        if inertia > 0.0 { self.thruster_thrust().in_newtons() * self.hull.radius.in_meters() / inertia } else { 0.0 }
+    }
+
+    /// Seconds at full thrust to change velocity by `delta_v` m s⁻¹.
+    pub fn burn_time(&self, delta_v: f64) -> f64 {
+        let mass_flow = self.mass_flow();
+        if mass_flow > 0.0 {
+            propellant_for(delta_v, self.exhaust_velocity(), self.total_mass()).in_kilograms() / mass_flow
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    pub fn power_budget(&self) -> PowerBudget {
+        PowerBudget {
+            supply: (self.reactors.iter().map(|reactor| reactor.output).sum::<Power>() * self.working(Subsystem::Reactors))
+                .to_scale(power::Scale::Megawatt),
+            demand: (self.engines.iter().map(|engine| engine.power).sum::<Power>()
+                     + self.sensors.iter().map(|sensor| sensor.power).sum()
+                     + self.weapons.iter().map(|weapon| weapon.power).sum()
+                     + self.shields.iter().map(|shield| shield.recharge).sum())
+                .to_scale(power::Scale::Megawatt)
+        }
+    }
+
+    /// Heat the ship makes with its engines at `throttle`: the reactors' waste, the sensors' draw,
+    /// and what the engines keep of their own power.
+    pub fn waste_heat(&self, throttle: f64) -> Power {
+        let engines: Power = self.engines.iter().map(|engine| engine.power).sum::<Power>() * throttle;
+        let sensors: Power = self.sensors.iter().map(|sensor| sensor.power).sum();
+        ((engines + sensors) * (1.0 / REACTOR_EFFICIENCY - 1.0) + sensors + engines * ENGINE_HEAT_FRACTION)
+            .to_scale(power::Scale::Megawatt)
+    }
+
+    /// How far the best sensor sees, less any damage.
+    pub fn sensor_range(&self) -> Length {
+        let range = self.sensors.iter().map(|sensor| sensor.range).fold(length::ZERO, |best, range| if range > best { range } else { best });
+        range * self.working(Subsystem::Sensors)
+    }
+
+    /// The finest resolution of any sensor, coarsened by damage, in radians; infinite with the sensors wrecked.
+    pub fn sensor_resolution(&self) -> f64 {
+        self.sensors.iter().map(|sensor| sensor.resolution).fold(f64::INFINITY, f64::min) / self.working(Subsystem::Sensors)
+    }
+
+    /// Shield charge left, in joules.
+    pub fn shield_charge(&self) -> f64 {
+        self.shields.iter().map(|shield| shield.charge).sum()
+    }
+
+    /// The heat that warms the ship by a kelvin, in J K⁻¹.
+    pub fn heat_capacity(&self) -> f64 {
+        self.hull.mass.in_kilograms() * HULL_SPECIFIC_HEAT + self.heat_sinks.iter().map(|sink| sink.heat_capacity).sum::<f64>()
+    }
+
+    /// εσA summed over the radiators, in W K⁻⁴.
+    pub fn radiator_constant(&self) -> f64 {
+        self.radiators.iter().map(|radiator| radiator.emissivity * radiator.area).sum::<f64>()
+            * STEFAN_BOLTZMANN_CONSTANT * self.working(Subsystem::Radiators)
+    }
+
+    /// The radiators' rejection with their coolant at `temperature`.
+    pub fn heat_rejection(&self, temperature: Temperature) -> Power {
+        Power::watts(self.radiator_constant() * (temperature.in_kelvin() as f64).powi(4)).to_scale(power::Scale::Megawatt)
+    }
+
+    /// The hottest the ship can run, set by its least tolerant radiator, or by the crew without any.
+    pub fn max_temperature(&self) -> Temperature {
+        let mut limits = self.radiators.iter().map(|radiator| radiator.max_temperature);
+        match limits.next() {
+            Some(first) => limits.fold(first, |coolest, limit| if limit < coolest { limit } else { coolest }),
+            None        => temperature::BOILING_POINT_OF_WATER
+        }
+    }
+
+    /// Everything that stops the design flying; empty if it can.
+    pub fn validate(&self) -> Vec<DesignError> {
+        let mut errors = Vec::new();
+        if self.engines.is_empty() {
+            errors.push(DesignError::NoEngines);
+        } else if self.fuel_tanks.is_empty() {
+            errors.push(DesignError::NoFuelTanks);
+        }
+        let budget = self.power_budget();
+        if budget.demand > budget.supply {
+            errors.push(DesignError::InsufficientPower { supply: budget.supply, demand: budget.demand });
+        }
+        for (tank, FuelTank { fuel, capacity, .. }) in self.fuel_tanks.iter().enumerate() {
+            if fuel > capacity {
+                errors.push(DesignError::OverfilledTank { tank, fuel: *fuel, capacity: *capacity });
+            }
+        }
+        for (hold, CargoHold { cargo, capacity, .. }) in self.cargo_holds.iter().enumerate() {
+            if cargo > capacity {
+                errors.push(DesignError::OverloadedHold { hold, cargo: *cargo, capacity: *capacity });
+            }
+        }
+        let acceleration = self.thrust().acceleration_of(self.dry_mass());
+        if acceleration > self.hull.max_acceleration {
+            errors.push(DesignError::ExceedsHullLimit { acceleration, limit: self.hull.max_acceleration });
+        }
+        let waste_heat = self.waste_heat(1.0);
+        let rejection = self.heat_rejection(self.max_temperature());
+        if waste_heat > rejection {
+            errors.push(DesignError::InsufficientCooling { waste_heat, rejection });
+        }
+        errors
+    }
+
+    /// Room left in the cargo holds.
+    pub fn cargo_space(&self) -> Mass {
+        self.cargo_holds.iter().map(|hold| hold.capacity - hold.cargo).sum::<Mass>().to_scale(mass::Scale::Tonne)
+    }
+
+    /// Stows up to `amount` of cargo, filling the holds in order, and returns how much fitted.
+    pub fn load_cargo(&mut self, amount: Mass) -> Mass {
+        let mut remaining = amount;
+        for hold in self.cargo_holds.iter_mut() {
+            let space = hold.capacity - hold.cargo;
+            let stowed = if space < remaining { space } else { remaining };
+            if stowed > mass::ZERO {
+                hold.cargo = hold.cargo + stowed;
+                remaining = remaining - stowed;
+            }
+        }
+        amount - remaining
+    }
+
+    /// Takes up to `amount` of cargo out of the holds, last first, and returns how much there was.
+    pub fn unload_cargo(&mut self, amount: Mass) -> Mass {
+        let mut remaining = amount;
+        for hold in self.cargo_holds.iter_mut().rev() {
+            let taken = if hold.cargo < remaining { hold.cargo } else { remaining };
+            hold.cargo = hold.cargo - taken;
+            remaining = remaining - taken;
+        }
+        amount - remaining
+    }
+
+    /// Burns up to `amount` of fuel, emptying the tanks in order, and returns how much was burnt.
+    pub fn consume_fuel(&mut self, amount: Mass) -> Mass {
+        let mut remaining = amount;
+        for tank in self.fuel_tanks.iter_mut() {
+            let burnt = if tank.fuel < remaining { tank.fuel } else { remaining };
+            tank.fuel = tank.fuel - burnt;
+            remaining = remaining - burnt;
+        }
+        amount - remaining
+    }
+}
+
+/// The starter ship, for tests throughout the crate.
+#[cfg(test)]
+pub fn courier() -> Ship {
+    Ship::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ships/courier.ron")).unwrap()
+}
+
+/// A ship's derived figures, kept up to date as it changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShipStats {
+    pub total_mass: Mass,
+    pub thrust: Force,
+    pub delta_v: f64,
+    pub acceleration: f64,
+    pub power: PowerBudget,
+    pub valid: bool
+}
+
+impl ShipStats {
+    pub fn of(ship: &Ship) -> ShipStats {
+        ShipStats {
+            total_mass: ship.total_mass(),
+            thrust: ship.thrust(),
+            delta_v: ship.delta_v(),
+            acceleration: ship.acceleration(),
+            power: ship.power_budget(),
+            valid: ship.validate().is_empty()
+        }
+    }
+}
+
+pub fn update_ship_stats(mut commands: Commands, ships: Query<(Entity, &Ship), Changed<Ship>>) {
+    for (entity, ship) in ships.iter() {
+        commands.entity(entity).insert(ShipStats::of(ship));
+    }
+}
+
+/// Ships and their derived figures.
+pub struct ShipPlugin;
+
+impl Plugin for ShipPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(update_ship_stats.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frigate() -> Ship {
+        Ship::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ships/frigate.ron")).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1.0e-9 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn the_rocket_equation_gives_delta_v_and_propellant() {
+        let exhaust_velocity = 10000.0;
+        assert_eq!(delta_v(exhaust_velocity, Mass::t(100.0), Mass::t(100.0)), 0.0);
+        assert!(close(delta_v(exhaust_velocity, Mass::t(100.0 * std::f64::consts::E), Mass::t(100.0)), exhaust_velocity));
+        assert!(close(propellant_for(exhaust_velocity, exhaust_velocity, Mass::t(100.0)).in_grams(),
+                      Mass::t(100.0 * (1.0 - (-1.0_f64).exp())).in_grams()));
+
+        // The courier is 80.5 t dry with 60 t of fuel, burnt at a specific impulse of 20000 s.
+        let ship = courier();
+        let exhaust_velocity = 20000.0 * force::STANDARD_GRAVITY;
+        assert!(close(ship.exhaust_velocity(), exhaust_velocity));
+        assert!(close(ship.dry_mass().in_grams(), Mass::t(80.5).in_grams()));
+        assert!(close(ship.delta_v(), exhaust_velocity * (140.5_f64 / 80.5).ln()));
+        assert!(close(propellant_for(ship.delta_v(), ship.exhaust_velocity(), ship.total_mass()).in_grams(), ship.fuel().in_grams()));
+        assert!(close(ship.mass_flow(), 400.0e3 / exhaust_velocity));
+        assert!(close(ship.burn_time(ship.delta_v()), Mass::t(60.0).in_kilograms() / ship.mass_flow()));
+
+        let mut empty = ship.clone();
+        empty.consume_fuel(ship.fuel());
+        assert_eq!(empty.delta_v(), 0.0);
+        let mut wrecked = ship;
+        wrecked.damage.insert(Subsystem::Engines, 1.0);
+        assert_eq!(wrecked.burn_time(1.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn reactors_must_power_everything_at_once() {
+        let budget = courier().power_budget();
+        assert!(close(budget.supply.in_watts(), 350.0e6) && close(budget.demand.in_watts(), 305.0e6));
+        assert!(close(budget.surplus().in_watts(), 45.0e6));
+
+        // The frigate's weapons and shields draw on its reactor too.
+        let budget = frigate().power_budget();
+        assert!(close(budget.supply.in_watts(), 1.5e9) && close(budget.demand.in_watts(), 1.47e9));
+
+        let mut damaged = courier();
+        damaged.damage.insert(Subsystem::Reactors, 0.5);
+        assert!(close(damaged.power_budget().supply.in_watts(), 175.0e6));
+        assert_eq!(damaged.validate(), vec![DesignError::InsufficientPower {
+            supply: damaged.power_budget().supply,
+            demand: damaged.power_budget().demand
+        }]);
+    }
+
+    #[test]
+    fn broken_designs_say_what_is_wrong_with_them() {
+        assert_eq!(courier().validate(), vec![]);
+        assert_eq!(frigate().validate(), vec![]);
+
+        let broken = |change: &dyn Fn(&mut Ship)| {
+            let mut ship = courier();
+            change(&mut ship);
+            ship.validate()
+        };
+        assert_eq!(broken(&|ship| ship.engines.clear()), vec![DesignError::NoEngines]);
+        assert_eq!(broken(&|ship| ship.fuel_tanks.clear()), vec![DesignError::NoFuelTanks]);
+        assert!(matches!(broken(&|ship| ship.reactors[0].output = Power::megawatts(300.0)).as_slice(),
+                         [DesignError::InsufficientPower { .. }]));
+        assert!(matches!(broken(&|ship| ship.fuel_tanks[0].fuel = Mass::t(61.0)).as_slice(),
+                         [DesignError::OverfilledTank { tank: 0, .. }]));
+        assert!(matches!(broken(&|ship| ship.cargo_holds[0].cargo = Mass::t(21.0)).as_slice(),
+                         [DesignError::OverloadedHold { hold: 0, .. }]));
+        match broken(&|ship| ship.hull.max_acceleration = 1.0).as_slice() {
+            [DesignError::ExceedsHullLimit { acceleration, limit }] => {
+                assert!(close(*acceleration, 400.0e3 / Mass::t(80.5).in_kilograms()));
+                assert_eq!(*limit, 1.0);
+            }
+            errors => panic!("{:?}", errors)
+        }
+        assert!(matches!(broken(&|ship| ship.radiators[0].area = 100.0).as_slice(),
+                         [DesignError::InsufficientCooling { .. }]));
+    }
+
+    #[test]
+    fn cargo_and_fuel_fill_and_empty_their_tanks_in_order() {
+        let mut ship = courier();
+        let hold = ship.cargo_holds[0].clone();
+        ship.cargo_holds.push(hold);
+        assert!(close(ship.load_cargo(Mass::t(15.0)).in_grams(), Mass::t(15.0).in_grams()));
+        assert!(close(ship.load_cargo(Mass::t(30.0)).in_grams(), Mass::t(25.0).in_grams()));
+        assert!(close(ship.cargo_holds[0].cargo.in_grams(), Mass::t(20.0).in_grams()));
+        assert_eq!(ship.cargo_space().in_grams(), 0.0);
+        assert_eq!(ship.load_cargo(Mass::t(1.0)).in_grams(), 0.0);
+
+        // Unloading empties the last hold first.
+        assert!(close(ship.unload_cargo(Mass::t(25.0)).in_grams(), Mass::t(25.0).in_grams()));
+        assert_eq!(ship.cargo_holds[1].cargo.in_grams(), 0.0);
+        assert!(close(ship.cargo_holds[0].cargo.in_grams(), Mass::t(15.0).in_grams()));
+        assert!(close(ship.unload_cargo(Mass::t(100.0)).in_grams(), Mass::t(15.0).in_grams()));
+        assert_eq!(ship.cargo().in_grams(), 0.0);
+
+        let tank = ship.fuel_tanks[0].clone();
+        ship.fuel_tanks.push(tank);
+        assert!(close(ship.consume_fuel(Mass::t(70.0)).in_grams(), Mass::t(70.0).in_grams()));
+        assert_eq!(ship.fuel_tanks[0].fuel.in_grams(), 0.0);
+        assert!(close(ship.fuel_tanks[1].fuel.in_grams(), Mass::t(50.0).in_grams()));
+        assert!(close(ship.consume_fuel(Mass::t(100.0)).in_grams(), Mass::t(50.0).in_grams()));
+        assert_eq!(ship.fuel().in_grams(), 0.0);
+    }
+
+    #[test]
+    fn damaged_sensors_see_less_and_less_finely() {
+        let mut ship = courier();
+        assert_eq!(ship.sensor_resolution(), 1.0e-6);
+        ship.damage.insert(Subsystem::Sensors, 0.5);
+        assert!(close(ship.sensor_resolution(), 2.0e-6));
+        assert!(close(ship.sensor_range().in_meters(), 0.75e12));
+        ship.damage.insert(Subsystem::Sensors, 1.0);
+        assert_eq!(ship.sensor_resolution(), f64::INFINITY);
+        assert_eq!(ship.sensor_range().in_meters(), 0.0);
+    }
+}
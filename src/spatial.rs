@@ -0,0 +1,613 @@
+use bevy::math::DVec3;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::galaxy::GalaxyStar;
+use super::length;
+use super::length::Length;
+use super::position::Position;
+use super::star::SpectralType;
+
+/// How many entries a leaf holds before it is split into eight.
+const LEAF_CAPACITY: usize = 16;
+/// Stops coincident points from splitting forever.
+const MAX_DEPTH: u32 = 48;
+
+/// Anything that has a spectral type can be filtered by one.
+pub trait Spectral {
+    fn spectral_type(&self) -> SpectralType;
+}
+
+impl Spectral for GalaxyStar {
+    fn spectral_type(&self) -> SpectralType {
+        self.spectral_type
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry<T> {
+    pub position: Position,
+    pub item: T,
+    meters: DVec3
+}
+
+/// A query result, with its distance from the query point.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbour<'a, T> {
+    pub position: Position,
+    pub distance: Length,
+    pub item: &'a T
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Bounds {
+    center: DVec3,
+    half_size: f64
+}
+
+impl Bounds {
+    fn contains(&self, point: DVec3) -> bool {
+        let offset = (point - self.center).abs();
+        offset.x <= self.half_size && offset.y <= self.half_size && offset.z <= self.half_size
+    }
+
+    fn octant(&self, point: DVec3) -> usize {
+        (if point.x >= self.center.x { 1 } else { 0 })
+            | (if point.y >= self.center.y { 2 } else { 0 })
+            | (if point.z >= self.center.z { 4 } else { 0 })
+    }
+
+    fn child(&self, octant: usize) -> Bounds {
+        let quarter = self.half_size / 2.0;
+        let sign = |bit: usize| if octant & bit != 0 { quarter } else { -quarter };
+        Bounds {
+            center: self.center + DVec3::new(sign(1), sign(2), sign(4)),
+            half_size: quarter
+        }
+    }
+
+    fn distance_squared(&self, point: DVec3) -> f64 {
+        let excess = ((point - self.center).abs() - DVec3::splat(self.half_size)).max(DVec3::ZERO);
+        excess.length_squared()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node<T> {
+    Leaf(Vec<Entry<T>>),
+    Branch(Box<[Node<T>; 8]>)
+}
+
+impl<T> Node<T> {
+    fn empty_branch() -> Node<T> {
+        Node::Branch(Box::new([
+            Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new()),
+            Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new()), Node::Leaf(Vec::new())
+        ]))
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(entries) => entries.len(),
+            Node::Branch(children) => children.iter().map(|child| child.len()).sum()
+        }
+    }
+
+    fn insert(&mut self, bounds: Bounds, depth: u32, entry: Entry<T>) {
+        match self {
+            Node::Branch(children) => {
+                let octant = bounds.octant(entry.meters);
+                children[octant].insert(bounds.child(octant), depth + 1, entry);
+            }
+            Node::Leaf(entries) => {
+                entries.push(entry);
+                if entries.len() > LEAF_CAPACITY && depth < MAX_DEPTH {
+                    let entries = std::mem::take(entries);
+                    *self = Node::empty_branch();
+                    for entry in entries {
+                        self.insert(bounds, depth, entry);
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove<F: Fn(&T) -> bool>(&mut self, bounds: Bounds, point: DVec3, matches: &F) -> Option<Entry<T>> {
+        let removed = match self {
+            Node::Leaf(entries) => {
+                let index = entries.iter().position(|entry| entry.meters == point && matches(&entry.item))?;
+                Some(entries.swap_remove(index))
+            }
+            Node::Branch(children) => {
+                let octant = bounds.octant(point);
+                children[octant].remove(bounds.child(octant), point, matches)
+            }
+        };
+        // Collapse branches whose children are all small leaves; this cascades upwards as the recursion unwinds.
+        let collapse = match self {
+            Node::Branch(children) => children.iter().all(|child| matches!(child, Node::Leaf(_)))
+                && self.len() <= LEAF_CAPACITY,
+            Node::Leaf(_) => false
+        };
+        if removed.is_some() && collapse {
+            let mut entries = Vec::with_capacity(LEAF_CAPACITY);
+            self.drain_into(&mut entries);
+            *self = Node::Leaf(entries);
+        }
+        removed
+    }
+
+    fn drain_into(&mut self, into: &mut Vec<Entry<T>>) {
+        match self {
+            Node::Leaf(entries) => into.append(entries),
+            Node::Branch(children) => children.iter_mut().for_each(|child| child.drain_into(into))
+        }
+    }
+
+    fn within<'a, F: Fn(&T) -> bool>(&'a self, bounds: Bounds, center: DVec3, radius_squared: f64,
+                                     filter: &F, found: &mut Vec<&'a Entry<T>>) {
+        if bounds.distance_squared(center) > radius_squared {
+            return;
+        }
+        match self {
+            Node::Leaf(entries) => found.extend(entries.iter().filter(|entry| {
+                (entry.meters - center).length_squared() <= radius_squared && filter(&entry.item)
+            })),
+            Node::Branch(children) => {
+                for (octant, child) in children.iter().enumerate() {
+                    child.within(bounds.child(octant), center, radius_squared, filter, found);
+                }
+            }
+        }
+    }
+
+    fn in_frustum<'a>(&'a self, bounds: Bounds, frustum: &Frustum, found: &mut Vec<&'a Entry<T>>) {
+        match frustum.classify(bounds) {
+            Containment::Outside => {}
+            Containment::Inside => self.for_each(&mut |entry| found.push(entry)),
+            Containment::Intersecting => match self {
+                Node::Leaf(entries) => found.extend(entries.iter().filter(|entry| frustum.contains(entry.meters))),
+                Node::Branch(children) => {
+                    for (octant, child) in children.iter().enumerate() {
+                        child.in_frustum(bounds.child(octant), frustum, found);
+                    }
+                }
+            }
+        }
+    }
+
+    fn for_each<'a>(&'a self, visit: &mut dyn FnMut(&'a Entry<T>)) {
+        match self {
+            Node::Leaf(entries) => entries.iter().for_each(visit),
+            Node::Branch(children) => children.iter().for_each(|child| child.for_each(visit))
+        }
+    }
+}
+
+/// A candidate in a nearest neighbour search, ordered by distance so the heap keeps the furthest on top.
+struct Candidate<'a, T> {
+    distance_squared: f64,
+    entry: &'a Entry<T>
+}
+
+impl<'a, T> PartialEq for Candidate<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance_squared == other.distance_squared
+    }
+}
+
+impl<'a, T> Eq for Candidate<'a, T> {}
+
+impl<'a, T> PartialOrd for Candidate<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T> Ord for Candidate<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared.partial_cmp(&other.distance_squared).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// An octree over positions, which grows to fit whatever is inserted into it.
+#[derive(Clone, Debug)]
+pub struct Octree<T> {
+    root: Node<T>,
+    bounds: Bounds,
+    len: usize
+}
+
+impl<T> Octree<T> {
+    /// An empty tree initially covering a cube around `center`.
+    pub fn new(center: Position, half_size: Length) -> Octree<T> {
+        Octree {
+            root: Node::Leaf(Vec::new()),
+            bounds: Bounds { center: center.in_meters(), half_size: half_size.in_meters() },
+            len: 0
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds an item at a position. Positions that are not finite have nowhere to go, and are left out.
+    pub fn insert(&mut self, position: Position, item: T) {
+        let meters = position.in_meters();
+        debug_assert!(meters.is_finite(), "cannot index the position {:?}", position);
+        if !meters.is_finite() {
+            return;
+        }
+        while !self.bounds.contains(meters) {
+            self.grow_towards(meters);
+        }
+        self.root.insert(self.bounds, 0, Entry { position, item, meters });
+        self.len += 1;
+    }
+
+    /// Doubles the root cube towards a point, keeping the old root as one of the new root's children.
+    fn grow_towards(&mut self, point: DVec3) {
+        let half_size = self.bounds.half_size;
+        let toward = |target: f64, center: f64| if target >= center { half_size } else { -half_size };
+        let grown = Bounds {
+            center: self.bounds.center + DVec3::new(toward(point.x, self.bounds.center.x),
+                                                    toward(point.y, self.bounds.center.y),
+                                                    toward(point.z, self.bounds.center.z)),
+            half_size: half_size * 2.0
+        };
+        let old_root = std::mem::replace(&mut self.root, Node::Leaf(Vec::new()));
+        if old_root.len() > 0 {
+            let mut branch = Node::empty_branch();
+            if let Node::Branch(children) = &mut branch {
+                children[grown.octant(self.bounds.center)] = old_root;
+            }
+            self.root = branch;
+        }
+        self.bounds = grown;
+    }
+
+    /// Removes the first entry at exactly `position` whose item satisfies `matches`.
+    pub fn remove_where<F: Fn(&T) -> bool>(&mut self, position: Position, matches: F) -> Option<T> {
+        let removed = self.root.remove(self.bounds, position.in_meters(), &matches)?;
+        self.len -= 1;
+        Some(removed.item)
+    }
+
+    pub fn remove(&mut self, position: Position, item: &T) -> Option<T> where T: PartialEq {
+        self.remove_where(position, |candidate| candidate == item)
+    }
+
+    /// Moves an item, e.g. a ship, from one position to another.
+    pub fn relocate(&mut self, from: Position, to: Position, item: &T) -> bool where T: PartialEq {
+        match self.remove(from, item) {
+            Some(item) => {
+                self.insert(to, item);
+                true
+            }
+            None => false
+        }
+    }
+
+    fn neighbour<'a>(entry: &'a Entry<T>, center: DVec3, scale: length::Scale) -> Neighbour<'a, T> {
+        Neighbour {
+            position: entry.position,
+            distance: Length::meters((entry.meters - center).length()).to_scale(scale),
+            item: &entry.item
+        }
+    }
+
+    /// Everything within `radius` of `center` that passes `filter`, nearest first.
+    pub fn within_filtered<F: Fn(&T) -> bool>(&self, center: Position, radius: Length, filter: F) -> Vec<Neighbour<'_, T>> {
+        let point = center.in_meters();
+        let mut found = Vec::new();
+        self.root.within(self.bounds, point, radius.in_meters().powi(2), &filter, &mut found);
+        let mut neighbours: Vec<Neighbour<T>> = found.into_iter()
+            .map(|entry| Octree::neighbour(entry, point, radius.scale()))
+            .collect();
+        neighbours.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(Ordering::Equal));
+        neighbours
+    }
+
+    pub fn within(&self, center: Position, radius: Length) -> Vec<Neighbour<'_, T>> {
+        self.within_filtered(center, radius, |_| true)
+    }
+
+    /// The `k` nearest entries to `center` that pass `filter`, nearest first.
+    pub fn nearest_filtered<F: Fn(&T) -> bool>(&self, center: Position, k: usize, filter: F) -> Vec<Neighbour<'_, T>> {
+        let point = center.in_meters();
+        let mut best: BinaryHeap<Candidate<T>> = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.nearest_in(&self.root, self.bounds, point, k, &filter, &mut best);
+        }
+        best.into_sorted_vec().into_iter()
+            .map(|candidate| Octree::neighbour(candidate.entry, point, center.x.scale()))
+            .collect()
+    }
+
+    pub fn nearest(&self, center: Position, k: usize) -> Vec<Neighbour<'_, T>> {
+        self.nearest_filtered(center, k, |_| true)
+    }
+
+    fn nearest_in<'a, F: Fn(&T) -> bool>(&'a self, node: &'a Node<T>, bounds: Bounds, point: DVec3, k: usize,
+                                         filter: &F, best: &mut BinaryHeap<Candidate<'a, T>>) {
+        let worst = |best: &BinaryHeap<Candidate<T>>| {
+            if best.len() < k { f64::INFINITY } else { best.peek().map_or(f64::INFINITY, |c| c.distance_squared) }
+        };
+        if bounds.distance_squared(point) > worst(best) {
+            return;
+        }
+        match node {
+            Node::Leaf(entries) => {
+                for entry in entries.iter().filter(|entry| filter(&entry.item)) {
+                    let distance_squared = (entry.meters - point).length_squared();
+                    if distance_squared < worst(best) {
+                        best.push(Candidate { distance_squared, entry });
+                        if best.len() > k {
+                            best.pop();
+                        }
+                    }
+                }
+            }
+            Node::Branch(children) => {
+                // Visit the nearest children first so the search radius shrinks quickly.
+                let mut order: Vec<(f64, usize)> = (0..8)
+                    .map(|octant| (bounds.child(octant).distance_squared(point), octant))
+                    .collect();
+                order.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+                for (_, octant) in order {
+                    self.nearest_in(&children[octant], bounds.child(octant), point, k, filter, best);
+                }
+            }
+        }
+    }
+
+    pub fn in_frustum(&self, frustum: &Frustum) -> Vec<&Entry<T>> {
+        let mut found = Vec::new();
+        self.root.in_frustum(self.bounds, frustum, &mut found);
+        found
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry<T>> {
+        let mut all = Vec::with_capacity(self.len);
+        self.root.for_each(&mut |entry| all.push(entry));
+        all.into_iter()
+    }
+}
+
+impl<T: Spectral> Octree<T> {
+    /// Everything within `radius` of `center` whose spectral type is one of `types`.
+    pub fn within_of_types(&self, center: Position, radius: Length, types: &[SpectralType]) -> Vec<Neighbour<'_, T>> {
+        self.within_filtered(center, radius, |item| types.contains(&item.spectral_type()))
+    }
+
+    /// The nearest `k` entries whose spectral type is one of `types`.
+    pub fn nearest_of_types(&self, center: Position, k: usize, types: &[SpectralType]) -> Vec<Neighbour<'_, T>> {
+        self.nearest_filtered(center, k, |item| types.contains(&item.spectral_type()))
+    }
+}
+
+/// A plane, with everything on the side its normal points to counting as inside.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: DVec3,
+    /// The plane's signed distance from the origin along its normal, in meters.
+    pub offset: f64
+}
+
+impl Plane {
+    pub fn through(point: DVec3, normal: DVec3) -> Plane {
+        let normal = normal.normalize();
+        Plane { normal, offset: -normal.dot(point) }
+    }
+
+    pub fn signed_distance(&self, point: DVec3) -> f64 {
+        self.normal.dot(point) + self.offset
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Containment {
+    Outside,
+    Intersecting,
+    Inside
+}
+
+/// A view frustum, as six inward facing planes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Plane; 6]
+}
+
+impl Frustum {
+    /// The frustum of a perspective camera at `eye` looking along `forward`.
+    /// `vertical_fov` is in radians and `aspect` is width over height.
+    pub fn perspective(eye: Position, forward: DVec3, up: DVec3, vertical_fov: f64, aspect: f64,
+                       near: Length, far: Length) -> Frustum {
+        let eye = eye.in_meters();
+        let forward = forward.normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        let half_height = (vertical_fov / 2.0).tan();
+        let half_width = half_height * aspect;
+        Frustum {
+            planes: [
+                Plane::through(eye + forward * near.in_meters(), forward),
+                Plane::through(eye + forward * far.in_meters(), -forward),
+                Plane::through(eye, (forward - right * half_width).cross(up)),
+                Plane::through(eye, up.cross(forward + right * half_width)),
+                Plane::through(eye, (forward + up * half_height).cross(right)),
+                Plane::through(eye, right.cross(forward - up * half_height))
+            ]
+        }
+    }
+
+    pub fn contains(&self, point: DVec3) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    pub fn contains_position(&self, position: Position) -> bool {
+        self.contains(position.in_meters())
+    }
+
+    fn classify(&self, bounds: Bounds) -> Containment {
+        let mut containment = Containment::Inside;
+        for plane in self.planes.iter() {
+            let reach = bounds.half_size * (plane.normal.x.abs() + plane.normal.y.abs() + plane.normal.z.abs());
+            let distance = plane.signed_distance(bounds.center);
+            if distance < -reach {
+                return Containment::Outside;
+            }
+            if distance < reach {
+                containment = Containment::Intersecting;
+            }
+        }
+        containment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::random::Rng;
+
+    const TYPES: [SpectralType; 4] = [SpectralType::G, SpectralType::K, SpectralType::M, SpectralType::L];
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Point(usize, SpectralType);
+
+    impl Spectral for Point {
+        fn spectral_type(&self) -> SpectralType {
+            self.1
+        }
+    }
+
+    /// A few hundred points scattered through a cube 100 ly across, in a tree that starts too small for them.
+    fn scatter(seed: u64) -> (Octree<Point>, Vec<(Position, Point)>) {
+        let mut rng = Rng::new(seed);
+        let points: Vec<(Position, Point)> = (0..400)
+            .map(|index| {
+                let mut coordinate = || rng.range_f64(-50.0..50.0);
+                let position = Position::scaled(coordinate(), coordinate(), coordinate(), length::Scale::LightYear);
+                (position, Point(index, TYPES[rng.below(TYPES.len() as u64) as usize]))
+            })
+            .collect();
+        let mut tree = Octree::new(Position::scaled(0.0, 0.0, 0.0, length::Scale::LightYear), Length::ly(1.0));
+        for (position, point) in points.iter() {
+            tree.insert(*position, *point);
+        }
+        (tree, points)
+    }
+
+    fn probes() -> Vec<Position> {
+        let mut rng = Rng::new(99);
+        (0..20).map(|_| {
+            let mut coordinate = || rng.range_f64(-60.0..60.0);
+            Position::scaled(coordinate(), coordinate(), coordinate(), length::Scale::LightYear)
+        }).collect()
+    }
+
+    /// The points passing `keep`, nearest `center` first.
+    fn brute_force(points: &[(Position, Point)], center: Position, keep: impl Fn(&Position, &Point) -> bool) -> Vec<usize> {
+        let mut kept: Vec<(f64, usize)> = points.iter()
+            .filter(|(position, point)| keep(position, point))
+            .map(|(position, point)| ((position.in_meters() - center.in_meters()).length(), point.0))
+            .collect();
+        kept.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        kept.into_iter().map(|(_, index)| index).collect()
+    }
+
+    fn indices(neighbours: &[Neighbour<Point>]) -> Vec<usize> {
+        neighbours.iter().map(|neighbour| neighbour.item.0).collect()
+    }
+
+    #[test]
+    fn the_tree_grows_to_hold_everything() {
+        let (tree, points) = scatter(1);
+        assert_eq!(tree.len(), points.len());
+        assert!(points.iter().all(|(position, _)| tree.bounds.contains(position.in_meters())));
+        let mut held: Vec<usize> = tree.iter().map(|entry| entry.item.0).collect();
+        held.sort_unstable();
+        assert_eq!(held, (0..points.len()).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn nearest_neighbours_match_a_brute_force_search() {
+        let (tree, points) = scatter(2);
+        for probe in probes() {
+            let all = brute_force(&points, probe, |_, _| true);
+            for k in [0, 1, 5, 40, 1000].iter() {
+                assert_eq!(indices(&tree.nearest(probe, *k)), all.iter().copied().take(*k).collect::<Vec<usize>>());
+            }
+            let cool = brute_force(&points, probe, |_, point| point.1 == SpectralType::M || point.1 == SpectralType::L);
+            assert_eq!(indices(&tree.nearest_of_types(probe, 7, &[SpectralType::M, SpectralType::L])),
+                       cool.into_iter().take(7).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn radius_queries_match_a_brute_force_search() {
+        let (tree, points) = scatter(3);
+        let radius = Length::ly(20.0);
+        for probe in probes() {
+            let near = |position: &Position| (position.in_meters() - probe.in_meters()).length() <= radius.in_meters();
+            let found = tree.within(probe, radius);
+            assert_eq!(indices(&found), brute_force(&points, probe, |position, _| near(position)));
+            assert!(found.iter().all(|neighbour| neighbour.distance <= radius));
+            assert_eq!(indices(&tree.within_of_types(probe, radius, &[SpectralType::G])),
+                       brute_force(&points, probe, |position, point| near(position) && point.1 == SpectralType::G));
+        }
+    }
+
+    #[test]
+    fn frustum_culling_matches_a_brute_force_search() {
+        let (tree, points) = scatter(4);
+        let eye = Position::scaled(-70.0, 5.0, 0.0, length::Scale::LightYear);
+        for forward in [DVec3::X, DVec3::new(1.0, 0.3, -0.2), DVec3::new(1.0, -1.0, 1.0)].iter() {
+            let frustum = Frustum::perspective(eye, *forward, DVec3::Y, 0.8, 1.5, Length::ly(1.0), Length::ly(110.0));
+            let mut culled: Vec<usize> = tree.in_frustum(&frustum).iter().map(|entry| entry.item.0).collect();
+            culled.sort_unstable();
+            let expected: Vec<usize> = points.iter()
+                .filter(|(position, _)| frustum.contains_position(*position))
+                .map(|(_, point)| point.0)
+                .collect();
+            assert!(!expected.is_empty() && expected.len() < points.len());
+            assert_eq!(culled, expected);
+        }
+    }
+
+    #[test]
+    fn removing_entries_collapses_the_tree() {
+        let (mut tree, points) = scatter(5);
+        assert!(matches!(tree.root, Node::Branch(_)));
+        let (position, point) = points[0];
+        assert_eq!(tree.remove(position, &Point(point.0, SpectralType::O)), None);
+        let moved = Position::scaled(1.0, 2.0, 3.0, length::Scale::LightYear);
+        assert!(tree.relocate(position, moved, &point));
+        assert_eq!(indices(&tree.nearest(moved, 1)), vec![point.0]);
+        assert_eq!(tree.remove(moved, &point), Some(point));
+
+        for (position, point) in points.iter().skip(1).take(points.len() - 1 - LEAF_CAPACITY) {
+            assert_eq!(tree.remove(*position, point), Some(*point));
+        }
+        assert_eq!(tree.len(), LEAF_CAPACITY);
+        assert!(matches!(tree.root, Node::Leaf(_)));
+        let left: Vec<(Position, Point)> = points[points.len() - LEAF_CAPACITY..].to_vec();
+        for probe in probes() {
+            assert_eq!(indices(&tree.nearest(probe, 3)), brute_force(&left, probe, |_, _| true).into_iter().take(3).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic)]
+    fn positions_that_are_not_finite_are_left_out() {
+        let mut tree = Octree::new(Position::scaled(0.0, 0.0, 0.0, length::Scale::LightYear), Length::ly(1.0));
+        tree.insert(Position::scaled(f64::NAN, 0.0, 0.0, length::Scale::LightYear), Point(0, SpectralType::G));
+        tree.insert(Position::scaled(f64::INFINITY, 0.0, 0.0, length::Scale::LightYear), Point(1, SpectralType::G));
+        assert!(tree.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use std::ops::Range;
+use serde::{Deserialize, Serialize};
 use super::length;
 use super::length::Length;
 use super::mass;
@@ -8,6 +9,7 @@ use super::power::Power;
 use super::temperature;
 use super::temperature::Temperature;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LuminosityClass {
     IaPlus, // 0 or Ia+, hypergiants or extremely luminous supergiants.
     Ia,     // luminous supergiants.
@@ -21,6 +23,7 @@ pub enum LuminosityClass {
     VII     // D (prefix) or VII, white dwarfs.
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SpectralType {
     O, B, A, F, G, K, M,                            // Main sequence stars.
     WR,                                             // Wolf-Rayet stars.
@@ -31,6 +34,66 @@ pub enum SpectralType {
     P, Q                                            // Non-stellar spectral types.
 }
 
+/// The Sun's effective temperature in kelvin, IAU nominal 2015.
+pub const SOLAR_EFFECTIVE_TEMPERATURE: f32 = 5_772.0;
+/// Main sequence O types are open ended in mass; we cap them here when generating stars.
+pub const MAX_MAIN_SEQUENCE_MASS: f64 = 90.0;
+
+/// The physical properties of a star.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Star {
+    pub spectral_type: SpectralType,
+    pub luminosity_class: LuminosityClass,
+    pub mass: Mass,
+    pub radius: Length,
+    pub luminosity: Power,
+    pub temperature: Temperature,
+    /// Years since the star formed.
+    pub age: f64
+}
+
+impl Star {
+    /// A zero age main sequence star of a type, from the standard mass-luminosity and mass-radius relations.
+    /// `placement` in [0, 1) runs from the bottom of the type's mass range to the top.
+    pub fn main_sequence(spectral_type: SpectralType, placement: f64) -> Star {
+        let masses = spectral_type.main_sequence_mass();
+        let low = masses.start.in_solar_masses().max(0.08);
+        let high = masses.end.in_solar_masses().min(MAX_MAIN_SEQUENCE_MASS);
+        let mass = low * libm::pow(high / low, placement);
+        let luminosity = if mass < 0.43 {
+            0.23 * libm::pow(mass, 2.3)
+        } else if mass < 2.0 {
+            mass * mass * mass * mass
+        } else if mass < 55.0 {
+            1.4 * libm::pow(mass, 3.5)
+        } else {
+            32_000.0 * mass
+        };
+        let radius = if mass < 1.0 { libm::pow(mass, 0.8) } else { libm::pow(mass, 0.57) };
+        let temperature = SOLAR_EFFECTIVE_TEMPERATURE as f64 * libm::pow(luminosity / (radius * radius), 0.25);
+        Star {
+            spectral_type,
+            luminosity_class: LuminosityClass::V,
+            mass: Mass::Msol(mass),
+            radius: Length::Rsol(radius),
+            luminosity: Power::Lsol(luminosity),
+            temperature: Temperature::K(temperature as f32),
+            age: 0.0
+        }
+    }
+
+    /// Roughly how long the star spends on the main sequence, in years.
+    pub fn main_sequence_lifetime(&self) -> f64 {
+        1.0e10 * self.mass.in_solar_masses() / self.luminosity.in_solar_luminosities()
+    }
+}
+
+/// The main sequence spectral types, from hottest to coolest.
+pub const MAIN_SEQUENCE: [SpectralType; 7] = [
+    SpectralType::O, SpectralType::B, SpectralType::A, SpectralType::F,
+    SpectralType::G, SpectralType::K, SpectralType::M
+];
+
 impl SpectralType {
     pub fn is_main_sequence(self) -> bool {
         match self {
@@ -134,4 +197,17 @@ impl SpectralType {
             _               => 0.0 // Not main sequence.
         }
     }
+
+    /// Picks a main sequence spectral type, weighted by `main_sequence_fraction`, from a uniform sample in [0, 1).
+    pub fn sample_main_sequence(uniform: f64) -> SpectralType {
+        let total: f64 = MAIN_SEQUENCE.iter().map(|t| t.main_sequence_fraction() as f64).sum();
+        let mut remaining = uniform * total;
+        for spectral_type in MAIN_SEQUENCE.iter() {
+            remaining -= spectral_type.main_sequence_fraction() as f64;
+            if remaining < 0.0 {
+                return *spectral_type;
+            }
+        }
+        SpectralType::M
+    }
 }
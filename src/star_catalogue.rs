@@ -0,0 +1,422 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use super::floating_origin::WorldPosition;
+use super::galaxy::{Galaxy, StarId};
+use super::length;
+use super::length::Length;
+use super::mass::Mass;
+use super::position::Position;
+use super::power::Power;
+use super::star::{LuminosityClass, SpectralType, Star};
+use super::temperature::Temperature;
+use super::universe::UniverseSettings;
+
+/// Rows on a page of the in-game browser.
+pub const BROWSER_PAGE_SIZE: usize = 10;
+/// How far the browser's origin can move before its distances are measured again, less than they are shown to.
+pub const BROWSER_REFRESH_LIGHT_YEARS: f64 = 0.001;
+
+/// A star in the catalogue, with the entity it is in the world if it has been generated there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CatalogueEntry {
+    pub id: StarId,
+    pub star: Star,
+    pub position: Position,
+    pub entity: Option<Entity>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortKey {
+    Id,
+    Distance,
+    Temperature,
+    Mass,
+    Luminosity
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 5] = [SortKey::Id, SortKey::Distance, SortKey::Temperature, SortKey::Mass, SortKey::Luminosity];
+
+    /// The next key, for cycling through them.
+    pub fn next(self) -> SortKey {
+        let index = SortKey::ALL.iter().position(|key| *key == self).unwrap_or(0);
+        SortKey::ALL[(index + 1) % SortKey::ALL.len()]
+    }
+}
+
+/// Which stars a query matches. Empty lists and `None` ranges match anything; ranges include their start and not their end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StarFilter {
+    pub spectral_types: Vec<SpectralType>,
+    pub luminosity_classes: Vec<LuminosityClass>,
+    pub distance: Option<Range<Length>>,
+    pub temperature: Option<Range<Temperature>>,
+    pub mass: Option<Range<Mass>>,
+    pub luminosity: Option<Range<Power>>
+}
+
+fn within<T: PartialOrd>(range: &Option<Range<T>>, value: &T) -> bool {
+    match range {
+        Some(range) => range.contains(value),
+        None        => true
+    }
+}
+
+impl StarFilter {
+    /// Whether a star `distance` away matches.
+    pub fn matches(&self, star: &Star, distance: Length) -> bool {
+        (self.spectral_types.is_empty() || self.spectral_types.contains(&star.spectral_type))
+            && (self.luminosity_classes.is_empty() || self.luminosity_classes.contains(&star.luminosity_class))
+            && within(&self.distance, &distance)
+            && within(&self.temperature, &star.temperature)
+            && within(&self.mass, &star.mass)
+            && within(&self.luminosity, &star.luminosity)
+    }
+}
+
+/// A filtered, sorted page of the catalogue, with distances measured from `from`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarQuery {
+    pub from: Position,
+    pub filter: StarFilter,
+    pub sort: SortKey,
+    pub descending: bool,
+    /// Counting from zero.
+    pub page: usize,
+    pub per_page: usize
+}
+
+impl StarQuery {
+    /// Every star, by id, on one page.
+    pub fn new(from: Position) -> StarQuery {
+        StarQuery { from, filter: StarFilter::default(), sort: SortKey::Id, descending: false, page: 0, per_page: usize::MAX }
+    }
+
+    pub fn with_spectral_types(mut self, spectral_types: &[SpectralType]) -> StarQuery {
+        self.filter.spectral_types = spectral_types.to_vec();
+        self
+    }
+
+    pub fn with_luminosity_classes(mut self, luminosity_classes: &[LuminosityClass]) -> StarQuery {
+        self.filter.luminosity_classes = luminosity_classes.to_vec();
+        self
+    }
+
+    pub fn within(mut self, distance: Range<Length>) -> StarQuery {
+        self.filter.distance = Some(distance);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: Range<Temperature>) -> StarQuery {
+        self.filter.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_mass(mut self, mass: Range<Mass>) -> StarQuery {
+        self.filter.mass = Some(mass);
+        self
+    }
+
+    pub fn with_luminosity(mut self, luminosity: Range<Power>) -> StarQuery {
+        self.filter.luminosity = Some(luminosity);
+        self
+    }
+
+    pub fn sorted_by(mut self, sort: SortKey, descending: bool) -> StarQuery {
+        self.sort = sort;
+        self.descending = descending;
+        self
+    }
+
+    pub fn paged(mut self, page: usize, per_page: usize) -> StarQuery {
+        self.page = page;
+        self.per_page = per_page.max(1);
+        self
+    }
+}
+
+/// A page of matching stars, each with its distance from the query's `from`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CataloguePage {
+    pub entries: Vec<(CatalogueEntry, Length)>,
+    /// How many stars matched, on every page.
+    pub total: usize,
+    pub page: usize,
+    pub pages: usize
+}
+
+/// Every known star, in order of id.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StarCatalogue(pub Vec<CatalogueEntry>);
+
+impl StarCatalogue {
+    /// The stars a galaxy generates within a sphere, for tools working without a world.
+    pub fn from_galaxy(galaxy: &Galaxy, center: Position, radius: Length) -> StarCatalogue {
+        let mut entries: Vec<CatalogueEntry> = galaxy.stars_within(center, radius)
+            .map(|galaxy_star| CatalogueEntry { id: galaxy_star.id, star: galaxy_star.star(), position: galaxy_star.position, entity: None })
+            .collect();
+        entries.sort_by_key(|entry| entry.id);
+        StarCatalogue(entries)
+    }
+
+    pub fn get(&self, id: StarId) -> Option<&CatalogueEntry> {
+        self.0.binary_search_by_key(&id, |entry| entry.id).ok().map(|index| &self.0[index])
+    }
+
+    /// Every star matching the query, sorted, with distances; ties are broken by id.
+    pub fn matching(&self, query: &StarQuery) -> Vec<(CatalogueEntry, Length)> {
+        let mut matches: Vec<(CatalogueEntry, Length)> = self.0.iter()
+            .map(|entry| (*entry, entry.position.distance(query.from).to_scale(length::Scale::LightYear)))
+            .filter(|(entry, distance)| query.filter.matches(&entry.star, *distance))
+            .collect();
+        let order = |a: &(CatalogueEntry, Length), b: &(CatalogueEntry, Length)| -> Ordering {
+            let ordering = match query.sort {
+                SortKey::Id          => Ordering::Equal,
+                SortKey::Distance    => a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal),
+                SortKey::Temperature => a.0.star.temperature.partial_cmp(&b.0.star.temperature).unwrap_or(Ordering::Equal),
+                SortKey::Mass        => a.0.star.mass.partial_cmp(&b.0.star.mass).unwrap_or(Ordering::Equal),
+                SortKey::Luminosity  => a.0.star.luminosity.partial_cmp(&b.0.star.luminosity).unwrap_or(Ordering::Equal)
+            }.then(a.0.id.cmp(&b.0.id));
+            if query.descending { ordering.reverse() } else { ordering }
+        };
+        matches.sort_by(order);
+        matches
+    }
+
+    pub fn query(&self, query: &StarQuery) -> CataloguePage {
+        let matches = self.matching(query);
+        let total = matches.len();
+        let per_page = query.per_page.max(1);
+        // Rounded up by hand, as older compilers lack `div_ceil`; an empty catalogue still has a page.
+        let pages = match total % per_page {
+            0 => total / per_page,
+            _ => total / per_page + 1
+        }.max(1);
+        let page = query.page.min(pages - 1);
+        let entries = matches.into_iter().skip(page.saturating_mul(per_page)).take(per_page).collect();
+        CataloguePage { entries, total, page, pages }
+    }
+}
+
+/// What the in-game catalogue browser shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatalogueBrowser {
+    pub open: bool,
+    /// Only stars of this type, if any.
+    pub spectral_type: Option<SpectralType>,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub page: usize,
+    /// The row on the page last selected.
+    pub cursor: usize,
+    /// The page showing, and where its distances were measured from.
+    pub shown: Option<(Position, CataloguePage)>
+}
+
+impl Default for CatalogueBrowser {
+    fn default() -> CatalogueBrowser {
+        CatalogueBrowser { open: false, spectral_type: None, sort: SortKey::Distance, descending: false, page: 0, cursor: 0, shown: None }
+    }
+}
+
+impl CatalogueBrowser {
+    pub fn query(&self, from: Position) -> StarQuery {
+        let query = StarQuery::new(from).sorted_by(self.sort, self.descending).paged(self.page, BROWSER_PAGE_SIZE);
+        match self.spectral_type {
+            Some(spectral_type) => query.with_spectral_types(&[spectral_type]),
+            None                => query
+        }
+    }
+
+    /// Whether the page showing was measured too far from `from` to show from there.
+    pub fn is_stale(&self, from: Position) -> bool {
+        match &self.shown {
+            Some((origin, _)) => origin.distance(from).in_light_years() > BROWSER_REFRESH_LIGHT_YEARS,
+            None              => true
+        }
+    }
+
+    /// Queries the page to show from `from`, keeping to the pages there are.
+    pub fn refresh(&mut self, catalogue: &StarCatalogue, from: Position) {
+        let page = catalogue.query(&self.query(from));
+        self.page = page.page;
+        self.shown = Some((from, page));
+    }
+
+    /// The next spectral type to filter by among those in the catalogue, hottest first, and then none.
+    pub fn cycle_spectral_type(&mut self, catalogue: &StarCatalogue) {
+        let mut present: Vec<(Temperature, SpectralType)> = Vec::new();
+        for entry in catalogue.0.iter() {
+            match present.iter_mut().find(|(_, spectral_type)| *spectral_type == entry.star.spectral_type) {
+                Some((hottest, _)) => if entry.star.temperature > *hottest { *hottest = entry.star.temperature; },
+                None               => present.push((entry.star.temperature, entry.star.spectral_type))
+            }
+        }
+        present.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        let types: Vec<SpectralType> = present.into_iter().map(|(_, spectral_type)| spectral_type).collect();
+        self.spectral_type = match self.spectral_type.and_then(|current| types.iter().position(|spectral_type| *spectral_type == current)) {
+            Some(index) => types.get(index + 1).copied(),
+            None        => types.first().copied()
+        };
+        self.page = 0;
+        self.cursor = 0;
+    }
+}
+
+/// Where the browser measures distances from: the player's ship, or else where the universe starts.
+pub fn browser_origin(ship: Option<&WorldPosition>, settings: &UniverseSettings) -> Position {
+    ship.map_or(settings.start, |position| position.to_position(length::Scale::LightYear))
+}
+
+/// Keeps the catalogue to the stars in the world, whenever any are generated, loaded or removed.
+pub fn catalogue_stars(
+    mut catalogue: ResMut<StarCatalogue>,
+    added: Query<(), Added<StarId>>,
+    removed: RemovedComponents<StarId>,
+    stars: Query<(Entity, &StarId, &Star, &WorldPosition)>
+) {
+    if added.iter().next().is_none() && removed.iter().next().is_none() {
+        return;
+    }
+    let mut entries: Vec<CatalogueEntry> = stars.iter()
+        .map(|(entity, id, star, position)| CatalogueEntry {
+            id: *id,
+            star: *star,
+            position: position.to_position(length::Scale::LightYear),
+            entity: Some(entity)
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.id);
+    catalogue.0 = entries;
+}
+
+/// A catalogue of the stars in the world, queryable by type, class, distance, temperature, mass and luminosity.
+/// Needs the `UniversePlugin`.
+pub struct StarCataloguePlugin;
+
+impl Plugin for StarCataloguePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<StarCatalogue>()
+            .init_resource::<CatalogueBrowser>()
+            .add_system_to_stage(CoreStage::PostUpdate, catalogue_stars.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+    use super::super::camera::FocusCamera;
+    use super::super::gameplay::{browse_catalogue, GameplayBindings, Selection};
+    use super::super::universe::UniverseSeed;
+
+    fn catalogue() -> (StarCatalogue, Position) {
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        (StarCatalogue::from_galaxy(&settings.galaxy, settings.start, settings.radius), settings.start)
+    }
+
+    #[test]
+    fn filters_keep_only_matching_stars() {
+        let (catalogue, from) = catalogue();
+        let everything = catalogue.matching(&StarQuery::new(from));
+        assert_eq!(everything.len(), catalogue.0.len());
+
+        let spectral_type = catalogue.0[0].star.spectral_type;
+        let of_type = catalogue.matching(&StarQuery::new(from).with_spectral_types(&[spectral_type]));
+        assert!(!of_type.is_empty());
+        assert!(of_type.iter().all(|(entry, _)| entry.star.spectral_type == spectral_type));
+        assert_eq!(of_type.len(), catalogue.0.iter().filter(|entry| entry.star.spectral_type == spectral_type).count());
+
+        let near = catalogue.matching(&StarQuery::new(from).within(Length::ly(0.0)..Length::ly(10.0)));
+        assert!(!near.is_empty() && near.len() < everything.len());
+        assert!(near.iter().all(|(_, distance)| distance.in_light_years() < 10.0));
+
+        let cool = Temperature::K_range(0.0..5000.0);
+        let cool_and_near = catalogue.matching(&StarQuery::new(from).within(Length::ly(0.0)..Length::ly(10.0)).with_temperature(cool.clone()));
+        assert!(cool_and_near.iter().all(|(entry, _)| cool.contains(&entry.star.temperature)));
+        assert!(cool_and_near.len() <= near.len());
+    }
+
+    #[test]
+    fn sorting_orders_by_the_key_either_way() {
+        let (catalogue, from) = catalogue();
+        let by_id = catalogue.matching(&StarQuery::new(from));
+        assert!(by_id.windows(2).all(|pair| pair[0].0.id < pair[1].0.id));
+
+        let nearest = catalogue.matching(&StarQuery::new(from).sorted_by(SortKey::Distance, false));
+        assert!(nearest.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let hottest = catalogue.matching(&StarQuery::new(from).sorted_by(SortKey::Temperature, true));
+        assert!(hottest.windows(2).all(|pair| pair[0].0.star.temperature >= pair[1].0.star.temperature));
+
+        let by_id_descending = catalogue.matching(&StarQuery::new(from).sorted_by(SortKey::Id, true));
+        assert!(by_id_descending.windows(2).all(|pair| pair[0].0.id > pair[1].0.id));
+    }
+
+    #[test]
+    fn pages_cover_every_match_once() {
+        let (catalogue, from) = catalogue();
+        let query = StarQuery::new(from).sorted_by(SortKey::Mass, false);
+        let matching = catalogue.matching(&query);
+        let per_page = 7;
+        let first = catalogue.query(&query.clone().paged(0, per_page));
+        assert_eq!(first.total, matching.len());
+        assert!(first.pages * per_page >= matching.len() && (first.pages - 1) * per_page < matching.len());
+
+        let paged: Vec<(CatalogueEntry, Length)> = (0..first.pages)
+            .flat_map(|page| catalogue.query(&query.clone().paged(page, per_page)).entries)
+            .collect();
+        assert_eq!(paged, matching);
+
+        let beyond = catalogue.query(&query.clone().paged(first.pages + 3, per_page));
+        assert_eq!(beyond.page, first.pages - 1);
+        assert!(!beyond.entries.is_empty());
+
+        let none = catalogue.query(&query.within(Length::ly(1000.0)..Length::ly(2000.0)).paged(2, per_page));
+        assert_eq!((none.total, none.page, none.pages), (0, 0, 1));
+        assert!(none.entries.is_empty());
+    }
+
+    #[test]
+    fn the_browser_is_only_refreshed_when_its_page_would_change() {
+        let (catalogue, from) = catalogue();
+        let mut world = World::default();
+        world.insert_resource(catalogue);
+        world.insert_resource(UniverseSettings::from_seed(UniverseSeed(7)));
+        world.insert_resource(CatalogueBrowser { open: true, page: 1000, ..CatalogueBrowser::default() });
+        world.insert_resource(Input::<KeyCode>::default());
+        world.insert_resource(GameplayBindings::default());
+        world.insert_resource(Selection::default());
+        world.insert_resource(Events::<FocusCamera>::default());
+        world.insert_resource(0_u32);
+        let count_changes = |browser: Res<CatalogueBrowser>, mut changes: ResMut<u32>| if browser.is_changed() { *changes += 1; };
+        let mut stage = SystemStage::single_threaded()
+            .with_system(browse_catalogue.system().label("browse"))
+            .with_system(count_changes.system().after("browse"));
+
+        stage.run(&mut world);
+        let browser = world.get_resource::<CatalogueBrowser>().unwrap();
+        let (origin, page) = browser.shown.clone().unwrap();
+        assert_eq!(origin, from);
+        assert_eq!(browser.page, page.page);
+        assert_eq!(page.page, page.pages - 1);
+        let changes = *world.get_resource::<u32>().unwrap();
+
+        for _ in 0..3 {
+            stage.run(&mut world);
+        }
+        assert_eq!(*world.get_resource::<u32>().unwrap(), changes);
+        let browser = world.get_resource::<CatalogueBrowser>().unwrap();
+        assert!(!browser.is_stale(from + Position::scaled(BROWSER_REFRESH_LIGHT_YEARS / 2.0, 0.0, 0.0, length::Scale::LightYear)));
+        assert!(browser.is_stale(from + Position::scaled(BROWSER_REFRESH_LIGHT_YEARS * 2.0, 0.0, 0.0, length::Scale::LightYear)));
+
+        world.get_resource_mut::<CatalogueBrowser>().unwrap().sort = SortKey::Luminosity;
+        stage.run(&mut world);
+        let browser = world.get_resource::<CatalogueBrowser>().unwrap();
+        let (_, page) = browser.shown.as_ref().unwrap();
+        assert!(page.entries.windows(2).all(|pair| pair[0].0.star.luminosity <= pair[1].0.star.luminosity));
+    }
+}
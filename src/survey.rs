@@ -0,0 +1,576 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Mul;
+
+use super::astronomy::Orbiting;
+use super::clock::{GameClock, SimulationStage};
+use super::colony::{Environment, PLANET_ALBEDO};
+use super::flight::FlightSystem;
+use super::floating_origin::WorldPosition;
+use super::galaxy::StarId;
+use super::length;
+use super::length::Length;
+use super::mass;
+use super::mass::Mass;
+use super::orbit::Orbit;
+use super::planet::{Composition, Planet, PlanetKind};
+use super::power;
+use super::radiation::Irradiation;
+use super::random;
+use super::random::Rng;
+use super::sensors::detection_range;
+use super::ship::Ship;
+use super::star::{LuminosityClass, SpectralType, Star};
+use super::temperature::Temperature;
+use super::universe::UniverseSettings;
+
+/// Seconds of scanning, with a signal strong enough to saturate the sensors, for each unit of exposure.
+pub const SCAN_SECONDS: f64 = 60.0;
+/// The fractional uncertainty of a measurement before any exposure; it shrinks with the square root of exposure.
+pub const INITIAL_UNCERTAINTY: f64 = 0.5;
+/// How small the uncertainty must be for each stage to be revealed. A star's colour gives its spectral type at once.
+pub const LUMINOSITY_CLASS_UNCERTAINTY: f64 = 0.2;
+pub const MASS_UNCERTAINTY: f64 = 0.1;
+/// Planets and atmospheres need the finest spectra.
+pub const PLANETS_UNCERTAINTY: f64 = 0.05;
+pub const ATMOSPHERE_UNCERTAINTY: f64 = 0.05;
+const SURVEY_KEY: u64 = 0x7375_7276_6579;
+
+/// The fractional uncertainty left after an exposure.
+pub fn uncertainty(exposure: f64) -> f64 {
+    INITIAL_UNCERTAINTY / (1.0 + exposure.max(0.0)).sqrt()
+}
+
+/// A planet by its star and its place out from it, innermost first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PlanetId {
+    pub star: StarId,
+    pub index: u32
+}
+
+impl fmt::Display for PlanetId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.star, self.index + 1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BodyId {
+    Star(StarId),
+    Planet(PlanetId)
+}
+
+impl fmt::Display for BodyId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyId::Star(id)   => write!(f, "star {}", id),
+            BodyId::Planet(id) => write!(f, "planet {}", id)
+        }
+    }
+}
+
+impl BodyId {
+    /// The seed of the errors in what is measured of the body, so that repeated scans close in on the truth.
+    fn seed(&self, galaxy_seed: u64) -> u64 {
+        let star = match self {
+            BodyId::Star(id)   => id,
+            BodyId::Planet(id) => &id.star
+        };
+        let mut seed = random::derive(galaxy_seed, SURVEY_KEY);
+        for key in [star.sector.x as u64, star.sector.y as u64, star.sector.z as u64, star.index as u64].iter() {
+            seed = random::derive(seed, *key);
+        }
+        match self {
+            BodyId::Star(_)    => seed,
+            BodyId::Planet(id) => random::derive(seed, id.index as u64 + 1)
+        }
+    }
+}
+
+/// A measured quantity and its one sigma uncertainty.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Estimate<T> {
+    pub value: T,
+    pub uncertainty: T
+}
+
+impl<T: Copy + Mul<f64, Output = T>> Estimate<T> {
+    /// Of a true value, off by `error` standard deviations of a fractional uncertainty.
+    pub fn measure(truth: T, uncertainty: f64, error: f64) -> Estimate<T> {
+        Estimate { value: truth * (1.0 + uncertainty * error), uncertainty: truth * uncertainty }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Estimate<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} ± {:.*}", precision, self.value, precision, self.uncertainty),
+            None            => write!(f, "{} ± {}", self.value, self.uncertainty)
+        }
+    }
+}
+
+/// What is known of a star.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StarRecord {
+    /// In `SCAN_SECONDS` of saturated scanning, summed over every scan.
+    pub exposure: f64,
+    /// When it was first scanned.
+    pub discovered: f64,
+    /// From its colour.
+    pub spectral_type: Option<SpectralType>,
+    pub luminosity_class: Option<LuminosityClass>,
+    pub mass: Option<Estimate<Mass>>,
+    /// How many planets have been seen about it, which is all of them unless they lie too close to it for the sensors to part.
+    pub planets: Option<u32>
+}
+
+/// What a world's atmosphere is like, as far as its spectrum shows.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Atmosphere {
+    /// At the surface, in bar; `None` for a giant's envelope.
+    pub pressure: Option<Estimate<f64>>,
+    /// At the surface, or at the cloud tops of a giant.
+    pub temperature: Temperature,
+    /// The fraction of the world that is hydrogen and helium, and that is ices.
+    pub gases: f64,
+    pub ices: f64
+}
+
+impl fmt::Display for Atmosphere {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pressure {
+            Some(pressure) => write!(f, "{:.2} bar", pressure)?,
+            None           => write!(f, "deep envelope")?
+        }
+        write!(f, " at {:.0}, {:.0}% gases, {:.0}% ices", self.temperature, self.gases * 100.0, self.ices * 100.0)
+    }
+}
+
+/// What is known of a planet.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlanetRecord {
+    pub exposure: f64,
+    pub discovered: f64,
+    pub kind: Option<PlanetKind>,
+    pub mass: Option<Estimate<Mass>>,
+    pub radius: Option<Estimate<Length>>,
+    pub atmosphere: Option<Atmosphere>
+}
+
+/// Every star and planet the player's ships have scanned, or seen about a scanned star, and what has been learnt of them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Codex {
+    pub stars: BTreeMap<StarId, StarRecord>,
+    pub planets: BTreeMap<PlanetId, PlanetRecord>
+}
+
+impl Codex {
+    pub fn exposure(&self, body: BodyId) -> f64 {
+        match body {
+            BodyId::Star(id)   => self.stars.get(&id).map_or(0.0, |record| record.exposure),
+            BodyId::Planet(id) => self.planets.get(&id).map_or(0.0, |record| record.exposure)
+        }
+    }
+
+    pub fn describe_star(&self, id: StarId) -> Option<String> {
+        let record = self.stars.get(&id)?;
+        let mut parts = vec![format!("{}", id)];
+        parts.extend(record.spectral_type.map(|spectral_type| format!("{:?}{}", spectral_type,
+                                                                       record.luminosity_class.map_or_else(String::new, |class| format!("{:?}", class)))));
+        parts.extend(record.mass.map(|mass| format!("{:.3}", mass)));
+        parts.extend(record.planets.map(|planets| format!("{} planets", planets)));
+        parts.push(format!("±{:.1}%", uncertainty(record.exposure) * 100.0));
+        Some(parts.join("  "))
+    }
+
+    pub fn describe_planet(&self, id: PlanetId) -> Option<String> {
+        let record = self.planets.get(&id)?;
+        let mut parts = vec![format!("{}", id)];
+        parts.extend(record.kind.map(|kind| format!("{:?}", kind)));
+        parts.extend(record.mass.map(|mass| format!("{:.2}", mass)));
+        parts.extend(record.radius.map(|radius| format!("{:.2}", radius)));
+        parts.extend(record.atmosphere.map(|atmosphere| atmosphere.to_string()));
+        if record.exposure > 0.0 {
+            parts.push(format!("±{:.1}%", uncertainty(record.exposure) * 100.0));
+        }
+        Some(parts.join("  "))
+    }
+}
+
+/// A ship's scan of a star or planet, which goes on until it is told to stop or scan something else.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scanning {
+    pub target: Entity,
+    pub since: f64
+}
+
+/// Asks a ship to scan a body, or to stop scanning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scan {
+    pub ship: Entity,
+    pub target: Option<Entity>
+}
+
+/// What a scan has newly revealed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Revealed {
+    Discovered,
+    SpectralType,
+    LuminosityClass,
+    Mass,
+    Planets,
+    Kind,
+    Size,
+    Atmosphere
+}
+
+impl fmt::Display for Revealed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Revealed::Discovered      => write!(f, "discovery"),
+            Revealed::SpectralType    => write!(f, "spectral type"),
+            Revealed::LuminosityClass => write!(f, "luminosity class"),
+            Revealed::Mass            => write!(f, "mass"),
+            Revealed::Planets         => write!(f, "planets"),
+            Revealed::Kind            => write!(f, "kind"),
+            Revealed::Size            => write!(f, "mass and radius"),
+            Revealed::Atmosphere      => write!(f, "atmosphere")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurveyEvent {
+    pub body: BodyId,
+    pub revealed: Revealed
+}
+
+impl fmt::Display for SurveyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.revealed {
+            Revealed::Discovered => write!(f, "discovered {}", self.body),
+            revealed             => write!(f, "{} of {} revealed", revealed, self.body)
+        }
+    }
+}
+
+pub fn start_scans(clock: Res<GameClock>, mut commands: Commands, mut scans: EventReader<Scan>) {
+    for scan in scans.iter() {
+        match scan.target {
+            Some(target) => { commands.entity(scan.ship).insert(Scanning { target, since: clock.seconds }); }
+            None         => { commands.entity(scan.ship).remove::<Scanning>(); }
+        }
+    }
+}
+
+/// How strong a body's light is to a ship's sensors, from nothing to saturating them at one.
+fn signal(ship: &Ship, power: power::Power, distance: Length) -> f64 {
+    let range = detection_range(ship.sensor_range(), power);
+    (range.in_meters() / distance.in_meters().max(1.0)).powi(2).min(1.0)
+}
+
+/// A planet's place out from its star, counting the planets orbiting it that are nearer in.
+fn planet_index(planet: Entity, orbit: &Orbit, parent: Entity, planets: &Query<ScannedPlanet>) -> u32 {
+    planets.iter()
+        .filter(|(other, other_orbit, other_parent, ..)| *other != planet && other_parent.0 == parent
+                && (other_orbit.semi_major_axis.in_meters(), *other) < (orbit.semi_major_axis.in_meters(), planet))
+        .count() as u32
+}
+
+fn record_star(codex: &mut Codex, (id, star): (StarId, &Star), exposure: f64, now: f64, seed: u64, planets: Option<u32>, events: &mut Vec<SurveyEvent>) {
+    let body = BodyId::Star(id);
+    let record = codex.stars.entry(id).or_insert_with(|| {
+        events.push(SurveyEvent { body, revealed: Revealed::Discovered });
+        StarRecord { discovered: now, ..StarRecord::default() }
+    });
+    record.exposure += exposure;
+    let sigma = uncertainty(record.exposure);
+    let mut reveal = |revealed| events.push(SurveyEvent { body, revealed });
+    if record.spectral_type.is_none() {
+        record.spectral_type = Some(star.spectral_type);
+        reveal(Revealed::SpectralType);
+    }
+    if record.luminosity_class.is_none() && sigma <= LUMINOSITY_CLASS_UNCERTAINTY {
+        record.luminosity_class = Some(star.luminosity_class);
+        reveal(Revealed::LuminosityClass);
+    }
+    if sigma <= MASS_UNCERTAINTY {
+        if record.mass.is_none() {
+            reveal(Revealed::Mass);
+        }
+        let error = Rng::new(random::derive(seed, 1)).gaussian();
+        record.mass = Some(Estimate::measure(star.mass.to_scale(mass::Scale::SolarMass), sigma, error));
+    }
+    if sigma <= PLANETS_UNCERTAINTY {
+        if let Some(planets) = planets {
+            if !matches!(record.planets, Some(seen) if seen >= planets) {
+                record.planets = Some(planets);
+                reveal(Revealed::Planets);
+            }
+        }
+    }
+}
+
+fn record_planet(codex: &mut Codex, id: PlanetId, (planet, composition, environment): (&Planet, &Composition, Environment),
+                 exposure: f64, now: f64, seed: u64, events: &mut Vec<SurveyEvent>) {
+    let body = BodyId::Planet(id);
+    let record = codex.planets.entry(id).or_insert_with(|| {
+        events.push(SurveyEvent { body, revealed: Revealed::Discovered });
+        PlanetRecord { discovered: now, ..PlanetRecord::default() }
+    });
+    record.exposure += exposure;
+    let sigma = uncertainty(record.exposure);
+    let mut reveal = |revealed| events.push(SurveyEvent { body, revealed });
+    let mut errors = Rng::new(seed);
+    let (mass_error, radius_error, pressure_error) = (errors.gaussian(), errors.gaussian(), errors.gaussian());
+    if record.kind.is_none() {
+        record.kind = Some(planet.kind);
+        reveal(Revealed::Kind);
+    }
+    if sigma <= MASS_UNCERTAINTY {
+        if record.mass.is_none() {
+            reveal(Revealed::Size);
+        }
+        record.mass = Some(Estimate::measure(planet.mass.to_scale(mass::Scale::EarthMass), sigma, mass_error));
+        record.radius = Some(Estimate::measure(planet.radius.to_scale(length::Scale::EarthRadius), sigma, radius_error));
+    }
+    if sigma <= ATMOSPHERE_UNCERTAINTY {
+        if record.atmosphere.is_none() {
+            reveal(Revealed::Atmosphere);
+        }
+        record.atmosphere = Some(Atmosphere {
+            pressure: if environment.has_surface() { Some(Estimate::measure(environment.pressure, sigma, pressure_error)) } else { None },
+            temperature: environment.temperature,
+            gases: composition.gases,
+            ices: composition.ices
+        });
+    }
+}
+
+type Scanner<'a> = (Entity, &'a Scanning, &'a Ship, &'a WorldPosition);
+type ScannedPlanet<'a> = (Entity, &'a Orbit, &'a Orbiting, &'a Planet, &'a Composition, &'a WorldPosition, Option<&'a Environment>);
+
+/// Builds up each scanning ship's exposure of its target by how strongly it sees it, and records what that reveals.
+/// Scanning a star also counts the planets the sensors can part from it, and adds them to the codex unscanned.
+pub fn scan_bodies(
+    clock: Res<GameClock>,
+    universe: Res<UniverseSettings>,
+    mut codex: ResMut<Codex>,
+    scanners: Query<Scanner>,
+    stars: Query<(&StarId, &Star, &WorldPosition)>,
+    planets: Query<ScannedPlanet>,
+    mut events: EventWriter<SurveyEvent>
+) {
+    let now = clock.seconds;
+    let seed = universe.galaxy.seed;
+    let mut scanners: Vec<_> = scanners.iter().collect();
+    scanners.sort_by_key(|(entity, ..)| *entity);
+    let mut revealed = Vec::new();
+    for (_, scanning, ship, position) in scanners {
+        if let Ok((id, star, star_position)) = stars.get(scanning.target) {
+            let distance = star_position.distance(*position);
+            let exposure = signal(ship, star.luminosity, distance) * clock.tick_seconds() / SCAN_SECONDS;
+            let resolution = ship.sensor_resolution();
+            let seen: Vec<(Entity, &Orbit)> = planets.iter()
+                .filter(|(_, orbit, parent, ..)| parent.0 == scanning.target
+                        && orbit.semi_major_axis.in_meters() / distance.in_meters().max(1.0) >= resolution)
+                .map(|(planet, orbit, ..)| (planet, orbit))
+                .collect();
+            record_star(&mut codex, (*id, star), exposure, now, BodyId::Star(*id).seed(seed), Some(seen.len() as u32), &mut revealed);
+            if codex.stars[id].planets.is_some() {
+                for (planet, orbit) in seen {
+                    let planet_id = PlanetId { star: *id, index: planet_index(planet, orbit, scanning.target, &planets) };
+                    if let Entry::Vacant(entry) = codex.planets.entry(planet_id) {
+                        entry.insert(PlanetRecord { discovered: now, ..PlanetRecord::default() });
+                        revealed.push(SurveyEvent { body: BodyId::Planet(planet_id), revealed: Revealed::Discovered });
+                    }
+                }
+            }
+        } else if let Ok((planet, orbit, parent, body, composition, planet_position, environment)) = planets.get(scanning.target) {
+            let (id, star) = match stars.get(parent.0) {
+                Ok((id, star, _)) => (*id, star),
+                Err(_)            => continue
+            };
+            let planet_id = PlanetId { star: id, index: planet_index(planet, orbit, parent.0, &planets) };
+            let environment = environment.copied().unwrap_or_else(|| {
+                Environment::of(body, composition, Irradiation::from_star(star, power::ZERO, orbit.semi_major_axis).total)
+            });
+            // It shines by the starlight it reflects.
+            let reflected = star.luminosity * (PLANET_ALBEDO * (body.radius.in_meters() / (2.0 * orbit.semi_major_axis.in_meters())).powi(2));
+            let exposure = signal(ship, reflected, planet_position.distance(*position)) * clock.tick_seconds() / SCAN_SECONDS;
+            record_planet(&mut codex, planet_id, (body, composition, environment), exposure, now,
+                          BodyId::Planet(planet_id).seed(seed), &mut revealed);
+        }
+    }
+    for event in revealed {
+        events.send(event);
+    }
+}
+
+pub fn log_survey_events(mut events: EventReader<SurveyEvent>) {
+    for event in events.iter() {
+        info!("{}", event);
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum SurveySystem {
+    Scan
+}
+
+/// Scanning stars and planets to learn what they are, more surely the longer they are scanned, into a codex of discoveries.
+/// Needs the `ClockPlugin`, `UniversePlugin` and `FlightPlugin`.
+pub struct SurveyPlugin;
+
+impl Plugin for SurveyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Codex>()
+            .add_event::<Scan>()
+            .add_event::<SurveyEvent>()
+            .add_system(start_scans.system())
+            .add_system(log_survey_events.system())
+            .add_system_to_stage(SimulationStage, scan_bodies.system()
+                                 .label(SurveySystem::Scan)
+                                 .after(FlightSystem::Fly));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+    use bevy::math::DVec3;
+    use super::super::galaxy::GalaxyStar;
+    use super::super::planet;
+    use super::super::save;
+    use super::super::ship::courier;
+    use super::super::universe::UniverseSeed;
+
+
+    /// The first star about the start with planets, and its planets.
+    fn star_with_planets(settings: &UniverseSettings) -> (GalaxyStar, Vec<(Planet, Orbit)>) {
+        settings.galaxy.stars_within(settings.start, settings.radius)
+            .map(|galaxy_star| {
+                let planets = planet::generate_planets(&galaxy_star.star(), galaxy_star.system_seed());
+                (galaxy_star, planets)
+            })
+            .find(|(_, planets)| !planets.is_empty())
+            .unwrap()
+    }
+
+    /// Records exposure a unit at a time until the uncertainty is below every threshold, returning what was revealed in order.
+    fn reveal(mut record: impl FnMut(f64, &mut Vec<SurveyEvent>)) -> Vec<Revealed> {
+        let mut events = Vec::new();
+        for _ in 0..120 {
+            record(1.0, &mut events);
+        }
+        events.into_iter().map(|event| event.revealed).collect()
+    }
+
+    #[test]
+    fn uncertainty_shrinks_with_the_square_root_of_exposure() {
+        assert_eq!(uncertainty(0.0), INITIAL_UNCERTAINTY);
+        assert_eq!(uncertainty(-1.0), INITIAL_UNCERTAINTY);
+        assert!((uncertainty(3.0) - INITIAL_UNCERTAINTY / 2.0).abs() < 1e-12);
+        assert!(uncertainty(100.0) < uncertainty(10.0));
+
+        // Repeated scans close in on the truth, erring the same way.
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        let (galaxy_star, _) = star_with_planets(&settings);
+        let (id, star) = (galaxy_star.id, galaxy_star.star());
+        let truth = star.mass.in_grams();
+        let mut codex = Codex::default();
+        let mut estimates = Vec::new();
+        for exposure in [30.0, 70.0, 300.0].iter() {
+            record_star(&mut codex, (id, &star), *exposure, 0.0, BodyId::Star(id).seed(7), None, &mut Vec::new());
+            estimates.push(codex.stars[&id].mass.unwrap());
+        }
+        for pair in estimates.windows(2) {
+            assert!(pair[1].uncertainty.in_grams() < pair[0].uncertainty.in_grams());
+            assert!((pair[1].value.in_grams() - truth).abs() < (pair[0].value.in_grams() - truth).abs());
+        }
+    }
+
+    #[test]
+    fn stars_and_planets_reveal_themselves_in_order() {
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        let (galaxy_star, planets) = star_with_planets(&settings);
+        let star = galaxy_star.star();
+        let mut codex = Codex::default();
+        let seed = BodyId::Star(galaxy_star.id).seed(7);
+        assert_eq!(reveal(|exposure, events| record_star(&mut codex, (galaxy_star.id, &star), exposure, 0.0, seed, Some(1), events)),
+                   vec![Revealed::Discovered, Revealed::SpectralType, Revealed::LuminosityClass, Revealed::Mass, Revealed::Planets]);
+
+        let (planet, orbit) = &planets[0];
+        let composition = Composition::generate(planet.kind, planet::body_seed(galaxy_star.system_seed(), 0));
+        let environment = Environment::of(planet, &composition, Irradiation::from_star(&star, power::ZERO, orbit.semi_major_axis).total);
+        let id = PlanetId { star: galaxy_star.id, index: 0 };
+        let seed = BodyId::Planet(id).seed(7);
+        assert_eq!(reveal(|exposure, events| record_planet(&mut codex, id, (planet, &composition, environment), exposure, 0.0, seed, events)),
+                   vec![Revealed::Discovered, Revealed::Kind, Revealed::Size, Revealed::Atmosphere]);
+    }
+
+    #[test]
+    fn scanning_a_star_finds_its_planets() {
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        let (galaxy_star, planets) = star_with_planets(&settings);
+        let mut world = World::default();
+        let position = WorldPosition::from_position(galaxy_star.position);
+        let star = world.spawn().insert_bundle((galaxy_star.id, galaxy_star.star(), position)).id();
+        for (index, (planet, orbit)) in planets.iter().enumerate() {
+            let mut planet_position = position;
+            planet_position.translate(orbit.position_at(0.0));
+            let composition = Composition::generate(planet.kind, planet::body_seed(galaxy_star.system_seed(), index as u32));
+            world.spawn().insert_bundle((*planet, *orbit, composition, Orbiting(star), planet_position));
+        }
+        let mut ship_position = position;
+        ship_position.translate(DVec3::new(0.0, length::AU_TO_METERS, 0.0));
+        world.spawn().insert_bundle((courier(), ship_position, Scanning { target: star, since: 0.0 }));
+        let mut clock = GameClock::default();
+        clock.warp = 6000.0;
+        world.insert_resource(clock);
+        world.insert_resource(settings);
+        world.insert_resource(Codex::default());
+        world.insert_resource(Events::<SurveyEvent>::default());
+
+        let mut stage = SystemStage::single(scan_bodies.system());
+        stage.run(&mut world);
+        let codex = world.get_resource::<Codex>().unwrap();
+        assert!(codex.stars[&galaxy_star.id].exposure > 0.0);
+        assert_eq!(codex.stars[&galaxy_star.id].planets, None);
+        assert!(codex.planets.is_empty());
+
+        for _ in 0..200 {
+            stage.run(&mut world);
+        }
+        let codex = world.get_resource::<Codex>().unwrap();
+        let record = &codex.stars[&galaxy_star.id];
+        assert!(uncertainty(record.exposure) <= PLANETS_UNCERTAINTY);
+        let seen = record.planets.unwrap();
+        assert!(seen >= 1 && seen as usize <= planets.len());
+        assert_eq!(codex.planets.len(), seen as usize);
+        assert!(codex.planets.keys().all(|id| id.star == galaxy_star.id && id.index < planets.len() as u32));
+    }
+
+    #[test]
+    fn the_codex_is_saved() {
+        let settings = UniverseSettings::from_seed(UniverseSeed(7));
+        let (galaxy_star, _) = star_with_planets(&settings);
+        let star = galaxy_star.star();
+        let mut codex = Codex::default();
+        record_star(&mut codex, (galaxy_star.id, &star), 40.0, 12.0, BodyId::Star(galaxy_star.id).seed(7), Some(2), &mut Vec::new());
+        codex.planets.insert(PlanetId { star: galaxy_star.id, index: 1 }, PlanetRecord { discovered: 12.0, ..PlanetRecord::default() });
+
+        let mut world = World::default();
+        world.insert_resource(settings);
+        world.insert_resource(codex.clone());
+        let data = save::decode(&save::encode(&save::capture(&mut world)).unwrap()).unwrap();
+        let mut restored = World::default();
+        save::restore(&mut restored, data);
+        assert_eq!(restored.get_resource::<Codex>(), Some(&codex));
+    }
+}
@@ -1,8 +1,10 @@
+use std::cmp::Ordering;
 use std::default::Default;
 use std::convert::{TryFrom, Into};
 use std::fmt;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Scale {
     Celsius,
     Fahrenheit,
@@ -16,7 +18,7 @@ impl Default for Scale {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Temperature {
     kelvin: f32,
     scale: Scale
@@ -60,7 +62,7 @@ impl Temperature {
 
     pub fn rankine(rankine: f32) -> Temperature {
         Temperature {
-            kelvin: (rankine + 459.67),
+            kelvin: rankine * (5.0/9.0),
             scale: Scale::Rankine
         }
     }
@@ -132,6 +134,25 @@ impl Temperature {
             scale: Scale::Rankine
         }
     }
+
+    /// The same temperature, displayed in another scale.
+    pub fn to_scale(self, scale: Scale) -> Temperature {
+        Temperature { kelvin: self.kelvin, scale }
+    }
+
+    pub fn scale(self) -> Scale {
+        self.scale
+    }
+
+    pub fn in_kelvin(self) -> f32 {
+        self.kelvin
+    }
+}
+
+impl PartialOrd for Temperature {
+    fn partial_cmp(&self, other: &Temperature) -> Option<Ordering> {
+        self.kelvin.partial_cmp(&other.kelvin)
+    }
 }
 
 impl TryFrom<f32> for Temperature {
@@ -159,11 +180,40 @@ impl Into<f32> for Temperature {
 impl fmt::Display for Temperature {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let value: f32 = Temperature::into(*self);
-        match self.scale {
-            Scale::Celsius    => write!(f, "{} °C", value),
-            Scale::Fahrenheit => write!(f, "{} °F", value),
-            Scale::Kelvin     => write!(f, "{} K",  value),
-            Scale::Rankine    => write!(f, "{} °R", value)
+        let unit = match self.scale {
+            Scale::Celsius    => "°C",
+            Scale::Fahrenheit => "°F",
+            Scale::Kelvin     => "K",
+            Scale::Rankine    => "°R"
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} {}", precision, value, unit),
+            None            => write!(f, "{} {}", value, unit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn rankine_is_kelvin_in_fahrenheit_degrees() {
+        assert!(close(Temperature::R(0.0).in_kelvin(), 0.0));
+        assert!(close(Temperature::R(491.67).in_kelvin(), 273.15));
+        assert!(close(Temperature::R(491.67).into(), 491.67));
+    }
+
+    #[test]
+    fn conversions_agree_on_freezing_water() {
+        let freezing = [Temperature::C(0.0), Temperature::F(32.0), Temperature::K(273.15), Temperature::R(491.67)];
+        for temperature in freezing.iter() {
+            assert!(close(temperature.in_kelvin(), 273.15));
         }
+        assert!(close(Temperature::K(273.15).to_scale(Scale::Fahrenheit).into(), 32.0));
     }
 }
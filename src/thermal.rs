@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+
+use super::autopilot::AutopilotSystem;
+use super::clock::{ClockSystem, GameClock, SimulationStage};
+use super::flight::{FlightSystem, ShipControls};
+use super::power;
+use super::power::Power;
+use super::radiation::RadiationExposure;
+use super::ship::Ship;
+use super::temperature::Temperature;
+
+/// An overheated ship's engines stay off until it cools below this fraction of its limit.
+pub const RESTART_FRACTION: f32 = 0.9;
+
+/// Far hotter than any ship survives. Temperatures are held under it, so one that cannot shed its heat stays finite.
+pub const MAX_KELVIN: f64 = 1.0e6;
+/// Where a ship without working radiators starts, as it has no equilibrium to start at.
+pub const UNCOOLED_KELVIN: f64 = 293.15;
+
+/// The temperature at which the radiators reject `heat` watts, given their εσA. `None` without radiators,
+/// which can reject nothing.
+pub fn equilibrium_temperature(heat: f64, radiator_constant: f64) -> Option<f64> {
+    if radiator_constant > 0.0 { Some((heat.max(0.0) / radiator_constant).powf(0.25).min(MAX_KELVIN)) } else { None }
+}
+
+/// The temperature `seconds` on, taking in `heat` watts and radiating from a body of `heat_capacity` J K⁻¹.
+/// Integrated backwards, so ticks far longer than the ship's thermal time constant stay stable.
+/// Without radiators the heat only accumulates, up to `MAX_KELVIN`.
+pub fn step_temperature(kelvin: f64, heat: f64, heat_capacity: f64, radiator_constant: f64, seconds: f64) -> f64 {
+    if heat_capacity <= 0.0 {
+        // Nothing to store heat in: at once at equilibrium, or with no way to shed it, as hot as allowed.
+        return equilibrium_temperature(heat, radiator_constant).unwrap_or(if heat > 0.0 { MAX_KELVIN } else { kelvin });
+    }
+    // Newton's method on C (T - T₀) / dt + k T⁴ - P = 0, which is convex, so starting above the root converges.
+    // The root lies between T₀ and the equilibrium, and below where the heat alone would take it; the least of
+    // those bounds starts close enough to converge even for ticks far longer than the thermal time constant.
+    let residual = |t: f64| heat_capacity * (t - kelvin) / seconds + radiator_constant * t.powi(4) - heat;
+    let heated = kelvin.max(kelvin + heat * seconds / heat_capacity);
+    let mut t = match equilibrium_temperature(heat, radiator_constant) {
+        Some(equilibrium) => heated.min(kelvin.max(equilibrium)),
+        None              => heated
+    }.max(0.0);
+    for _ in 0..32 {
+        let slope = heat_capacity / seconds + 4.0 * radiator_constant * t.powi(3);
+        let next = t - residual(t) / slope;
+        if (next - t).abs() < 1.0e-6 * t.max(1.0) {
+            return next.clamp(0.0, MAX_KELVIN);
+        }
+        t = next;
+    }
+    t.clamp(0.0, MAX_KELVIN)
+}
+
+/// A ship's heat, and whether it has had to shut its engines down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShipThermal {
+    pub temperature: Temperature,
+    pub heat_in: Power,
+    pub heat_out: Power,
+    pub overheated: bool
+}
+
+impl ShipThermal {
+    /// The ship at rest with what it takes in, or without radiators, at `UNCOOLED_KELVIN` and starting to heat up.
+    pub fn equilibrium(ship: &Ship, heat_in: Power) -> ShipThermal {
+        match equilibrium_temperature(heat_in.in_watts(), ship.radiator_constant()) {
+            Some(kelvin) => ShipThermal { temperature: Temperature::K(kelvin as f32), heat_in, heat_out: heat_in, overheated: false },
+            None         => ShipThermal {
+                temperature: Temperature::K(UNCOOLED_KELVIN as f32),
+                heat_in,
+                heat_out: power::ZERO,
+                overheated: false
+            }
+        }
+    }
+}
+
+/// Sent when a ship gets too hot and shuts its engines down, and again when it has cooled enough to restart them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overheating {
+    pub ship: Entity,
+    pub temperature: Temperature,
+    pub overheated: bool
+}
+
+type HeatedShip<'a> = (Entity, &'a Ship, &'a mut ShipControls, Option<&'a RadiationExposure>, Option<&'a mut ShipThermal>);
+
+/// Heats ships with their own waste heat and the starlight they take, and cools them through their radiators.
+pub fn heat_ships(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut ships: Query<HeatedShip>,
+    mut events: EventWriter<Overheating>
+) {
+    let dt = clock.tick_seconds();
+    for (entity, ship, mut controls, exposure, thermal) in ships.iter_mut() {
+        let mut thermal = match thermal {
+            Some(thermal) => thermal,
+            None          => {
+                let heat_in = ship.waste_heat(0.0) + exposure.map_or(power::ZERO, |exposure| exposure.heat_load);
+                commands.entity(entity).insert(ShipThermal::equilibrium(ship, heat_in));
+                continue;
+            }
+        };
+        if thermal.overheated {
+            controls.throttle = 0.0;
+        }
+        if dt <= 0.0 {
+            continue;
+        }
+        let heat_in = (ship.waste_heat(controls.throttle.clamp(0.0, 1.0))
+                       + exposure.map_or(power::ZERO, |exposure| exposure.heat_load))
+            .to_scale(power::Scale::Megawatt);
+        let kelvin = step_temperature(thermal.temperature.in_kelvin() as f64, heat_in.in_watts(),
+                                      ship.heat_capacity(), ship.radiator_constant(), dt);
+        thermal.temperature = Temperature::K(kelvin as f32);
+        thermal.heat_in = heat_in;
+        thermal.heat_out = ship.heat_rejection(thermal.temperature);
+        let limit = ship.max_temperature().in_kelvin();
+        let overheated = if thermal.overheated {
+            thermal.temperature.in_kelvin() > limit * RESTART_FRACTION
+        } else {
+            thermal.temperature.in_kelvin() > limit
+        };
+        if overheated != thermal.overheated {
+            thermal.overheated = overheated;
+            if overheated {
+                controls.throttle = 0.0;
+            }
+            events.send(Overheating { ship: entity, temperature: thermal.temperature, overheated });
+        }
+    }
+}
+
+/// Ships' heat budgets, with their engines shut down when they overheat. Needs the `ClockPlugin`.
+pub struct ThermalPlugin;
+
+impl Plugin for ThermalPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Overheating>()
+            .add_system_to_stage(SimulationStage, heat_ships.system()
+                                 .after(ClockSystem::Advance)
+                                 .after(AutopilotSystem::Fly)
+                                 .before(FlightSystem::Fly));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::app::Events;
+    use super::super::ship::courier;
+
+    #[test]
+    fn ships_settle_at_their_equilibrium() {
+        let (heat, capacity, radiators) = (2.0e6, 5.0e7, 1.0e-4);
+        let equilibrium = equilibrium_temperature(heat, radiators).unwrap();
+        assert!((step_temperature(equilibrium, heat, capacity, radiators, 60.0) - equilibrium).abs() < 1e-3);
+        // Backwards integration stays stable with a tick far longer than the time constant.
+        let mut kelvin = 3.0;
+        for _ in 0..10 {
+            kelvin = step_temperature(kelvin, heat, capacity, radiators, 1.0e9);
+            assert!(kelvin <= equilibrium * (1.0 + 1e-6));
+        }
+        assert!((kelvin - equilibrium).abs() < 1e-3);
+    }
+
+    #[test]
+    fn without_radiators_heat_accumulates_up_to_the_maximum() {
+        assert_eq!(equilibrium_temperature(1.0e6, 0.0), None);
+        assert!((step_temperature(300.0, 1.0e6, 1.0e7, 0.0, 10.0) - 301.0).abs() < 1e-9);
+        let mut kelvin = 300.0;
+        for _ in 0..1_000 {
+            kelvin = step_temperature(kelvin, 1.0e9, 1.0e7, 0.0, 3600.0);
+            assert!(kelvin.is_finite());
+        }
+        assert_eq!(kelvin, MAX_KELVIN);
+        assert_eq!(step_temperature(300.0, 1.0e6, 0.0, 0.0, 10.0), MAX_KELVIN);
+        assert_eq!(step_temperature(300.0, 0.0, 0.0, 0.0, 10.0), 300.0);
+    }
+
+    #[test]
+    fn a_ship_without_radiators_overheats_without_going_infinite() {
+        let mut ship = courier();
+        ship.radiators.clear();
+        let mut world = World::default();
+        world.insert_resource(GameClock::default());
+        world.insert_resource(Events::<Overheating>::default());
+        let entity = world.spawn().insert(ship).insert(ShipControls { throttle: 1.0, ..ShipControls::default() }).id();
+        let mut stage = SystemStage::single(heat_ships.system());
+        stage.run(&mut world);
+        assert_eq!(world.get::<ShipThermal>(entity).unwrap().temperature.in_kelvin(), UNCOOLED_KELVIN as f32);
+        let mut overheated = false;
+        for _ in 0..100_000 {
+            world.get_mut::<ShipControls>(entity).unwrap().throttle = 1.0;
+            stage.run(&mut world);
+            let thermal = world.get::<ShipThermal>(entity).unwrap();
+            assert!(thermal.temperature.in_kelvin().is_finite());
+            if thermal.overheated {
+                overheated = true;
+                break;
+            }
+        }
+        assert!(overheated);
+        assert_eq!(world.get::<ShipControls>(entity).unwrap().throttle, 0.0);
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+use super::astronomy::UnitPreferences;
+use super::autopilot::Autopilot;
+use super::camera::{CameraController, CameraMode};
+use super::clock::GameClock;
+use super::faction::{Borders, Factions};
+use super::flight::{PlayerShip, ShipControls, Velocity};
+use super::floating_origin::WorldPosition;
+use super::galaxy::StarId;
+use super::gameplay::Selection;
+use super::length::Length;
+use super::market::{Credits, Manifest, Market, DOCKING_RANGE_METERS};
+use super::mission::{MissionLog, MissionStatus};
+use super::power;
+use super::radiation::{Flaring, RadiationExposure, SafeApproach};
+use super::route::PlottedRoute;
+use super::sensors::Contacts;
+use super::ship::Ship;
+use super::star::Star;
+use super::star_catalogue::{CatalogueBrowser, CataloguePage};
+use super::survey::{Codex, PlanetId, Scanning};
+use super::thermal::ShipThermal;
+
+/// How the HUD looks. Insert before adding the `UiPlugin` to change it.
+pub struct UiSettings {
+    /// Relative to `assets`.
+    pub font: String,
+    pub font_size: f32,
+    pub color: Color
+}
+
+impl Default for UiSettings {
+    fn default() -> UiSettings {
+        UiSettings {
+            font: "fonts/DejaVuSansMono.ttf".to_string(),
+            font_size: 16.0,
+            color: Color::WHITE
+        }
+    }
+}
+
+/// The text showing the camera and selection.
+pub struct Hud;
+
+/// The text showing the star catalogue, when it is open.
+pub struct CataloguePanel;
+
+pub fn spawn_hud(mut commands: Commands, settings: Res<UiSettings>, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load(settings.font.as_str()),
+        font_size: settings.font_size,
+        color: settings.color
+    };
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect { top: Val::Px(8.0), left: Val::Px(8.0), ..Default::default() },
+            ..Default::default()
+        },
+        text: Text::with_section("", style.clone(), TextAlignment::default()),
+        ..Default::default()
+    })
+        .insert(Hud);
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect { top: Val::Px(8.0), right: Val::Px(8.0), ..Default::default() },
+            ..Default::default()
+        },
+        text: Text::with_section("", style, TextAlignment::default()),
+        ..Default::default()
+    })
+        .insert(CataloguePanel);
+}
+
+pub fn describe_camera(controller: &CameraController, units: &UnitPreferences) -> String {
+    let mode = match controller.mode {
+        CameraMode::Orbit     => "Orbit",
+        CameraMode::FreeFly   => "Free flight",
+        CameraMode::Follow(_) => "Follow"
+    };
+    format!("{}, {:.2} from focus", mode, units.length(Length::m(controller.distance)))
+}
+
+pub fn describe_clock(clock: &GameClock) -> String {
+    if clock.paused {
+        format!("{}  paused", clock.date())
+    } else {
+        format!("{}  {}x", clock.date(), clock.warp)
+    }
+}
+
+pub fn describe_star(star: &Star, units: &UnitPreferences) -> String {
+    format!("{:?}{:?}  {:.3}  {:.3}  {:.0}  {:.3}",
+            star.spectral_type,
+            star.luminosity_class,
+            star.mass.to_scale(units.mass),
+            star.luminosity.to_scale(units.power),
+            star.temperature.to_scale(units.temperature),
+            units.length(star.radius))
+}
+
+/// Who holds a star system, or whose borders it lies inside.
+pub fn describe_allegiance(star: StarId, factions: &Factions, borders: &Borders) -> String {
+    let name = |faction| factions.factions.get(&faction).map_or_else(|| faction.to_string(), |faction| faction.name.clone());
+    match (factions.owners.get(&star), borders.claimant(star)) {
+        (Some(owner), _)     => format!("held by the {}{}", name(*owner), if factions.is_capital(star) { ", capital" } else { "" }),
+        (None, Some(claim))  => format!("unsettled, inside the borders of the {}", name(claim)),
+        (None, None)         => "unclaimed".to_string()
+    }
+}
+
+pub fn describe_thermal(ship: &Ship, thermal: &ShipThermal, units: &UnitPreferences) -> String {
+    format!("heat {:.0} of {:.0}  {:.1} in  {:.1} out{}",
+            thermal.temperature.to_scale(units.temperature),
+            ship.max_temperature().to_scale(units.temperature),
+            thermal.heat_in,
+            thermal.heat_out,
+            if thermal.overheated { "  OVERHEATED, engines off" } else { "" })
+}
+
+pub fn describe_ship(ship: &Ship, controls: &ShipControls, velocity: &Velocity, autopilot: Option<&Autopilot>) -> String {
+    let pilot = match autopilot.and_then(|autopilot| autopilot.current().map(|burn| (autopilot, burn))) {
+        Some((autopilot, burn)) => format!("  autopilot: {} burn {} of {}, {:.1} m/s left, starts at {:.0} s",
+                                           autopilot.plan.manoeuvre, autopilot.burn + 1, autopilot.plan.burns.len(),
+                                           autopilot.remaining.length(), burn.start()),
+        None                    => String::new()
+    };
+    format!("{}  throttle {:.0}%  {:.1} m/s  Δv {:.1} m/s  fuel {:.2}{}",
+            ship.name, controls.throttle * 100.0, velocity.0.length(), ship.delta_v(), ship.fuel(), pilot)
+}
+
+pub fn describe_cargo(ship: &Ship, credits: &Credits, manifest: &Manifest) -> String {
+    let cargo: Vec<String> = manifest.0.0.iter()
+        .filter(|(_, amount)| amount.in_kilograms() > 0.0)
+        .map(|(commodity, amount)| format!("{} {:.1}", commodity, amount))
+        .collect();
+    format!("credits {:.0}  cargo space {:.1}{}{}", credits.0, ship.cargo_space(),
+            if cargo.is_empty() { "" } else { "  " }, cargo.join(", "))
+}
+
+/// Shields, armour and whatever is damaged; `None` for an unarmoured, unshielded ship in one piece.
+pub fn describe_condition(ship: &Ship) -> Option<String> {
+    if ship.shields.is_empty() && ship.armour.is_empty() && ship.damage.is_empty() {
+        return None;
+    }
+    let capacity: f64 = ship.shields.iter().map(|shield| shield.capacity).sum();
+    let armour: f64 = ship.armour.iter().map(|armour| armour.integrity).sum();
+    let armour_capacity: f64 = ship.armour.iter().map(|armour| armour.capacity).sum();
+    let damage: Vec<String> = ship.damage.iter()
+        .filter(|(_, lost)| **lost > 0.0)
+        .map(|(subsystem, lost)| format!("{:?} {:.0}%", subsystem, lost * 100.0))
+        .collect();
+    Some(format!("shields {:.0}%  armour {:.0}%{}{}",
+                 if capacity > 0.0 { ship.shield_charge() / capacity * 100.0 } else { 0.0 },
+                 if armour_capacity > 0.0 { armour / armour_capacity * 100.0 } else { 0.0 },
+                 if damage.is_empty() { "" } else { "  damaged: " }, damage.join(", ")))
+}
+
+/// What the ship's sensors show, with the nearest contact as it was when its light left it.
+pub fn describe_contacts(contacts: &Contacts, position: WorldPosition, now: f64, units: &UnitPreferences) -> String {
+    let in_sight = contacts.detected().count();
+    let nearest = contacts.detected()
+        .map(|(_, contact)| (contact.position.distance(position), contact))
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    format!("contacts: {} in sight, {} remembered{}", in_sight, contacts.0.len() - in_sight,
+            nearest.map_or_else(String::new, |(distance, contact)| format!("  nearest {:.2} ± {:.1}, as it was {:.0} s ago",
+                                                                            units.length(distance), units.length(contact.uncertainty),
+                                                                            contact.age(now))))
+}
+
+/// Missions taken up, then those offered by the market the ship is docked at, if any.
+pub fn describe_missions(log: &MissionLog, docked: Option<Entity>) -> Vec<String> {
+    let mut lines: Vec<String> = log.with_status(MissionStatus::Active).map(|mission| format!("mission {}", mission)).collect();
+    if let Some(market) = docked {
+        lines.extend(log.offers(market).map(|mission| format!("offered {}", mission)));
+    }
+    lines
+}
+
+/// What the codex holds of a star and the planets seen about it, one line each.
+pub fn describe_discoveries(codex: &Codex, star: StarId, scanning: bool) -> Vec<String> {
+    let mut lines: Vec<String> = codex.describe_star(star)
+        .map(|line| format!("codex: {}{}", line, if scanning { "  scanning" } else { "" }))
+        .into_iter()
+        .collect();
+    lines.extend(codex.planets.range(PlanetId { star, index: 0 }..=PlanetId { star, index: u32::MAX })
+                 .filter_map(|(id, _)| codex.describe_planet(*id))
+                 .map(|line| format!("  {}", line)));
+    lines
+}
+
+type HudShip<'a> = (&'a Ship, &'a ShipControls, &'a Velocity, Option<&'a Autopilot>, Option<&'a PlottedRoute>,
+                   Option<&'a RadiationExposure>, Option<&'a ShipThermal>, Option<(&'a Credits, &'a Manifest)>,
+                   Option<(&'a Contacts, &'a WorldPosition)>);
+
+/// The selection, with who holds what and what has been discovered, for describing the selected star.
+type SelectedStar<'a> = (Option<Res<'a, Selection>>, Option<Res<'a, Factions>>, Option<Res<'a, Borders>>, Option<Res<'a, Codex>>);
+
+/// The mission log, with where the player's ship and the markets are for finding what is offered where it is docked,
+/// and what the ship is scanning.
+type MissionBoard<'w, 'q> = (Option<Res<'w, MissionLog>>, Query<'w, (&'q WorldPosition, Option<&'q Scanning>), With<PlayerShip>>,
+                             Query<'w, (Entity, &'q WorldPosition), With<Market>>);
+
+pub fn update_hud(
+    units: Res<UnitPreferences>,
+    clock: Option<Res<GameClock>>,
+    cameras: Query<&CameraController>,
+    (selection, factions, borders, codex): SelectedStar,
+    stars: Query<(&Star, &StarId, Option<&Flaring>)>,
+    (ships, (missions, positions, markets)): (Query<HudShip, With<PlayerShip>>, MissionBoard),
+    mut huds: Query<&mut Text, With<Hud>>
+) {
+    let mut lines: Vec<String> = clock.iter().map(|clock| describe_clock(clock)).collect();
+    lines.extend(cameras.iter().map(|controller| describe_camera(controller, &units)));
+    let selected = selection.and_then(|selection| selection.0);
+    if let Some((entity, (star, id, flaring))) = selected.and_then(|entity| stars.get(entity).ok().map(|found| (entity, found))) {
+        lines.push(describe_star(star, &units));
+        if let (Some(factions), Some(borders)) = (&factions, &borders) {
+            lines.push(describe_allegiance(*id, factions, borders));
+        }
+        if let Some(codex) = &codex {
+            let scanning = matches!(positions.single(), Ok((_, Some(scanning))) if scanning.target == entity);
+            lines.extend(describe_discoveries(codex, *id, scanning));
+        }
+        let flare = flaring.map_or(power::ZERO, |flaring| flaring.peak);
+        lines.push(format!("safe approach: {:.2}{}", SafeApproach::of(star, flare), if flaring.is_some() { "  flaring" } else { "" }));
+    }
+    let now = clock.as_ref().map_or(0.0, |clock| clock.seconds);
+    for (ship, controls, velocity, autopilot, route, exposure, thermal, account, sensors) in ships.iter() {
+        lines.push(describe_ship(ship, controls, velocity, autopilot));
+        lines.extend(exposure.map(|exposure| exposure.to_string()));
+        lines.extend(thermal.map(|thermal| describe_thermal(ship, thermal, &units)));
+        lines.extend(describe_condition(ship));
+        lines.extend(route.map(|route| format!("route: {}", route.0)));
+        lines.extend(account.map(|(credits, manifest)| describe_cargo(ship, credits, manifest)));
+        lines.extend(sensors.map(|(contacts, position)| describe_contacts(contacts, *position, now, &units)));
+    }
+    if let Some(log) = &missions {
+        let docked = positions.single().ok().and_then(|(position, _)| markets.iter()
+            .find(|(_, market)| market.distance(*position).in_meters() <= DOCKING_RANGE_METERS)
+            .map(|(market, _)| market));
+        lines.extend(describe_missions(log, docked));
+    }
+    let value = lines.join("\n");
+    for mut text in huds.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// A page of the catalogue, one star a row, marking the selected star.
+pub fn describe_catalogue(browser: &CatalogueBrowser, page: &CataloguePage, selected: Option<Entity>, units: &UnitPreferences) -> Vec<String> {
+    let mut lines = vec![format!("catalogue: {} stars{}, by {:?}{}, page {} of {}",
+                                 page.total,
+                                 browser.spectral_type.map_or_else(String::new, |spectral_type| format!(" of type {:?}", spectral_type)),
+                                 browser.sort,
+                                 if browser.descending { " descending" } else { "" },
+                                 page.page + 1, page.pages)];
+    lines.extend(page.entries.iter().map(|(entry, distance)| {
+        format!("{} {:<14} {:<6} {:.2}  {:.0}  {:.3}  {:.3}",
+                if selected.is_some() && entry.entity == selected { ">" } else { " " },
+                entry.id.to_string(), format!("{:?}{:?}", entry.star.spectral_type, entry.star.luminosity_class), distance,
+                entry.star.temperature.to_scale(units.temperature), entry.star.mass.to_scale(units.mass),
+                entry.star.luminosity.to_scale(units.power))
+    }));
+    lines
+}
+
+/// Shows the page `browse_catalogue` keeps in the browser.
+pub fn update_catalogue_panel(
+    units: Res<UnitPreferences>,
+    browser: Option<Res<CatalogueBrowser>>,
+    selection: Option<Res<Selection>>,
+    mut panels: Query<&mut Text, With<CataloguePanel>>
+) {
+    let value = match browser.as_ref().filter(|browser| browser.open).and_then(|browser| browser.shown.as_ref().map(|(_, page)| (browser, page))) {
+        Some((browser, page)) => describe_catalogue(browser, page, selection.and_then(|selection| selection.0), &units).join("\n"),
+        None                  => String::new()
+    };
+    for mut text in panels.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// A heads up display of the camera, the selected star and the player's ship, and the star catalogue when it is open.
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<UiSettings>()
+            .add_startup_system(spawn_hud.system())
+            .add_system(update_hud.system())
+            .add_system(update_catalogue_panel.system());
+    }
+}
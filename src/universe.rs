@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::astronomy::Orbiting;
+use super::floating_origin::WorldPosition;
+use super::galaxy::Galaxy;
+use super::industry;
+use super::length::Length;
+use super::planet;
+use super::planet::Composition;
+use super::position::Position;
+use super::spatial::Octree;
+
+/// The seed everything procedural derives from: universe, then sector, then system, then body.
+/// Sharing it reproduces a universe exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UniverseSeed(pub u64);
+
+pub const DEFAULT_SEED: UniverseSeed = UniverseSeed(0x5a9f_17e5_7a20_0001);
+
+impl Default for UniverseSeed {
+    fn default() -> UniverseSeed {
+        DEFAULT_SEED
+    }
+}
+
+/// What part of which galaxy to generate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniverseSettings {
+    pub galaxy: Galaxy,
+    /// Where the game starts; stars are generated around here.
+    pub start: Position,
+    pub radius: Length
+}
+
+impl Default for UniverseSettings {
+    fn default() -> UniverseSettings {
+        UniverseSettings::from_seed(DEFAULT_SEED)
+    }
+}
+
+impl UniverseSettings {
+    /// Our own galaxy's shape, generated from a seed, starting where the Sun is in ours.
+    pub fn from_seed(seed: UniverseSeed) -> UniverseSettings {
+        let galaxy = Galaxy::new(seed.0);
+        UniverseSettings {
+            start: galaxy.solar_position(),
+            radius: Length::ly(20.0),
+            galaxy
+        }
+    }
+}
+
+/// Every generated star entity, by position.
+pub struct StarIndex(pub Octree<Entity>);
+
+/// Generates the stars around the start, with their planets.
+pub fn generate_universe(
+    mut commands: Commands,
+    settings: Res<UniverseSettings>,
+    mut index: ResMut<StarIndex>
+) {
+    for galaxy_star in settings.galaxy.stars_within(settings.start, settings.radius) {
+        let star = galaxy_star.star();
+        let position = WorldPosition::from_position(galaxy_star.position);
+        let entity = commands.spawn()
+            .insert(galaxy_star.id)
+            .insert(star)
+            .insert(position)
+            .id();
+        index.0.insert(galaxy_star.position, entity);
+
+        for (index, (planet, orbit)) in planet::generate_planets(&star, galaxy_star.system_seed()).into_iter().enumerate() {
+            let seed = planet::body_seed(galaxy_star.system_seed(), index as u32);
+            let composition = Composition::generate(planet.kind, seed);
+            let mut planet_position = position;
+            planet_position.translate(orbit.position_at(0.0));
+            commands.spawn()
+                .insert(planet)
+                .insert(orbit)
+                .insert(composition)
+                .insert(industry::generate_deposits(&planet, &composition, seed))
+                .insert(Orbiting(entity))
+                .insert(planet_position);
+        }
+    }
+}
+
+/// Procedural generation of the stars and planets around the start.
+/// Uses the `UniverseSettings` resource if there is one, or else generates them from the `UniverseSeed`.
+pub struct UniversePlugin;
+
+impl Plugin for UniversePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let settings = match app.world().get_resource::<UniverseSettings>() {
+            Some(settings) => settings.clone(),
+            None           => UniverseSettings::from_seed(app.world().get_resource::<UniverseSeed>().copied().unwrap_or_default())
+        };
+        app.insert_resource(UniverseSeed(settings.galaxy.seed))
+            .insert_resource(StarIndex(Octree::new(settings.start, Length::pc(16.0))))
+            .insert_resource(settings)
+            .add_startup_system(generate_universe.system());
+    }
+}
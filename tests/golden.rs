@@ -0,0 +1,55 @@
+//! Generation from a fixed seed, compared against golden files that were generated the same way.
+//! Generation only uses `libm` for transcendental functions, so these match on every platform.
+//! After an intended change to generation, rerun with `UPDATE_GOLDEN=1` to rewrite the files.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use the_sapphire_star::length::Length;
+use the_sapphire_star::planet;
+use the_sapphire_star::universe::{UniverseSeed, UniverseSettings};
+
+const SEED: UniverseSeed = UniverseSeed(42);
+
+/// Every star within `light_years` of the start, each with its planets, where they orbit and where they start.
+fn generate(light_years: f64) -> String {
+    let settings = UniverseSettings::from_seed(SEED);
+    let mut stars: Vec<_> = settings.galaxy.stars_within(settings.start, Length::ly(light_years)).collect();
+    stars.sort_by_key(|galaxy_star| galaxy_star.id);
+    let mut generated = String::new();
+    for galaxy_star in stars {
+        let star = galaxy_star.star();
+        writeln!(generated, "{:?}", galaxy_star).unwrap();
+        writeln!(generated, "  {:?}", star).unwrap();
+        for (planet, orbit) in planet::generate_planets(&star, galaxy_star.system_seed()) {
+            writeln!(generated, "    {:?}", planet).unwrap();
+            writeln!(generated, "    {:?}", orbit).unwrap();
+            writeln!(generated, "    at {:?}", orbit.position_at(0.0)).unwrap();
+        }
+    }
+    generated
+}
+
+fn compare(name: &str, generated: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, generated).unwrap();
+        return;
+    }
+    let golden = fs::read_to_string(&path).unwrap_or_else(|error| panic!("reading {}: {}", path.display(), error));
+    for (line, (expected, actual)) in golden.lines().zip(generated.lines()).enumerate() {
+        assert_eq!(expected, actual, "{} differs at line {}", name, line + 1);
+    }
+    assert_eq!(golden.lines().count(), generated.lines().count(), "{} has a different number of lines", name);
+}
+
+#[test]
+fn generation_is_repeatable() {
+    assert_eq!(generate(8.0), generate(8.0));
+}
+
+#[test]
+fn stars_and_planets_match_the_golden_file() {
+    compare("universe-42.txt", &generate(8.0));
+}
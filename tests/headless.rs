@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use the_sapphire_star::clock::GameClock;
+use the_sapphire_star::faction::{Borders, Factions};
+use the_sapphire_star::floating_origin::WorldPosition;
+use the_sapphire_star::planet::Planet;
+use the_sapphire_star::plugins;
+use the_sapphire_star::star::Star;
+use the_sapphire_star::universe::{UniverseSeed, UniverseSettings};
+
+const TICK_SECONDS: f64 = 3600.0;
+
+fn headless_app() -> App {
+    let mut builder = plugins::headless_app(UniverseSettings::from_seed(UniverseSeed(7)));
+    let mut clock = GameClock::default();
+    clock.tick = TICK_SECONDS;
+    builder.insert_resource(clock);
+    builder.app
+}
+
+fn planet_positions(world: &mut World) -> Vec<WorldPosition> {
+    world.query_filtered::<&WorldPosition, With<Planet>>().iter(world).copied().collect()
+}
+
+#[test]
+fn each_update_simulates_one_tick() {
+    let mut app = headless_app();
+    for _ in 0..24 {
+        app.update();
+    }
+    let clock = app.world.get_resource::<GameClock>().unwrap();
+    assert_eq!(clock.ticks, 24);
+    assert_eq!(clock.seconds, 24.0 * TICK_SECONDS);
+}
+
+#[test]
+fn the_universe_is_generated_and_its_planets_move() {
+    let mut app = headless_app();
+    app.update();
+    assert!(app.world.query::<&Star>().iter(&app.world).count() > 0);
+    let before = planet_positions(&mut app.world);
+    assert!(!before.is_empty());
+    for _ in 0..24 {
+        app.update();
+    }
+    let after = planet_positions(&mut app.world);
+    assert_eq!(before.len(), after.len());
+    assert!(before.iter().zip(after.iter()).any(|(before, after)| before != after));
+}
+
+#[test]
+fn factions_are_founded_and_claim_borders_on_the_first_tick() {
+    let mut app = headless_app();
+    app.update();
+    let factions = app.world.get_resource::<Factions>().unwrap();
+    assert!(!factions.factions.is_empty());
+    let borders = app.world.get_resource::<Borders>().unwrap();
+    assert!(factions.factions.keys().all(|faction| borders.extent(*faction) > 0));
+}
@@ -0,0 +1,21 @@
+use std::process::Command;
+
+/// Runs the headless simulation with markets, traders and NPCs of every role, and returns what it reports.
+fn simulate(seed: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_sim"))
+        .args(["--seed", seed, "--ticks", "240", "--markets", "3", "--traders", "4", "--npcs", "2"].iter())
+        .env("CARGO_MANIFEST_DIR", env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn a_run_replays_exactly_from_its_seed() {
+    let first = simulate("7");
+    assert!(first.lines().any(|line| line.starts_with("npc ")));
+    assert!(first.lines().any(|line| line.starts_with("market ")));
+    assert_eq!(first, simulate("7"));
+    assert_ne!(first, simulate("8"));
+}